@@ -227,6 +227,7 @@ pub fn liquidate_collateral(
                     repay_address: Some(
                         deps.api.addr_humanize(&config.market_contract)?.to_string(),
                     ),
+                    borrower: Some(borrower.to_string()),
                 })?,
             })?,
         }))
@@ -10,12 +10,19 @@ use crate::collateral::{
     unlock_collateral, withdraw_collateral,
 };
 use crate::error::ContractError;
+use crate::pause::{assert_not_paused, query_pause_status, set_paused, update_guardian};
+use crate::shutdown::{assert_not_shutdown, settle_collateral, shutdown};
 use crate::state::{
-    read_config, read_new_owner, store_config, store_new_owner, Config, NewOwnerAddr,
+    migrate_legacy_state, read_config, read_new_owner, store_config, store_new_owner, Config,
+    NewOwnerAddr,
 };
 
 use cw20::Cw20ReceiveMsg;
+use cw2::set_contract_version;
 use moneymarket::common::optional_addr_validate;
+use moneymarket::config_history::{config_change, query_config_history, record_config_change};
+use moneymarket::migration::assert_migrate_version;
+use moneymarket::pause::PausableOperation;
 use moneymarket::custody_base::{
     ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
 };
@@ -23,6 +30,9 @@ use moneymarket::custody_base::{
 pub const CLAIM_REWARDS_OPERATION: u64 = 1u64;
 pub const SWAP_TO_STABLE_OPERATION: u64 = 2u64;
 
+const CONTRACT_NAME: &str = "crates.io:moneymarket-custody-base";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -30,6 +40,8 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     let config = Config {
         owner: deps.api.addr_canonicalize(&msg.owner)?,
         overseer_contract: deps.api.addr_canonicalize(&msg.overseer_contract)?,
@@ -55,7 +67,7 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -67,25 +79,35 @@ pub fn execute(
             let api = deps.api;
             update_config(
                 deps,
+                env,
                 info,
                 optional_addr_validate(api, liquidation_contract)?,
             )
         }
         ExecuteMsg::SetOwner { new_owner_addr } => {
             let api = deps.api;
-            set_new_owner(deps, info, api.addr_validate(&new_owner_addr)?)
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
+        }
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::UpdateGuardian { guardian } => {
+            let api = deps.api;
+            update_guardian(deps, env, info, optional_addr_validate(api, guardian)?)
         }
-        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::SetPaused { operation, paused } => set_paused(deps, info, operation, paused),
         ExecuteMsg::LockCollateral { borrower, amount } => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::CollateralLocks)?;
             let borrower_addr = deps.api.addr_validate(&borrower)?;
             lock_collateral(deps, info, borrower_addr, amount)
         }
         ExecuteMsg::UnlockCollateral { borrower, amount } => {
+            assert_not_paused(deps.storage, PausableOperation::CollateralUnlocks)?;
             let borrower_addr = deps.api.addr_validate(&borrower)?;
             unlock_collateral(deps, info, borrower_addr, amount)
         }
         ExecuteMsg::DistributeRewards {} => Ok(Response::new()),
         ExecuteMsg::WithdrawCollateral { borrower, amount } => {
+            assert_not_paused(deps.storage, PausableOperation::CollateralUnlocks)?;
             withdraw_collateral(deps, info, borrower, amount)
         }
         ExecuteMsg::LiquidateCollateral {
@@ -93,10 +115,16 @@ pub fn execute(
             borrower,
             amount,
         } => {
+            assert_not_paused(deps.storage, PausableOperation::Liquidations)?;
             let liquidator_addr = deps.api.addr_validate(&liquidator)?;
             let borrower_addr = deps.api.addr_validate(&borrower)?;
             liquidate_collateral(deps, info, liquidator_addr, borrower_addr, amount)
         }
+        ExecuteMsg::Shutdown {} => shutdown(deps, info),
+        ExecuteMsg::SettleCollateral { borrower, amount } => {
+            let borrower_addr = deps.api.addr_validate(&borrower)?;
+            settle_collateral(deps, info, borrower_addr, amount)
+        }
         ExecuteMsg::UpdateSwapContract { swap_contract: _ } => Ok(Response::new()),
         ExecuteMsg::UpdateSwapDenom {
             swap_denom: _,
@@ -122,6 +150,8 @@ pub fn receive_cw20(
                 ))));
             }
 
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Deposits)?;
             let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
             deposit_collateral(deps, cw20_sender_addr, cw20_msg.amount.into())
         }
@@ -131,6 +161,7 @@ pub fn receive_cw20(
 
 pub fn set_new_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner_addr: Addr,
 ) -> Result<Response, ContractError> {
@@ -140,13 +171,26 @@ pub fn set_new_owner(
     if sender_raw != config.owner {
         return Err(ContractError::Unauthorized {});
     }
+    let old_new_owner = deps.api.addr_humanize(&new_owner.new_owner_addr)?;
     new_owner.new_owner_addr = deps.api.addr_canonicalize(&new_owner_addr.to_string())?;
     store_new_owner(deps.storage, &new_owner)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_owner",
+        vec![config_change("new_owner_addr", &old_new_owner, &new_owner_addr)?],
+    )?;
+
     Ok(Response::default())
 }
 
-pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let new_owner = read_new_owner(deps.as_ref().storage)?;
     let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
     let mut config = read_config(deps.as_ref().storage)?;
@@ -154,14 +198,24 @@ pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, Co
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_owner = deps.api.addr_humanize(&config.owner)?;
     config.owner = new_owner.new_owner_addr;
     store_config(deps.storage, &config)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "accept_ownership",
+        vec![config_change("owner", &old_owner, &info.sender)?],
+    )?;
+
     Ok(Response::default())
 }
 
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     liquidation_contract: Option<Addr>,
 ) -> Result<Response, ContractError> {
@@ -171,11 +225,24 @@ pub fn update_config(
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_liquidation_contract = deps.api.addr_humanize(&config.liquidation_contract)?;
     if let Some(liquidation_contract) = liquidation_contract {
         config.liquidation_contract = deps.api.addr_canonicalize(liquidation_contract.as_str())?;
     }
 
     store_config(deps.storage, &config)?;
+
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_config",
+        vec![config_change(
+            "liquidation_contract",
+            &old_liquidation_contract,
+            &deps.api.addr_humanize(&config.liquidation_contract)?,
+        )?],
+    )?;
     Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
 }
 
@@ -192,6 +259,10 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             optional_addr_validate(deps.api, start_after)?,
             limit,
         )?),
+        QueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(deps)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
 
@@ -221,6 +292,14 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
 }
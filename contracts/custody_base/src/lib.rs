@@ -1,6 +1,8 @@
 pub mod collateral;
 pub mod contract;
 pub mod error;
+pub mod pause;
+pub mod shutdown;
 pub mod state;
 
 #[cfg(test)]
@@ -3,12 +3,14 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Uint256;
 use cosmwasm_std::{CanonicalAddr, Deps, Order, StdResult, Storage};
-use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use cw_storage_plus::{Bound, Item, Map};
 use moneymarket::custody_base::{BAssetInfo, BorrowerResponse};
+use moneymarket::migration::migrate_singleton;
 
-const KEY_CONFIG: &[u8] = b"config";
-const PREFIX_BORROWER: &[u8] = b"borrower";
-const KEY_NEWOWNER: &[u8] = b"newowner";
+const CONFIG: Item<Config> = Item::new("config");
+const BORROWERS: Map<&[u8], BorrowerInfo> = Map::new("borrower");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const SHUTDOWN: Item<bool> = Item::new("shutdown");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -33,21 +35,29 @@ pub struct NewOwnerAddr {
     pub new_owner_addr: CanonicalAddr, 
 }
 
+pub fn store_shutdown(storage: &mut dyn Storage, shutdown: bool) -> StdResult<()> {
+    SHUTDOWN.save(storage, &shutdown)
+}
+
+pub fn read_shutdown(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(SHUTDOWN.may_load(storage)?.unwrap_or(false))
+}
+
 pub fn store_config(storage: &mut dyn Storage, data: &Config) -> StdResult<()> {
-    Singleton::new(storage, KEY_CONFIG).save(data)
+    CONFIG.save(storage, data)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    ReadonlySingleton::new(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
 }
 
 
 pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
-    Singleton::new(storage, KEY_NEWOWNER).save(data)
+    NEWOWNER.save(storage, data)
 }
 
 pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
-    ReadonlySingleton::new(storage, KEY_NEWOWNER).load()
+    NEWOWNER.load(storage)
 }
 
 
@@ -56,21 +66,15 @@ pub fn store_borrower_info(
     borrower: &CanonicalAddr,
     borrower_info: &BorrowerInfo,
 ) -> StdResult<()> {
-    let mut borrower_bucket: Bucket<BorrowerInfo> = Bucket::new(storage, PREFIX_BORROWER);
-    borrower_bucket.save(borrower.as_slice(), borrower_info)?;
-
-    Ok(())
+    BORROWERS.save(storage, borrower.as_slice(), borrower_info)
 }
 
 pub fn remove_borrower_info(storage: &mut dyn Storage, borrower: &CanonicalAddr) {
-    let mut borrower_bucket: Bucket<BorrowerInfo> = Bucket::new(storage, PREFIX_BORROWER);
-    borrower_bucket.remove(borrower.as_slice());
+    BORROWERS.remove(storage, borrower.as_slice());
 }
 
 pub fn read_borrower_info(storage: &dyn Storage, borrower: &CanonicalAddr) -> BorrowerInfo {
-    let borrower_bucket: ReadonlyBucket<BorrowerInfo> =
-        ReadonlyBucket::new(storage, PREFIX_BORROWER);
-    match borrower_bucket.load(borrower.as_slice()) {
+    match BORROWERS.load(storage, borrower.as_slice()) {
         Ok(v) => v,
         _ => BorrowerInfo {
             balance: Uint256::zero(),
@@ -87,14 +91,11 @@ pub fn read_borrowers(
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
 ) -> StdResult<Vec<BorrowerResponse>> {
-    let position_bucket: ReadonlyBucket<BorrowerInfo> =
-        ReadonlyBucket::new(deps.storage, PREFIX_BORROWER);
-
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let start = start_after.map(|addr| Bound::ExclusiveRaw(addr.to_vec()));
 
-    position_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+    BORROWERS
+        .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
             let (k, v) = item?;
@@ -108,11 +109,10 @@ pub fn read_borrowers(
         .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
-    })
+/// Move state written by `cosmwasm_storage` singletons, whether or not cw2
+/// versions were recorded yet; a no-op once moved. The borrower bucket shares
+/// the byte layout of its map.
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_singleton(storage, &CONFIG)?;
+    migrate_singleton(storage, &NEWOWNER)
 }
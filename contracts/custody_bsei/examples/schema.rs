@@ -3,10 +3,12 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use moneymarket::config_history::ConfigHistoryResponse;
 use moneymarket::custody::{
     BorrowerResponse, BorrowersResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
     MigrateMsg, QueryMsg,
 };
+use moneymarket::pause::PauseStatusResponse;
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -22,4 +24,6 @@ fn main() {
     export_schema(&schema_for!(BorrowerResponse), &out_dir);
     export_schema(&schema_for!(BorrowersResponse), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(PauseStatusResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
@@ -1,4 +1,5 @@
 use cosmwasm_std::{OverflowError, StdError};
+use moneymarket::pause::PauseError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -29,4 +30,27 @@ pub enum ContractError {
 
     #[error("Withdraw amount cannot exceed the user's spendable amount: {0}")]
     WithdrawAmountExceedsSpendable(u128),
+
+    #[error("Custody is shut down")]
+    ShutDown {},
+
+    #[error("Custody is not shut down")]
+    NotShutDown {},
+
+    #[error("Custody {0} are paused")]
+    Paused(String),
+
+    #[error("Custody {0} cannot be paused")]
+    NotPausable(String),
+}
+
+impl From<PauseError> for ContractError {
+    fn from(err: PauseError) -> Self {
+        match err {
+            PauseError::Unauthorized => ContractError::Unauthorized {},
+            PauseError::NotPausable(operation) => ContractError::NotPausable(operation),
+            PauseError::Paused(operation) => ContractError::Paused(operation),
+            PauseError::Std(err) => ContractError::Std(err),
+        }
+    }
 }
@@ -1,9 +1,11 @@
-use cosmwasm_std::{DepsMut, MessageInfo, Response};
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
 use crate::error::ContractError;
+use moneymarket::config_history::{config_change, record_config_change};
 use crate::state::{read_config, store_config};
 
 pub fn update_swap_contract(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     swap_contract: String,
 ) -> Result<Response, ContractError> {
@@ -11,8 +13,16 @@ pub fn update_swap_contract(
     if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
         return Err(ContractError::Unauthorized {});
     }
+    let old_swap_contract = deps.api.addr_humanize(&config.swap_contract)?;
     config.swap_contract = deps.api.addr_canonicalize(swap_contract.as_str())?;
     store_config(deps.storage, &config)?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_swap_contract",
+        vec![config_change("swap_contract", &old_swap_contract.to_string(), &swap_contract)?],
+    )?;
     Ok(Response::new()
         .add_attribute("action", "update_swap_contract")
         .add_attribute("swap_contract", swap_contract)
@@ -21,6 +31,7 @@ pub fn update_swap_contract(
 
 pub fn update_swap_denom(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     swap_denom: String,
     is_add: bool,
@@ -29,12 +40,20 @@ pub fn update_swap_denom(
     if config.owner != deps.api.addr_canonicalize(info.sender.as_str())? {
         return Err(ContractError::Unauthorized {});
     }
+    let old_swap_denoms = config.swap_denoms.clone();
     if is_add {
         config.swap_denoms.push(swap_denom.clone());
     } else {
         config.swap_denoms.retain(|x| x != &swap_denom);
     }
     store_config(deps.storage, &config)?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_swap_denom",
+        vec![config_change("swap_denoms", &old_swap_denoms, &config.swap_denoms)?],
+    )?;
     Ok(Response::new()
         .add_attribute("action", "update_swap_denom")
         .add_attribute("swap_denom", swap_denom.as_str())
@@ -2,6 +2,8 @@ pub mod collateral;
 pub mod contract;
 pub mod distribution;
 pub mod error;
+pub mod pause;
+pub mod shutdown;
 pub mod state;
 pub mod handler;
 mod external;
@@ -0,0 +1,83 @@
+use cosmwasm_bignumber::Uint256;
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, CanonicalAddr, CosmosMsg, DepsMut, MessageInfo, Response,
+    Storage, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use crate::error::ContractError;
+use crate::state::{
+    read_borrower_info, read_config, read_shutdown, store_borrower_info, store_shutdown,
+    BorrowerInfo, Config,
+};
+
+/// Stop taking collateral deposits and locks for good
+/// Executor: overseer
+pub fn shutdown(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    assert_not_shutdown(deps.storage)?;
+    store_shutdown(deps.storage, true)?;
+
+    Ok(Response::new().add_attributes(vec![attr("action", "shutdown")]))
+}
+
+/// Send locked collateral to the market, to settle the borrower loan
+/// after the shutdown
+/// Executor: overseer
+pub fn settle_collateral(
+    deps: DepsMut,
+    info: MessageInfo,
+    borrower: Addr,
+    amount: Uint256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if !read_shutdown(deps.storage)? {
+        return Err(ContractError::NotShutDown {});
+    }
+
+    let borrower_raw: CanonicalAddr = deps.api.addr_canonicalize(borrower.as_str())?;
+    let mut borrower_info: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
+    let locked_amount = borrower_info.balance - borrower_info.spendable;
+    if amount > locked_amount {
+        return Err(ContractError::LiquidationAmountExceedsLocked(
+            locked_amount.into(),
+        ));
+    }
+
+    borrower_info.balance = borrower_info.balance - amount;
+    store_borrower_info(deps.storage, &borrower_raw, &borrower_info)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps
+                .api
+                .addr_humanize(&config.collateral_token)?
+                .to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: deps.api.addr_humanize(&config.market_contract)?.to_string(),
+                amount: amount.into(),
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "settle_collateral"),
+            attr("borrower", borrower),
+            attr("amount", amount),
+        ]))
+}
+
+pub fn assert_not_shutdown(storage: &dyn Storage) -> Result<(), ContractError> {
+    if read_shutdown(storage)? {
+        return Err(ContractError::ShutDown {});
+    }
+
+    Ok(())
+}
@@ -1,12 +1,14 @@
 use cosmwasm_bignumber::Uint256;
 use cosmwasm_std::{
     attr, from_json, to_json_binary, Api, Attribute, BankMsg, Coin, CosmosMsg, Decimal, Reply,
-    Response, StdError, SubMsg, SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
+    Response, StdError, Storage, SubMsg, SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
 };
+use cosmwasm_storage::to_length_prefixed;
 use moneymarket::swap_ext::SwapExecteMsg;
 
 use crate::contract::{
-    execute, instantiate, query, reply, CLAIM_REWARDS_OPERATION, SWAP_TO_STABLE_OPERATION,
+    execute, instantiate, migrate, query, reply, CLAIM_REWARDS_OPERATION,
+    SWAP_TO_STABLE_OPERATION,
 };
 use crate::error::ContractError;
 use crate::external::handle::RewardContractExecuteMsg;
@@ -15,10 +17,13 @@ use crate::testing::mock_querier::mock_dependencies;
 
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use moneymarket::config_history::{ConfigChange, ConfigHistoryResponse};
 use moneymarket::custody::{
-    BAssetInfo, BorrowerResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
+    BAssetInfo, BorrowerResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
+    MigrateMsg, QueryMsg,
 };
 use moneymarket::liquidation::Cw20HookMsg as LiquidationCw20HookMsg;
+use moneymarket::pause::{PausableOperation, PauseStatusResponse};
 
 #[test]
 fn proper_initialization() {
@@ -949,6 +954,7 @@ fn liquidate_collateral() {
                     liquidator: "liquidator".to_string(),
                     fee_address: Some("overseer".to_string()),
                     repay_address: Some("market".to_string()),
+                    borrower: Some("addr0000".to_string()),
                 })
                 .unwrap()
             })
@@ -1032,3 +1038,391 @@ fn proper_distribute_rewards_with_no_rewards() {
         ),]
     );
 }
+
+#[test]
+fn pause_operations() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "bsei".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        swap_contract: "swap".to_string(),
+        swap_denoms: vec!["uusd".to_string()],
+        basset_info: BAssetInfo {
+            name: "bsei".to_string(),
+            symbol: "bsei".to_string(),
+            decimals: 6,
+        },
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian".to_string()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::BidSubmissions,
+        paused: true,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg);
+    match res {
+        Err(ContractError::NotPausable(operation)) => assert_eq!(operation, "bid_submissions"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Deposits,
+        paused: true,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::CollateralUnlocks,
+        paused: true,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap();
+    let status: PauseStatusResponse = from_json(&res).unwrap();
+    assert_eq!(
+        status,
+        PauseStatusResponse {
+            guardian: Some("guardian".to_string()),
+            paused: vec![
+                PausableOperation::Deposits,
+                PausableOperation::CollateralUnlocks,
+            ],
+        }
+    );
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_json_binary(&Cw20HookMsg::DepositCollateral {}).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("bsei", &[]), deposit_msg.clone());
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "deposits"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::WithdrawCollateral {
+        borrower: "addr0000".to_string(),
+        amount: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "collateral_unlocks"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UnlockCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(100u64),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "collateral_unlocks"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // resume deposits
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Deposits,
+        paused: false,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("bsei", &[]), deposit_msg).unwrap();
+}
+
+#[test]
+fn shutdown() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "bsei".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        swap_contract: "swap".to_string(),
+        swap_denoms: vec!["uusd".to_string()],
+        basset_info: BAssetInfo {
+            name: "bsei".to_string(),
+            symbol: "bsei".to_string(),
+            decimals: 6,
+        },
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_json_binary(&Cw20HookMsg::DepositCollateral {}).unwrap(),
+    });
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bsei", &[]),
+        deposit_msg.clone(),
+    )
+    .unwrap();
+
+    let lock_msg = ExecuteMsg::LockCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(100u64),
+    };
+    let _res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer", &[]),
+        lock_msg.clone(),
+    )
+    .unwrap();
+
+    let settle_msg = ExecuteMsg::SettleCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(60u64),
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("overseer", &[]),
+        settle_msg.clone(),
+    );
+    match res {
+        Err(ContractError::NotShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::Shutdown {};
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res =
+        execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("bsei", &[]), deposit_msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), lock_msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::SettleCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(200u64),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg);
+    match res {
+        Err(ContractError::LiquidationAmountExceedsLocked(100)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), settle_msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "bsei".to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "market".to_string(),
+                amount: Uint128::from(60u128),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    let borrower_raw = deps.api.addr_canonicalize("addr0000").unwrap();
+    let borrower_info = read_borrower_info(deps.as_ref().storage, &borrower_raw);
+    assert_eq!(borrower_info.balance, Uint256::from(40u64));
+    assert_eq!(borrower_info.spendable, Uint256::zero());
+
+    // the collateral left over can still be unlocked
+    let msg = ExecuteMsg::UnlockCollateral {
+        borrower: "addr0000".to_string(),
+        amount: Uint256::from(40u64),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg).unwrap();
+}
+
+#[test]
+fn config_history() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "bsei".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        swap_contract: "swap".to_string(),
+        swap_denoms: vec!["uusd".to_string()],
+        basset_info: BAssetInfo {
+            name: "bsei".to_string(),
+            symbol: "bsei".to_string(),
+            decimals: 6,
+        },
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateSwapContract {
+        swap_contract: "swap2".to_string(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateSwapDenom {
+        swap_denom: "ukrw".to_string(),
+        is_add: true,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetOwner {
+        new_owner_addr: "owner2".to_string(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    let changes: Vec<(String, Vec<ConfigChange>)> = history
+        .entries
+        .into_iter()
+        .map(|entry| (entry.action, entry.changes))
+        .collect();
+    let change = |field: &str, old_value: &str, new_value: &str| ConfigChange {
+        field: field.to_string(),
+        old_value: old_value.to_string(),
+        new_value: new_value.to_string(),
+    };
+    assert_eq!(
+        changes,
+        vec![
+            (
+                "update_swap_contract".to_string(),
+                vec![change("swap_contract", "\"swap\"", "\"swap2\"")],
+            ),
+            (
+                "update_swap_denom".to_string(),
+                vec![change("swap_denoms", "[\"uusd\"]", "[\"uusd\",\"ukrw\"]")],
+            ),
+            (
+                "set_owner".to_string(),
+                vec![change("new_owner_addr", "\"owner\"", "\"owner2\"")],
+            ),
+        ]
+    );
+}
+
+#[test]
+fn migrate_legacy_state() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner".to_string(),
+        collateral_token: "bsei".to_string(),
+        overseer_contract: "overseer".to_string(),
+        market_contract: "market".to_string(),
+        reward_contract: "reward".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        stable_denom: "uusd".to_string(),
+        swap_contract: "swap".to_string(),
+        swap_denoms: vec!["uusd".to_string()],
+        basset_info: BAssetInfo {
+            name: "bsei".to_string(),
+            symbol: "bsei".to_string(),
+            decimals: 6,
+        },
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100u128),
+        msg: to_json_binary(&Cw20HookMsg::DepositCollateral {}).unwrap(),
+    });
+    let info = mock_info("bsei", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // rewrite the config to the singleton layout of releases without cw2 info,
+    // the borrower bucket is left as is
+    let value = deps.storage.get(b"config").unwrap();
+    deps.storage.remove(b"config");
+    deps.storage.set(&to_length_prefixed(b"config"), &value);
+    deps.storage.remove(b"contract_info");
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1], attr("previous_version", "none"));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(config_res.owner, "owner");
+    assert_eq!(config_res.swap_contract, Some("swap".to_string()));
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Borrower {
+            address: "addr0000".to_string(),
+        },
+    )
+    .unwrap();
+    let borrower_res: BorrowerResponse = from_json(&res).unwrap();
+    assert_eq!(borrower_res.balance, Uint256::from(100u64));
+
+    // releases that recorded cw2 info may still be on the singleton layout
+    let value = deps.storage.get(b"config").unwrap();
+    deps.storage.remove(b"config");
+    deps.storage.set(&to_length_prefixed(b"config"), &value);
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes[1],
+        attr("previous_version", env!("CARGO_PKG_VERSION"))
+    );
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(config_res.owner, "owner");
+}
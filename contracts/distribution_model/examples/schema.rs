@@ -3,8 +3,10 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use moneymarket::config_history::ConfigHistoryResponse;
 use moneymarket::distribution_model::{
-    KptEmissionRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    ConfigResponse, EmissionProjectionResponse, EmissionScheduleResponse, ExecuteMsg,
+    InstantiateMsg, KptEmissionRateResponse, MigrateMsg, QueryMsg,
 };
 
 fn main() {
@@ -16,6 +18,10 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(KptEmissionRateResponse), &out_dir);
+    export_schema(&schema_for!(EmissionScheduleResponse), &out_dir);
+    export_schema(&schema_for!(EmissionProjectionResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
@@ -1,14 +1,29 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
 
 use crate::error::ContractError;
-use crate::state::{read_config, store_config, Config, read_new_owner, store_new_owner, NewOwnerAddr};
+use crate::state::{
+    read_config, read_emission_schedule, read_new_owner, store_config, store_emission_schedule,
+    store_new_owner, migrate_legacy_state, Config, EmissionSchedule, NewOwnerAddr,
+};
 
-use cosmwasm_bignumber::Decimal256;
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cw2::set_contract_version;
+use moneymarket::config_history::{config_change, query_config_history, record_config_change};
 use moneymarket::distribution_model::{
-    KptEmissionRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    ConfigResponse, EmissionPhase, EmissionProjectionResponse, EmissionScheduleResponse,
+    EpochEmission, ExecuteMsg, InstantiateMsg, KptEmissionRateResponse, MigrateMsg, QueryMsg,
 };
+use moneymarket::migration::assert_migrate_version;
+
+const CONTRACT_NAME: &str = "crates.io:moneymarket-distribution-model";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Maximum number of epochs returned by the emission projection query
+const MAX_PROJECTION_EPOCHS: u32 = 100;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -17,6 +32,8 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     store_config(
         deps.storage,
         &Config {
@@ -37,10 +54,23 @@ pub fn instantiate(
     Ok(Response::default())
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -53,6 +83,7 @@ pub fn execute(
         } => {
             update_config(
                 deps,
+                env,
                 info,
                 emission_cap,
                 emission_floor,
@@ -62,14 +93,18 @@ pub fn execute(
         }
         ExecuteMsg::SetOwner { new_owner_addr } => {
             let api = deps.api;
-            set_new_owner(deps, info, api.addr_validate(&new_owner_addr)?)
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
         }
-        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
-
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::UpdateEmissionSchedule {
+            phases,
+            total_budget,
+        } => update_emission_schedule(deps, env, info, phases, total_budget),
     }
 }
 pub fn set_new_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner_addr: Addr,
 ) -> Result<Response, ContractError> {
@@ -79,13 +114,26 @@ pub fn set_new_owner(
     if sender_raw != config.owner {
         return Err(ContractError::Unauthorized{});
     }
+    let old_new_owner = deps.api.addr_humanize(&new_owner.new_owner_addr)?;
     new_owner.new_owner_addr = deps.api.addr_canonicalize(&new_owner_addr.to_string())?;
     store_new_owner(deps.storage, &new_owner)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_owner",
+        vec![config_change("new_owner_addr", &old_new_owner, &new_owner_addr)?],
+    )?;
+
     Ok(Response::default())
 }
 
-pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let new_owner = read_new_owner(deps.as_ref().storage)?;
     let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
     let mut config = read_config(deps.as_ref().storage)?;
@@ -93,14 +141,24 @@ pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, Co
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_owner = deps.api.addr_humanize(&config.owner)?;
     config.owner = new_owner.new_owner_addr;
     store_config(deps.storage, &config)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "accept_ownership",
+        vec![config_change("owner", &old_owner, &info.sender)?],
+    )?;
+
     Ok(Response::default())
 }
 
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     emission_cap: Option<Decimal256>,
     emission_floor: Option<Decimal256>,
@@ -112,6 +170,7 @@ pub fn update_config(
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_config = config.clone();
     if let Some(emission_cap) = emission_cap {
         config.emission_cap = emission_cap;
     }
@@ -129,11 +188,86 @@ pub fn update_config(
     }
 
     store_config(deps.storage, &config)?;
+
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_config",
+        vec![
+            config_change("emission_cap", &old_config.emission_cap, &config.emission_cap)?,
+            config_change("emission_floor", &old_config.emission_floor, &config.emission_floor)?,
+            config_change(
+                "increment_multiplier",
+                &old_config.increment_multiplier,
+                &config.increment_multiplier,
+            )?,
+            config_change(
+                "decrement_multiplier",
+                &old_config.decrement_multiplier,
+                &config.decrement_multiplier,
+            )?,
+        ],
+    )?;
+
+    Ok(Response::default())
+}
+
+pub fn update_emission_schedule(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    phases: Vec<EmissionPhase>,
+    total_budget: Option<Decimal256>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for (i, phase) in phases.iter().enumerate() {
+        if i > 0 && phase.start_time <= phases[i - 1].start_time {
+            return Err(ContractError::InvalidEmissionSchedule(
+                "phases must be sorted by ascending start_time".to_string(),
+            ));
+        }
+
+        if phase.emission_floor > phase.emission_cap {
+            return Err(ContractError::InvalidEmissionSchedule(
+                "emission_floor must not exceed emission_cap".to_string(),
+            ));
+        }
+
+        if phase.halving_period == Some(0) {
+            return Err(ContractError::InvalidEmissionSchedule(
+                "halving_period must be positive".to_string(),
+            ));
+        }
+    }
+
+    let old_schedule = read_emission_schedule(deps.storage)?;
+    let schedule = EmissionSchedule {
+        phases,
+        total_budget,
+    };
+    store_emission_schedule(deps.storage, &schedule)?;
+
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_emission_schedule",
+        vec![
+            config_change("phases", &old_schedule.phases, &schedule.phases)?,
+            config_change("total_budget", &old_schedule.total_budget, &schedule.total_budget)?,
+        ],
+    )?;
+
     Ok(Response::default())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
         QueryMsg::KptEmissionRate {
@@ -141,13 +275,35 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             target_deposit_rate,
             threshold_deposit_rate,
             current_emission_rate,
+            total_emitted,
         } => to_json_binary(&query_kpt_emission_rate(
             deps,
+            env,
             deposit_rate,
             target_deposit_rate,
             threshold_deposit_rate,
             current_emission_rate,
+            total_emitted.unwrap_or_default(),
+        )?),
+        QueryMsg::EmissionSchedule {} => to_json_binary(&query_emission_schedule(deps)?),
+        QueryMsg::EmissionProjection {
+            current_emission_rate,
+            total_emitted,
+            epoch_period,
+            epoch_duration,
+            epochs,
+        } => to_json_binary(&query_emission_projection(
+            deps,
+            env,
+            current_emission_rate,
+            total_emitted.unwrap_or_default(),
+            epoch_period,
+            epoch_duration,
+            epochs,
         )?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
 
@@ -164,14 +320,26 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     Ok(resp)
 }
 
+fn query_emission_schedule(deps: Deps) -> StdResult<EmissionScheduleResponse> {
+    let schedule = read_emission_schedule(deps.storage)?;
+    Ok(EmissionScheduleResponse {
+        phases: schedule.phases,
+        total_budget: schedule.total_budget,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 fn query_kpt_emission_rate(
     deps: Deps,
+    env: Env,
     deposit_rate: Decimal256,
     target_deposit_rate: Decimal256,
     threshold_deposit_rate: Decimal256,
     current_emission_rate: Decimal256,
+    total_emitted: Decimal256,
 ) -> StdResult<KptEmissionRateResponse> {
     let config: Config = read_config(deps.storage)?;
+    let schedule = read_emission_schedule(deps.storage)?;
 
     let half_dec = Decimal256::one() + Decimal256::one();
     let mid_rate = (threshold_deposit_rate + target_deposit_rate) / half_dec;
@@ -186,13 +354,109 @@ fn query_kpt_emission_rate(
         current_emission_rate
     };
 
-    let emission_rate = if emission_rate > config.emission_cap {
-        config.emission_cap
-    } else if emission_rate < config.emission_floor {
-        config.emission_floor
+    let (emission_cap, emission_floor) =
+        emission_bounds(&config, &schedule, env.block.time.seconds());
+    let emission_rate = if emission_rate > emission_cap {
+        emission_cap
+    } else if emission_rate < emission_floor {
+        emission_floor
     } else {
         emission_rate
     };
 
-    Ok(KptEmissionRateResponse { emission_rate })
+    // Stop emissions once the budget is spent
+    let emission_rate = match schedule.total_budget {
+        Some(total_budget) if total_emitted >= total_budget => Decimal256::zero(),
+        _ => emission_rate,
+    };
+
+    Ok(KptEmissionRateResponse {
+        emission_rate,
+        total_budget: schedule.total_budget,
+    })
+}
+
+fn query_emission_projection(
+    deps: Deps,
+    env: Env,
+    current_emission_rate: Decimal256,
+    total_emitted: Decimal256,
+    epoch_period: u64,
+    epoch_duration: u64,
+    epochs: u32,
+) -> StdResult<EmissionProjectionResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let schedule = read_emission_schedule(deps.storage)?;
+
+    let mut emission_rate = current_emission_rate;
+    let mut total_emitted = total_emitted;
+    let mut start_time = env.block.time.seconds();
+    let mut projection: Vec<EpochEmission> = vec![];
+    for _ in 0..epochs.min(MAX_PROJECTION_EPOCHS) {
+        let (emission_cap, emission_floor) = emission_bounds(&config, &schedule, start_time);
+        emission_rate = emission_rate.min(emission_cap).max(emission_floor);
+
+        let mut emission = emission_rate * Decimal256::from_uint256(epoch_period);
+        if let Some(total_budget) = schedule.total_budget {
+            let remaining = if total_budget > total_emitted {
+                total_budget - total_emitted
+            } else {
+                Decimal256::zero()
+            };
+
+            if remaining.is_zero() {
+                emission_rate = Decimal256::zero();
+            }
+
+            emission = emission.min(remaining);
+        }
+
+        total_emitted += emission;
+        projection.push(EpochEmission {
+            start_time,
+            emission_rate,
+            emission,
+            total_emitted,
+        });
+
+        start_time += epoch_duration;
+    }
+
+    Ok(EmissionProjectionResponse { epochs: projection })
+}
+
+/// Returns the emission cap and floor in effect at the given block time
+fn emission_bounds(
+    config: &Config,
+    schedule: &EmissionSchedule,
+    block_time: u64,
+) -> (Decimal256, Decimal256) {
+    let phase = match schedule
+        .phases
+        .iter()
+        .rev()
+        .find(|phase| phase.start_time <= block_time)
+    {
+        Some(phase) => phase,
+        None => return (config.emission_cap, config.emission_floor),
+    };
+
+    let halvings = match phase.halving_period {
+        Some(halving_period) => (block_time - phase.start_time) / halving_period,
+        None => 0,
+    };
+
+    (
+        halve(phase.emission_cap, halvings),
+        halve(phase.emission_floor, halvings),
+    )
+}
+
+fn halve(value: Decimal256, times: u64) -> Decimal256 {
+    // Decimal256 keeps 18 decimal places, so anything beyond 127 halvings is zero
+    if times >= 128 {
+        return Decimal256::zero();
+    }
+
+    value / Decimal256::from_uint256(Uint256::from(1u128 << times))
 }
@@ -3,10 +3,13 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
-use cosmwasm_storage::{singleton, singleton_read};
+use moneymarket::distribution_model::EmissionPhase;
+use moneymarket::migration::migrate_singleton;
+use cw_storage_plus::Item;
 
-static KEY_CONFIG: &[u8] = b"config";
-const KEY_NEWOWNER: &[u8] = b"newowner";
+const CONFIG: Item<Config> = Item::new("config");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const EMISSION_SCHEDULE: Item<EmissionSchedule> = Item::new("emission_schedule");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -18,13 +21,30 @@ pub struct Config {
 }
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
-    singleton(storage, KEY_CONFIG).save(config)
+    CONFIG.save(storage, config)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct EmissionSchedule {
+    /// Phases sorted by ascending start time
+    pub phases: Vec<EmissionPhase>,
+    pub total_budget: Option<Decimal256>,
+}
+
+pub fn store_emission_schedule(
+    storage: &mut dyn Storage,
+    schedule: &EmissionSchedule,
+) -> StdResult<()> {
+    EMISSION_SCHEDULE.save(storage, schedule)
+}
+
+pub fn read_emission_schedule(storage: &dyn Storage) -> StdResult<EmissionSchedule> {
+    Ok(EMISSION_SCHEDULE.may_load(storage)?.unwrap_or_default())
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct NewOwnerAddr {
@@ -32,9 +52,16 @@ pub struct NewOwnerAddr {
 }
 
 pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
-    singleton(storage, KEY_NEWOWNER).save(data)
+    NEWOWNER.save(storage, data)
 }
 
 pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
-    singleton_read(storage, KEY_NEWOWNER).load()
-}
\ No newline at end of file
+    NEWOWNER.load(storage)
+}
+
+/// Move state written by `cosmwasm_storage` singletons to the `Item`s above,
+/// whether or not cw2 versions were recorded yet; a no-op once moved
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_singleton(storage, &CONFIG)?;
+    migrate_singleton(storage, &NEWOWNER)
+}
@@ -1,10 +1,15 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::error::ContractError;
+use crate::state::{Config, NewOwnerAddr};
 use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::from_json;
 use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
+use cosmwasm_std::{attr, from_json, Api, StdError};
+use cosmwasm_storage::singleton;
+use cw2::{get_contract_version, set_contract_version};
+use moneymarket::config_history::{ConfigChange, ConfigHistoryResponse};
 use moneymarket::distribution_model::{
-    KptEmissionRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    ConfigResponse, EmissionPhase, EmissionProjectionResponse, EmissionScheduleResponse,
+    EpochEmission, ExecuteMsg, InstantiateMsg, KptEmissionRateResponse, MigrateMsg, QueryMsg,
 };
 
 #[test]
@@ -122,6 +127,7 @@ fn proper_emission_rate() {
         target_deposit_rate: Decimal256::percent(10),
         threshold_deposit_rate: Decimal256::percent(5),
         current_emission_rate: Decimal256::from_uint256(99u128),
+        total_emitted: None,
     };
     let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
     let value: KptEmissionRateResponse = from_json(&res).unwrap();
@@ -133,6 +139,7 @@ fn proper_emission_rate() {
         target_deposit_rate: Decimal256::percent(10),
         threshold_deposit_rate: Decimal256::percent(5),
         current_emission_rate: Decimal256::from_uint256(80u128),
+        total_emitted: None,
     };
     let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
     let value: KptEmissionRateResponse = from_json(&res).unwrap();
@@ -144,6 +151,7 @@ fn proper_emission_rate() {
         target_deposit_rate: Decimal256::percent(10),
         threshold_deposit_rate: Decimal256::percent(5),
         current_emission_rate: Decimal256::from_uint256(99u128),
+        total_emitted: None,
     };
     let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
     let value: KptEmissionRateResponse = from_json(&res).unwrap();
@@ -155,6 +163,7 @@ fn proper_emission_rate() {
         target_deposit_rate: Decimal256::percent(10),
         threshold_deposit_rate: Decimal256::percent(5),
         current_emission_rate: Decimal256::from_uint256(99u128),
+        total_emitted: None,
     };
     let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
     let value: KptEmissionRateResponse = from_json(&res).unwrap();
@@ -166,8 +175,299 @@ fn proper_emission_rate() {
         target_deposit_rate: Decimal256::percent(10),
         threshold_deposit_rate: Decimal256::percent(5),
         current_emission_rate: Decimal256::from_uint256(11u128),
+        total_emitted: None,
     };
     let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
     let value: KptEmissionRateResponse = from_json(&res).unwrap();
     assert_eq!("10", &value.emission_rate.to_string());
 }
+
+#[test]
+fn emission_schedule() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::from_uint256(10u64),
+        increment_multiplier: Decimal256::percent(110),
+        decrement_multiplier: Decimal256::percent(90),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let now = mock_env().block.time.seconds();
+    let phases = vec![
+        EmissionPhase {
+            start_time: now - 400,
+            emission_cap: Decimal256::from_uint256(50u64),
+            emission_floor: Decimal256::from_uint256(5u64),
+            halving_period: Some(1000),
+        },
+        EmissionPhase {
+            start_time: now + 10000,
+            emission_cap: Decimal256::from_uint256(20u64),
+            emission_floor: Decimal256::from_uint256(2u64),
+            halving_period: None,
+        },
+    ];
+
+    // Unauthorized err
+    let info = mock_info("addr0000", &[]);
+    let msg = ExecuteMsg::UpdateEmissionSchedule {
+        phases: phases.clone(),
+        total_budget: Some(Decimal256::from_uint256(1000u64)),
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // phases must be sorted
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::UpdateEmissionSchedule {
+        phases: vec![phases[1].clone(), phases[0].clone()],
+        total_budget: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::InvalidEmissionSchedule(_)) => (),
+        _ => panic!("Must return invalid emission schedule error"),
+    }
+
+    let msg = ExecuteMsg::UpdateEmissionSchedule {
+        phases: phases.clone(),
+        total_budget: Some(Decimal256::from_uint256(1000u64)),
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::EmissionSchedule {}).unwrap();
+    let value: EmissionScheduleResponse = from_json(&res).unwrap();
+    assert_eq!(value.phases, phases);
+    assert_eq!(value.total_budget, Some(Decimal256::from_uint256(1000u64)));
+
+    // increment is capped by the phase cap instead of the config cap
+    let query_msg = QueryMsg::KptEmissionRate {
+        deposit_rate: Decimal256::percent(5),
+        target_deposit_rate: Decimal256::percent(10),
+        threshold_deposit_rate: Decimal256::percent(5),
+        current_emission_rate: Decimal256::from_uint256(99u128),
+        total_emitted: Some(Decimal256::from_uint256(100u64)),
+    };
+    let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+    let value: KptEmissionRateResponse = from_json(&res).unwrap();
+    assert_eq!("50", &value.emission_rate.to_string());
+    assert_eq!(value.total_budget, Some(Decimal256::from_uint256(1000u64)));
+
+    // first halving
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(600);
+    let res = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+    let value: KptEmissionRateResponse = from_json(&res).unwrap();
+    assert_eq!("25", &value.emission_rate.to_string());
+
+    // budget exhausted
+    let query_msg = QueryMsg::KptEmissionRate {
+        deposit_rate: Decimal256::percent(5),
+        target_deposit_rate: Decimal256::percent(10),
+        threshold_deposit_rate: Decimal256::percent(5),
+        current_emission_rate: Decimal256::from_uint256(99u128),
+        total_emitted: Some(Decimal256::from_uint256(1000u64)),
+    };
+    let res = query(deps.as_ref(), env, query_msg).unwrap();
+    let value: KptEmissionRateResponse = from_json(&res).unwrap();
+    assert_eq!("0", &value.emission_rate.to_string());
+
+    // next phase
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(10000);
+    let query_msg = QueryMsg::KptEmissionRate {
+        deposit_rate: Decimal256::percent(7),
+        target_deposit_rate: Decimal256::percent(10),
+        threshold_deposit_rate: Decimal256::percent(5),
+        current_emission_rate: Decimal256::from_uint256(25u128),
+        total_emitted: None,
+    };
+    let res = query(deps.as_ref(), env, query_msg).unwrap();
+    let value: KptEmissionRateResponse = from_json(&res).unwrap();
+    assert_eq!("20", &value.emission_rate.to_string());
+
+    let query_msg = QueryMsg::EmissionProjection {
+        current_emission_rate: Decimal256::from_uint256(40u64),
+        total_emitted: Some(Decimal256::from_uint256(900u64)),
+        epoch_period: 2,
+        epoch_duration: 600,
+        epochs: 3,
+    };
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let value: EmissionProjectionResponse = from_json(&res).unwrap();
+    assert_eq!(
+        value.epochs,
+        vec![
+            EpochEmission {
+                start_time: now,
+                emission_rate: Decimal256::from_uint256(40u64),
+                emission: Decimal256::from_uint256(80u64),
+                total_emitted: Decimal256::from_uint256(980u64),
+            },
+            EpochEmission {
+                start_time: now + 600,
+                emission_rate: Decimal256::from_uint256(25u64),
+                emission: Decimal256::from_uint256(20u64),
+                total_emitted: Decimal256::from_uint256(1000u64),
+            },
+            EpochEmission {
+                start_time: now + 1200,
+                emission_rate: Decimal256::zero(),
+                emission: Decimal256::zero(),
+                total_emitted: Decimal256::from_uint256(1000u64),
+            },
+        ]
+    );
+}
+
+#[test]
+fn config_history() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::from_uint256(10u64),
+        increment_multiplier: Decimal256::percent(110),
+        decrement_multiplier: Decimal256::percent(90),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        emission_cap: Some(Decimal256::from_uint256(200u64)),
+        emission_floor: Some(Decimal256::from_uint256(10u64)),
+        increment_multiplier: None,
+        decrement_multiplier: None,
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetOwner {
+        new_owner_addr: "owner0001".to_string(),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AcceptOwnership {};
+    let info = mock_info("owner0001", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    let actions: Vec<(String, String)> = history
+        .entries
+        .iter()
+        .map(|entry| (entry.action.clone(), entry.sender.clone()))
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            ("update_config".to_string(), "owner0000".to_string()),
+            ("set_owner".to_string(), "owner0000".to_string()),
+            ("accept_ownership".to_string(), "owner0001".to_string()),
+        ]
+    );
+    // the unchanged floor is left out
+    assert_eq!(
+        history.entries[0].changes,
+        vec![ConfigChange {
+            field: "emission_cap".to_string(),
+            old_value: "\"100\"".to_string(),
+            new_value: "\"200\"".to_string(),
+        }]
+    );
+    assert_eq!(
+        history.entries[2].changes,
+        vec![ConfigChange {
+            field: "owner".to_string(),
+            old_value: "\"owner0000\"".to_string(),
+            new_value: "\"owner0001\"".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn migrate_legacy_state() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    // state as written before cw2 versions were recorded
+    let owner = deps.api.addr_canonicalize("owner0000").unwrap();
+    singleton(&mut deps.storage, b"config")
+        .save(&Config {
+            owner: owner.clone(),
+            emission_cap: Decimal256::from_uint256(100u64),
+            emission_floor: Decimal256::from_uint256(10u64),
+            increment_multiplier: Decimal256::percent(110),
+            decrement_multiplier: Decimal256::percent(90),
+        })
+        .unwrap();
+    singleton(&mut deps.storage, b"newowner")
+        .save(&NewOwnerAddr {
+            new_owner_addr: owner,
+        })
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("previous_version", "none"),
+            attr("version", env!("CARGO_PKG_VERSION")),
+        ]
+    );
+    assert_eq!(
+        get_contract_version(&deps.storage).unwrap().contract,
+        "crates.io:moneymarket-distribution-model"
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!("owner0000", value.owner.as_str());
+    assert_eq!(Decimal256::from_uint256(100u64), value.emission_cap);
+
+    // the emission schedule is new, there is none to move
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::EmissionSchedule {}).unwrap();
+    let value: EmissionScheduleResponse = from_json(&res).unwrap();
+    assert_eq!(
+        value,
+        EmissionScheduleResponse {
+            phases: vec![],
+            total_budget: None,
+        }
+    );
+
+    // the new owner moved as well
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AcceptOwnership {}).unwrap();
+
+    // contracts of another name are rejected
+    set_contract_version(&mut deps.storage, "crates.io:moneymarket-interest-model", "0.0.1")
+        .unwrap();
+    match migrate(deps.as_mut(), mock_env(), MigrateMsg {}) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            "Cannot migrate crates.io:moneymarket-interest-model to \
+             crates.io:moneymarket-distribution-model"
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
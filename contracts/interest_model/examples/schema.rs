@@ -3,8 +3,10 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use moneymarket::config_history::ConfigHistoryResponse;
 use moneymarket::interest_model::{
-    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    AdaptiveStateResponse, BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
+    MigrateMsg, QueryMsg,
 };
 
 fn main() {
@@ -16,6 +18,9 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(BorrowRateResponse), &out_dir);
+    export_schema(&schema_for!(AdaptiveStateResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
@@ -0,0 +1,62 @@
+use cosmwasm_bignumber::Decimal256;
+
+use crate::state::{AdaptiveRateConfig, AdaptiveState};
+
+/// Rate at target once `state.last_utilization` held until `block_height`
+pub fn compute_rate_at_target(
+    adaptive_rate: &AdaptiveRateConfig,
+    state: &AdaptiveState,
+    block_height: u64,
+) -> Decimal256 {
+    if block_height <= state.last_updated_height {
+        return state.rate_at_target;
+    }
+
+    let passed_blocks = Decimal256::from_uint256(block_height - state.last_updated_height);
+    let change = adaptive_rate.adjustment_speed
+        * utilization_error(adaptive_rate, state.last_utilization)
+        * passed_blocks;
+
+    // dividing on the way down keeps the rate positive however long it lasts
+    let rate_at_target = if state.last_utilization > adaptive_rate.target_utilization {
+        state.rate_at_target * (Decimal256::one() + change)
+    } else {
+        state.rate_at_target / (Decimal256::one() + change)
+    };
+
+    rate_at_target
+        .max(adaptive_rate.min_rate_at_target)
+        .min(adaptive_rate.max_rate_at_target)
+}
+
+/// Borrow rate on the curve going through `rate_at_target` at the target utilization
+pub fn compute_adaptive_borrow_rate(
+    adaptive_rate: &AdaptiveRateConfig,
+    rate_at_target: Decimal256,
+    utilization_ratio: Decimal256,
+) -> Decimal256 {
+    let error = utilization_error(adaptive_rate, utilization_ratio);
+    if utilization_ratio > adaptive_rate.target_utilization {
+        rate_at_target
+            * (Decimal256::one() + (adaptive_rate.curve_steepness - Decimal256::one()) * error)
+    } else {
+        rate_at_target
+            * (Decimal256::one()
+                - (Decimal256::one() - Decimal256::one() / adaptive_rate.curve_steepness) * error)
+    }
+}
+
+/// Distance to the target utilization, normalized to [0, 1]
+fn utilization_error(
+    adaptive_rate: &AdaptiveRateConfig,
+    utilization_ratio: Decimal256,
+) -> Decimal256 {
+    let target = adaptive_rate.target_utilization;
+    let error = if utilization_ratio > target {
+        (utilization_ratio - target) / (Decimal256::one() - target)
+    } else {
+        (target - utilization_ratio) / target
+    };
+
+    error.min(Decimal256::one())
+}
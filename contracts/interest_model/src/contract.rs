@@ -1,30 +1,53 @@
+use crate::adaptive::{compute_adaptive_borrow_rate, compute_rate_at_target};
 use crate::error::ContractError;
-use crate::state::{read_config, store_config, Config, read_new_owner, store_new_owner, NewOwnerAddr};
+use crate::state::{read_config, store_config, Config, read_new_owner, store_new_owner, NewOwnerAddr,
+    read_adaptive_state, store_adaptive_state, AdaptiveRateConfig, AdaptiveState,
+    migrate_legacy_state};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_bignumber::Uint256;
-use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw2::set_contract_version;
+use moneymarket::config_history::{config_change, query_config_history, record_config_change};
 use moneymarket::interest_model::{
-    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    AdaptiveRate, AdaptiveStateResponse, BorrowRateResponse, ConfigResponse, ExecuteMsg,
+    InstantiateMsg, JumpRate, MigrateMsg, QueryMsg,
 };
+use moneymarket::migration::assert_migrate_version;
+
+const CONTRACT_NAME: &str = "crates.io:moneymarket-interest-model";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
-    store_config(
-        deps.storage,
-        &Config {
-            owner: deps.api.addr_canonicalize(&msg.owner)?,
-            base_rate: msg.base_rate,
-            interest_multiplier: msg.interest_multiplier,
-        },
-    )?;
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    if let Some(jump_rate) = &msg.jump_rate {
+        validate_jump_rate(jump_rate)?;
+    }
+
+    let mut config = Config {
+        owner: deps.api.addr_canonicalize(&msg.owner)?,
+        base_rate: msg.base_rate,
+        interest_multiplier: msg.interest_multiplier,
+        jump_rate: msg.jump_rate,
+        adaptive_rate: None,
+    };
+    if let Some(adaptive_rate) = msg.adaptive_rate {
+        config.adaptive_rate =
+            Some(set_adaptive_rate(deps.branch(), &env, &config, adaptive_rate)?);
+    }
+
+    store_config(deps.storage, &config)?;
 
     store_new_owner(deps.storage, &{
         NewOwnerAddr {
@@ -35,10 +58,23 @@ pub fn instantiate(
     Ok(Response::default())
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -46,24 +82,35 @@ pub fn execute(
         ExecuteMsg::UpdateConfig {
             base_rate,
             interest_multiplier,
+            jump_rate,
+            adaptive_rate,
         } => {
             update_config(
                 deps,
+                env,
                 info,
                 base_rate,
                 interest_multiplier,
+                jump_rate,
+                adaptive_rate,
             )
         }
+        ExecuteMsg::UpdateRate {
+            market_balance,
+            total_liabilities,
+            total_reserves,
+        } => update_rate(deps, env, info, market_balance, total_liabilities, total_reserves),
         ExecuteMsg::SetOwner { new_owner_addr } => {
             let api = deps.api;
-            set_new_owner(deps, info, api.addr_validate(&new_owner_addr)?)
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
         }
-        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
     }
 }
 
 pub fn set_new_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner_addr: Addr,
 ) -> Result<Response, ContractError> {
@@ -73,13 +120,26 @@ pub fn set_new_owner(
     if sender_raw != config.owner {
         return Err(ContractError::Unauthorized{});
     }
+    let old_new_owner = deps.api.addr_humanize(&new_owner.new_owner_addr)?;
     new_owner.new_owner_addr = deps.api.addr_canonicalize(&new_owner_addr.to_string())?;
     store_new_owner(deps.storage, &new_owner)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_owner",
+        vec![config_change("new_owner_addr", &old_new_owner, &new_owner_addr)?],
+    )?;
+
     Ok(Response::default())
 }
 
-pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let new_owner = read_new_owner(deps.as_ref().storage)?;
     let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
     let mut config = read_config(deps.as_ref().storage)?;
@@ -87,23 +147,36 @@ pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, Co
         return Err(ContractError::Unauthorized{});
     }
 
+    let old_owner = deps.api.addr_humanize(&config.owner)?;
     config.owner = new_owner.new_owner_addr;
     store_config(deps.storage, &config)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "accept_ownership",
+        vec![config_change("owner", &old_owner, &info.sender)?],
+    )?;
+
     Ok(Response::default())
 }
 
 pub fn update_config(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     base_rate: Option<Decimal256>,
     interest_multiplier: Option<Decimal256>,
+    jump_rate: Option<JumpRate>,
+    adaptive_rate: Option<AdaptiveRate>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_config = query_config(deps.as_ref())?;
     if let Some(base_rate) = base_rate {
         config.base_rate = base_rate;
     }
@@ -112,12 +185,140 @@ pub fn update_config(
         config.interest_multiplier = interest_multiplier;
     }
 
+    if let Some(jump_rate) = jump_rate {
+        validate_jump_rate(&jump_rate)?;
+        config.jump_rate = Some(jump_rate);
+    }
+
+    if let Some(adaptive_rate) = adaptive_rate {
+        config.adaptive_rate =
+            Some(set_adaptive_rate(deps.branch(), &env, &config, adaptive_rate)?);
+    }
+
     store_config(deps.storage, &config)?;
+
+    let new_config = query_config(deps.as_ref())?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_config",
+        vec![
+            config_change("base_rate", &old_config.base_rate, &new_config.base_rate)?,
+            config_change(
+                "interest_multiplier",
+                &old_config.interest_multiplier,
+                &new_config.interest_multiplier,
+            )?,
+            config_change("jump_rate", &old_config.jump_rate, &new_config.jump_rate)?,
+            config_change(
+                "adaptive_rate",
+                &old_config.adaptive_rate,
+                &new_config.adaptive_rate,
+            )?,
+        ],
+    )?;
+
     Ok(Response::default())
 }
 
+/// Validate the adaptive rate and (re)start its state. The rate at target starts
+/// from the previous adaptive state, or from the static curve when switching models.
+fn set_adaptive_rate(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    adaptive_rate: AdaptiveRate,
+) -> Result<AdaptiveRateConfig, ContractError> {
+    if adaptive_rate.target_utilization.is_zero()
+        || adaptive_rate.target_utilization >= Decimal256::one()
+    {
+        return Err(ContractError::InvalidAdaptiveRate(
+            "target utilization must be between 0 and 1".to_string(),
+        ));
+    }
+    if adaptive_rate.curve_steepness < Decimal256::one() {
+        return Err(ContractError::InvalidAdaptiveRate(
+            "curve steepness must be at least 1".to_string(),
+        ));
+    }
+    if adaptive_rate.min_rate_at_target > adaptive_rate.max_rate_at_target {
+        return Err(ContractError::InvalidAdaptiveRate(
+            "min rate at target is greater than max rate at target".to_string(),
+        ));
+    }
+
+    let adaptive_rate = AdaptiveRateConfig {
+        market_contract: deps.api.addr_canonicalize(&adaptive_rate.market_contract)?,
+        target_utilization: adaptive_rate.target_utilization,
+        adjustment_speed: adaptive_rate.adjustment_speed,
+        curve_steepness: adaptive_rate.curve_steepness,
+        min_rate_at_target: adaptive_rate.min_rate_at_target,
+        max_rate_at_target: adaptive_rate.max_rate_at_target,
+    };
+
+    let rate_at_target = match (&config.adaptive_rate, read_adaptive_state(deps.storage)) {
+        (Some(_), Ok(state)) => state.rate_at_target,
+        _ => compute_static_borrow_rate(config, adaptive_rate.target_utilization),
+    };
+    store_adaptive_state(
+        deps.storage,
+        &AdaptiveState {
+            rate_at_target: rate_at_target
+                .max(adaptive_rate.min_rate_at_target)
+                .min(adaptive_rate.max_rate_at_target),
+            last_utilization: adaptive_rate.target_utilization,
+            last_updated_height: env.block.height,
+        },
+    )?;
+
+    Ok(adaptive_rate)
+}
+
+/// Called by the market on every interest computation, records the utilization
+/// the adaptive rate moves with until the next call
+pub fn update_rate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_balance: Uint256,
+    total_liabilities: Decimal256,
+    total_reserves: Decimal256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let adaptive_rate = match config.adaptive_rate {
+        Some(adaptive_rate) => adaptive_rate,
+        None => return Ok(Response::default()),
+    };
+
+    if deps.api.addr_canonicalize(info.sender.as_str())? != adaptive_rate.market_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut state: AdaptiveState = read_adaptive_state(deps.storage)?;
+    state.rate_at_target = compute_rate_at_target(&adaptive_rate, &state, env.block.height);
+    state.last_utilization =
+        compute_utilization_ratio(market_balance, total_liabilities, total_reserves);
+    state.last_updated_height = env.block.height;
+    store_adaptive_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_rate"),
+        attr("rate_at_target", state.rate_at_target.to_string()),
+        attr("utilization", state.last_utilization.to_string()),
+    ]))
+}
+
+fn validate_jump_rate(jump_rate: &JumpRate) -> Result<(), ContractError> {
+    if jump_rate.kink > Decimal256::one() {
+        return Err(ContractError::InvalidKink {});
+    }
+
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
         QueryMsg::BorrowRate {
@@ -126,10 +327,15 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             total_reserves,
         } => to_json_binary(&query_borrow_rate(
             deps,
+            env,
             market_balance,
             total_liabilities,
             total_reserves,
         )?),
+        QueryMsg::AdaptiveState {} => to_json_binary(&query_adaptive_state(deps, env)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
 
@@ -139,30 +345,90 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         owner: deps.api.addr_humanize(&state.owner)?.to_string(),
         base_rate: state.base_rate,
         interest_multiplier: state.interest_multiplier,
+        jump_rate: state.jump_rate,
+        adaptive_rate: match state.adaptive_rate {
+            Some(adaptive_rate) => Some(AdaptiveRate {
+                market_contract: deps
+                    .api
+                    .addr_humanize(&adaptive_rate.market_contract)?
+                    .to_string(),
+                target_utilization: adaptive_rate.target_utilization,
+                adjustment_speed: adaptive_rate.adjustment_speed,
+                curve_steepness: adaptive_rate.curve_steepness,
+                min_rate_at_target: adaptive_rate.min_rate_at_target,
+                max_rate_at_target: adaptive_rate.max_rate_at_target,
+            }),
+            None => None,
+        },
     };
 
     Ok(resp)
 }
 
+/// Adaptive state as of the current block
+fn query_adaptive_state(deps: Deps, env: Env) -> StdResult<AdaptiveStateResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let mut state: AdaptiveState = read_adaptive_state(deps.storage)?;
+    if let Some(adaptive_rate) = config.adaptive_rate {
+        state.rate_at_target = compute_rate_at_target(&adaptive_rate, &state, env.block.height);
+    }
+
+    Ok(AdaptiveStateResponse {
+        rate_at_target: state.rate_at_target,
+        last_utilization: state.last_utilization,
+        last_updated_height: state.last_updated_height,
+    })
+}
+
 fn query_borrow_rate(
     deps: Deps,
+    env: Env,
     market_balance: Uint256,
     total_liabilities: Decimal256,
     total_reserves: Decimal256,
 ) -> StdResult<BorrowRateResponse> {
     let config: Config = read_config(deps.storage)?;
 
+    let utilization_ratio =
+        compute_utilization_ratio(market_balance, total_liabilities, total_reserves);
+
+    let rate = match &config.adaptive_rate {
+        Some(adaptive_rate) => {
+            let state: AdaptiveState = read_adaptive_state(deps.storage)?;
+            let rate_at_target = compute_rate_at_target(adaptive_rate, &state, env.block.height);
+            compute_adaptive_borrow_rate(adaptive_rate, rate_at_target, utilization_ratio)
+        }
+        None => compute_static_borrow_rate(&config, utilization_ratio),
+    };
+
+    Ok(BorrowRateResponse { rate })
+}
+
+fn compute_utilization_ratio(
+    market_balance: Uint256,
+    total_liabilities: Decimal256,
+    total_reserves: Decimal256,
+) -> Decimal256 {
     // ignore decimal parts
     let total_value_in_market =
         Decimal256::from_uint256(market_balance) + total_liabilities - total_reserves;
 
-    let utilization_ratio = if total_value_in_market.is_zero() {
+    if total_value_in_market.is_zero() {
         Decimal256::zero()
     } else {
         total_liabilities / total_value_in_market
-    };
+    }
+}
 
-    Ok(BorrowRateResponse {
-        rate: utilization_ratio * config.interest_multiplier + config.base_rate,
-    })
+/// Borrow rate of the linear model, or of the jump rate model when configured
+fn compute_static_borrow_rate(config: &Config, utilization_ratio: Decimal256) -> Decimal256 {
+    match &config.jump_rate {
+        Some(jump_rate) if utilization_ratio > jump_rate.kink => {
+            // rate at the kink, then the jump slope on the excess utilization
+            jump_rate.kink * config.interest_multiplier
+                + config.base_rate
+                + (utilization_ratio - jump_rate.kink) * jump_rate.jump_multiplier
+        }
+        _ => utilization_ratio * config.interest_multiplier + config.base_rate,
+    }
 }
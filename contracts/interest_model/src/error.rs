@@ -11,4 +11,10 @@ pub enum ContractError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("Kink must be a utilization ratio between 0 and 1")]
+    InvalidKink {},
+
+    #[error("Invalid adaptive rate: {0}")]
+    InvalidAdaptiveRate(String),
 }
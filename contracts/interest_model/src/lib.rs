@@ -1,3 +1,4 @@
+pub mod adaptive;
 pub mod contract;
 pub mod error;
 pub mod state;
@@ -3,16 +3,41 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_std::{CanonicalAddr, StdResult, Storage};
-use cosmwasm_storage::{singleton, singleton_read};
+use cw_storage_plus::Item;
+use moneymarket::interest_model::JumpRate;
+use moneymarket::migration::migrate_singleton;
 
-static KEY_CONFIG: &[u8] = b"config";
-static KEY_NEWOWNER: &[u8] = b"newowner";
+const CONFIG: Item<Config> = Item::new("config");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const ADAPTIVE_STATE: Item<AdaptiveState> = Item::new("adaptive_state");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub owner: CanonicalAddr,
     pub base_rate: Decimal256,
     pub interest_multiplier: Decimal256,
+    #[serde(default)]
+    pub jump_rate: Option<JumpRate>,
+    #[serde(default)]
+    pub adaptive_rate: Option<AdaptiveRateConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdaptiveRateConfig {
+    pub market_contract: CanonicalAddr,
+    pub target_utilization: Decimal256,
+    pub adjustment_speed: Decimal256,
+    pub curve_steepness: Decimal256,
+    pub min_rate_at_target: Decimal256,
+    pub max_rate_at_target: Decimal256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdaptiveState {
+    pub rate_at_target: Decimal256,
+    /// Utilization since `last_updated_height`
+    pub last_utilization: Decimal256,
+    pub last_updated_height: u64,
 }
 
 
@@ -22,18 +47,33 @@ pub struct NewOwnerAddr {
 }
 
 pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
-    singleton(storage, KEY_NEWOWNER).save(data)
+    NEWOWNER.save(storage, data)
 }
 
 pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
-    singleton_read(storage, KEY_NEWOWNER).load()
+    NEWOWNER.load(storage)
 }
 
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
-    singleton(storage, KEY_CONFIG).save(config)
+    CONFIG.save(storage, config)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
+}
+
+pub fn store_adaptive_state(storage: &mut dyn Storage, state: &AdaptiveState) -> StdResult<()> {
+    ADAPTIVE_STATE.save(storage, state)
+}
+
+pub fn read_adaptive_state(storage: &dyn Storage) -> StdResult<AdaptiveState> {
+    ADAPTIVE_STATE.load(storage)
+}
+
+/// Move state written by `cosmwasm_storage` singletons to the `Item`s above,
+/// whether or not cw2 versions were recorded yet; a no-op once moved
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_singleton(storage, &CONFIG)?;
+    migrate_singleton(storage, &NEWOWNER)
 }
@@ -1,10 +1,15 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::error::ContractError;
+use crate::state::{Config, NewOwnerAddr};
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::from_json;
 use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
+use cosmwasm_std::{attr, from_json, Api, StdError};
+use cosmwasm_storage::singleton;
+use cw2::{get_contract_version, set_contract_version};
+use moneymarket::config_history::{ConfigChange, ConfigHistoryEntryResponse, ConfigHistoryResponse};
 use moneymarket::interest_model::{
-    BorrowRateResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg,
+    AdaptiveRate, AdaptiveStateResponse, BorrowRateResponse, ConfigResponse, ExecuteMsg,
+    InstantiateMsg, JumpRate, MigrateMsg, QueryMsg,
 };
 
 #[test]
@@ -15,6 +20,8 @@ fn proper_initialization() {
         owner: "owner0000".to_string(),
         base_rate: Decimal256::percent(10),
         interest_multiplier: Decimal256::percent(10),
+        jump_rate: None,
+        adaptive_rate: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -59,6 +66,8 @@ fn update_config() {
         owner: "owner0000".to_string(),
         base_rate: Decimal256::percent(10),
         interest_multiplier: Decimal256::percent(10),
+        jump_rate: None,
+        adaptive_rate: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -69,6 +78,8 @@ fn update_config() {
     let msg = ExecuteMsg::UpdateConfig {
         base_rate: None,
         interest_multiplier: None,
+        jump_rate: None,
+        adaptive_rate: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -97,6 +108,8 @@ fn update_config() {
     let msg = ExecuteMsg::UpdateConfig {
         base_rate: Some(Decimal256::percent(1)),
         interest_multiplier: Some(Decimal256::percent(1)),
+        jump_rate: None,
+        adaptive_rate: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -105,3 +118,325 @@ fn update_config() {
         _ => panic!("Must return unauthorized error"),
     }
 }
+
+#[test]
+fn jump_rate() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_rate: Decimal256::percent(2),
+        interest_multiplier: Decimal256::percent(10),
+        jump_rate: Some(JumpRate {
+            kink: Decimal256::percent(80),
+            jump_multiplier: Decimal256::from_uint256(2u128),
+        }),
+        adaptive_rate: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // utilization_ratio = 0.5, below the kink
+    // borrow_rate = 0.5 * 0.1 + 0.02
+    let query_msg = QueryMsg::BorrowRate {
+        market_balance: Uint256::from(500000u128),
+        total_liabilities: Decimal256::from_uint256(500000u128),
+        total_reserves: Decimal256::zero(),
+    };
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let value: BorrowRateResponse = from_json(&res).unwrap();
+    assert_eq!("0.07", &value.rate.to_string());
+
+    // utilization_ratio = 0.9, above the kink
+    // borrow_rate = 0.8 * 0.1 + 0.02 + (0.9 - 0.8) * 2
+    let query_msg = QueryMsg::BorrowRate {
+        market_balance: Uint256::from(100000u128),
+        total_liabilities: Decimal256::from_uint256(900000u128),
+        total_reserves: Decimal256::zero(),
+    };
+    let res = query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap();
+    let value: BorrowRateResponse = from_json(&res).unwrap();
+    assert_eq!("0.3", &value.rate.to_string());
+
+    // kink above 1 is rejected
+    let info = mock_info("owner0000", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        base_rate: None,
+        interest_multiplier: None,
+        jump_rate: Some(JumpRate {
+            kink: Decimal256::percent(101),
+            jump_multiplier: Decimal256::one(),
+        }),
+        adaptive_rate: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::InvalidKink {}) => (),
+        _ => panic!("Must return invalid kink error"),
+    }
+
+    // kink at 1 falls back to the linear model
+    let msg = ExecuteMsg::UpdateConfig {
+        base_rate: None,
+        interest_multiplier: None,
+        jump_rate: Some(JumpRate {
+            kink: Decimal256::one(),
+            jump_multiplier: Decimal256::one(),
+        }),
+        adaptive_rate: None,
+    };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let value: BorrowRateResponse = from_json(&res).unwrap();
+    assert_eq!("0.11", &value.rate.to_string());
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(
+        Some(JumpRate {
+            kink: Decimal256::one(),
+            jump_multiplier: Decimal256::one(),
+        }),
+        value.jump_rate
+    );
+}
+
+#[test]
+fn adaptive_rate() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+    let mut env = mock_env();
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_rate: Decimal256::percent(2),
+        interest_multiplier: Decimal256::percent(10),
+        jump_rate: None,
+        adaptive_rate: Some(AdaptiveRate {
+            market_contract: "market0000".to_string(),
+            target_utilization: Decimal256::percent(80),
+            adjustment_speed: Decimal256::percent(1),
+            curve_steepness: Decimal256::from_uint256(4u128),
+            min_rate_at_target: Decimal256::percent(1),
+            max_rate_at_target: Decimal256::one(),
+        }),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // rate at target starts from the static curve: 0.8 * 0.1 + 0.02
+    let borrow_rate = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, env, liabilities: u128| {
+        let query_msg = QueryMsg::BorrowRate {
+            market_balance: Uint256::from(1000000u128 - liabilities),
+            total_liabilities: Decimal256::from_uint256(liabilities),
+            total_reserves: Decimal256::zero(),
+        };
+        let res = query(deps.as_ref(), env, query_msg).unwrap();
+        let value: BorrowRateResponse = from_json(&res).unwrap();
+        value.rate.to_string()
+    };
+    assert_eq!("0.1", borrow_rate(&deps, env.clone(), 800000));
+    // utilization 0.9: 0.1 * (1 + 3 * 0.5)
+    assert_eq!("0.25", borrow_rate(&deps, env.clone(), 900000));
+    // utilization 0.4: 0.1 * (1 - 0.75 * 0.5)
+    assert_eq!("0.0625", borrow_rate(&deps, env.clone(), 400000));
+
+    // only the market can move the rate
+    let update_msg = |liabilities: u128| ExecuteMsg::UpdateRate {
+        market_balance: Uint256::from(1000000u128 - liabilities),
+        total_liabilities: Decimal256::from_uint256(liabilities),
+        total_reserves: Decimal256::zero(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), update_msg(900000));
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("Must return unauthorized error"),
+    }
+
+    // utilization stays above target for 10 blocks: 0.1 * (1 + 0.01 * 0.5 * 10)
+    execute(deps.as_mut(), env.clone(), mock_info("market0000", &[]), update_msg(900000)).unwrap();
+    env.block.height += 10;
+    assert_eq!("0.105", borrow_rate(&deps, env.clone(), 800000));
+
+    // then below target for 20 blocks: 0.105 / (1 + 0.01 * 0.5 * 20)
+    execute(deps.as_mut(), env.clone(), mock_info("market0000", &[]), update_msg(400000)).unwrap();
+    env.block.height += 20;
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::AdaptiveState {}).unwrap();
+    let value: AdaptiveStateResponse = from_json(&res).unwrap();
+    assert_eq!("0.095454545454545454", value.rate_at_target.to_string());
+    assert_eq!(Decimal256::percent(40), value.last_utilization);
+
+    // bounded by min_rate_at_target
+    env.block.height += 100000;
+    assert_eq!("0.01", borrow_rate(&deps, env.clone(), 800000));
+
+    // invalid target utilization
+    let msg = ExecuteMsg::UpdateConfig {
+        base_rate: None,
+        interest_multiplier: None,
+        jump_rate: None,
+        adaptive_rate: Some(AdaptiveRate {
+            market_contract: "market0000".to_string(),
+            target_utilization: Decimal256::one(),
+            adjustment_speed: Decimal256::percent(1),
+            curve_steepness: Decimal256::from_uint256(4u128),
+            min_rate_at_target: Decimal256::percent(1),
+            max_rate_at_target: Decimal256::one(),
+        }),
+    };
+    let res = execute(deps.as_mut(), env, mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::InvalidAdaptiveRate(_)) => (),
+        _ => panic!("Must return invalid adaptive rate error"),
+    }
+}
+
+#[test]
+fn config_history() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        base_rate: Decimal256::percent(10),
+        interest_multiplier: Decimal256::percent(10),
+        jump_rate: None,
+        adaptive_rate: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        base_rate: Some(Decimal256::percent(2)),
+        interest_multiplier: Some(Decimal256::percent(10)),
+        jump_rate: Some(JumpRate {
+            kink: Decimal256::percent(80),
+            jump_multiplier: Decimal256::from_uint256(2u64),
+        }),
+        adaptive_rate: None,
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetOwner {
+        new_owner_addr: "owner0001".to_string(),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    assert_eq!(
+        history.entries,
+        vec![
+            ConfigHistoryEntryResponse {
+                id: 0,
+                action: "update_config".to_string(),
+                sender: "owner0000".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![
+                    ConfigChange {
+                        field: "base_rate".to_string(),
+                        old_value: "\"0.1\"".to_string(),
+                        new_value: "\"0.02\"".to_string(),
+                    },
+                    ConfigChange {
+                        field: "jump_rate".to_string(),
+                        old_value: "null".to_string(),
+                        new_value: "{\"kink\":\"0.8\",\"jump_multiplier\":\"2\"}".to_string(),
+                    },
+                ],
+            },
+            ConfigHistoryEntryResponse {
+                id: 1,
+                action: "set_owner".to_string(),
+                sender: "owner0000".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![ConfigChange {
+                    field: "new_owner_addr".to_string(),
+                    old_value: "\"owner0000\"".to_string(),
+                    new_value: "\"owner0001\"".to_string(),
+                }],
+            },
+        ]
+    );
+}
+
+#[test]
+fn migrate_legacy_state() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    // state as written before cw2 versions were recorded
+    let owner = deps.api.addr_canonicalize("owner0000").unwrap();
+    singleton(&mut deps.storage, b"config")
+        .save(&Config {
+            owner: owner.clone(),
+            base_rate: Decimal256::percent(10),
+            interest_multiplier: Decimal256::percent(10),
+            jump_rate: None,
+            adaptive_rate: None,
+        })
+        .unwrap();
+    singleton(&mut deps.storage, b"newowner")
+        .save(&NewOwnerAddr {
+            new_owner_addr: owner,
+        })
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("previous_version", "none"),
+            attr("version", env!("CARGO_PKG_VERSION")),
+        ]
+    );
+    assert_eq!(
+        get_contract_version(&deps.storage).unwrap().contract,
+        "crates.io:moneymarket-interest-model"
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!("owner0000", value.owner.as_str());
+    assert_eq!("0.1", &value.base_rate.to_string());
+
+    // the new owner moved as well
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::AcceptOwnership {}).unwrap();
+
+    // migrating the current layout again keeps the state
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(
+        res.attributes[1],
+        attr("previous_version", env!("CARGO_PKG_VERSION"))
+    );
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!("owner0000", value.owner.as_str());
+
+    // downgrades are rejected
+    set_contract_version(&mut deps.storage, "crates.io:moneymarket-interest-model", "99.0.0")
+        .unwrap();
+    match migrate(deps.as_mut(), mock_env(), MigrateMsg {}) {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            format!(
+                "Cannot migrate from version 99.0.0 to older version {}",
+                env!("CARGO_PKG_VERSION")
+            )
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
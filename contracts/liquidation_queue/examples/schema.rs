@@ -3,10 +3,13 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use moneymarket::config_history::ConfigHistoryResponse;
 use moneymarket::liquidation_queue::{
     BidPoolResponse, BidPoolsResponse, BidResponse, BidsResponse, CollateralInfoResponse,
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, LiquidationAmountResponse, QueryMsg,
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, LiquidationAmountResponse, MigrateMsg,
+    QueryMsg,
 };
+use moneymarket::pause::PauseStatusResponse;
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -17,6 +20,7 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(BidResponse), &out_dir);
@@ -25,4 +29,6 @@ fn main() {
     export_schema(&schema_for!(BidPoolResponse), &out_dir);
     export_schema(&schema_for!(BidPoolsResponse), &out_dir);
     export_schema(&schema_for!(CollateralInfoResponse), &out_dir);
+    export_schema(&schema_for!(PauseStatusResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
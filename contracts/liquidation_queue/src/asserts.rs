@@ -10,6 +10,15 @@ pub fn assert_activate_status(
     available_bids: Uint256,
     bid_threshold: Uint256,
 ) -> StdResult<()> {
+    if let Some(expires_at) = bid.expires_at {
+        if expires_at <= env.block.time.seconds() {
+            return Err(StdError::generic_err(format!(
+                "Bid expired at {}",
+                expires_at
+            )));
+        }
+    }
+
     match bid.wait_end {
         Some(wait_end) => {
             if available_bids < bid_threshold {
@@ -27,6 +36,15 @@ pub fn assert_activate_status(
     Ok(())
 }
 
+pub fn assert_expires_at(expires_at: Option<u64>, env: &Env) -> StdResult<()> {
+    if let Some(expires_at) = expires_at {
+        if expires_at <= env.block.time.seconds() {
+            return Err(StdError::generic_err("Bid expiry must be in the future"));
+        }
+    }
+    Ok(())
+}
+
 pub fn assert_withdraw_amount(
     withdraw_amount: Option<Uint256>,
     withdrawable_amount: Uint256,
@@ -79,4 +97,13 @@ pub fn assert_safe_ratio(safe_ratio: Decimal256) -> StdResult<()> {
         ));
     }
     Ok(())
+}
+
+pub fn assert_close_factor(close_factor: Decimal256) -> StdResult<()> {
+    if close_factor.is_zero() || close_factor > Decimal256::one() {
+        return Err(StdError::generic_err(
+            "The close factor must be greater than zero and not greater than one",
+        ));
+    }
+    Ok(())
 }
\ No newline at end of file
@@ -1,20 +1,32 @@
-use crate::asserts::{assert_activate_status, assert_withdraw_amount};
+use crate::asserts::{assert_activate_status, assert_expires_at, assert_withdraw_amount};
+use crate::contract::SWAP_CLAIM_OPERATION;
 use crate::error::ContractError;
-use crate::querier::query_collateral_whitelist_info;
+use crate::querier::{query_collateral_whitelist_info, query_frozen_liquidation_policy};
 use crate::state::{
     pop_bid_idx, read_bid, read_bid_pool, read_bids_by_user, read_collateral_info, read_config,
-    read_epoch_scale_sum, read_or_create_bid_pool, read_total_bids, remove_bid, store_bid,
-    store_bid_pool, store_epoch_scale_sum, store_total_bids, Bid, BidPool, CollateralInfo, Config,
+    read_epoch_scale_sum, read_expired_bids, read_or_create_bid_pool, read_total_bids, remove_bid,
+    store_bid, store_bid_pool, store_epoch_scale_sum, store_expired_refund, store_pending_swap,
+    store_total_bids, take_expired_refunds, take_pending_swap, Bid, BidPool, CollateralInfo,
+    Config, ExpiredBidRefund, PendingSwap,
 };
 use bigint::U256;
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    attr, to_json_binary, BankMsg, CanonicalAddr, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response,
-    StdError, StdResult, Storage, Uint128, WasmMsg,
+    attr, to_json_binary, BankMsg, CanonicalAddr, Coin, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
-use moneymarket::oracle::PriceResponse;
-use moneymarket::querier::{deduct_tax, query_price, TimeConstraints};
+use moneymarket::liquidation_queue::SwapToStable;
+use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::overseer::FrozenLiquidationPolicy;
+use moneymarket::querier::{deduct_tax, query_balance, query_checked_prices, TimeConstraints};
+use moneymarket::swap_ext::SwapExecteMsg;
+
+const MAX_CLEANUP_LIMIT: u32 = 100;
+const DEFAULT_CLEANUP_LIMIT: u32 = 30;
+/// Expired bids a liquidation takes out of its bid pools, the rest are left
+/// to `CleanupExpiredBids`
+pub(crate) const MAX_LIQUIDATION_EXPIRIES: usize = 30;
 
 /// Stable asset is submitted to create a bid record. If available bids for the collateral is under
 /// the threshold, the bid is activated. Bids are not used for liquidations until activated
@@ -24,7 +36,9 @@ pub fn submit_bid(
     info: MessageInfo,
     collateral_token: String,
     premium_slot: u8,
+    expires_at: Option<u64>,
 ) -> Result<Response, ContractError> {
+    assert_expires_at(expires_at, &env)?;
     let config: Config = read_config(deps.storage)?;
     let collateral_token_raw: CanonicalAddr = deps.api.addr_canonicalize(&collateral_token)?;
     let collateral_info: CollateralInfo =
@@ -53,15 +67,47 @@ pub fn submit_bid(
         })??
         .into();
 
+    let bid_idx = create_bid(
+        deps.storage,
+        &env,
+        &config,
+        &collateral_info,
+        bidder_raw,
+        premium_slot,
+        amount,
+        expires_at,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "submit_bid"),
+        attr("bid_idx", bid_idx),
+        attr("amount", amount),
+    ]))
+}
+
+/// Creates a bid in a bid pool. The bid is activated right away if the
+/// available bids for the collateral are under the threshold
+#[allow(clippy::too_many_arguments)]
+fn create_bid(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    collateral_info: &CollateralInfo,
+    bidder: CanonicalAddr,
+    premium_slot: u8,
+    amount: Uint256,
+    expires_at: Option<u64>,
+) -> StdResult<Uint128> {
+    let collateral_token_raw = &collateral_info.collateral_token;
+
     // read or create bid_pool, make sure slot is valid
-    let mut bid_pool: BidPool =
-        read_or_create_bid_pool(deps.storage, &collateral_info, premium_slot)?;
+    let mut bid_pool: BidPool = read_or_create_bid_pool(storage, collateral_info, premium_slot)?;
 
     // create bid object
-    let bid_idx: Uint128 = pop_bid_idx(deps.storage)?;
+    let bid_idx: Uint128 = pop_bid_idx(storage)?;
     let mut bid = Bid {
         idx: bid_idx,
-        bidder: bidder_raw,
+        bidder,
         collateral_token: collateral_token_raw.clone(),
         product_snapshot: Decimal256::one(),
         amount,
@@ -71,33 +117,30 @@ pub fn submit_bid(
         wait_end: None,
         epoch_snapshot: Uint128::zero(),
         scale_snapshot: Uint128::zero(),
+        expires_at,
     };
 
     // if available bids is lower than bid_threshold, directly activate bid
     let available_bids: Uint256 =
-        read_total_bids(deps.storage, &collateral_token_raw).unwrap_or_default();
+        read_total_bids(storage, collateral_token_raw).unwrap_or_default();
     if available_bids < collateral_info.bid_threshold {
         // update bid and bid pool, add new share and pool indexes to bid
         process_bid_activation(&mut bid, &mut bid_pool, amount);
 
         // store bid_pool
-        store_bid_pool(deps.storage, &collateral_token_raw, premium_slot, &bid_pool)?;
+        store_bid_pool(storage, collateral_token_raw, premium_slot, &bid_pool)?;
 
         // increase total bid amount
-        store_total_bids(deps.storage, &collateral_token_raw, available_bids + amount)?;
+        store_total_bids(storage, collateral_token_raw, available_bids + amount)?;
     } else {
         // calculate wait_end from current time
         bid.wait_end = Some(env.block.time.plus_seconds(config.waiting_period).seconds());
     };
 
     // save to storage
-    store_bid(deps.storage, bid_idx, &bid)?;
+    store_bid(storage, bid_idx, &bid)?;
 
-    Ok(Response::new().add_attributes(vec![
-        attr("action", "submit_bid"),
-        attr("bid_idx", bid_idx),
-        attr("amount", amount),
-    ]))
+    Ok(bid_idx)
 }
 
 /// After bids are submitted, need to execute the activation after wait_period expires
@@ -290,7 +333,8 @@ pub fn retract_bid(
 
 /// Overseer executes the liquidation providing a whitelisted collateral.
 /// This operation returns a repay_amount based on the available bids on each
-/// premium slot, consuming bids from lowest to higher premium slots
+/// premium slot, consuming bids from lowest to higher premium slots. With a
+/// `borrower`, the repay amount settles their loan on the market at `repay_address`
 #[allow(clippy::too_many_arguments)]
 pub fn execute_liquidation(
     deps: DepsMut,
@@ -301,6 +345,7 @@ pub fn execute_liquidation(
     fee_address: String,
     collateral_token: String,
     amount: Uint256,
+    borrower: Option<String>,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
     let collateral_token_raw = deps.api.addr_canonicalize(&collateral_token)?;
@@ -322,26 +367,57 @@ pub fn execute_liquidation(
         )));
     }
 
+    // price with the same oracle checks the overseer used to start the liquidation
+    let frozen_liquidation_policy =
+        query_frozen_liquidation_policy(&deps.querier, overseer.to_string())?;
     let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
-    let price: PriceResponse = query_price(
+    let price = query_checked_prices(
         deps.as_ref(),
         oracle_contract,
-        collateral_token.to_string(),
-        config.stable_denom.clone(),
+        vec![collateral_token.to_string()],
         Some(TimeConstraints {
             block_time: env.block.time.seconds(),
             valid_timeframe: config.price_timeframe,
         }),
-    )?;
+        frozen_liquidation_policy == FrozenLiquidationPolicy::UseEmaPrice,
+    )?
+    .remove(0);
 
     let mut remaining_collateral_to_liquidate = amount;
     let mut repay_amount = Uint256::zero();
+    let mut expired_amount = Uint256::zero();
+    let mut expiry_limit = MAX_LIQUIDATION_EXPIRIES;
     let mut filled: bool = false;
     for slot in 0..collateral_info.max_slot + 1 {
         let mut bid_pool: BidPool = match read_bid_pool(deps.storage, &collateral_token_raw, slot) {
             Ok(bid_pool) => bid_pool,
             Err(_) => continue,
         };
+
+        // expired bids stop absorbing liquidations, their refunds are paid on cleanup
+        let (pool_expired_amount, pool_expired_refunds) = expire_pool_bids(
+            deps.storage,
+            &mut bid_pool,
+            &collateral_token_raw,
+            slot,
+            env.block.time.seconds(),
+            expiry_limit,
+        )?;
+        if !pool_expired_refunds.is_empty() {
+            expiry_limit -= pool_expired_refunds.len();
+            expired_amount += pool_expired_amount;
+            for (bidder, stable_amount, collateral_amount) in pool_expired_refunds.into_iter() {
+                store_expired_refund(
+                    deps.storage,
+                    &collateral_token_raw,
+                    &bidder,
+                    stable_amount,
+                    collateral_amount,
+                )?;
+            }
+            store_bid_pool(deps.storage, &collateral_token_raw, slot, &bid_pool)?;
+        }
+
         if bid_pool.total_bid_amount.is_zero() {
             continue;
         };
@@ -378,7 +454,7 @@ pub fn execute_liquidation(
     store_total_bids(
         deps.storage,
         &collateral_token_raw,
-        available_bids - repay_amount,
+        available_bids - expired_amount - repay_amount,
     )?;
 
     let bid_fee = repay_amount * config.bid_fee;
@@ -386,16 +462,24 @@ pub fn execute_liquidation(
     let repay_amount = repay_amount - bid_fee - liquidator_fee;
 
 
-    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Bank(BankMsg::Send {
-        to_address: repay_address,
-        amount: vec![deduct_tax(
-            deps.as_ref(),
-            Coin {
-                denom: config.stable_denom.clone(),
-                amount: repay_amount.into(),
-            },
-        )?],
-    })];
+    let repay_coin = deduct_tax(
+        deps.as_ref(),
+        Coin {
+            denom: config.stable_denom.clone(),
+            amount: repay_amount.into(),
+        },
+    )?;
+    let mut messages: Vec<CosmosMsg> = vec![match borrower {
+        Some(borrower) => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: repay_address,
+            funds: vec![repay_coin],
+            msg: to_json_binary(&MarketExecuteMsg::RepayStableFromLiquidation { borrower })?,
+        }),
+        None => CosmosMsg::Bank(BankMsg::Send {
+            to_address: repay_address,
+            amount: vec![repay_coin],
+        }),
+    }];
 
     if !bid_fee.is_zero() {
         messages.push(CosmosMsg::Bank(BankMsg::Send {
@@ -422,7 +506,6 @@ pub fn execute_liquidation(
         }));
     }
 
-
     Ok(Response::new().add_messages(messages).add_attributes(vec![
         attr("action", "execute_bid"),
         attr("stable_denom", config.stable_denom),
@@ -435,14 +518,25 @@ pub fn execute_liquidation(
 }
 
 /// Bid owner can claim their share of the liquidated collateral until the
-/// bid is consumed
+/// bid is consumed. The collateral can be swapped to the stable denom, which
+/// is sent to the bid owner or submitted as a new bid
 pub fn claim_liquidations(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     collateral_token: String,
     bids_idx: Option<Vec<Uint128>>,
+    swap_to_stable: Option<SwapToStable>,
 ) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if let Some(swap_to_stable) = swap_to_stable.as_ref() {
+        if swap_to_stable.min_return.is_zero() {
+            return Err(ContractError::ZeroMinReturn {});
+        }
+        if config.swap_contract.is_none() {
+            return Err(ContractError::SwapContractNotSet {});
+        }
+    }
     let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
     let collateral_token_raw = deps.api.addr_canonicalize(&collateral_token)?;
 
@@ -456,6 +550,8 @@ pub fn claim_liquidations(
     };
 
     let mut claim_amount = Uint256::zero();
+    // premium slot and expiry of the claimed bids
+    let mut claim_slot: Option<(u8, Option<u64>)> = None;
     for bid in bids.into_iter() {
         if bid.bidder != sender_raw {
             return Err(ContractError::Std(StdError::generic_err("unauthorized")));
@@ -469,6 +565,14 @@ pub fn claim_liquidations(
             // bid not activated
             continue;
         }
+        if swap_to_stable.as_ref().is_some_and(|swap| swap.resubmit_bid) {
+            if claim_slot.is_some_and(|slot| slot != (bid.premium_slot, bid.expires_at)) {
+                return Err(ContractError::ResubmitSlotMismatch {});
+            }
+            // the resubmitted bid keeps the expiry of the claimed ones
+            assert_expires_at(bid.expires_at, &env)?;
+        }
+        claim_slot = Some((bid.premium_slot, bid.expires_at));
 
         let mut bid_pool: BidPool =
             read_bid_pool(deps.storage, &bid.collateral_token, bid.premium_slot)?;
@@ -516,25 +620,319 @@ pub fn claim_liquidations(
         }
     }
 
-    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut messages: Vec<SubMsg> = vec![];
     if !claim_amount.is_zero() {
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: collateral_token.clone(),
-            funds: vec![],
-            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: info.sender.to_string(),
-                amount: claim_amount.into(),
-            })?,
-        }));
+        match swap_to_stable {
+            Some(swap_to_stable) => {
+                let swap_contract = config
+                    .swap_contract
+                    .ok_or(ContractError::SwapContractNotSet {})?;
+
+                // the proceeds are checked and forwarded by the swap callback
+                let prev_balance = query_balance(
+                    deps.as_ref(),
+                    env.contract.address.clone(),
+                    config.stable_denom.clone(),
+                )?;
+                store_pending_swap(
+                    deps.storage,
+                    &PendingSwap {
+                        bidder: sender_raw,
+                        collateral_token: collateral_token_raw,
+                        collateral_amount: claim_amount,
+                        prev_balance,
+                        min_return: swap_to_stable.min_return,
+                        resubmit_slot: claim_slot.filter(|_| swap_to_stable.resubmit_bid),
+                    },
+                )?;
+
+                // the swap contract takes the collateral with an allowance,
+                // whatever is left of it is revoked by the swap callback
+                let swap_contract = deps.api.addr_humanize(&swap_contract)?.to_string();
+                messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: collateral_token.clone(),
+                    funds: vec![],
+                    msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                        spender: swap_contract.clone(),
+                        amount: claim_amount.into(),
+                        expires: None,
+                    })?,
+                })));
+                messages.push(SubMsg::reply_on_success(
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr: swap_contract,
+                        funds: vec![],
+                        msg: to_json_binary(&SwapExecteMsg::SwapDenom {
+                            from_coin: Coin {
+                                denom: collateral_token.clone(),
+                                amount: claim_amount.into(),
+                            },
+                            target_denom: config.stable_denom,
+                            to_address: Some(env.contract.address.to_string()),
+                        })?,
+                    }),
+                    SWAP_CLAIM_OPERATION,
+                ));
+            }
+            None => {
+                messages.push(SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: collateral_token.clone(),
+                    funds: vec![],
+                    msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: info.sender.to_string(),
+                        amount: claim_amount.into(),
+                    })?,
+                })));
+            }
+        }
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
+    Ok(Response::new().add_submessages(messages).add_attributes(vec![
         attr("action", "claim_liquidations"),
         attr("collateral_token", collateral_token),
         attr("collateral_amount", claim_amount),
     ]))
 }
 
+/// Anyone can remove the expired bids of a collateral from its bid pools.
+/// The remaining bids and the claimable collateral go back to the bidders,
+/// starting with the refunds of the bids expired by liquidations
+pub fn cleanup_expired_bids(
+    deps: DepsMut,
+    env: Env,
+    collateral_token: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let collateral_token_raw = deps.api.addr_canonicalize(&collateral_token)?;
+    let collateral_info: CollateralInfo =
+        read_collateral_info(deps.storage, &collateral_token_raw)?;
+    let limit = limit
+        .unwrap_or(DEFAULT_CLEANUP_LIMIT)
+        .min(MAX_CLEANUP_LIMIT) as usize;
+
+    let mut expired_amount = Uint256::zero();
+    let mut expired_refunds: Vec<ExpiredBidRefund> =
+        take_expired_refunds(deps.storage, &collateral_token_raw, limit)?;
+    let pending_refunds = expired_refunds.len();
+    for slot in 0..collateral_info.max_slot + 1 {
+        if expired_refunds.len() >= limit {
+            break;
+        }
+        let mut bid_pool: BidPool = match read_bid_pool(deps.storage, &collateral_token_raw, slot) {
+            Ok(bid_pool) => bid_pool,
+            Err(_) => continue,
+        };
+
+        let (pool_expired_amount, pool_expired_refunds) = expire_pool_bids(
+            deps.storage,
+            &mut bid_pool,
+            &collateral_token_raw,
+            slot,
+            env.block.time.seconds(),
+            limit - expired_refunds.len(),
+        )?;
+        if pool_expired_refunds.is_empty() {
+            continue;
+        }
+
+        expired_amount += pool_expired_amount;
+        expired_refunds.extend(pool_expired_refunds);
+        store_bid_pool(deps.storage, &collateral_token_raw, slot, &bid_pool)?;
+    }
+
+    let available_bids: Uint256 =
+        read_total_bids(deps.storage, &collateral_token_raw).unwrap_or_default();
+    store_total_bids(
+        deps.storage,
+        &collateral_token_raw,
+        available_bids - expired_amount,
+    )?;
+
+    let expired_bids = expired_refunds.len() - pending_refunds;
+    let messages = expired_refund_messages(
+        deps.as_ref(),
+        &config.stable_denom,
+        &collateral_token,
+        expired_refunds,
+    )?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "cleanup_expired_bids"),
+        attr("collateral_token", collateral_token),
+        attr("expired_bids", expired_bids.to_string()),
+    ]))
+}
+
+/// Removes up to `limit` bids of a bid pool that expired at or before `time`.
+/// Returns the amount taken out of the pool, which only holds active bids,
+/// and the refunds owed to the bidders
+fn expire_pool_bids(
+    storage: &mut dyn Storage,
+    bid_pool: &mut BidPool,
+    collateral_token: &CanonicalAddr,
+    premium_slot: u8,
+    time: u64,
+    limit: usize,
+) -> StdResult<(Uint256, Vec<ExpiredBidRefund>)> {
+    let expired_bids = read_expired_bids(storage, collateral_token, premium_slot, time, limit)?;
+
+    let mut expired_amount = Uint256::zero();
+    let mut refunds: Vec<ExpiredBidRefund> = vec![];
+    for bid in expired_bids.into_iter() {
+        remove_bid(storage, bid.idx)?;
+
+        if bid.wait_end.is_some() {
+            // waiting bid amount is refunded as a whole
+            refunds.push((bid.bidder, bid.amount, Uint256::zero()));
+            continue;
+        }
+
+        // calculate spent and reward until this moment
+        let (remaining_bid, residue_bid) = calculate_remaining_bid(&bid, bid_pool)?;
+        let (liquidated_collateral, residue_collateral) =
+            calculate_liquidated_collateral(storage, &bid)?;
+
+        // stack residues, will give it to next claimer if it becomes bigger than 1.0
+        bid_pool.residue_collateral += residue_collateral;
+        bid_pool.residue_bid += residue_bid;
+        bid_pool.total_bid_amount = bid_pool.total_bid_amount - remaining_bid;
+        expired_amount += remaining_bid;
+
+        refunds.push((
+            bid.bidder,
+            remaining_bid,
+            bid.pending_liquidated_collateral + liquidated_collateral,
+        ));
+    }
+
+    Ok((expired_amount, refunds))
+}
+
+/// Sends the remaining stable and the claimable collateral of expired bids
+/// back to their bidders
+fn expired_refund_messages(
+    deps: Deps,
+    stable_denom: &str,
+    collateral_token: &str,
+    refunds: Vec<ExpiredBidRefund>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (bidder, stable_amount, collateral_amount) in refunds.into_iter() {
+        let bidder = deps.api.addr_humanize(&bidder)?.to_string();
+        if !stable_amount.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: bidder.clone(),
+                amount: vec![deduct_tax(
+                    deps,
+                    Coin {
+                        denom: stable_denom.to_string(),
+                        amount: stable_amount.into(),
+                    },
+                )?],
+            }));
+        }
+        if !collateral_amount.is_zero() {
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: collateral_token.to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: bidder,
+                    amount: collateral_amount.into(),
+                })?,
+            }));
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Swap callback of a claim. The stable returned by the swap has to cover the
+/// minimum return; it is sent to the bid owner or submitted as a new bid
+pub fn swap_claim_hook(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let pending_swap: PendingSwap = take_pending_swap(deps.storage)?;
+
+    let balance = query_balance(
+        deps.as_ref(),
+        env.contract.address.clone(),
+        config.stable_denom.clone(),
+    )?;
+    let swap_return = if balance > pending_swap.prev_balance {
+        balance - pending_swap.prev_balance
+    } else {
+        Uint256::zero()
+    };
+    if swap_return < pending_swap.min_return {
+        return Err(ContractError::SwapReturnTooLow(
+            swap_return,
+            pending_swap.min_return,
+        ));
+    }
+
+    let bidder = deps.api.addr_humanize(&pending_swap.bidder)?;
+    let swap_contract = config
+        .swap_contract
+        .as_ref()
+        .ok_or(ContractError::SwapContractNotSet {})?;
+    let revoke_allowance = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps
+            .api
+            .addr_humanize(&pending_swap.collateral_token)?
+            .to_string(),
+        funds: vec![],
+        msg: to_json_binary(&Cw20ExecuteMsg::DecreaseAllowance {
+            spender: deps.api.addr_humanize(swap_contract)?.to_string(),
+            amount: pending_swap.collateral_amount.into(),
+            expires: None,
+        })?,
+    });
+    match pending_swap.resubmit_slot {
+        Some((premium_slot, expires_at)) if !swap_return.is_zero() => {
+            let collateral_info: CollateralInfo =
+                read_collateral_info(deps.storage, &pending_swap.collateral_token)?;
+            let bid_idx = create_bid(
+                deps.storage,
+                &env,
+                &config,
+                &collateral_info,
+                pending_swap.bidder,
+                premium_slot,
+                swap_return,
+                expires_at,
+            )?;
+
+            Ok(Response::new().add_message(revoke_allowance).add_attributes(vec![
+                attr("action", "resubmit_bid"),
+                attr("bidder", bidder),
+                attr("bid_idx", bid_idx),
+                attr("amount", swap_return),
+            ]))
+        }
+        _ => {
+            let mut messages: Vec<CosmosMsg> = vec![revoke_allowance];
+            if !swap_return.is_zero() {
+                messages.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: bidder.to_string(),
+                    amount: vec![deduct_tax(
+                        deps.as_ref(),
+                        Coin {
+                            denom: config.stable_denom,
+                            amount: swap_return.into(),
+                        },
+                    )?],
+                }));
+            }
+
+            Ok(Response::new().add_messages(messages).add_attributes(vec![
+                attr("action", "swap_claim"),
+                attr("bidder", bidder),
+                attr("amount", swap_return),
+            ]))
+        }
+    }
+}
+
 fn process_bid_activation(bid: &mut Bid, bid_pool: &mut BidPool, amount: Uint256) {
     bid.product_snapshot = bid_pool.product_snapshot;
     bid.sum_snapshot = bid_pool.sum_snapshot;
@@ -651,6 +1049,29 @@ pub(crate) fn calculate_remaining_bid(
     Ok((remaining_bid, bid_residue))
 }
 
+/// Remaining amount of the active bids among the first `limit` bids of a bid
+/// pool that expired at or before `time`, which a liquidation takes out of the
+/// pool, and the number of expired bids read
+pub(crate) fn calculate_expired_bid_amount(
+    storage: &dyn Storage,
+    bid_pool: &BidPool,
+    collateral_token: &CanonicalAddr,
+    premium_slot: u8,
+    time: u64,
+    limit: usize,
+) -> StdResult<(Uint256, usize)> {
+    let expired_bids = read_expired_bids(storage, collateral_token, premium_slot, time, limit)?;
+    let expired_bid_amount = expired_bids
+        .iter()
+        .filter(|bid| bid.wait_end.is_none())
+        .try_fold(Uint256::zero(), |acc, bid| {
+            let (remaining_bid, _) = calculate_remaining_bid(bid, bid_pool)?;
+            Ok::<_, StdError>(acc + remaining_bid)
+        })?;
+
+    Ok((expired_bid_amount, expired_bids.len()))
+}
+
 pub(crate) fn calculate_liquidated_collateral(
     storage: &dyn Storage,
     bid: &Bid,
@@ -1,24 +1,49 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
-use crate::asserts::{assert_fees, assert_max_slot, assert_max_slot_premium, assert_safe_ratio};
-use crate::bid::{activate_bids, claim_liquidations, execute_liquidation, retract_bid, submit_bid};
+use crate::asserts::{
+    assert_close_factor, assert_fees, assert_max_slot, assert_max_slot_premium, assert_safe_ratio,
+};
+use crate::bid::{
+    activate_bids, claim_liquidations, cleanup_expired_bids, execute_liquidation, retract_bid,
+    submit_bid, swap_claim_hook,
+};
+use crate::pause::{assert_not_paused, query_pause_status, set_paused, update_guardian};
 use crate::querier::query_collateral_whitelist_info;
 use crate::query::{
     query_bid, query_bid_pool, query_bid_pools, query_bids_by_user, query_collateral_info,
     query_config, query_liquidation_amount,
 };
 use crate::state::{
-    read_collateral_info, read_config, store_collateral_info, store_config, CollateralInfo, Config, read_new_owner, store_new_owner, NewOwnerAddr,
+    migrate_legacy_bids, migrate_legacy_state, read_collateral_info, read_config,
+    store_collateral_info, store_config, CollateralInfo, Config, read_new_owner, store_new_owner,
+    NewOwnerAddr,
 };
 
 use crate::error::ContractError;
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    from_json, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Addr,
+    attr, from_json, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdError, StdResult, Addr,
 };
 use cw20::Cw20ReceiveMsg;
-use moneymarket::liquidation_queue::{Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw2::set_contract_version;
+use moneymarket::common::optional_addr_validate;
+use moneymarket::config_history::{config_change, query_config_history, record_config_change};
+use moneymarket::liquidation_queue::{
+    Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+};
+use moneymarket::migration::assert_migrate_version;
+use moneymarket::pause::PausableOperation;
+
+const CONTRACT_NAME: &str = "crates.io:moneymarket-liquidation-queue";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub const SWAP_CLAIM_OPERATION: u64 = 1u64;
+
+// settings for bid migration pages
+const MAX_MIGRATION_LIMIT: u32 = 100;
+const DEFAULT_MIGRATION_LIMIT: u32 = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -27,6 +52,8 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     assert_fees(msg.liquidator_fee + msg.bid_fee)?;
     assert_safe_ratio(msg.safe_ratio)?;
 
@@ -43,6 +70,8 @@ pub fn instantiate(
             price_timeframe: msg.price_timeframe,
             waiting_period: msg.waiting_period,
             overseer: deps.api.addr_canonicalize(&msg.overseer)?,
+            close_factor: None,
+            swap_contract: None,
         },
     )?;
 
@@ -55,6 +84,19 @@ pub fn instantiate(
     Ok(Response::new())
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -73,8 +115,11 @@ pub fn execute(
             price_timeframe,
             waiting_period,
             overseer,
+            close_factor,
+            swap_contract,
         } => update_config(
             deps,
+            env,
             info,
             oracle_contract,
             safe_ratio,
@@ -84,12 +129,19 @@ pub fn execute(
             price_timeframe,
             waiting_period,
             overseer,
+            close_factor,
+            swap_contract,
         ),
         ExecuteMsg::SetOwner { new_owner_addr } => {
             let api = deps.api;
-            set_new_owner(deps, info, api.addr_validate(&new_owner_addr)?)
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
         }
-        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::UpdateGuardian { guardian } => {
+            let api = deps.api;
+            update_guardian(deps, env, info, optional_addr_validate(api, guardian)?)
+        }
+        ExecuteMsg::SetPaused { operation, paused } => set_paused(deps, info, operation, paused),
         ExecuteMsg::WhitelistCollateral {
             collateral_token,
             bid_threshold,
@@ -97,6 +149,7 @@ pub fn execute(
             premium_rate_per_slot,
         } => whitelist_collateral(
             deps,
+            env,
             info,
             collateral_token,
             bid_threshold,
@@ -107,11 +160,15 @@ pub fn execute(
             collateral_token,
             bid_threshold,
             max_slot,
-        } => update_collateral_info(deps, info, collateral_token, bid_threshold, max_slot),
+        } => update_collateral_info(deps, env, info, collateral_token, bid_threshold, max_slot),
         ExecuteMsg::SubmitBid {
             collateral_token,
             premium_slot,
-        } => submit_bid(deps, env, info, collateral_token, premium_slot),
+            expires_at,
+        } => {
+            assert_not_paused(deps.storage, PausableOperation::BidSubmissions)?;
+            submit_bid(deps, env, info, collateral_token, premium_slot, expires_at)
+        }
         ExecuteMsg::ActivateBids {
             collateral_token,
             bids_idx,
@@ -120,17 +177,30 @@ pub fn execute(
         ExecuteMsg::ClaimLiquidations {
             collateral_token,
             bids_idx,
-        } => claim_liquidations(deps, env, info, collateral_token, bids_idx),
+            swap_to_stable,
+        } => {
+            if swap_to_stable.as_ref().is_some_and(|swap| swap.resubmit_bid) {
+                assert_not_paused(deps.storage, PausableOperation::BidSubmissions)?;
+            }
+            claim_liquidations(deps, env, info, collateral_token, bids_idx, swap_to_stable)
+        }
+        ExecuteMsg::CleanupExpiredBids {
+            collateral_token,
+            limit,
+        } => cleanup_expired_bids(deps, env, collateral_token, limit),
+        ExecuteMsg::MigrateBids { limit } => execute_migrate_bids(deps, limit),
         ExecuteMsg::ExecuteBid {
             liquidator,
             repay_address,
             fee_address,
             collateral_denom,
             amount,
+            borrower,
         } => {
+            assert_not_paused(deps.storage, PausableOperation::Liquidations)?;
             let sender = deps
                 .api
-                .addr_canonicalize(&info.sender.as_str())?
+                .addr_canonicalize(info.sender.as_str())?
                 .to_string();
             let collateral_token = collateral_denom;
             execute_liquidation(
@@ -142,11 +212,21 @@ pub fn execute(
                 fee_address,
                 collateral_token,
                 amount,
+                borrower,
             )
         }
     }
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        // Claimed collateral swap callback
+        SWAP_CLAIM_OPERATION => swap_claim_hook(deps, env),
+        _ => Err(ContractError::InvalidReplyId {}),
+    }
+}
+
 pub fn receive_cw20(
     deps: DepsMut,
     env: Env,
@@ -160,7 +240,9 @@ pub fn receive_cw20(
             liquidator,
             repay_address,
             fee_address,
+            borrower,
         }) => {
+            assert_not_paused(deps.storage, PausableOperation::Liquidations)?;
             let collateral_token = contract_addr.to_string();
             let repay_address = repay_address.unwrap_or_else(|| cw20_msg.sender.clone());
             let fee_address = fee_address.unwrap_or_else(|| cw20_msg.sender.clone());
@@ -173,6 +255,7 @@ pub fn receive_cw20(
                 fee_address,
                 collateral_token,
                 cw20_msg.amount.into(),
+                borrower,
             )
         }
         _ => Err(ContractError::Std(StdError::generic_err(
@@ -184,6 +267,7 @@ pub fn receive_cw20(
 
 pub fn set_new_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner_addr: Addr,
 ) -> Result<Response, ContractError> {
@@ -193,13 +277,26 @@ pub fn set_new_owner(
     if sender_raw != config.owner {
         return Err(ContractError::Unauthorized{});
     }
+    let old_new_owner = deps.api.addr_humanize(&new_owner.new_owner_addr)?;
     new_owner.new_owner_addr = deps.api.addr_canonicalize(&new_owner_addr.to_string())?;
     store_new_owner(deps.storage, &new_owner)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_owner",
+        vec![config_change("new_owner_addr", &old_new_owner, &new_owner_addr)?],
+    )?;
+
     Ok(Response::default())
 }
 
-pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let new_owner = read_new_owner(deps.as_ref().storage)?;
     let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
     let mut config = read_config(deps.as_ref().storage)?;
@@ -207,15 +304,37 @@ pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, Co
         return Err(ContractError::Unauthorized{});
     }
 
+    let old_owner = deps.api.addr_humanize(&config.owner)?;
     config.owner = new_owner.new_owner_addr;
     store_config(deps.storage, &config)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "accept_ownership",
+        vec![config_change("owner", &old_owner, &info.sender)?],
+    )?;
+
     Ok(Response::default())
 }
 
+/// Re-save a page of bids of the legacy layout, see `migrate_legacy_bids`
+pub fn execute_migrate_bids(deps: DepsMut, limit: Option<u32>) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_MIGRATION_LIMIT).min(MAX_MIGRATION_LIMIT) as usize;
+    let migrated = migrate_legacy_bids(deps.storage, limit)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate_bids"),
+        attr("migrated", migrated.to_string()),
+        attr("done", (migrated < limit).to_string()),
+    ]))
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     oracle_contract: Option<String>,
     safe_ratio: Option<Decimal256>,
@@ -225,12 +344,15 @@ pub fn update_config(
     price_timeframe: Option<u64>,
     waiting_period: Option<u64>,
     overseer: Option<String>,
+    close_factor: Option<Option<Decimal256>>,
+    swap_contract: Option<Option<String>>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
     if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_config = query_config(deps.as_ref())?;
     if let Some(oracle_contract) = oracle_contract {
         config.oracle_contract = deps.api.addr_canonicalize(&oracle_contract)?;
     }
@@ -266,12 +388,72 @@ pub fn update_config(
         config.overseer = deps.api.addr_canonicalize(&overseer)?;
     }
 
+    if let Some(close_factor) = close_factor {
+        if let Some(close_factor) = close_factor {
+            assert_close_factor(close_factor)?;
+        }
+        config.close_factor = close_factor;
+    }
+
+    if let Some(swap_contract) = swap_contract {
+        config.swap_contract = match swap_contract {
+            Some(swap_contract) => Some(deps.api.addr_canonicalize(&swap_contract)?),
+            None => None,
+        };
+    }
+
     store_config(deps.storage, &config)?;
+
+    let new_config = query_config(deps.as_ref())?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_config",
+        vec![
+            config_change(
+                "oracle_contract",
+                &old_config.oracle_contract,
+                &new_config.oracle_contract,
+            )?,
+            config_change("safe_ratio", &old_config.safe_ratio, &new_config.safe_ratio)?,
+            config_change("bid_fee", &old_config.bid_fee, &new_config.bid_fee)?,
+            config_change(
+                "liquidator_fee",
+                &old_config.liquidator_fee,
+                &new_config.liquidator_fee,
+            )?,
+            config_change(
+                "liquidation_threshold",
+                &old_config.liquidation_threshold,
+                &new_config.liquidation_threshold,
+            )?,
+            config_change(
+                "price_timeframe",
+                &old_config.price_timeframe,
+                &new_config.price_timeframe,
+            )?,
+            config_change(
+                "waiting_period",
+                &old_config.waiting_period,
+                &new_config.waiting_period,
+            )?,
+            config_change("overseer", &old_config.overseer, &new_config.overseer)?,
+            config_change("close_factor", &old_config.close_factor, &new_config.close_factor)?,
+            config_change(
+                "swap_contract",
+                &old_config.swap_contract,
+                &new_config.swap_contract,
+            )?,
+        ],
+    )?;
+
     Ok(Response::new())
 }
 
 pub fn whitelist_collateral(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     collateral_token: String,
     bid_threshold: Uint256,
@@ -293,7 +475,7 @@ pub fn whitelist_collateral(
 
     // check if the colalteral is whitelisted in overseer
     let overseer = deps.api.addr_humanize(&config.overseer)?;
-    query_collateral_whitelist_info(&deps.querier, overseer.to_string(), collateral_token)
+    query_collateral_whitelist_info(&deps.querier, overseer.to_string(), collateral_token.clone())
         .map_err(|_| {
             ContractError::Std(StdError::generic_err(
                 "This collateral is not whitelisted in kryptonite overseer",
@@ -316,11 +498,25 @@ pub fn whitelist_collateral(
         },
     )?;
 
+    let field = |name: &str| format!("{}.{}", collateral_token, name);
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "whitelist_collateral",
+        vec![
+            config_change(&field("bid_threshold"), &None, &Some(bid_threshold))?,
+            config_change(&field("max_slot"), &None, &Some(max_slot))?,
+            config_change(&field("premium_rate_per_slot"), &None, &Some(premium_rate_per_slot))?,
+        ],
+    )?;
+
     Ok(Response::new().add_attribute("action", "whitelist_collateral"))
 }
 
 pub fn update_collateral_info(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     collateral_token: String,
     bid_threshold: Option<Uint256>,
@@ -335,6 +531,7 @@ pub fn update_collateral_info(
     // update collateral info
     let mut collateral_info: CollateralInfo =
         read_collateral_info(deps.storage, &collateral_token_raw)?;
+    let old_info = collateral_info.clone();
 
     if let Some(bid_threshold) = bid_threshold {
         collateral_info.bid_threshold = bid_threshold;
@@ -350,11 +547,27 @@ pub fn update_collateral_info(
     // save collateral info
     store_collateral_info(deps.storage, &collateral_token_raw, &collateral_info)?;
 
+    let field = |name: &str| format!("{}.{}", collateral_token, name);
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_collateral_info",
+        vec![
+            config_change(
+                &field("bid_threshold"),
+                &old_info.bid_threshold,
+                &collateral_info.bid_threshold,
+            )?,
+            config_change(&field("max_slot"), &old_info.max_slot, &collateral_info.max_slot)?,
+        ],
+    )?;
+
     Ok(Response::new().add_attribute("action", "update_collateral_info"))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
         QueryMsg::LiquidationAmount {
@@ -364,6 +577,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             collateral_prices,
         } => to_json_binary(&query_liquidation_amount(
             deps,
+            env.block.time.seconds(),
             borrow_amount,
             borrow_limit,
             collaterals,
@@ -399,5 +613,9 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             start_after,
             limit,
         )?),
+        QueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(deps)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
@@ -1,4 +1,7 @@
+use cosmwasm_bignumber::Uint256;
 use cosmwasm_std::{Addr, OverflowError, StdError};
+use moneymarket::pause::PauseError;
+use moneymarket::querier::PriceQueryError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -35,4 +38,48 @@ pub enum ContractError {
 
     #[error("Functionality deprecated")]
     Deprecated {},
+
+    #[error("Liquidation queue {0} are paused")]
+    Paused(String),
+
+    #[error("Liquidation queue {0} cannot be paused")]
+    NotPausable(String),
+
+    #[error("Invalid reply ID")]
+    InvalidReplyId {},
+
+    #[error("Swap contract is not set")]
+    SwapContractNotSet {},
+
+    #[error("Minimum swap return must be greater than zero")]
+    ZeroMinReturn {},
+
+    #[error("Swap returned {0}, less than the minimum return {1}")]
+    SwapReturnTooLow(Uint256, Uint256),
+
+    #[error("Claimed bids must share a premium slot and an expiry to resubmit the proceeds")]
+    ResubmitSlotMismatch {},
+
+    #[error("Collateral price is frozen by the oracle circuit breaker: {0}")]
+    PriceFrozen(String),
+}
+
+impl From<PriceQueryError> for ContractError {
+    fn from(err: PriceQueryError) -> Self {
+        match err {
+            PriceQueryError::CircuitBreaker(reason) => ContractError::PriceFrozen(reason),
+            PriceQueryError::Std(err) => ContractError::Std(err),
+        }
+    }
+}
+
+impl From<PauseError> for ContractError {
+    fn from(err: PauseError) -> Self {
+        match err {
+            PauseError::Unauthorized => ContractError::Unauthorized {},
+            PauseError::NotPausable(operation) => ContractError::NotPausable(operation),
+            PauseError::Paused(operation) => ContractError::Paused(operation),
+            PauseError::Std(err) => ContractError::Std(err),
+        }
+    }
 }
@@ -2,6 +2,7 @@ mod asserts;
 mod bid;
 pub mod contract;
 pub mod error;
+mod pause;
 mod querier;
 mod query;
 mod state;
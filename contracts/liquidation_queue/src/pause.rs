@@ -0,0 +1,51 @@
+use cosmwasm_std::{Addr, CanonicalAddr, DepsMut, Env, MessageInfo, Response, StdResult, Storage};
+
+use moneymarket::pause::{self, PausableOperation};
+
+use crate::error::ContractError;
+use crate::state::read_config;
+
+pub use moneymarket::pause::query_pause_status;
+
+/// Operations gated by the liquidation queue
+const PAUSABLE_OPERATIONS: [PausableOperation; 2] = [
+    PausableOperation::BidSubmissions,
+    PausableOperation::Liquidations,
+];
+
+fn read_owner(storage: &dyn Storage) -> StdResult<CanonicalAddr> {
+    Ok(read_config(storage)?.owner)
+}
+
+pub fn update_guardian(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    guardian: Option<Addr>,
+) -> Result<Response, ContractError> {
+    Ok(pause::update_guardian(
+        deps, env, info, guardian, read_owner,
+    )?)
+}
+
+pub fn set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    operation: PausableOperation,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    Ok(pause::set_paused(
+        deps,
+        info,
+        &PAUSABLE_OPERATIONS,
+        operation,
+        paused,
+    )?)
+}
+
+pub fn assert_not_paused(
+    storage: &dyn Storage,
+    operation: PausableOperation,
+) -> Result<(), ContractError> {
+    Ok(pause::assert_not_paused(storage, operation)?)
+}
@@ -1,7 +1,17 @@
 use cosmwasm_std::{to_json_binary, QuerierWrapper, QueryRequest, StdResult, WasmQuery};
 use moneymarket::overseer::{
-    QueryMsg as OverseerQueryMsg, WhitelistResponse, WhitelistResponseElem,
+    FrozenLiquidationPolicy, QueryMsg as OverseerQueryMsg, WhitelistResponse,
+    WhitelistResponseElem,
 };
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Only the frozen liquidation policy is read; other fields of the overseer config are ignored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OverseerPolicyResponse {
+    #[serde(default)]
+    pub frozen_liquidation_policy: FrozenLiquidationPolicy,
+}
 
 pub fn query_collateral_whitelist_info(
     querier: &QuerierWrapper,
@@ -20,3 +30,17 @@ pub fn query_collateral_whitelist_info(
 
     Ok(whitelist_res.elems[0].clone())
 }
+
+/// How the overseer prices liquidations while the oracle circuit breaker is tripped
+pub fn query_frozen_liquidation_policy(
+    querier: &QuerierWrapper,
+    overseer: String,
+) -> StdResult<FrozenLiquidationPolicy> {
+    let overseer_config: OverseerPolicyResponse =
+        querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: overseer,
+            msg: to_json_binary(&OverseerQueryMsg::Config {})?,
+        }))?;
+
+    Ok(overseer_config.frozen_liquidation_policy)
+}
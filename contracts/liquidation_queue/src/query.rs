@@ -1,4 +1,7 @@
-use crate::bid::{calculate_liquidated_collateral, calculate_remaining_bid};
+use crate::bid::{
+    calculate_expired_bid_amount, calculate_liquidated_collateral, calculate_remaining_bid,
+    MAX_LIQUIDATION_EXPIRIES,
+};
 use crate::querier::query_collateral_whitelist_info;
 use crate::state::{
     read_bid, read_bid_pool, read_bid_pools, read_bids_by_user, read_collateral_info, read_config,
@@ -27,6 +30,12 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         price_timeframe: config.price_timeframe,
         waiting_period: config.waiting_period,
         overseer: deps.api.addr_humanize(&config.overseer)?.to_string(),
+        close_factor: config.close_factor,
+        swap_contract: config
+            .swap_contract
+            .map(|swap_contract| deps.api.addr_humanize(&swap_contract))
+            .transpose()?
+            .map(|swap_contract| swap_contract.to_string()),
     };
 
     Ok(resp)
@@ -37,9 +46,11 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
 /// we find the intersaction between f(x) and g(x); where x = liquidated collateral,
 /// f(x) determines liquidation amount at which the safe ratio is satisfied, and g(x) gives
 /// the repay amount based on the collateral being liquidated, which takes into account the
-/// available bids at different premium rates
+/// available bids at different premium rates. Bids expired at `block_time`
+/// are left out, up to the number a liquidation removes from the pools
 pub fn query_liquidation_amount(
     deps: Deps,
+    block_time: u64,
     borrow_amount: Uint256,
     borrow_limit: Uint256,
     collaterals: TokensHuman,
@@ -60,7 +71,8 @@ pub fn query_liquidation_amount(
         compute_collateral_weights(deps, overseer, &collaterals, &collateral_prices)?;
 
     // check partial liquidation condition
-    let safe_ratio = if collaterals_value <= config.liquidation_threshold {
+    let full_liquidation = collaterals_value <= config.liquidation_threshold;
+    let safe_ratio = if full_liquidation {
         Decimal256::zero()
     } else {
         config.safe_ratio
@@ -92,16 +104,35 @@ pub fn query_liquidation_amount(
         let collateral_borrow_amount = borrow_amount * position_portion;
         let collateral_borrow_limit = borrow_limit * position_portion;
 
+        // the close factor caps the loan share repaid by a partial liquidation
+        let max_repay = match config.close_factor {
+            Some(close_factor) if !full_liquidation => {
+                Some(collateral_borrow_amount * close_factor)
+            }
+            _ => None,
+        };
+
         // iterate bid pools until safe ratio condition is met (intersection f(x) and g(x))
         let mut x = Uint256::zero();
         let mut g_x = Uint256::zero();
         let mut intersected = false;
+        let mut expiry_limit = MAX_LIQUIDATION_EXPIRIES;
         for slot in 0..collateral_info.max_slot + 1 {
-            let (slot_available_bids, premium_rate) =
-                match read_bid_pool(deps.storage, &collateral_token_raw, slot) {
-                    Ok(bid_pool) => (bid_pool.total_bid_amount, bid_pool.premium_rate),
-                    Err(_) => continue,
-                };
+            let bid_pool = match read_bid_pool(deps.storage, &collateral_token_raw, slot) {
+                Ok(bid_pool) => bid_pool,
+                Err(_) => continue,
+            };
+            let (expired_bid_amount, expired_bids) = calculate_expired_bid_amount(
+                deps.storage,
+                &bid_pool,
+                &collateral_token_raw,
+                slot,
+                block_time,
+                expiry_limit,
+            )?;
+            expiry_limit -= expired_bids;
+            let slot_available_bids = bid_pool.total_bid_amount - expired_bid_amount;
+            let premium_rate = bid_pool.premium_rate;
             if slot_available_bids.is_zero() {
                 continue;
             };
@@ -117,6 +148,13 @@ pub fn query_liquidation_amount(
 
             g_x += slot_available_bids;
 
+            let capped_x = match max_repay {
+                Some(max_repay) if g_x >= max_repay => {
+                    Some(prev_x + (max_repay - prev_g_x) / discounted_price)
+                }
+                _ => None,
+            };
+
             if g_x > f_x {
                 let nominator =
                     collateral_borrow_amount - safe_borrow + tax_cap_adj + (discounted_price * prev_x) - prev_g_x;
@@ -125,6 +163,10 @@ pub fn query_liquidation_amount(
                         - (safe_ratio * max_ltv));
 
                 let liquidation_amount = (nominator / denominator) + Uint256::one(); // round up
+                let liquidation_amount = match capped_x {
+                    Some(capped_x) => liquidation_amount.min(capped_x),
+                    None => liquidation_amount,
+                };
 
                 result.push((
                     collateral.0.to_string(),
@@ -133,10 +175,16 @@ pub fn query_liquidation_amount(
                 intersected = true;
                 break;
             }
+
+            if let Some(capped_x) = capped_x {
+                x = capped_x;
+                break;
+            }
         }
-        // Intersection is not reached in two situations:
+        // Intersection is not reached in three situations:
         //      1. Not enough bids. Should try to liquidate as much as possible
         //      2. Not enouugh collateral. Also liquidate all collateral
+        //      3. The close factor is reached. Liquidate up to the cap
         if !intersected {
             result.push((collateral.0.to_string(), x)); // liquidate all collateral possible
         }
@@ -220,6 +268,7 @@ pub fn query_bid(deps: Deps, bid_idx: Uint128) -> StdResult<BidResponse> {
         wait_end: bid.wait_end,
         epoch_snapshot: bid.epoch_snapshot,
         scale_snapshot: bid.scale_snapshot,
+        expires_at: bid.expires_at,
     })
 }
 
@@ -270,6 +319,7 @@ pub fn query_bids_by_user(
             wait_end: bid.wait_end,
             epoch_snapshot: bid.epoch_snapshot,
             scale_snapshot: bid.scale_snapshot,
+            expires_at: bid.expires_at,
         };
         Ok(res)
     })
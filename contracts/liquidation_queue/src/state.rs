@@ -1,21 +1,66 @@
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{CanonicalAddr, Order, StdError, StdResult, Storage, Uint128};
-use cosmwasm_storage::{singleton, singleton_read, Bucket, ReadonlyBucket};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
 
-static KEY_CONFIG: &[u8] = b"config";
-static KEY_BID_IDX: &[u8] = b"bid_idx";
-static KEY_NEWOWNER: &[u8] = b"newowner";
+use moneymarket::migration::migrate_singleton;
+
+const CONFIG: Item<Config> = Item::new("config");
+const BID_IDX: Item<Uint128> = Item::new("bid_idx");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const PENDING_SWAP: Item<PendingSwap> = Item::new("pending_swap");
+
+const BID_POOLS: Map<(&[u8], u8), BidPool> = Map::new("bid_pool_by_col");
+const TOTAL_BIDS: Map<&[u8], Uint256> = Map::new("total_bids_by_col");
+/// Keyed by collateral token and bidder
+type ExpiredRefundKey<'a> = (&'a [u8], &'a [u8]);
+/// Stable and collateral owed to the bidders of the bids a liquidation expired
+const EXPIRED_REFUNDS: Map<ExpiredRefundKey, (Uint256, Uint256)> = Map::new("expired_refund");
+const COLLATERAL_INFOS: Map<&[u8], CollateralInfo> = Map::new("col_info");
+/// Keyed by collateral token and premium slot, epoch and scale
+type EpochScaleSumKey<'a> = ((&'a [u8], u8), u128, u128);
+const EPOCH_SCALE_SUMS: Map<EpochScaleSumKey, Decimal256> = Map::new("epoch_scale_sum");
+
+/// Per-bidder index of the `cosmwasm_storage` releases, replaced by the
+/// collateral and bidder index of `bids`
+const LEGACY_BIDS_BY_USER: Map<(&[u8], &[u8], u128), bool> = Map::new("bid_by_user");
+
+/// Bids keyed by index, indexed by collateral token and bidder, and by bid
+/// pool and expiry time (bids without expiry sort last)
+fn bids<'a>() -> IndexedMap<'a, u128, Bid, BidIndexes<'a>> {
+    let indexes = BidIndexes {
+        collateral_bidder: MultiIndex::new(
+            |_, bid| (bid.collateral_token.to_vec(), bid.bidder.to_vec()),
+            "bid",
+            "bid__collateral_bidder",
+        ),
+        pool_expiry: MultiIndex::new(
+            |_, bid| {
+                (
+                    bid.collateral_token.to_vec(),
+                    bid.premium_slot,
+                    bid.expires_at.unwrap_or(u64::MAX),
+                )
+            },
+            "bid",
+            "bid__pool_expiry",
+        ),
+    };
+    IndexedMap::new("bid", indexes)
+}
 
+pub struct BidIndexes<'a> {
+    pub collateral_bidder: MultiIndex<'a, (Vec<u8>, Vec<u8>), Bid, u128>,
+    pub pool_expiry: MultiIndex<'a, (Vec<u8>, u8, u64), Bid, u128>,
+}
 
-static PREFIX_BID: &[u8] = b"bid";
-static PREFIX_BID_BY_USER: &[u8] = b"bid_by_user";
-static PREFIX_BID_POOL_BY_COLLATERAL: &[u8] = b"bid_pool_by_col";
-static PREFIX_TOTAL_BIDS_BY_COLLATERAL: &[u8] = b"total_bids_by_col";
-static PREFIX_COLLATERAL_INFO: &[u8] = b"col_info";
-static PREFIX_EPOCH_SCALE_SUM: &[u8] = b"epoch_scale_sum";
+impl<'a> IndexList<Bid> for BidIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Bid>> + '_> {
+        let v: Vec<&dyn Index<Bid>> = vec![&self.collateral_bidder, &self.pool_expiry];
+        Box::new(v.into_iter())
+    }
+}
 
 
 const MAX_LIMIT: u8 = 31;
@@ -33,6 +78,12 @@ pub struct Config {
     pub price_timeframe: u64,
     pub waiting_period: u64,
     pub overseer: CanonicalAddr,
+    /// Maximum share of the loan repaid by one partial liquidation
+    #[serde(default)]
+    pub close_factor: Option<Decimal256>,
+    /// Swaps claimed collateral to the stable denom
+    #[serde(default)]
+    pub swap_contract: Option<CanonicalAddr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -41,26 +92,49 @@ pub struct NewOwnerAddr {
 }
 
 pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
-    singleton(storage, KEY_NEWOWNER).save(data)
+    NEWOWNER.save(storage, data)
 }
 
 pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
-    singleton_read(storage, KEY_NEWOWNER).load()
+    NEWOWNER.load(storage)
 }
 
 
+/// Claim waiting for the swap of its collateral to return
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingSwap {
+    pub bidder: CanonicalAddr,
+    pub collateral_token: CanonicalAddr,
+    /// Collateral the swap contract is allowed to take
+    pub collateral_amount: Uint256,
+    /// Stable balance of the contract before the swap
+    pub prev_balance: Uint256,
+    pub min_return: Uint256,
+    /// Premium slot and expiry of the bid the proceeds are submitted to
+    pub resubmit_slot: Option<(u8, Option<u64>)>,
+}
+
+pub fn store_pending_swap(storage: &mut dyn Storage, pending_swap: &PendingSwap) -> StdResult<()> {
+    PENDING_SWAP.save(storage, pending_swap)
+}
+
+pub fn take_pending_swap(storage: &mut dyn Storage) -> StdResult<PendingSwap> {
+    let pending_swap = PENDING_SWAP.load(storage)?;
+    PENDING_SWAP.remove(storage);
+    Ok(pending_swap)
+}
+
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
-    singleton(storage, KEY_CONFIG).save(config)
+    CONFIG.save(storage, config)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
 }
 
 pub fn pop_bid_idx(storage: &mut dyn Storage) -> StdResult<Uint128> {
-    let mut idx_store = singleton(storage, KEY_BID_IDX);
-    let last_idx: Uint128 = idx_store.load().unwrap_or_else(|_| Uint128::from(1u128));
-    idx_store.save(&(last_idx + Uint128::from(1u128)))?;
+    let last_idx: Uint128 = BID_IDX.load(storage).unwrap_or_else(|_| Uint128::from(1u128));
+    BID_IDX.save(storage, &(last_idx + Uint128::from(1u128)))?;
     Ok(last_idx)
 }
 
@@ -69,20 +143,57 @@ pub fn store_total_bids(
     collateral_token: &CanonicalAddr,
     total_bids: Uint256,
 ) -> StdResult<()> {
-    let mut total_bids_bucket: Bucket<Uint256> =
-        Bucket::new(storage, PREFIX_TOTAL_BIDS_BY_COLLATERAL);
-    total_bids_bucket.save(collateral_token.as_slice(), &total_bids)?;
-
-    Ok(())
+    TOTAL_BIDS.save(storage, collateral_token.as_slice(), &total_bids)
 }
 
 pub fn read_total_bids(
     storage: &dyn Storage,
     collateral_token: &CanonicalAddr,
 ) -> StdResult<Uint256> {
-    let total_bids_bucket: ReadonlyBucket<Uint256> =
-        ReadonlyBucket::new(storage, PREFIX_TOTAL_BIDS_BY_COLLATERAL);
-    total_bids_bucket.load(collateral_token.as_slice())
+    TOTAL_BIDS.load(storage, collateral_token.as_slice())
+}
+
+pub fn store_expired_refund(
+    storage: &mut dyn Storage,
+    collateral_token: &CanonicalAddr,
+    bidder: &CanonicalAddr,
+    stable_amount: Uint256,
+    collateral_amount: Uint256,
+) -> StdResult<()> {
+    EXPIRED_REFUNDS.update(
+        storage,
+        (collateral_token.as_slice(), bidder.as_slice()),
+        |refund| -> StdResult<_> {
+            let (stable, collateral) = refund.unwrap_or_default();
+            Ok((stable + stable_amount, collateral + collateral_amount))
+        },
+    )?;
+    Ok(())
+}
+
+/// Bidder, remaining stable and claimable collateral of an expired bid
+pub type ExpiredBidRefund = (CanonicalAddr, Uint256, Uint256);
+
+/// Removes and returns up to `limit` refunds owed for a collateral
+pub fn take_expired_refunds(
+    storage: &mut dyn Storage,
+    collateral_token: &CanonicalAddr,
+    limit: usize,
+) -> StdResult<Vec<ExpiredBidRefund>> {
+    let refunds = EXPIRED_REFUNDS
+        .prefix(collateral_token.as_slice())
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (bidder, (stable_amount, collateral_amount)) = elem?;
+            Ok((CanonicalAddr::from(bidder), stable_amount, collateral_amount))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (bidder, _, _) in refunds.iter() {
+        EXPIRED_REFUNDS.remove(storage, (collateral_token.as_slice(), bidder.as_slice()));
+    }
+    Ok(refunds)
 }
 
 pub fn store_epoch_scale_sum(
@@ -93,16 +204,11 @@ pub fn store_epoch_scale_sum(
     scale: Uint128,
     sum: Decimal256,
 ) -> StdResult<()> {
-    let mut epoch_scale_sum: Bucket<Decimal256> = Bucket::multilevel(
+    EPOCH_SCALE_SUMS.save(
         storage,
-        &[
-            PREFIX_EPOCH_SCALE_SUM,
-            collateral_token.as_slice(),
-            &premium_slot.to_be_bytes(),
-            &epoch.u128().to_be_bytes(),
-        ],
-    );
-    epoch_scale_sum.save(&scale.u128().to_be_bytes(), &sum)
+        ((collateral_token.as_slice(), premium_slot), epoch.u128(), scale.u128()),
+        &sum,
+    )
 }
 
 pub fn read_epoch_scale_sum(
@@ -112,16 +218,10 @@ pub fn read_epoch_scale_sum(
     epoch: Uint128,
     scale: Uint128,
 ) -> StdResult<Decimal256> {
-    let epoch_scale_sum: ReadonlyBucket<Decimal256> = ReadonlyBucket::multilevel(
+    EPOCH_SCALE_SUMS.load(
         storage,
-        &[
-            PREFIX_EPOCH_SCALE_SUM,
-            collateral_token.as_slice(),
-            &premium_slot.to_be_bytes(),
-            &epoch.u128().to_be_bytes(),
-        ],
-    );
-    epoch_scale_sum.load(&scale.u128().to_be_bytes())
+        ((collateral_token.as_slice(), premium_slot), epoch.u128(), scale.u128()),
+    )
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -137,21 +237,15 @@ pub fn store_collateral_info(
     collateral_token: &CanonicalAddr,
     collateral_info: &CollateralInfo,
 ) -> StdResult<()> {
-    let mut collateral_info_bucket: Bucket<CollateralInfo> =
-        Bucket::new(storage, PREFIX_COLLATERAL_INFO);
-    collateral_info_bucket.save(collateral_token.as_slice(), collateral_info)?;
-
-    Ok(())
+    COLLATERAL_INFOS.save(storage, collateral_token.as_slice(), collateral_info)
 }
 
 pub fn read_collateral_info(
     storage: &dyn Storage,
     collateral_token: &CanonicalAddr,
 ) -> StdResult<CollateralInfo> {
-    let collateral_info_bucket: ReadonlyBucket<CollateralInfo> =
-        ReadonlyBucket::new(storage, PREFIX_COLLATERAL_INFO);
-    collateral_info_bucket
-        .load(collateral_token.as_slice())
+    COLLATERAL_INFOS
+        .load(storage, collateral_token.as_slice())
         .map_err(|_| StdError::generic_err("Collateral is not whitelisted"))
 }
 
@@ -173,11 +267,7 @@ pub fn store_bid_pool(
     premium_slot: u8,
     bid_pool: &BidPool,
 ) -> StdResult<()> {
-    let mut bid_pool_bucket: Bucket<BidPool> = Bucket::multilevel(
-        storage,
-        &[PREFIX_BID_POOL_BY_COLLATERAL, collateral_token.as_slice()],
-    );
-    bid_pool_bucket.save(&premium_slot.to_be_bytes(), bid_pool)
+    BID_POOLS.save(storage, (collateral_token.as_slice(), premium_slot), bid_pool)
 }
 
 pub fn read_bid_pool(
@@ -185,12 +275,8 @@ pub fn read_bid_pool(
     collateral_token: &CanonicalAddr,
     premium_slot: u8,
 ) -> StdResult<BidPool> {
-    let bid_pool_bucket: ReadonlyBucket<BidPool> = ReadonlyBucket::multilevel(
-        storage,
-        &[PREFIX_BID_POOL_BY_COLLATERAL, collateral_token.as_slice()],
-    );
-    bid_pool_bucket
-        .load(&premium_slot.to_be_bytes())
+    BID_POOLS
+        .load(storage, (collateral_token.as_slice(), premium_slot))
         .map_err(|_| StdError::generic_err("Bid pool not found"))
 }
 
@@ -199,14 +285,8 @@ pub fn read_or_create_bid_pool(
     collateral_info: &CollateralInfo,
     premium_slot: u8,
 ) -> StdResult<BidPool> {
-    let bid_pool_bucket: ReadonlyBucket<BidPool> = ReadonlyBucket::multilevel(
-        storage,
-        &[
-            PREFIX_BID_POOL_BY_COLLATERAL,
-            collateral_info.collateral_token.as_slice(),
-        ],
-    );
-    match bid_pool_bucket.load(&premium_slot.to_be_bytes()) {
+    let key = (collateral_info.collateral_token.as_slice(), premium_slot);
+    match BID_POOLS.load(storage, key) {
         Ok(bid_pool) => Ok(bid_pool),
         Err(_) => {
             if (0..collateral_info.max_slot + 1).contains(&premium_slot) {
@@ -241,16 +321,12 @@ pub fn read_bid_pools(
     start_after: Option<u8>,
     limit: Option<u8>,
 ) -> StdResult<Vec<BidPool>> {
-    let bid_pool_bucket: ReadonlyBucket<BidPool> = ReadonlyBucket::multilevel(
-        storage,
-        &[PREFIX_BID_POOL_BY_COLLATERAL, collateral_token.as_slice()],
-    );
-
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let start = start_after.map(Bound::exclusive);
 
-    bid_pool_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+    BID_POOLS
+        .prefix(collateral_token.as_slice())
+        .range(storage, start, None, Order::Ascending)
         .take(limit)
         .map(|elem| {
             let (_, pool) = elem?;
@@ -272,48 +348,22 @@ pub struct Bid {
     pub wait_end: Option<u64>,
     pub epoch_snapshot: Uint128,
     pub scale_snapshot: Uint128,
+    #[serde(default)]
+    pub expires_at: Option<u64>,
 }
 
 pub fn store_bid(storage: &mut dyn Storage, bid_idx: Uint128, bid: &Bid) -> StdResult<()> {
-    let mut bid_bucket: Bucket<Bid> = Bucket::new(storage, PREFIX_BID);
-    bid_bucket.save(&bid_idx.u128().to_be_bytes(), bid)?;
-
-    let mut bid_indexer_by_user: Bucket<bool> = Bucket::multilevel(
-        storage,
-        &[
-            PREFIX_BID_BY_USER,
-            bid.collateral_token.as_slice(),
-            bid.bidder.as_slice(),
-        ],
-    );
-    bid_indexer_by_user.save(&bid_idx.u128().to_be_bytes(), &true)?;
-
-    Ok(())
+    bids().save(storage, bid_idx.u128(), bid)
 }
 
 pub fn remove_bid(storage: &mut dyn Storage, bid_idx: Uint128) -> StdResult<()> {
-    let bid: Bid = read_bid(storage, bid_idx)?;
-    let mut bid_bucket: Bucket<Bid> = Bucket::new(storage, PREFIX_BID);
-    bid_bucket.remove(&bid_idx.u128().to_be_bytes());
-
-    // remove indexer
-    let mut bid_indexer_by_user: Bucket<bool> = Bucket::multilevel(
-        storage,
-        &[
-            PREFIX_BID_BY_USER,
-            bid.collateral_token.as_slice(),
-            bid.bidder.as_slice(),
-        ],
-    );
-    bid_indexer_by_user.remove(&bid_idx.u128().to_be_bytes());
-
-    Ok(())
+    read_bid(storage, bid_idx)?;
+    bids().remove(storage, bid_idx.u128())
 }
 
 pub fn read_bid(storage: &dyn Storage, bid_idx: Uint128) -> StdResult<Bid> {
-    let bid_bucket: ReadonlyBucket<Bid> = ReadonlyBucket::new(storage, PREFIX_BID);
-    bid_bucket
-        .load(&bid_idx.u128().to_be_bytes())
+    bids()
+        .load(storage, bid_idx.u128())
         .map_err(|_| StdError::generic_err("No bids with the specified information exist"))
 }
 
@@ -324,51 +374,94 @@ pub fn read_bids_by_user(
     start_after: Option<Uint128>,
     limit: Option<u8>,
 ) -> StdResult<Vec<Bid>> {
-    let bid_user_index: ReadonlyBucket<bool> = ReadonlyBucket::multilevel(
-        storage,
-        &[
-            PREFIX_BID_BY_USER,
-            collateral_token.as_slice(),
-            bidder.as_slice(),
-        ],
-    );
-
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start_idx(start_after);
+    let start_after = start_after.map(|idx| idx.u128());
 
-    bid_user_index
-        .range(start.as_deref(), None, Order::Ascending)
+    let mut user_bids: Vec<Bid> = bids()
+        .idx
+        .collateral_bidder
+        .prefix((collateral_token.to_vec(), bidder.to_vec()))
+        .range(storage, start_after.map(Bound::exclusive), None, Order::Ascending)
         .take(limit)
         .map(|elem| {
-            let (k, _) = elem?;
-            read_bid(storage, Uint128::from(bytes_to_u128(&k)?))
+            let (_, bid) = elem?;
+            Ok(bid)
         })
-        .collect()
+        .collect::<StdResult<Vec<Bid>>>()?;
+
+    // bids `migrate_legacy_bids` has not moved yet are still listed by the
+    // legacy index, and bids updated since are listed by both
+    let legacy_bids = LEGACY_BIDS_BY_USER
+        .prefix((collateral_token.as_slice(), bidder.as_slice()))
+        .keys(storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+        .filter_map(|bid_idx| match bid_idx {
+            Ok(bid_idx) => bids().may_load(storage, bid_idx).transpose(),
+            Err(err) => Some(Err(err)),
+        })
+        .take(limit)
+        .collect::<StdResult<Vec<Bid>>>()?;
+    if legacy_bids.is_empty() {
+        return Ok(user_bids);
+    }
+
+    user_bids.extend(legacy_bids);
+    user_bids.sort_by_key(|bid| bid.idx);
+    user_bids.dedup_by_key(|bid| bid.idx);
+    user_bids.truncate(limit);
+    Ok(user_bids)
 }
 
-fn bytes_to_u128(data: &[u8]) -> StdResult<u128> {
-    match data[0..16].try_into() {
-        Ok(bytes) => Ok(u128::from_be_bytes(bytes)),
-        Err(_) => Err(StdError::generic_err(
-            "Corrupted data found. 16 byte expected.",
-        )),
-    }
+/// Bids of a bid pool that expired at or before `time`, earliest expiry first
+pub fn read_expired_bids(
+    storage: &dyn Storage,
+    collateral_token: &CanonicalAddr,
+    premium_slot: u8,
+    time: u64,
+    limit: usize,
+) -> StdResult<Vec<Bid>> {
+    bids()
+        .idx
+        .pool_expiry
+        .sub_prefix((collateral_token.to_vec(), premium_slot))
+        .range(
+            storage,
+            None,
+            Some(Bound::inclusive((time, u128::MAX))),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|elem| {
+            let (_, bid) = elem?;
+            Ok(bid)
+        })
+        .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start_idx(start_after: Option<Uint128>) -> Option<Vec<u8>> {
-    start_after.map(|idx| {
-        let mut v = idx.u128().to_be_bytes().to_vec();
-        v.push(1);
-        v
-    })
+/// Move the singletons written by `cosmwasm_storage`, whether or not cw2
+/// versions were recorded yet. Every step is a no-op once the state is moved.
+/// Bids are moved in pages by `migrate_legacy_bids`.
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_singleton(storage, &CONFIG)?;
+    migrate_singleton(storage, &BID_IDX)?;
+    migrate_singleton(storage, &NEWOWNER)
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<u8>) -> Option<Vec<u8>> {
-    start_after.map(|id| {
-        let mut v = id.to_be_bytes().to_vec();
-        v.push(1);
-        v
-    })
+/// Drop up to `limit` entries of the legacy per-bidder index, re-saving their
+/// bids to build the collateral and bidder index and the bid pool and expiry
+/// index. Returns how many entries were dropped, the migration is done once
+/// fewer than `limit` are
+pub fn migrate_legacy_bids(storage: &mut dyn Storage, limit: usize) -> StdResult<usize> {
+    let keys = LEGACY_BIDS_BY_USER
+        .keys(storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (collateral_token, bidder, bid_idx) in keys.iter() {
+        LEGACY_BIDS_BY_USER.remove(storage, (collateral_token, bidder, *bid_idx));
+        // bids removed since the migration only left their index entry
+        if let Some(bid) = bids().may_load(storage, *bid_idx)? {
+            bids().save(storage, *bid_idx, &bid)?;
+        }
+    }
+
+    Ok(keys.len())
 }
@@ -61,6 +61,7 @@ fn one_bidder_distribution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -91,6 +92,7 @@ fn one_bidder_distribution() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -100,6 +102,7 @@ fn one_bidder_distribution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(1u128)]),
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -178,6 +181,7 @@ fn two_bidder_distribution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -208,6 +212,7 @@ fn two_bidder_distribution() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -217,6 +222,7 @@ fn two_bidder_distribution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -259,6 +265,7 @@ fn two_bidder_distribution() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -270,6 +277,7 @@ fn two_bidder_distribution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(1u128)]),
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -299,6 +307,7 @@ fn two_bidder_distribution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -374,6 +383,7 @@ fn two_bidder_distribution_big_numbers() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -404,6 +414,7 @@ fn two_bidder_distribution_big_numbers() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -413,6 +424,7 @@ fn two_bidder_distribution_big_numbers() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -455,6 +467,7 @@ fn two_bidder_distribution_big_numbers() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -466,6 +479,7 @@ fn two_bidder_distribution_big_numbers() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(1u128)]),
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -495,6 +509,7 @@ fn two_bidder_distribution_big_numbers() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -570,6 +585,7 @@ fn one_user_two_bid_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -586,6 +602,7 @@ fn one_user_two_bid_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -614,6 +631,7 @@ fn one_user_two_bid_slots() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -623,6 +641,7 @@ fn one_user_two_bid_slots() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -644,6 +663,7 @@ fn one_user_two_bid_slots() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -653,6 +673,7 @@ fn one_user_two_bid_slots() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -739,6 +760,7 @@ fn partial_withdraw_after_execution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -769,6 +791,7 @@ fn partial_withdraw_after_execution() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -786,6 +809,7 @@ fn partial_withdraw_after_execution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -813,6 +837,7 @@ fn partial_withdraw_after_execution() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -825,6 +850,7 @@ fn partial_withdraw_after_execution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(1u128)]),
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -863,6 +889,7 @@ fn partial_withdraw_after_execution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -947,6 +974,7 @@ fn completely_empty_pool() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -966,6 +994,7 @@ fn completely_empty_pool() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -975,6 +1004,7 @@ fn completely_empty_pool() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -1033,6 +1063,7 @@ fn completely_empty_pool() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1042,6 +1073,7 @@ fn completely_empty_pool() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1064,6 +1096,7 @@ fn completely_empty_pool() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -1130,6 +1163,7 @@ fn product_truncated_to_zero() {
         let msg = ExecuteMsg::SubmitBid {
             collateral_token: "col0000".to_string(),
             premium_slot: 0u8,
+            expires_at: None,
         };
         let info = mock_info(
             "alice0000",
@@ -1149,6 +1183,7 @@ fn product_truncated_to_zero() {
                 liquidator: "liquidator00000".to_string(),
                 fee_address: Some("fee0000".to_string()),
                 repay_address: Some("repay0000".to_string()),
+                borrower: None,
             })
             .unwrap(),
         });
@@ -1159,6 +1194,7 @@ fn product_truncated_to_zero() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1254,6 +1290,7 @@ fn two_bidder_reward_distribution_common_slot() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -1279,6 +1316,7 @@ fn two_bidder_reward_distribution_common_slot() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -1311,6 +1349,7 @@ fn two_bidder_reward_distribution_common_slot() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1323,6 +1362,7 @@ fn two_bidder_reward_distribution_common_slot() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1355,6 +1395,7 @@ fn two_bidder_reward_distribution_common_slot() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1434,6 +1475,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -1460,6 +1502,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -1485,6 +1528,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -1512,6 +1556,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -1546,6 +1591,7 @@ fn two_bidder_distribution_multiple_common_slots() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1561,6 +1607,7 @@ fn two_bidder_distribution_multiple_common_slots() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1574,6 +1621,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(1u128)]),
+        swap_to_stable: None,
     };
 
     let info = mock_info("alice0000", &[]);
@@ -1590,6 +1638,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(3u128)]),
+        swap_to_stable: None,
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(
@@ -1605,6 +1654,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(2u128)]),
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1619,6 +1669,7 @@ fn two_bidder_distribution_multiple_common_slots() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: Some(vec![Uint128::from(4u128)]),
+        swap_to_stable: None,
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
     assert_eq!(
@@ -1752,6 +1803,7 @@ fn two_bidder_unequal_deposit_reward_distribution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 2u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -1777,6 +1829,7 @@ fn two_bidder_unequal_deposit_reward_distribution() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 2u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -1809,6 +1862,7 @@ fn two_bidder_unequal_deposit_reward_distribution() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1831,6 +1885,7 @@ fn two_bidder_unequal_deposit_reward_distribution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1860,6 +1915,7 @@ fn two_bidder_unequal_deposit_reward_distribution() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1939,6 +1995,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -1965,6 +2022,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -1990,6 +2048,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -2027,6 +2086,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2040,6 +2100,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -2066,6 +2127,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
 
     let info = mock_info(
@@ -2100,6 +2162,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2113,6 +2176,7 @@ fn scalable_reward_distribution_after_multiple_liquidations() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
 
     let info = mock_info("alice0000", &[]);
@@ -2196,6 +2260,7 @@ fn not_enough_bid_for_collateral() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 6u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -2221,6 +2286,7 @@ fn not_enough_bid_for_collateral() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 6u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -2252,6 +2318,7 @@ fn not_enough_bid_for_collateral() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2318,6 +2385,7 @@ fn two_bidder_reward_distribution_common_slot_large_numbers() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "alice0000",
@@ -2342,6 +2410,7 @@ fn two_bidder_reward_distribution_common_slot_large_numbers() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "col0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "bob0000",
@@ -2373,6 +2442,7 @@ fn two_bidder_reward_distribution_common_slot_large_numbers() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2385,6 +2455,7 @@ fn two_bidder_reward_distribution_common_slot_large_numbers() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("alice0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -2417,6 +2488,7 @@ fn two_bidder_reward_distribution_common_slot_large_numbers() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "col0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("bob0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1,4 +1,5 @@
-use moneymarket::overseer::{WhitelistResponse, WhitelistResponseElem};
+use crate::querier::OverseerPolicyResponse;
+use moneymarket::overseer::{FrozenLiquidationPolicy, WhitelistResponse, WhitelistResponseElem};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -9,13 +10,17 @@ use cosmwasm_std::{
     QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery, CustomQuery,
 };
 use std::collections::HashMap;
-use moneymarket::oracle_pyth::PriceResponse;
+use moneymarket::oracle_pyth::{CheckedPriceResponse, PriceResponse};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
 /// Query oracle price to oracle contract
     QueryPrice { asset: String },
+    /// Query oracle prices, reporting circuit breaker trips in the response
+    QueryCheckedPrices { assets: Vec<String>, ema_only: bool },
+    /// Query config to overseer contract
+    Config {},
     Whitelist {
         collateral_token: Option<String>,
         start_after: Option<String>,
@@ -77,6 +82,7 @@ pub struct WasmMockQuerier {
     tax_querier: TaxQuerier,
     oracle_price_querier: OraclePriceQuerier,
     collateral_querier: CollateralQuerier,
+    frozen_liquidation_policy: FrozenLiquidationPolicy,
 }
 
 #[derive(Clone, Default)]
@@ -131,6 +137,8 @@ pub(crate) fn caps_to_map(caps: &[(&String, &Uint128)]) -> HashMap<String, Uint1
 pub struct OraclePriceQuerier {
     // this lets us iterate over all pairs that match the first string
     oracle_price: HashMap<String, (Decimal256, i64, Decimal256, i64, u64, u64)>,
+    // assets whose spot price trips the oracle circuit breaker
+    frozen_assets: Vec<String>,
 }
 
 #[allow(clippy::type_complexity)]
@@ -138,8 +146,21 @@ impl OraclePriceQuerier {
     pub fn new(oracle_price: &[(&String, &(Decimal256, i64, Decimal256, i64, u64, u64))]) -> Self {
         OraclePriceQuerier {
             oracle_price: oracle_price_to_map(oracle_price),
+            frozen_assets: vec![],
         }
     }
+
+    fn price(&self, asset: &str) -> Option<PriceResponse> {
+        self.oracle_price.get(asset).map(|v| PriceResponse {
+            asset: asset.to_string(),
+            emv_price: v.0,
+            emv_price_raw: v.1,
+            price: v.2,
+            price_raw: v.3,
+            last_updated_base: v.4,
+            last_updated_quote: v.5,
+        })
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -215,6 +236,36 @@ impl WasmMockQuerier {
                             }),
                         }
                     }
+                    QueryMsg::QueryCheckedPrices { assets, ema_only } => {
+                        let mut prices = vec![];
+                        for asset in assets {
+                            let price = match self.oracle_price_querier.price(&asset) {
+                                Some(price) => price,
+                                None => {
+                                    return SystemResult::Err(SystemError::InvalidRequest {
+                                        error: "No oracle price exists".to_string(),
+                                        request: msg.as_slice().into(),
+                                    })
+                                }
+                            };
+                            let frozen = !ema_only
+                                && self.oracle_price_querier.frozen_assets.contains(&asset);
+                            prices.push(CheckedPriceResponse {
+                                circuit_breaker: frozen
+                                    .then(|| format!("{} price deviates from ema price", asset)),
+                                price: (!frozen).then_some(price),
+                                asset,
+                            });
+                        }
+                        SystemResult::Ok(ContractResult::from(to_json_binary(&prices)))
+                    }
+                    QueryMsg::Config {} => {
+                        SystemResult::Ok(ContractResult::from(to_json_binary(
+                            &OverseerPolicyResponse {
+                                frozen_liquidation_policy: self.frozen_liquidation_policy,
+                            },
+                        )))
+                    }
                     QueryMsg::Whitelist {
                         collateral_token,
                         start_after: _,
@@ -255,6 +306,7 @@ impl WasmMockQuerier {
             tax_querier: TaxQuerier::default(),
             oracle_price_querier: OraclePriceQuerier::default(),
             collateral_querier: CollateralQuerier::default(),
+            frozen_liquidation_policy: FrozenLiquidationPolicy::default(),
         }
     }
 
@@ -271,7 +323,25 @@ impl WasmMockQuerier {
         self.oracle_price_querier = OraclePriceQuerier::new(oracle_price);
     }
 
+    pub fn with_frozen_oracle_prices(&mut self, frozen_assets: &[&String]) {
+        self.oracle_price_querier.frozen_assets =
+            frozen_assets.iter().map(|asset| (*asset).clone()).collect();
+    }
+
+    pub fn with_frozen_liquidation_policy(&mut self, policy: FrozenLiquidationPolicy) {
+        self.frozen_liquidation_policy = policy;
+    }
+
     pub fn with_collateral_max_ltv(&mut self, collaterals: &[(&String, &Decimal256)]) {
         self.collateral_querier = CollateralQuerier::new(collaterals);
     }
+
+    // set a new balance for the given address and return the old balance
+    pub fn update_balance<U: Into<String>>(
+        &mut self,
+        addr: U,
+        balance: Vec<Coin>,
+    ) -> Option<Vec<Coin>> {
+        self.base.update_balance(addr, balance)
+    }
 }
@@ -161,6 +161,7 @@ fn simulate_bids_with_2_liq_amounts(
         let msg = ExecuteMsg::SubmitBid {
             collateral_token: "col0000".to_string(),
             premium_slot: 0u8,
+            expires_at: None,
         };
         let info = mock_info(
             "alice0000",
@@ -181,6 +182,7 @@ fn simulate_bids_with_2_liq_amounts(
                     liquidator: "liquidator00000".to_string(),
                     fee_address: Some("fee0000".to_string()),
                     repay_address: Some("repay0000".to_string()),
+                    borrower: None,
                 })
                 .unwrap(),
             });
@@ -197,6 +199,7 @@ fn simulate_bids_with_2_liq_amounts(
                     liquidator: "liquidator00000".to_string(),
                     fee_address: Some("fee0000".to_string()),
                     repay_address: Some("repay0000".to_string()),
+                    borrower: None,
                 })
                 .unwrap(),
             });
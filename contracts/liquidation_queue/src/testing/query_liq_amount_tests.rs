@@ -46,6 +46,7 @@ fn partial_one_collateral_one_slot_high_ltv() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -100,6 +101,7 @@ fn partial_one_collateral_one_slot_high_ltv() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -117,6 +119,109 @@ fn partial_one_collateral_one_slot_high_ltv() {
     );
 }
 
+#[test]
+fn partial_one_collateral_one_slot_close_factor() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_tax(
+        Decimal::percent(0),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+    deps.querier
+        .with_collateral_max_ltv(&[(&"token0000".to_string(), &Decimal256::percent(90))]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(80),
+        bid_fee: Decimal256::percent(0),
+        liquidator_fee: Decimal256::percent(0),
+        liquidation_threshold: Uint256::zero(),
+        price_timeframe: 60u64,
+        waiting_period: 60u64,
+        overseer: "overseer0000".to_string(),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "token0000".to_string(),
+        max_slot: 30u8,
+        bid_threshold: Uint256::from(10000u128), // to get instant activation
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "token0000".to_string(),
+        premium_slot: 0u8,
+        expires_at: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(10000000u128),
+        }],
+    );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // at most half of the loan can be repaid at once
+    let update_config = |close_factor: Option<Decimal256>, liquidation_threshold| {
+        ExecuteMsg::UpdateConfig {
+            oracle_contract: None,
+            safe_ratio: None,
+            bid_fee: None,
+            liquidator_fee: None,
+            liquidation_threshold,
+            price_timeframe: None,
+            waiting_period: None,
+            overseer: None,
+            close_factor: close_factor.map(Some),
+            swap_contract: None,
+        }
+    };
+    let info = mock_info("owner0000", &[]);
+    let msg = update_config(Some(Decimal256::zero()), None);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    let msg = update_config(Some(Decimal256::percent(101)), None);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    let msg = update_config(Some(Decimal256::percent(50)), None);
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = QueryMsg::LiquidationAmount {
+        borrow_amount: Uint256::from(19000u64),
+        borrow_limit: Uint256::from(18000u64),
+        collaterals: vec![("token0000".to_string(), Uint256::from(20000u64))], // value 20000 (LTV 90%), limit = 18,000
+        collateral_prices: vec![Decimal256::percent(100)],
+    };
+
+    // capped at 19000 * 0.5 = 9500, instead of 16433 to reach the safe ratio
+    let res = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+    let res: LiquidationAmountResponse = from_json(&res).unwrap();
+    assert_eq!(
+        res,
+        LiquidationAmountResponse {
+            collaterals: vec![("token0000".to_string(), Uint256::from(9500u64))],
+        }
+    );
+
+    // collateral value below the liquidation threshold is liquidated completely
+    let msg_update = update_config(None, Some(Uint256::from(100000u64)));
+    execute(deps.as_mut(), mock_env(), info, msg_update).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+    let res: LiquidationAmountResponse = from_json(&res).unwrap();
+    assert_eq!(
+        res,
+        LiquidationAmountResponse {
+            collaterals: vec![("token0000".to_string(), Uint256::from(19002u64))],
+        }
+    );
+}
+
 #[test]
 fn partial_one_collateral_one_slot() {
     let mut deps = mock_dependencies(&[]);
@@ -155,6 +260,7 @@ fn partial_one_collateral_one_slot() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -209,6 +315,7 @@ fn partial_one_collateral_one_slot() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -264,6 +371,7 @@ fn partial_one_collateral_one_slot_with_fees() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -318,6 +426,7 @@ fn partial_one_collateral_one_slot_with_fees() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -382,6 +491,7 @@ fn partial_one_collateral_one_slot_with_fees_all() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -436,6 +546,7 @@ fn partial_one_collateral_one_slot_with_fees_all() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -507,6 +618,7 @@ fn partial_one_collateral_two_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -519,6 +631,7 @@ fn partial_one_collateral_two_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -568,6 +681,7 @@ fn partial_one_collateral_two_slots() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -623,6 +737,7 @@ fn partial_one_collateral_two_slots_with_fees() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -635,6 +750,7 @@ fn partial_one_collateral_two_slots_with_fees() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -684,6 +800,7 @@ fn partial_one_collateral_two_slots_with_fees() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -748,6 +865,7 @@ fn non_partial_liquidation() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -795,6 +913,7 @@ fn non_partial_liquidation() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -850,6 +969,7 @@ fn non_partial_liquidation_two_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -862,6 +982,7 @@ fn non_partial_liquidation_two_slots() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -902,6 +1023,7 @@ fn non_partial_liquidation_two_slots() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -957,6 +1079,7 @@ fn non_partial_liquidation_with_fees() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1004,6 +1127,7 @@ fn non_partial_liquidation_with_fees() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1068,6 +1192,7 @@ fn non_partial_liquidation_two_slots_with_fees() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1080,6 +1205,7 @@ fn non_partial_liquidation_two_slots_with_fees() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -1120,6 +1246,7 @@ fn non_partial_liquidation_two_slots_with_fees() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1184,6 +1311,7 @@ fn non_partial_liquidation_two_slots_with_fees_big_nums() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1196,6 +1324,7 @@ fn non_partial_liquidation_two_slots_with_fees_big_nums() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -1236,6 +1365,7 @@ fn non_partial_liquidation_two_slots_with_fees_big_nums() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1309,6 +1439,7 @@ fn partial_two_collaterals_ltv_diff() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1321,6 +1452,7 @@ fn partial_two_collaterals_ltv_diff() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 0u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -1391,6 +1523,7 @@ fn partial_two_collaterals_ltv_diff() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1415,6 +1548,7 @@ fn partial_two_collaterals_ltv_diff() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1480,6 +1614,7 @@ fn partial_two_collaterals_multi_slots_per_col() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1492,16 +1627,19 @@ fn partial_two_collaterals_multi_slots_per_col() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 11u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 3u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -1572,6 +1710,7 @@ fn partial_two_collaterals_multi_slots_per_col() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1596,6 +1735,7 @@ fn partial_two_collaterals_multi_slots_per_col() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1661,6 +1801,7 @@ fn partial_two_collaterals_one_slot_diff_ltv() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1673,6 +1814,7 @@ fn partial_two_collaterals_one_slot_diff_ltv() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -1743,6 +1885,7 @@ fn partial_two_collaterals_one_slot_diff_ltv() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1767,6 +1910,7 @@ fn partial_two_collaterals_one_slot_diff_ltv() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1840,6 +1984,7 @@ fn partial_three_collaterals_one_slot_diff_ltv() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -1852,11 +1997,13 @@ fn partial_three_collaterals_one_slot_diff_ltv() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0002".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -1946,6 +2093,7 @@ fn partial_three_collaterals_one_slot_diff_ltv() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1970,6 +2118,7 @@ fn partial_three_collaterals_one_slot_diff_ltv() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -1994,6 +2143,7 @@ fn partial_three_collaterals_one_slot_diff_ltv() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2067,6 +2217,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -2079,11 +2230,13 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0002".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -2179,6 +2332,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2203,6 +2357,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2227,6 +2382,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2300,6 +2456,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts_2() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -2312,11 +2469,13 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts_2() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0002".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -2412,6 +2571,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts_2() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2436,6 +2596,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts_2() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2460,6 +2621,7 @@ fn partial_three_collaterals_one_slot_diff_ltv_big_amounts_2() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2525,6 +2687,7 @@ fn not_enough_bids_for_one_of_two_col() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -2544,6 +2707,7 @@ fn not_enough_bids_for_one_of_two_col() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -2614,6 +2778,7 @@ fn not_enough_bids_for_one_of_two_col() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2647,6 +2812,7 @@ fn not_enough_bids_for_one_of_two_col() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -2715,6 +2881,7 @@ fn integration_test_simul() {
         let msg = ExecuteMsg::SubmitBid {
             collateral_token: "token0000".to_string(),
             premium_slot: slot as u8,
+            expires_at: None,
         };
         let info = mock_info(
             "addr0000",
@@ -2729,6 +2896,7 @@ fn integration_test_simul() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 30u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -2784,6 +2952,7 @@ fn integration_test_simul() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -63,6 +63,7 @@ fn query_liquidation_amount() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -75,11 +76,13 @@ fn query_liquidation_amount() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0001".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0002".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -186,6 +189,7 @@ fn query_bids() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -198,11 +202,13 @@ fn query_bids() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
@@ -231,6 +237,7 @@ fn query_bids() {
             sum_snapshot: Decimal256::zero(),
             epoch_snapshot: Uint128::zero(),
             scale_snapshot: Uint128::zero(),
+            expires_at: None,
         }
     );
 
@@ -264,6 +271,7 @@ fn query_bids() {
                     sum_snapshot: Decimal256::zero(),
                     epoch_snapshot: Uint128::zero(),
                     scale_snapshot: Uint128::zero(),
+                    expires_at: None,
                 },
                 BidResponse {
                     idx: Uint128::from(2u128),
@@ -277,6 +285,7 @@ fn query_bids() {
                     sum_snapshot: Decimal256::zero(),
                     epoch_snapshot: Uint128::zero(),
                     scale_snapshot: Uint128::zero(),
+                    expires_at: None,
                 },
                 BidResponse {
                     idx: Uint128::from(3u128),
@@ -290,6 +299,7 @@ fn query_bids() {
                     sum_snapshot: Decimal256::zero(),
                     epoch_snapshot: Uint128::zero(),
                     scale_snapshot: Uint128::zero(),
+                    expires_at: None,
                 }
             ]
         }
@@ -324,6 +334,7 @@ fn query_bids() {
                 sum_snapshot: Decimal256::zero(),
                 epoch_snapshot: Uint128::zero(),
                 scale_snapshot: Uint128::zero(),
+                expires_at: None,
             }]
         }
     );
@@ -367,6 +378,7 @@ fn query_bid_pools() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 5u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -379,11 +391,13 @@ fn query_bid_pools() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 6u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "token0000".to_string(),
         premium_slot: 10u8,
+        expires_at: None,
     };
     execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
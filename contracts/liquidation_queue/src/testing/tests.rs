@@ -1,17 +1,24 @@
-use crate::contract::{execute, instantiate, query};
-use crate::testing::mock_querier::mock_dependencies;
+use crate::contract::{execute, instantiate, migrate, query, reply, SWAP_CLAIM_OPERATION};
+use crate::testing::mock_querier::{mock_dependencies, WasmMockQuerier};
 
 use crate::error::ContractError;
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, from_json, to_json_binary, BankMsg, Coin, CosmosMsg, Decimal, StdError, SubMsg, Uint128,
+    attr, from_json, to_json_binary, to_json_vec, Api, BankMsg, Coin, CosmosMsg, Decimal, Order,
+    OwnedDeps, Reply, StdError, Storage, SubMsg, SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
 };
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_storage::{to_length_prefixed, to_length_prefixed_nested};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use moneymarket::config_history::{ConfigChange, ConfigHistoryEntryResponse, ConfigHistoryResponse};
 use moneymarket::liquidation_queue::{
-    BidResponse, CollateralInfoResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg,
-    QueryMsg,
+    BidResponse, BidsResponse, CollateralInfoResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg,
+    InstantiateMsg, LiquidationAmountResponse, MigrateMsg, QueryMsg, SwapToStable,
 };
+use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::overseer::FrozenLiquidationPolicy;
+use moneymarket::pause::{PausableOperation, PauseStatusResponse};
+use moneymarket::swap_ext::SwapExecteMsg;
 
 #[test]
 fn proper_initialization() {
@@ -52,6 +59,8 @@ fn proper_initialization() {
             price_timeframe: 60u64,
             waiting_period: 60u64,
             overseer: "overseer0000".to_string(),
+            close_factor: None,
+            swap_contract: None,
         }
     );
 }
@@ -87,6 +96,8 @@ fn update_config() {
         price_timeframe: None,
         waiting_period: None,
         overseer: None,
+        close_factor: None,
+        swap_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -119,6 +130,8 @@ fn update_config() {
             price_timeframe: 60u64,
             waiting_period: 60u64,
             overseer: "overseer0000".to_string(),
+            close_factor: None,
+            swap_contract: None,
         }
     );
 
@@ -133,6 +146,8 @@ fn update_config() {
         price_timeframe: Some(120u64),
         waiting_period: Some(100u64),
         overseer: Some("overseer0001".to_string()),
+        close_factor: Some(Some(Decimal256::percent(50))),
+        swap_contract: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -154,6 +169,8 @@ fn update_config() {
             price_timeframe: 120u64,
             waiting_period: 100u64,
             overseer: "overseer0001".to_string(),
+            close_factor: Some(Decimal256::percent(50)),
+            swap_contract: None,
         }
     );
 
@@ -168,6 +185,8 @@ fn update_config() {
         price_timeframe: Some(100u64),
         waiting_period: Some(100u64),
         overseer: Some("overseer0001".to_string()),
+        close_factor: None,
+        swap_contract: None,
     };
 
     let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
@@ -175,6 +194,37 @@ fn update_config() {
         err,
         ContractError::Unauthorized {}
     );
+
+    // the close factor and the swap contract can be cleared with null,
+    // while omitted fields keep their value
+    let info = mock_info("owner0001", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        oracle_contract: None,
+        safe_ratio: None,
+        bid_fee: None,
+        liquidator_fee: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        waiting_period: None,
+        overseer: None,
+        close_factor: None,
+        swap_contract: Some(Some("swap0000".to_string())),
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let value: ConfigResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(value.close_factor, Some(Decimal256::percent(50)));
+    assert_eq!(value.swap_contract, Some("swap0000".to_string()));
+
+    let msg: ExecuteMsg = from_json(
+        br#"{"update_config":{"close_factor":null,"swap_contract":null}}"#,
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let value: ConfigResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(value.close_factor, None);
+    assert_eq!(value.swap_contract, None);
 }
 
 #[test]
@@ -211,6 +261,7 @@ fn submit_bid() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info("addr0000", &[]);
     let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
@@ -291,6 +342,7 @@ fn submit_bid() {
             wait_end: Some(wait_end.seconds()),
             epoch_snapshot: Uint128::zero(),
             scale_snapshot: Uint128::zero(),
+            expires_at: None,
         }
     );
 }
@@ -329,6 +381,7 @@ fn activate_bid() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -412,6 +465,7 @@ fn activate_bid() {
             wait_end: None,
             epoch_snapshot: Uint128::zero(),
             scale_snapshot: Uint128::zero(),
+            expires_at: None,
         }
     );
 }
@@ -450,6 +504,7 @@ fn retract_bid() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -523,6 +578,7 @@ fn retract_unactive_bid() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -615,6 +671,7 @@ fn execute_bid() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -647,6 +704,7 @@ fn execute_bid() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -669,6 +727,7 @@ fn execute_bid() {
             liquidator: "liquidator0000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -708,6 +767,7 @@ fn execute_bid() {
             liquidator: "liquidator0000".to_string(),
             fee_address: None,
             repay_address: None,
+            borrower: None,
         })
         .unwrap(),
     });
@@ -739,6 +799,45 @@ fn execute_bid() {
         ]
     );
 
+    // the settled amount repays the borrower's loan on the market
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(10000u128),
+        msg: to_json_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("market0000".to_string()),
+            borrower: Some("borrower0000".to_string()),
+        })
+        .unwrap(),
+    });
+
+    // a tripped circuit breaker blocks the liquidation, unless the overseer policy
+    // prices liquidations with the ema price
+    deps.querier.with_frozen_oracle_prices(&[&"asset0000".to_string()]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::PriceFrozen(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    deps.querier.with_frozen_liquidation_policy(FrozenLiquidationPolicy::UseEmaPrice);
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "market0000".to_string(),
+            funds: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(4803u128), // 4851 / (1 + tax_rate)
+            }],
+            msg: to_json_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
+                borrower: "borrower0000".to_string(),
+            })
+            .unwrap(),
+        }))
+    );
+
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "custody0000".to_string(),
         amount: Uint128::from(2020206u128),
@@ -746,6 +845,7 @@ fn execute_bid() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -758,6 +858,263 @@ fn execute_bid() {
     );
 }
 
+#[test]
+fn expired_bids() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_tax(
+        Decimal::percent(0),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+    deps.querier
+        .with_collateral_max_ltv(&[(&"asset0000".to_string(), &Decimal256::percent(90))]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        liquidator_fee: Decimal256::zero(),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 100000u64,
+        waiting_period: 60u64,
+        overseer: "overseer0000".to_string(),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string()),
+        &(
+            Decimal256::percent(50),
+            500000,
+            Decimal256::percent(50),
+            500000,
+            env.block.time.seconds(),
+            env.block.time.seconds(),
+        ),
+    )]);
+
+    let _res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 30u8,
+        bid_threshold: Uint256::from(1500000u64),
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let expires_at = env.block.time.plus_seconds(100u64).seconds();
+    let submit_bid = |bidder: &str, amount: u128, premium_slot: u8, expires_at: Option<u64>| {
+        (
+            mock_info(
+                bidder,
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(amount),
+                }],
+            ),
+            ExecuteMsg::SubmitBid {
+                collateral_token: "asset0000".to_string(),
+                premium_slot,
+                expires_at,
+            },
+        )
+    };
+
+    // expiry must be in the future
+    let (info, msg) = submit_bid("addr0000", 1000000u128, 0u8, Some(env.block.time.seconds()));
+    let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("Bid expiry must be in the future"))
+    );
+
+    // two active bids, one of them expiring, and an expiring waiting bid
+    let (info, msg) = submit_bid("addr0000", 1000000u128, 0u8, Some(expires_at));
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let (info, msg) = submit_bid("addr0001", 1000000u128, 0u8, None);
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let (info, msg) = submit_bid("addr0002", 500000u128, 0u8, Some(expires_at));
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let bid: BidResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Bid {
+                bid_idx: Uint128::from(1u128),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bid.expires_at, Some(expires_at));
+
+    // both active bids absorb the liquidation before the expiry
+    let execute_bid = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(1000000u128),
+        msg: to_json_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator0000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("repay0000".to_string()),
+            borrower: None,
+        })
+        .unwrap(),
+    });
+    let info = mock_info("asset0000", &[]);
+    execute(deps.as_mut(), env.clone(), info.clone(), execute_bid.clone()).unwrap();
+
+    let mut env = env;
+    env.block.time = env.block.time.plus_seconds(100u64);
+
+    // expired waiting bids can not be activated
+    let msg = ExecuteMsg::ActivateBids {
+        collateral_token: "asset0000".to_string(),
+        bids_idx: Some(vec![Uint128::from(3u128)]),
+    };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("addr0002", &[]), msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(format!(
+            "Bid expired at {}",
+            expires_at
+        )))
+    );
+
+    // only the 750,000 left of the unexpired bid is available to liquidations
+    let res: LiquidationAmountResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::LiquidationAmount {
+                borrow_amount: Uint256::from(4900000u64),
+                borrow_limit: Uint256::from(4500000u64),
+                collaterals: vec![("asset0000".to_string(), Uint256::from(10000000u64))],
+                collateral_prices: vec![Decimal256::percent(50)],
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.collaterals,
+        vec![("asset0000".to_string(), Uint256::from(1500000u64))]
+    );
+
+    // the expired bids are taken out when the pool is touched, the remaining bid
+    // absorbs the whole liquidation
+    let res = execute(deps.as_mut(), env.clone(), info, execute_bid).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "repay0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(500000u128),
+            }]
+        }))]
+    );
+
+    let bid: BidResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Bid {
+                bid_idx: Uint128::from(2u128),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bid.amount, Uint256::from(250000u64));
+    query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Bid {
+            bid_idx: Uint128::from(1u128),
+        },
+    )
+    .unwrap_err();
+
+    // anyone can clean up expired bids, the refunds owed for the bids expired
+    // by the liquidation are paid first
+    let (info, msg) = submit_bid("addr0003", 1000000u128, 1u8, Some(expires_at + 100));
+    execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    env.block.time = env.block.time.plus_seconds(100u64);
+
+    let msg = ExecuteMsg::CleanupExpiredBids {
+        collateral_token: "asset0000".to_string(),
+        limit: Some(1u32),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(750000u128),
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0000".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0000".to_string(),
+                    amount: Uint128::from(500000u128),
+                })
+                .unwrap(),
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "cleanup_expired_bids"),
+            attr("collateral_token", "asset0000"),
+            attr("expired_bids", "0"),
+        ]
+    );
+
+    let msg = ExecuteMsg::CleanupExpiredBids {
+        collateral_token: "asset0000".to_string(),
+        limit: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0002".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(500000u128),
+                }]
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0003".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(1000000u128),
+                }]
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "cleanup_expired_bids"),
+            attr("collateral_token", "asset0000"),
+            attr("expired_bids", "1"),
+        ]
+    );
+}
+
 #[test]
 fn claim_liquidations() {
     let mut deps = mock_dependencies(&[]);
@@ -808,6 +1165,7 @@ fn claim_liquidations() {
     let msg = ExecuteMsg::SubmitBid {
         collateral_token: "asset0000".to_string(),
         premium_slot: 1u8,
+        expires_at: None,
     };
     let info = mock_info(
         "addr0000",
@@ -839,6 +1197,7 @@ fn claim_liquidations() {
             liquidator: "liquidator00000".to_string(),
             fee_address: Some("fee0000".to_string()),
             repay_address: Some("repay0000".to_string()),
+            borrower: None,
         })
         .unwrap(),
     });
@@ -847,6 +1206,7 @@ fn claim_liquidations() {
     let msg = ExecuteMsg::ClaimLiquidations {
         collateral_token: "asset0000".to_string(),
         bids_idx: None,
+        swap_to_stable: None,
     };
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -860,6 +1220,278 @@ fn claim_liquidations() {
     );
 }
 
+#[test]
+fn claim_liquidations_swap_to_stable() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_tax(
+        Decimal::percent(0),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+    deps.querier
+        .with_collateral_max_ltv(&[(&"asset0000".to_string(), &Decimal256::percent(90))]);
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::zero(),
+        liquidator_fee: Decimal256::zero(),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 1000000u64,
+        waiting_period: 60u64,
+        overseer: "overseer0000".to_string(),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    deps.querier.with_oracle_price(&[(
+        &("asset0000".to_string()),
+        &(
+            Decimal256::percent(50),
+            500000,
+            Decimal256::percent(50),
+            500000,
+            env.block.time.seconds(),
+            env.block.time.seconds(),
+        ),
+    )]);
+
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 30u8,
+        bid_threshold: Uint256::from(100000000u64),
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let bid_expiry = mock_env().block.time.plus_seconds(86400u64).seconds();
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1u8,
+        expires_at: Some(bid_expiry),
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // liquidates collateral_amount * 0.5 * 0.99 uusd of the bid
+    let execute_bid = |collateral_amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "custody0000".to_string(),
+            amount: Uint128::from(collateral_amount),
+            msg: to_json_binary(&Cw20HookMsg::ExecuteBid {
+                liquidator: "liquidator00000".to_string(),
+                fee_address: Some("fee0000".to_string()),
+                repay_address: Some("repay0000".to_string()),
+                borrower: None,
+            })
+            .unwrap(),
+        })
+    };
+    let claim = |min_return: u64, resubmit_bid: bool| ExecuteMsg::ClaimLiquidations {
+        collateral_token: "asset0000".to_string(),
+        bids_idx: None,
+        swap_to_stable: Some(SwapToStable {
+            min_return: Uint256::from(min_return),
+            resubmit_bid,
+        }),
+    };
+    let swap_reply = || Reply {
+        id: SWAP_CLAIM_OPERATION,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    let set_stable_balance = |deps: &mut OwnedDeps<_, _, WasmMockQuerier>, amount: u128| {
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(amount),
+            }],
+        );
+    };
+
+    let info = mock_info("asset0000", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), execute_bid(1000000u128)).unwrap();
+
+    // the swap contract has to be set
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), claim(1, false));
+    match res {
+        Err(ContractError::SwapContractNotSet {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the minimum return can not be zero
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), claim(0, false));
+    match res {
+        Err(ContractError::ZeroMinReturn {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UpdateConfig {
+        oracle_contract: None,
+        safe_ratio: None,
+        bid_fee: None,
+        liquidator_fee: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        waiting_period: None,
+        overseer: None,
+        close_factor: None,
+        swap_contract: Some(Some("swap0000".to_string())),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    // the swap contract is allowed to take the claimed collateral
+    set_stable_balance(&mut deps, 505000u128);
+    let msg = claim(480000, false);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0000".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: "swap0000".to_string(),
+                    amount: Uint128::from(1000000u128),
+                    expires: None,
+                })
+                .unwrap(),
+            })),
+            SubMsg::reply_on_success(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: "swap0000".to_string(),
+                    funds: vec![],
+                    msg: to_json_binary(&SwapExecteMsg::SwapDenom {
+                        from_coin: Coin {
+                            denom: "asset0000".to_string(),
+                            amount: Uint128::from(1000000u128),
+                        },
+                        target_denom: "uusd".to_string(),
+                        to_address: Some(MOCK_CONTRACT_ADDR.to_string()),
+                    })
+                    .unwrap(),
+                }),
+                SWAP_CLAIM_OPERATION,
+            ),
+        ]
+    );
+
+    // the allowance left is revoked and the proceeds go to the bidder
+    set_stable_balance(&mut deps, 985000u128);
+    let res = reply(deps.as_mut(), mock_env(), swap_reply()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "asset0000".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::DecreaseAllowance {
+                    spender: "swap0000".to_string(),
+                    amount: Uint128::from(1000000u128),
+                    expires: None,
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0000".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(480000u128),
+                }],
+            })),
+        ]
+    );
+
+    // the swap has to return the minimum amount
+    execute(deps.as_mut(), mock_env(), info.clone(), execute_bid(200000u128)).unwrap();
+    set_stable_balance(&mut deps, 505000u128);
+    let msg = claim(100000, false);
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    set_stable_balance(&mut deps, 595000u128);
+    let res = reply(deps.as_mut(), mock_env(), swap_reply());
+    match res {
+        Err(ContractError::SwapReturnTooLow(swap_return, min_return)) => {
+            assert_eq!(swap_return, Uint256::from(90000u64));
+            assert_eq!(min_return, Uint256::from(100000u64));
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // no bid is resubmitted while bid submissions are paused
+    execute(deps.as_mut(), mock_env(), info, execute_bid(200000u128)).unwrap();
+    let pause = |paused: bool| ExecuteMsg::SetPaused {
+        operation: PausableOperation::BidSubmissions,
+        paused,
+    };
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian0000".to_string()),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("guardian0000", &[]), pause(true)).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), claim(90000, true));
+    match res {
+        Err(ContractError::Paused(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("guardian0000", &[]), pause(false)).unwrap();
+
+    // nor once the claimed bid expired
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(86400u64);
+    let res = execute(deps.as_mut(), env, mock_info("addr0000", &[]), claim(90000, true));
+    match res {
+        Err(ContractError::Std(StdError::GenericErr { msg, .. })) => {
+            assert_eq!(msg, "Bid expiry must be in the future")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the proceeds are submitted as a new bid in the same premium slot, with
+    // the expiry of the claimed bid
+    set_stable_balance(&mut deps, 406000u128);
+    let msg = claim(90000, true);
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    set_stable_balance(&mut deps, 501000u128);
+    let res = reply(deps.as_mut(), mock_env(), swap_reply()).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "resubmit_bid"),
+            attr("bidder", "addr0000"),
+            attr("bid_idx", "2"),
+            attr("amount", "95000"),
+        ]
+    );
+
+    let bid: BidResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Bid {
+                bid_idx: Uint128::from(2u128),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bid.premium_slot, 1u8);
+    assert_eq!(bid.amount, Uint256::from(95000u64));
+    assert_eq!(bid.expires_at, Some(bid_expiry));
+}
+
 #[test]
 fn update_collateral_info() {
     let mut deps = mock_dependencies(&[]);
@@ -931,3 +1563,388 @@ fn update_collateral_info() {
         }
     );
 }
+
+#[test]
+fn pause_operations() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .with_collateral_max_ltv(&[(&"asset0000".to_string(), &Decimal256::percent(90))]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        liquidator_fee: Decimal256::percent(0),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        waiting_period: 60u64,
+        overseer: "overseer0000".to_string(),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 30u8,
+        bid_threshold: Uint256::zero(),
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian0000".to_string()),
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Deposits,
+        paused: true,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("guardian0000", &[]), msg)
+        .unwrap_err();
+    assert_eq!(err, ContractError::NotPausable("deposits".to_string()));
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::BidSubmissions,
+        paused: true,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), mock_env(), mock_info("guardian0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Liquidations,
+        paused: true,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("guardian0000", &[]), msg).unwrap();
+
+    let status: PauseStatusResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap()).unwrap();
+    assert_eq!(
+        status,
+        PauseStatusResponse {
+            guardian: Some("guardian0000".to_string()),
+            paused: vec![
+                PausableOperation::BidSubmissions,
+                PausableOperation::Liquidations,
+            ],
+        }
+    );
+
+    let bid_msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1u8,
+        expires_at: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), bid_msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Paused("bid_submissions".to_string()));
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "custody0000".to_string(),
+        amount: Uint128::from(1000000u128),
+        msg: to_json_binary(&Cw20HookMsg::ExecuteBid {
+            liquidator: "liquidator00000".to_string(),
+            fee_address: Some("fee0000".to_string()),
+            repay_address: Some("repay0000".to_string()),
+            borrower: None,
+        })
+        .unwrap(),
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg)
+        .unwrap_err();
+    assert_eq!(err, ContractError::Paused("liquidations".to_string()));
+
+    // resume bid submissions
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::BidSubmissions,
+        paused: false,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("guardian0000", &[]), msg).unwrap();
+    execute(deps.as_mut(), mock_env(), info, bid_msg).unwrap();
+}
+
+#[test]
+fn config_history() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .with_collateral_max_ltv(&[(&"token0000".to_string(), &Decimal256::percent(90))]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        liquidator_fee: Decimal256::percent(0),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        waiting_period: 60u64,
+        overseer: "overseer0000".to_string(),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        oracle_contract: None,
+        safe_ratio: None,
+        bid_fee: Some(Decimal256::percent(2)),
+        liquidator_fee: None,
+        liquidation_threshold: None,
+        price_timeframe: None,
+        waiting_period: Some(60u64),
+        overseer: None,
+        close_factor: None,
+        swap_contract: None,
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "token0000".to_string(),
+        max_slot: 30u8,
+        bid_threshold: Uint256::from(10000u128),
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateCollateralInfo {
+        collateral_token: "token0000".to_string(),
+        bid_threshold: None,
+        max_slot: Some(20u8),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    let change = |field: &str, old_value: &str, new_value: &str| ConfigChange {
+        field: field.to_string(),
+        old_value: old_value.to_string(),
+        new_value: new_value.to_string(),
+    };
+    assert_eq!(
+        history.entries,
+        vec![
+            ConfigHistoryEntryResponse {
+                id: 0,
+                action: "update_config".to_string(),
+                sender: "owner0000".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![change("bid_fee", "\"0.01\"", "\"0.02\"")],
+            },
+            ConfigHistoryEntryResponse {
+                id: 1,
+                action: "whitelist_collateral".to_string(),
+                sender: "owner0000".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![
+                    change("token0000.bid_threshold", "null", "\"10000\""),
+                    change("token0000.max_slot", "null", "30"),
+                    change("token0000.premium_rate_per_slot", "null", "\"0.01\""),
+                ],
+            },
+            ConfigHistoryEntryResponse {
+                id: 2,
+                action: "update_collateral_info".to_string(),
+                sender: "owner0000".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![change("token0000.max_slot", "30", "20")],
+            },
+        ]
+    );
+}
+
+#[test]
+fn migrate_legacy_state() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier
+        .with_collateral_max_ltv(&[(&"asset0000".to_string(), &Decimal256::percent(90))]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        oracle_contract: "oracle0000".to_string(),
+        stable_denom: "uusd".to_string(),
+        safe_ratio: Decimal256::percent(10),
+        bid_fee: Decimal256::percent(1),
+        liquidator_fee: Decimal256::percent(0),
+        liquidation_threshold: Uint256::from(100000000u64),
+        price_timeframe: 60u64,
+        waiting_period: 60u64,
+        overseer: "overseer0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::WhitelistCollateral {
+        collateral_token: "asset0000".to_string(),
+        max_slot: 30u8,
+        bid_threshold: Uint256::zero(),
+        premium_rate_per_slot: Decimal256::percent(1),
+    };
+    let info = mock_info("owner0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SubmitBid {
+        collateral_token: "asset0000".to_string(),
+        premium_slot: 1u8,
+        expires_at: None,
+    };
+    for bidder in ["addr0000", "addr0001", "addr0000"] {
+        let info = mock_info(
+            bidder,
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::from(1000000u128),
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+    }
+
+    // rewrite to the layout of releases without cw2 info: singletons and the
+    // per-bidder bucket instead of the collateral and bidder index
+    for key in [&b"config"[..], b"bid_idx"] {
+        let value = deps.storage.get(key).unwrap();
+        deps.storage.remove(key);
+        deps.storage.set(&to_length_prefixed(key), &value);
+    }
+    let index_prefix = to_length_prefixed(b"bid__collateral_bidder");
+    let index_keys: Vec<Vec<u8>> = deps
+        .storage
+        .range(Some(&index_prefix), None, Order::Ascending)
+        .map(|(k, _)| k)
+        .take_while(|k| k.starts_with(&index_prefix))
+        .collect();
+    assert_eq!(index_keys.len(), 3);
+    for key in index_keys {
+        deps.storage.remove(&key);
+    }
+    let collateral_raw = deps.api.addr_canonicalize("asset0000").unwrap();
+    let mut legacy_keys = vec![];
+    for (bidder, bid_idx) in [("addr0000", 1u128), ("addr0001", 2u128), ("addr0000", 3u128)] {
+        let bidder_raw = deps.api.addr_canonicalize(bidder).unwrap();
+        let key = [
+            to_length_prefixed_nested(&[
+                b"bid_by_user",
+                collateral_raw.as_slice(),
+                bidder_raw.as_slice(),
+            ]),
+            bid_idx.to_be_bytes().to_vec(),
+        ]
+        .concat();
+        deps.storage.set(&key, &to_json_vec(&true).unwrap());
+        legacy_keys.push(key);
+    }
+    deps.storage.remove(b"contract_info");
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1], attr("previous_version", "none"));
+
+    // bids not moved yet are listed from the legacy index, once each when they
+    // were updated since the migration
+    let msg_retract = ExecuteMsg::RetractBid {
+        bid_idx: Uint128::from(1u128),
+        amount: Some(Uint256::from(100u64)),
+    };
+    let info = mock_info("addr0000", &[]);
+    execute(deps.as_mut(), mock_env(), info, msg_retract).unwrap();
+    let bids_by_user = |deps: &OwnedDeps<_, _, WasmMockQuerier>, bidder: &str| {
+        let bids_response: BidsResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BidsByUser {
+                    collateral_token: "asset0000".to_string(),
+                    bidder: bidder.to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        bids_response
+            .bids
+            .iter()
+            .map(|bid| bid.idx.u128())
+            .collect::<Vec<u128>>()
+    };
+    assert_eq!(bids_by_user(&deps, "addr0000"), vec![1u128, 3u128]);
+    assert_eq!(bids_by_user(&deps, "addr0001"), vec![2u128]);
+
+    // the bids are moved in pages, by anyone
+    let msg_migrate = ExecuteMsg::MigrateBids { limit: Some(2) };
+    let info = mock_info("addr0002", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg_migrate).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate_bids"),
+            attr("migrated", "2"),
+            attr("done", "false"),
+        ]
+    );
+    assert_eq!(bids_by_user(&deps, "addr0000"), vec![1u128, 3u128]);
+    assert_eq!(bids_by_user(&deps, "addr0001"), vec![2u128]);
+    let msg_migrate = ExecuteMsg::MigrateBids { limit: None };
+    let res = execute(deps.as_mut(), mock_env(), info, msg_migrate).unwrap();
+    assert_eq!(res.attributes[1], attr("migrated", "1"));
+    assert_eq!(res.attributes[2], attr("done", "true"));
+
+    let bids_response: BidsResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BidsByUser {
+                collateral_token: "asset0000".to_string(),
+                bidder: "addr0000".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        bids_response.bids.iter().map(|bid| bid.idx).collect::<Vec<Uint128>>(),
+        vec![Uint128::from(1u128), Uint128::from(3u128)]
+    );
+    for key in legacy_keys {
+        assert_eq!(deps.storage.get(&key), None);
+    }
+
+    // the bid index counter carries over
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1000000u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert!(res.attributes.contains(&attr("bid_idx", "4")));
+}
@@ -3,10 +3,13 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use moneymarket::config_history::ConfigHistoryResponse;
 use moneymarket::market::{
-    BorrowerInfoResponse, BorrowerInfosResponse, ConfigResponse, Cw20HookMsg, EpochStateResponse,
-    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    BorrowerInfoResponse, BorrowerInfosResponse, ConfigResponse, Cw20HookMsg, DepositorInfoResponse,
+    EpochStateResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, RatesResponse,
+    ShutdownStateResponse, WithdrawalTicketResponse, WithdrawalTicketsResponse,
 };
+use moneymarket::pause::PauseStatusResponse;
 use moneymarket_market::state::State;
 
 fn main() {
@@ -25,4 +28,11 @@ fn main() {
     export_schema(&schema_for!(EpochStateResponse), &out_dir);
     export_schema(&schema_for!(BorrowerInfoResponse), &out_dir);
     export_schema(&schema_for!(BorrowerInfosResponse), &out_dir);
+    export_schema(&schema_for!(RatesResponse), &out_dir);
+    export_schema(&schema_for!(DepositorInfoResponse), &out_dir);
+    export_schema(&schema_for!(WithdrawalTicketResponse), &out_dir);
+    export_schema(&schema_for!(WithdrawalTicketsResponse), &out_dir);
+    export_schema(&schema_for!(PauseStatusResponse), &out_dir);
+    export_schema(&schema_for!(ShutdownStateResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
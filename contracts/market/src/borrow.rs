@@ -1,21 +1,31 @@
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    attr, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult, WasmMsg, to_json_binary,
+    attr, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult, WasmMsg, to_json_binary,
 };
 
-use moneymarket::interest_model::BorrowRateResponse;
-use moneymarket::market::{BorrowerInfoResponse, BorrowerInfosResponse};
+use cw20::Cw20ExecuteMsg;
+use moneymarket::interest_model::{BorrowRateResponse, ExecuteMsg as InterestModelExecuteMsg};
+use moneymarket::market::{BorrowerInfoResponse, BorrowerInfosResponse, ExecuteMsg};
+use moneymarket::oracle_pyth::price_update_messages;
 use moneymarket::overseer::BorrowLimitResponse;
-use moneymarket::querier::{deduct_tax, query_balance, query_supply};
+use moneymarket::querier::{
+    deduct_tax, query_balance, query_price_update_fee, query_supply, query_token_balance,
+};
 use moneymarket::thirdpart::ExecuteMsg as ThirdpartExecuteMsg;
 
-use crate::deposit::compute_exchange_rate_raw;
+use crate::contract::INITIAL_DEPOSIT_AMOUNT;
+use crate::deposit::{compute_depositor_reward, compute_exchange_rate, compute_exchange_rate_raw};
 use crate::error::ContractError;
-use crate::querier::{query_borrow_limit, query_borrow_rate, query_target_deposit_rate};
+use crate::querier::{
+    query_borrow_limit, query_borrow_rate, query_liquidation_contract, query_oracle_contract,
+    query_target_deposit_rate,
+};
 use crate::state::{
-    read_borrower_info, read_borrower_infos, read_config, read_state, store_borrower_info,
-    store_state, BorrowerInfo, Config, State,
+    read_borrower_info, read_borrower_infos, read_borrowers_by_debt, read_config,
+    read_depositor_info, read_shutdown_state, read_state, store_borrower_info,
+    store_depositor_info, store_state, BorrowerInfo, Config, DepositorInfo, State,
 };
+use crate::withdrawal::{queued_redeem_amount, withdrawal_queue_messages};
 
 
 pub fn borrow_stable(
@@ -34,11 +44,12 @@ pub fn borrow_stable(
     let mut liability: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
 
     // Compute interest
-    compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+    let hook_messages =
+        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
     compute_borrower_interest(&state, &mut liability);
 
     // Compute KPT reward
-    compute_reward(&mut state, env.block.height);
+    compute_reward(&mut state, &config, env.block.height);
     compute_borrower_reward(&state, &mut liability);
 
     let overseer = deps.api.addr_humanize(&config.overseer_contract)?;
@@ -63,7 +74,9 @@ pub fn borrow_stable(
     )?;
 
     // Assert borrow amount
-    assert_max_borrow_factor(&config, &state, current_balance, borrow_amount)?;
+    let queued_amount = queued_redeem_amount(deps.as_ref(), &config, &state)?;
+    assert_max_borrow_factor(&config, &state, current_balance, borrow_amount, queued_amount)?;
+    assert_max_total_liabilities(&config, &state, borrow_amount)?;
 
     liability.loan_amount += borrow_amount;
     state.total_liabilities += Decimal256::from_uint256(borrow_amount);
@@ -82,6 +95,7 @@ pub fn borrow_stable(
                 },
             )?],
         }))
+        .add_messages(hook_messages)
         .add_attributes(vec![
             attr("action", "borrow_stable"),
             attr("borrower", borrower),
@@ -89,37 +103,97 @@ pub fn borrow_stable(
         ]))
 }
 
-pub fn repay_stable_from_liquidation(
+/// Push the price update to the oracle first, then borrow through
+/// `BorrowStableAfterPriceUpdate` so the borrow limit uses the fresh prices
+pub fn borrow_stable_with_price_update(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    borrower: Addr,
-    prev_balance: Uint256,
+    borrow_amount: Uint256,
+    to: Option<Addr>,
+    price_update: Vec<Binary>,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
-    if config.overseer_contract != deps.api.addr_canonicalize(info.sender.as_str())? {
-        return Err(ContractError::Unauthorized {});
-    }
-
-    let cur_balance: Uint256 = query_balance(
+    let oracle_contract = query_oracle_contract(
         deps.as_ref(),
-        env.contract.address.clone(),
-        config.stable_denom.to_string(),
+        deps.api.addr_humanize(&config.overseer_contract)?,
+    )?;
+    let fee = query_price_update_fee(deps.as_ref(), oracle_contract.clone(), price_update.clone())?;
+
+    let mut messages = price_update_messages(
+        &oracle_contract,
+        price_update,
+        fee,
+        info.funds,
+        &info.sender,
     )?;
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&ExecuteMsg::BorrowStableAfterPriceUpdate {
+            borrower: info.sender.to_string(),
+            borrow_amount,
+            to: to.map(|to| to.to_string()),
+        })?,
+    }));
 
-    // override env
-    let mut info = info;
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "update_prices"),
+        attr("borrower", info.sender),
+    ]))
+}
 
+pub fn borrow_stable_after_price_update(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    borrower: Addr,
+    borrow_amount: Uint256,
+    to: Option<Addr>,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // override info
+    let mut info = info;
     info.sender = borrower;
-    info.funds = vec![Coin {
-        denom: config.stable_denom,
-        amount: (cur_balance - prev_balance).into(),
-    }];
+    info.funds = vec![];
 
-    repay_stable(deps, env, info)
+    borrow_stable(deps, env, info, borrow_amount, to)
 }
 
-pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+/// Repay with exactly the funds sent by the liquidation queue, or by the
+/// overseer from the yield reserve. Unused funds go back to the borrower
+pub fn repay_stable_from_liquidation(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    borrower: Addr,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if config.overseer_contract != sender_raw {
+        let overseer_contract = deps.api.addr_humanize(&config.overseer_contract)?;
+        let liquidation_contract = query_liquidation_contract(deps.as_ref(), overseer_contract)?;
+        if info.sender != liquidation_contract {
+            return Err(ContractError::Unauthorized {});
+        }
+    }
+
+    // override info
+    let mut info = info;
+    info.sender = borrower.clone();
+
+    repay_stable(deps, env, info, borrower)
+}
+
+pub fn repay_stable(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    borrower: Addr,
+) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
 
     // Check stable denom deposit
@@ -137,12 +211,11 @@ pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respon
 
     let mut state: State = read_state(deps.storage)?;
 
-    let borrower = info.sender;
     let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
     let mut liability: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
 
     // Compute interest
-    compute_interest(
+    let hook_messages = compute_interest(
         deps.as_ref(),
         &config,
         &mut state,
@@ -152,7 +225,7 @@ pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respon
     compute_borrower_interest(&state, &mut liability);
 
     // Compute KPT reward
-    compute_reward(&mut state, env.block.height);
+    compute_reward(&mut state, &config, env.block.height);
     compute_borrower_reward(&state, &mut liability);
 
     let repay_amount: Uint256;
@@ -164,7 +237,7 @@ pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respon
         // Payback left repay amount to sender
         messages.push(
             CosmosMsg::Bank(BankMsg::Send {
-                to_address: borrower.to_string(),
+                to_address: info.sender.to_string(),
                 amount: vec![deduct_tax(
                     deps.as_ref(),
                     Coin {
@@ -184,11 +257,101 @@ pub fn repay_stable(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respon
     store_borrower_info(deps.storage, &borrower_raw, &liability)?;
     store_state(deps.storage, &state)?;
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "repay_stable"),
-        attr("borrower", borrower),
-        attr("repay_amount", repay_amount),
-    ]))
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(hook_messages)
+        .add_messages(withdrawal_queue_messages(deps.as_ref(), &env)?)
+        .add_attributes(vec![
+            attr("action", "repay_stable"),
+            attr("borrower", borrower),
+            attr("repay_amount", repay_amount),
+        ]))
+}
+
+/// Repay the liability of `borrower` by burning aTokens sent by `sender`
+/// at the current exchange rate. Unused aTokens are returned to the sender
+pub fn repay_stable_with_atoken(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    borrower: Addr,
+    atoken_amount: Uint256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
+    let mut liability: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
+
+    // Compute interest
+    let hook_messages =
+        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+    compute_borrower_interest(&state, &mut liability);
+
+    // Compute KPT reward
+    compute_reward(&mut state, &config, env.block.height);
+    compute_borrower_reward(&state, &mut liability);
+
+    // Burning aTokens against liabilities keeps the exchange rate unchanged
+    let exchange_rate = compute_exchange_rate(deps.as_ref(), &config, &state, None)?;
+    let atoken_value = atoken_amount * exchange_rate;
+
+    let repay_amount: Uint256;
+    let burn_amount: Uint256;
+    if liability.loan_amount < atoken_value {
+        repay_amount = liability.loan_amount;
+
+        // round up so the burnt aTokens always cover the repaid amount
+        let mut amount = repay_amount / exchange_rate;
+        if amount * exchange_rate < repay_amount {
+            amount += Uint256::one();
+        }
+        burn_amount = amount.min(atoken_amount);
+    } else {
+        repay_amount = atoken_value;
+        burn_amount = atoken_amount;
+    }
+
+    liability.loan_amount = liability.loan_amount - repay_amount;
+    state.total_liabilities = state.total_liabilities - Decimal256::from_uint256(repay_amount);
+    state.prev_atoken_supply = state.prev_atoken_supply - burn_amount;
+
+    store_borrower_info(deps.storage, &borrower_raw, &liability)?;
+    store_state(deps.storage, &state)?;
+
+    let atoken_addr = deps.api.addr_humanize(&config.atoken_contract)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !burn_amount.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: atoken_addr.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                amount: burn_amount.into(),
+            })?,
+        }));
+    }
+
+    // Payback left aTokens to sender
+    if atoken_amount > burn_amount {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: atoken_addr.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: sender.to_string(),
+                amount: (atoken_amount - burn_amount).into(),
+            })?,
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(hook_messages)
+        .add_attributes(vec![
+            attr("action", "repay_stable_with_atoken"),
+            attr("borrower", borrower),
+            attr("repay_amount", repay_amount),
+            attr("burn_amount", burn_amount),
+        ]))
 }
 
 pub fn claim_rewards(
@@ -205,18 +368,33 @@ pub fn claim_rewards(
     let mut liability: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
 
     // Compute interest
-    compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+    let hook_messages =
+        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
     compute_borrower_interest(&state, &mut liability);
 
     // Compute KPT reward
-    compute_reward(&mut state, env.block.height);
+    compute_reward(&mut state, &config, env.block.height);
     compute_borrower_reward(&state, &mut liability);
 
-    let claim_amount = liability.pending_rewards * Uint256::one();
-    liability.pending_rewards = liability.pending_rewards - Decimal256::from_uint256(claim_amount);
+    let atoken_balance = query_token_balance(
+        deps.as_ref(),
+        deps.api.addr_humanize(&config.atoken_contract)?,
+        borrower.clone(),
+    )?;
+    let mut depositor_info: DepositorInfo = read_depositor_info(deps.storage, &borrower_raw);
+    compute_depositor_reward(&state, &mut depositor_info, atoken_balance);
+
+    let borrower_claim_amount = liability.pending_rewards * Uint256::one();
+    liability.pending_rewards =
+        liability.pending_rewards - Decimal256::from_uint256(borrower_claim_amount);
+    let depositor_claim_amount = depositor_info.pending_rewards * Uint256::one();
+    depositor_info.pending_rewards =
+        depositor_info.pending_rewards - Decimal256::from_uint256(depositor_claim_amount);
+    let claim_amount = borrower_claim_amount + depositor_claim_amount;
 
     store_state(deps.storage, &state)?;
     store_borrower_info(deps.storage, &borrower_raw, &liability)?;
+    store_depositor_info(deps.storage, &borrower_raw, &depositor_info)?;
 
     let messages: Vec<CosmosMsg> = if !claim_amount.is_zero() {
         vec![CosmosMsg::Wasm(WasmMsg::Execute {
@@ -238,23 +416,30 @@ pub fn claim_rewards(
         vec![]
     };
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "claim_rewards"),
-        attr("claim_amount", claim_amount),
-    ]))
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(hook_messages)
+        .add_attributes(vec![
+            attr("action", "claim_rewards"),
+            attr("claim_amount", claim_amount),
+        ]))
 }
 
 /// Compute interest and update state
 /// total liabilities and total reserves
+/// Returns the interest model hook messages, see `interest_model_hook_messages`.
+/// Interest stops accruing once the market is shut down
 pub fn compute_interest(
     deps: Deps,
     config: &Config,
     state: &mut State,
     block_height: u64,
     deposit_amount: Option<Uint256>,
-) -> StdResult<()> {
-    if state.last_interest_updated >= block_height {
-        return Ok(());
+) -> StdResult<Vec<CosmosMsg>> {
+    if state.last_interest_updated >= block_height
+        || read_shutdown_state(deps.storage)?.is_some()
+    {
+        return Ok(vec![]);
     }
 
     let atoken_supply = query_supply(deps, deps.api.addr_humanize(&config.atoken_contract)?)?;
@@ -272,6 +457,7 @@ pub fn compute_interest(
         state.total_liabilities,
         state.total_reserves,
     )?;
+    let hook_messages = interest_model_hook_messages(deps, config, state, balance)?;
 
     let target_deposit_rate: Decimal256 =
         query_target_deposit_rate(deps, deps.api.addr_humanize(&config.overseer_contract)?)?;
@@ -285,7 +471,30 @@ pub fn compute_interest(
         target_deposit_rate,
     );
 
-    Ok(())
+    Ok(hook_messages)
+}
+
+/// Reports the utilization the borrow rate was just computed with to the
+/// interest model, so adaptive models can move their rate over time
+pub(crate) fn interest_model_hook_messages(
+    deps: Deps,
+    config: &Config,
+    state: &State,
+    balance: Uint256,
+) -> StdResult<Vec<CosmosMsg>> {
+    if !config.interest_model_hook {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&config.interest_model)?.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&InterestModelExecuteMsg::UpdateRate {
+            market_balance: balance,
+            total_liabilities: state.total_liabilities,
+            total_reserves: state.total_reserves,
+        })?,
+    })])
 }
 
 // CONTRACT: to use this function as state update purpose,
@@ -346,18 +555,57 @@ pub(crate) fn compute_borrower_interest(state: &State, liability: &mut BorrowerI
     liability.interest_index = state.global_interest_index;
 }
 
-/// Compute distributed reward and update global index
-pub fn compute_reward(state: &mut State, block_height: u64) {
+/// Compute distributed reward and update global indexes
+pub fn compute_reward(state: &mut State, config: &Config, block_height: u64) {
+    let atoken_supply = state.prev_atoken_supply;
+    compute_reward_raw(state, config, atoken_supply, block_height);
+}
+
+/// Compute distributed reward with the given aToken supply,
+/// splitting it between borrowers and aToken holders
+pub fn compute_reward_raw(
+    state: &mut State,
+    config: &Config,
+    atoken_supply: Uint256,
+    block_height: u64,
+) {
     if state.last_reward_updated >= block_height {
         return;
     }
 
     let passed_blocks = Decimal256::from_uint256(block_height - state.last_reward_updated);
-    let reward_accrued = passed_blocks * state.kpt_emission_rate;
+    let mut reward_accrued = passed_blocks * state.kpt_emission_rate;
     let borrow_amount = state.total_liabilities / state.global_interest_index;
 
-    if !reward_accrued.is_zero() && !borrow_amount.is_zero() {
-        state.global_reward_index += reward_accrued / borrow_amount;
+    // Never distribute beyond the emission budget
+    if let Some(budget) = state.kpt_emission_budget {
+        let remaining = if budget > state.total_kpt_emitted {
+            budget - state.total_kpt_emitted
+        } else {
+            Decimal256::zero()
+        };
+        reward_accrued = reward_accrued.min(remaining);
+    }
+
+    let deposit_reward = reward_accrued * config.depositor_reward_share;
+    let borrow_reward = reward_accrued - deposit_reward;
+    if !borrow_reward.is_zero() && !borrow_amount.is_zero() {
+        state.global_reward_index += borrow_reward / borrow_amount;
+        state.total_kpt_emitted += borrow_reward;
+    }
+
+    // aTokens held by the market itself, the initial deposit and the
+    // escrowed ones, earn nothing
+    let market_atokens = Uint256::from(INITIAL_DEPOSIT_AMOUNT) + state.escrowed_atokens;
+    let atoken_supply = if atoken_supply > market_atokens {
+        atoken_supply - market_atokens
+    } else {
+        Uint256::zero()
+    };
+    if !deposit_reward.is_zero() && !atoken_supply.is_zero() {
+        state.global_deposit_reward_index +=
+            deposit_reward / Decimal256::from_uint256(atoken_supply);
+        state.total_kpt_emitted += deposit_reward;
     }
 
     state.last_reward_updated = block_height;
@@ -394,20 +642,24 @@ pub fn query_borrower_info(
     compute_interest(deps, &config, &mut state, block_height, None)?;
     compute_borrower_interest(&state, &mut borrower_info);
 
-    // compute_reward(&mut state, block_height);
-    // compute_borrower_reward(&state, &mut borrower_info);
+    compute_reward(&mut state, &config, block_height);
+    compute_borrower_reward(&state, &mut borrower_info);
 
     Ok(BorrowerInfoResponse {
         borrower: borrower.to_string(),
         interest_index: borrower_info.interest_index,
+        reward_index: borrower_info.reward_index,
         loan_amount: borrower_info.loan_amount,
+        pending_rewards: borrower_info.pending_rewards,
     })
 }
 
 pub fn query_borrower_infos(
     deps: Deps,
+    env: Env,
     start_after: Option<Addr>,
     limit: Option<u32>,
+    block_height: Option<u64>,
 ) -> StdResult<BorrowerInfosResponse> {
     let start_after = if let Some(start_after) = start_after {
         Some(deps.api.addr_canonicalize(start_after.as_str())?)
@@ -415,7 +667,69 @@ pub fn query_borrower_infos(
         None
     };
 
-    let borrower_infos: Vec<BorrowerInfoResponse> = read_borrower_infos(deps, start_after, limit)?;
+    let borrower_infos = read_borrower_infos(deps, start_after, limit)?;
+    project_borrower_infos(deps, env, borrower_infos, block_height)
+}
+
+/// Borrowers ordered by their recorded loan amount, largest first
+pub fn query_borrowers_by_debt(
+    deps: Deps,
+    env: Env,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+    block_height: Option<u64>,
+) -> StdResult<BorrowerInfosResponse> {
+    let start_after = if let Some(start_after) = start_after {
+        Some(deps.api.addr_canonicalize(start_after.as_str())?)
+    } else {
+        None
+    };
+
+    let borrower_infos = read_borrowers_by_debt(deps, start_after, limit)?;
+    project_borrower_infos(deps, env, borrower_infos, block_height)
+}
+
+fn project_borrower_infos(
+    deps: Deps,
+    env: Env,
+    borrower_infos: Vec<BorrowerInfoResponse>,
+    block_height: Option<u64>,
+) -> StdResult<BorrowerInfosResponse> {
+    let block_height = if let Some(block_height) = block_height {
+        block_height
+    } else {
+        env.block.height
+    };
+
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    compute_interest(deps, &config, &mut state, block_height, None)?;
+    compute_reward(&mut state, &config, block_height);
+
+    // project every stored liability to the queried block height
+    let borrower_infos: Vec<BorrowerInfoResponse> = borrower_infos
+        .into_iter()
+        .map(|res| {
+            let mut borrower_info = BorrowerInfo {
+                interest_index: res.interest_index,
+                reward_index: res.reward_index,
+                loan_amount: res.loan_amount,
+                pending_rewards: res.pending_rewards,
+            };
+            compute_borrower_interest(&state, &mut borrower_info);
+            compute_borrower_reward(&state, &mut borrower_info);
+
+            BorrowerInfoResponse {
+                borrower: res.borrower,
+                interest_index: borrower_info.interest_index,
+                reward_index: borrower_info.reward_index,
+                loan_amount: borrower_info.loan_amount,
+                pending_rewards: borrower_info.pending_rewards,
+            }
+        })
+        .collect();
+
     Ok(BorrowerInfosResponse { borrower_infos })
 }
 
@@ -424,9 +738,11 @@ fn assert_max_borrow_factor(
     state: &State,
     current_balance: Uint256,
     borrow_amount: Uint256,
+    queued_amount: Uint256,
 ) -> Result<(), ContractError> {
     let current_balance = Decimal256::from_uint256(current_balance);
     let borrow_amount = Decimal256::from_uint256(borrow_amount);
+    let queued_amount = Decimal256::from_uint256(queued_amount);
 
     // Assert max borrow factor
     if state.total_liabilities + borrow_amount
@@ -439,8 +755,8 @@ fn assert_max_borrow_factor(
         return Err(ContractError::MaxBorrowFactorReached(error_msg));
     }
 
-    // Assert available balance
-    if borrow_amount + state.total_reserves > current_balance {
+    // Assert available balance, queued withdrawals are served first
+    if borrow_amount + queued_amount + state.total_reserves > current_balance {
         return Err(ContractError::NoStableAvailable(
             config.stable_denom.clone(),
         ));
@@ -448,3 +764,21 @@ fn assert_max_borrow_factor(
 
     Ok(())
 }
+
+fn assert_max_total_liabilities(
+    config: &Config,
+    state: &State,
+    borrow_amount: Uint256,
+) -> Result<(), ContractError> {
+    if let Some(max_total_liabilities) = config.max_total_liabilities {
+        if state.total_liabilities + Decimal256::from_uint256(borrow_amount)
+            > Decimal256::from_uint256(max_total_liabilities)
+        {
+            return Err(ContractError::MaxTotalLiabilitiesReached(
+                max_total_liabilities.to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -2,14 +2,30 @@
 use cosmwasm_std::entry_point;
 
 use crate::borrow::{
-    borrow_stable, claim_rewards, compute_interest, compute_interest_raw, compute_reward,
-    query_borrower_info, query_borrower_infos, repay_stable, repay_stable_from_liquidation,
+    borrow_stable, borrow_stable_after_price_update, borrow_stable_with_price_update, claim_rewards, compute_interest, compute_interest_raw, compute_reward,
+    interest_model_hook_messages, query_borrower_info, query_borrower_infos,
+    query_borrowers_by_debt, repay_stable, repay_stable_from_liquidation, repay_stable_with_atoken,
+};
+use crate::deposit::{
+    balance_change_hook, compute_exchange_rate_raw, deposit_stable, query_depositor_info,
+    redeem_stable,
 };
-use crate::deposit::{compute_exchange_rate_raw, deposit_stable, redeem_stable};
 use crate::error::ContractError;
+use crate::pause::{assert_not_paused, query_pause_status, set_paused, update_guardian};
 use crate::querier::{query_borrow_rate, query_target_deposit_rate, query_kpt_emission_rate};
 use crate::response::MsgInstantiateContractResponse;
-use crate::state::{read_config, read_state, store_config, store_state, read_new_owner, store_new_owner, Config, State, NewOwnerAddr};
+use crate::shutdown::{
+    assert_not_shutdown, query_shutdown_state, redeem_stable_pro_rata, settle_loan, shutdown,
+};
+use crate::state::{
+    migrate_legacy_state, migrate_liabilities, read_config, read_shutdown_state, read_state,
+    read_withdrawal_queue, store_config, store_state,
+    read_new_owner, store_new_owner, Config, State, NewOwnerAddr,
+};
+use crate::withdrawal::{
+    cancel_withdrawal, process_withdrawal_queue, query_withdrawal_ticket,
+    query_withdrawal_tickets, queue_redeem_stable,
+};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
@@ -17,19 +33,32 @@ use cosmwasm_std::{
     DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use cw20::{Cw20Coin, Cw20ReceiveMsg, MinterResponse};
+use cw2::set_contract_version;
 
-use moneymarket::common::optional_addr_validate;
+use moneymarket::common::{optional_addr_validate, BLOCKS_PER_YEAR};
+use moneymarket::config_history::{config_change, query_config_history, record_config_change};
 use moneymarket::interest_model::BorrowRateResponse;
 use moneymarket::market::{
     ConfigResponse, Cw20HookMsg, EpochStateResponse, ExecuteMsg, InstantiateMsg, MigrateMsg,
-    QueryMsg, StateResponse,
+    QueryMsg, RatesResponse, StateResponse,
 };
+use moneymarket::migration::assert_migrate_version;
+use moneymarket::pause::PausableOperation;
 use moneymarket::querier::{deduct_tax, query_balance, query_supply};
-use moneymarket::terraswap::InstantiateMsg as TokenInstantiateMsg;
+use moneymarket::terraswap::{InstantiateMsg as TokenInstantiateMsg, TokenExecuteMsg};
 use protobuf::Message;
 
 pub const INITIAL_DEPOSIT_AMOUNT: u128 = 1000000;
 
+const MAX_COMPOUND_FACTOR: u64 = 1_000_000_000;
+
+// settings for liability migration pages
+const MAX_MIGRATION_LIMIT: u32 = 100;
+const DEFAULT_MIGRATION_LIMIT: u32 = 30;
+
+const CONTRACT_NAME: &str = "crates.io:moneymarket-market";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -37,6 +66,8 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     let initial_deposit = info
         .funds
         .iter()
@@ -64,6 +95,11 @@ pub fn instantiate(
             distributor_contract: CanonicalAddr::from(vec![]),
             stable_denom: msg.stable_denom.clone(),
             max_borrow_factor: msg.max_borrow_factor,
+            interest_model_hook: false,
+            atoken_balance_hook: msg.atoken_balance_hook.unwrap_or(false),
+            depositor_reward_share: Decimal256::zero(),
+            max_total_liabilities: None,
+            max_total_deposits: None,
         },
     )?;
 
@@ -79,6 +115,10 @@ pub fn instantiate(
             kpt_emission_rate: msg.kpt_emission_rate,
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )?;
 
@@ -113,6 +153,11 @@ pub fn instantiate(
                     minter: env.contract.address.to_string(),
                     cap: None,
                 }),
+                balance_hook: if msg.atoken_balance_hook.unwrap_or(false) {
+                    Some(env.contract.address.to_string())
+                } else {
+                    None
+                },
             })?,
         }),
         1,
@@ -153,6 +198,10 @@ pub fn execute(
             interest_model,
             distribution_model,
             max_borrow_factor,
+            interest_model_hook,
+            depositor_reward_share,
+            max_total_liabilities,
+            max_total_deposits,
         } => {
             let api = deps.api;
             update_config(
@@ -162,57 +211,125 @@ pub fn execute(
                 optional_addr_validate(api, interest_model)?,
                 optional_addr_validate(api, distribution_model)?,
                 max_borrow_factor,
+                interest_model_hook,
+                depositor_reward_share,
+                max_total_liabilities,
+                max_total_deposits,
             )
         }
+        ExecuteMsg::RegisterAtokenBalanceHook {} => register_atoken_balance_hook(deps, env, info),
+        ExecuteMsg::MigrateLiabilities { limit } => execute_migrate_liabilities(deps, limit),
         ExecuteMsg::SetOwner { new_owner_addr } => {
             let api = deps.api;
-            set_new_owner(deps, info, api.addr_validate(&new_owner_addr)?)
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
         }
-        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::UpdateGuardian { guardian } => {
+            let api = deps.api;
+            update_guardian(deps, env, info, optional_addr_validate(api, guardian)?)
+        }
+        ExecuteMsg::SetPaused { operation, paused } => set_paused(deps, info, operation, paused),
         ExecuteMsg::ExecuteEpochOperations {
             deposit_rate,
             target_deposit_rate,
             threshold_deposit_rate,
             distributed_interest,
-        } => execute_epoch_operations(
-            deps,
-            env,
-            info,
-            deposit_rate,
-            target_deposit_rate,
-            threshold_deposit_rate,
-            distributed_interest,
-        ),
-        ExecuteMsg::DepositStable {} => deposit_stable(deps, env, info),
-        ExecuteMsg::BorrowStable { borrow_amount, to } => {
-            let api = deps.api;
-            borrow_stable(
+        } => {
+            assert_not_shutdown(deps.storage)?;
+            execute_epoch_operations(
                 deps,
                 env,
                 info,
-                borrow_amount,
-                optional_addr_validate(api, to)?,
+                deposit_rate,
+                target_deposit_rate,
+                threshold_deposit_rate,
+                distributed_interest,
             )
         }
-
-        ExecuteMsg::RepayStable {} => repay_stable(deps, env, info),
-        ExecuteMsg::RepayStableFromLiquidation {
+        ExecuteMsg::Shutdown {
+            collateral_tokens,
+            settlement_period,
+        } => shutdown(deps, env, info, collateral_tokens, settlement_period),
+        ExecuteMsg::SettleLoan { borrower } => {
+            let api = deps.api;
+            settle_loan(deps, env, info, api.addr_validate(&borrower)?)
+        }
+        ExecuteMsg::DepositStable {} => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Deposits)?;
+            deposit_stable(deps, env, info)
+        }
+        ExecuteMsg::BorrowStable {
+            borrow_amount,
+            to,
+            price_update,
+        } => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Borrows)?;
+            let api = deps.api;
+            match price_update {
+                Some(price_update) => borrow_stable_with_price_update(
+                    deps,
+                    env,
+                    info,
+                    borrow_amount,
+                    optional_addr_validate(api, to)?,
+                    price_update,
+                ),
+                None => borrow_stable(
+                    deps,
+                    env,
+                    info,
+                    borrow_amount,
+                    optional_addr_validate(api, to)?,
+                ),
+            }
+        }
+        ExecuteMsg::BorrowStableAfterPriceUpdate {
             borrower,
-            prev_balance,
+            borrow_amount,
+            to,
         } => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Borrows)?;
             let api = deps.api;
-            repay_stable_from_liquidation(
+            borrow_stable_after_price_update(
                 deps,
                 env,
                 info,
                 api.addr_validate(&borrower)?,
-                prev_balance,
+                borrow_amount,
+                optional_addr_validate(api, to)?,
             )
         }
+
+        ExecuteMsg::RepayStable { borrower } => {
+            let borrower = match borrower {
+                Some(borrower) => deps.api.addr_validate(&borrower)?,
+                None => info.sender.clone(),
+            };
+            repay_stable(deps, env, info, borrower)
+        }
+        ExecuteMsg::RepayStableFromLiquidation { borrower } => {
+            let api = deps.api;
+            repay_stable_from_liquidation(deps, env, info, api.addr_validate(&borrower)?)
+        }
         ExecuteMsg::ClaimRewards { to } => {
             let api = deps.api;
             claim_rewards(deps, env, info, optional_addr_validate(api, to)?)
         }
+        ExecuteMsg::ProcessWithdrawalQueue {} => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Redemptions)?;
+            process_withdrawal_queue(deps, env)
+        }
+        ExecuteMsg::CancelWithdrawal { ticket_id } => {
+            cancel_withdrawal(deps, env, info, ticket_id)
+        }
+        ExecuteMsg::BalanceChangeHook {
+            balances_before,
+            total_supply_before,
+        } => balance_change_hook(deps, env, info, balances_before, total_supply_before),
     }
 }
 
@@ -246,15 +363,70 @@ pub fn receive_cw20(
 ) -> Result<Response, ContractError> {
     let contract_addr = info.sender;
     match from_json(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::RedeemStable {}) => {
+        Ok(Cw20HookMsg::RedeemStable {
+            to,
+            exact_underlying,
+        }) => {
+            // only asset contract can execute this message
+            let config: Config = read_config(deps.storage)?;
+            if deps.api.addr_canonicalize(contract_addr.as_str())? != config.atoken_contract {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            assert_not_paused(deps.storage, PausableOperation::Redemptions)?;
+            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+            let to = optional_addr_validate(deps.api, to)?;
+            if read_shutdown_state(deps.storage)?.is_some() {
+                // the exchange rate no longer applies, redemptions are pro-rata
+                if exact_underlying.is_some() {
+                    return Err(ContractError::ShutDown {});
+                }
+
+                return redeem_stable_pro_rata(deps, env, cw20_sender_addr, cw20_msg.amount, to);
+            }
+
+            redeem_stable(
+                deps,
+                env,
+                cw20_sender_addr,
+                cw20_msg.amount,
+                to,
+                exact_underlying,
+            )
+        }
+        Ok(Cw20HookMsg::QueueRedeemStable {}) => {
             // only asset contract can execute this message
             let config: Config = read_config(deps.storage)?;
             if deps.api.addr_canonicalize(contract_addr.as_str())? != config.atoken_contract {
                 return Err(ContractError::Unauthorized {});
             }
 
+            assert_not_shutdown(deps.storage)?;
             let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
-            redeem_stable(deps, env, cw20_sender_addr, cw20_msg.amount)
+            queue_redeem_stable(deps, env, cw20_sender_addr, Uint256::from(cw20_msg.amount))
+        }
+        Ok(Cw20HookMsg::RepayStable { borrower }) => {
+            // only asset contract can execute this message
+            let config: Config = read_config(deps.storage)?;
+            if deps.api.addr_canonicalize(contract_addr.as_str())? != config.atoken_contract {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            // repaying with aTokens redeems deposits, unlike native repayments
+            assert_not_paused(deps.storage, PausableOperation::Redemptions)?;
+            assert_not_shutdown(deps.storage)?;
+            let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
+            let borrower = match borrower {
+                Some(borrower) => deps.api.addr_validate(&borrower)?,
+                None => cw20_sender_addr.clone(),
+            };
+            repay_stable_with_atoken(
+                deps,
+                env,
+                cw20_sender_addr,
+                borrower,
+                Uint256::from(cw20_msg.amount),
+            )
         }
 
         // Ok(Cw20HookMsg::DepositStable {}) => {
@@ -267,11 +439,6 @@ pub fn receive_cw20(
 
         // }
 
-        // Ok(Cw20HookMsg::RepayStable {}) => {
-        //     let cw20_sender_addr = deps.api.addr_validate(&cw20_msg.sender)?;
-
-        //     repay_stable(deps, env, cw20_sender_addr, Uint256::from(cw20_msg.amount))
-        // }
         _ => Err(ContractError::MissingRedeemStableHook {}),
     }
 }
@@ -320,6 +487,7 @@ pub fn register_contracts(
 
 pub fn set_new_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner_addr: Addr,
 ) -> Result<Response, ContractError> {
@@ -329,13 +497,26 @@ pub fn set_new_owner(
     if sender_raw != config.owner_addr {
         return Err(ContractError::Unauthorized{});
     }
+    let old_new_owner = deps.api.addr_humanize(&new_owner.new_owner_addr)?;
     new_owner.new_owner_addr = deps.api.addr_canonicalize(&new_owner_addr.to_string())?;
     store_new_owner(deps.storage, &new_owner)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_owner",
+        vec![config_change("new_owner_addr", &old_new_owner, &new_owner_addr)?],
+    )?;
+
     Ok(Response::default())
 }
 
-pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let new_owner = read_new_owner(deps.as_ref().storage)?;
     let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
     let mut config = read_config(deps.as_ref().storage)?;
@@ -343,12 +524,22 @@ pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, Co
         return Err(ContractError::Unauthorized{});
     }
 
+    let old_owner = deps.api.addr_humanize(&config.owner_addr)?;
     config.owner_addr = new_owner.new_owner_addr;
     store_config(deps.storage, &config)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "accept_ownership",
+        vec![config_change("owner_addr", &old_owner, &info.sender)?],
+    )?;
+
     Ok(Response::default())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
     env: Env,
@@ -356,6 +547,10 @@ pub fn update_config(
     interest_model: Option<Addr>,
     distribution_model: Option<Addr>,
     max_borrow_factor: Option<Decimal256>,
+    interest_model_hook: Option<bool>,
+    depositor_reward_share: Option<Decimal256>,
+    max_total_liabilities: Option<Option<Uint256>>,
+    max_total_deposits: Option<Option<Uint256>>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
 
@@ -364,9 +559,14 @@ pub fn update_config(
         return Err(ContractError::Unauthorized {});
     }
 
-    if interest_model.is_some() {
+    let old_config = config.clone();
+    let mut hook_messages: Vec<CosmosMsg> = vec![];
+    if interest_model.is_some() || depositor_reward_share.is_some() {
         let mut state: State = read_state(deps.storage)?;
-        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+        hook_messages =
+            compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+        // Distribute the reward accrued so far with the previous share
+        compute_reward(&mut state, &config, env.block.height);
         store_state(deps.storage, &state)?;
 
         if let Some(interest_model) = interest_model {
@@ -382,8 +582,137 @@ pub fn update_config(
         config.max_borrow_factor = max_borrow_factor;
     }
 
+    if let Some(interest_model_hook) = interest_model_hook {
+        config.interest_model_hook = interest_model_hook;
+    }
+
+    if let Some(depositor_reward_share) = depositor_reward_share {
+        if depositor_reward_share > Decimal256::one() {
+            return Err(ContractError::InvalidDepositorRewardShare {});
+        }
+
+        // aToken transfers are only visible through the balance hook
+        if !depositor_reward_share.is_zero() && !config.atoken_balance_hook {
+            return Err(ContractError::AtokenBalanceHookDisabled {});
+        }
+
+        config.depositor_reward_share = depositor_reward_share;
+    }
+
+    if let Some(max_total_liabilities) = max_total_liabilities {
+        config.max_total_liabilities = max_total_liabilities;
+    }
+
+    if let Some(max_total_deposits) = max_total_deposits {
+        config.max_total_deposits = max_total_deposits;
+    }
+
+    store_config(deps.storage, &config)?;
+
+    // contracts are left unset until they are registered
+    let api = deps.api;
+    let humanize = |addr: &CanonicalAddr| api.addr_humanize(addr).ok();
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_config",
+        vec![
+            config_change(
+                "interest_model",
+                &humanize(&old_config.interest_model),
+                &humanize(&config.interest_model),
+            )?,
+            config_change(
+                "distribution_model",
+                &humanize(&old_config.distribution_model),
+                &humanize(&config.distribution_model),
+            )?,
+            config_change(
+                "max_borrow_factor",
+                &old_config.max_borrow_factor,
+                &config.max_borrow_factor,
+            )?,
+            config_change(
+                "interest_model_hook",
+                &old_config.interest_model_hook,
+                &config.interest_model_hook,
+            )?,
+            config_change(
+                "depositor_reward_share",
+                &old_config.depositor_reward_share,
+                &config.depositor_reward_share,
+            )?,
+            config_change(
+                "max_total_liabilities",
+                &old_config.max_total_liabilities,
+                &config.max_total_liabilities,
+            )?,
+            config_change(
+                "max_total_deposits",
+                &old_config.max_total_deposits,
+                &config.max_total_deposits,
+            )?,
+        ],
+    )?;
+
+    Ok(Response::new()
+        .add_messages(hook_messages)
+        .add_attributes(vec![attr("action", "update_config")]))
+}
+
+/// Register the market as the balance hook of the aToken,
+/// for aTokens instantiated without it
+pub fn register_atoken_balance_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let old_atoken_balance_hook = config.atoken_balance_hook;
+    config.atoken_balance_hook = true;
     store_config(deps.storage, &config)?;
-    Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
+
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "register_atoken_balance_hook",
+        vec![config_change(
+            "atoken_balance_hook",
+            &old_atoken_balance_hook,
+            &config.atoken_balance_hook,
+        )?],
+    )?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.atoken_contract)?.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&TokenExecuteMsg::UpdateBalanceHook {
+                balance_hook: Some(env.contract.address.to_string()),
+            })?,
+        }))
+        .add_attributes(vec![attr("action", "register_atoken_balance_hook")]))
+}
+
+/// Re-save a page of liabilities of the legacy layout, see `migrate_liabilities`
+pub fn execute_migrate_liabilities(
+    deps: DepsMut,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_MIGRATION_LIMIT).min(MAX_MIGRATION_LIMIT) as usize;
+    let migrated = migrate_liabilities(deps.storage, limit)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate_liabilities"),
+        attr("migrated", migrated.to_string()),
+        attr("done", (migrated < limit).to_string()),
+    ]))
 }
 
 pub fn execute_epoch_operations(
@@ -421,6 +750,8 @@ pub fn execute_epoch_operations(
         state.total_liabilities,
         state.total_reserves,
     )?;
+    let hook_messages =
+        interest_model_hook_messages(deps.as_ref(), &config, &state, balance)?;
 
     compute_interest_raw(
         &mut state,
@@ -435,7 +766,7 @@ pub fn execute_epoch_operations(
     state.prev_exchange_rate =
         compute_exchange_rate_raw(&state, atoken_supply, balance + distributed_interest);
 
-    compute_reward(&mut state, env.block.height);
+    compute_reward(&mut state, &config, env.block.height);
 
     // Compute total_reserves to fund collector contract
     // Update total_reserves and send it to collector contract
@@ -462,22 +793,27 @@ pub fn execute_epoch_operations(
     };
 
     // Query updated kpt_emission_rate
-    state.kpt_emission_rate = query_kpt_emission_rate(
+    let emission_rate_res = query_kpt_emission_rate(
         deps.as_ref(),
         deps.api.addr_humanize(&config.distribution_model)?,
         deposit_rate,
         target_deposit_rate,
         threshold_deposit_rate,
         state.kpt_emission_rate,
-    )?
-    .emission_rate;
+        state.total_kpt_emitted,
+    )?;
+    state.kpt_emission_rate = emission_rate_res.emission_rate;
+    state.kpt_emission_budget = emission_rate_res.total_budget;
     store_state(deps.storage, &state)?;
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "execute_epoch_operations"),
-        attr("total_reserves", total_reserves),
-        attr("kpt_emission_rate", state.kpt_emission_rate.to_string()),
-    ]))
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(hook_messages)
+        .add_attributes(vec![
+            attr("action", "execute_epoch_operations"),
+            attr("total_reserves", total_reserves),
+            attr("kpt_emission_rate", state.kpt_emission_rate.to_string()),
+        ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -502,12 +838,57 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             deps.api.addr_validate(&borrower)?,
             block_height,
         )?),
-        QueryMsg::BorrowerInfos { start_after, limit } => to_json_binary(&query_borrower_infos(
+        QueryMsg::BorrowerInfos {
+            start_after,
+            limit,
+            block_height,
+        } => to_json_binary(&query_borrower_infos(
             deps,
+            env,
             optional_addr_validate(deps.api, start_after)?,
             limit,
+            block_height,
+        )?),
+        QueryMsg::BorrowersByDebt {
+            start_after,
+            limit,
+            block_height,
+        } => to_json_binary(&query_borrowers_by_debt(
+            deps,
+            env,
+            optional_addr_validate(deps.api, start_after)?,
+            limit,
+            block_height,
+        )?),
+        QueryMsg::DepositorInfo {
+            depositor,
+            block_height,
+        } => to_json_binary(&query_depositor_info(
+            deps,
+            env,
+            deps.api.addr_validate(&depositor)?,
+            block_height,
+        )?),
+        QueryMsg::Rates {} => to_json_binary(&query_rates(deps, env)?),
+        QueryMsg::WithdrawalTicket { ticket_id } => {
+            to_json_binary(&query_withdrawal_ticket(deps, env, ticket_id)?)
+        }
+        QueryMsg::WithdrawalTickets {
+            owner,
+            start_after,
+            limit,
+        } => to_json_binary(&query_withdrawal_tickets(
+            deps,
+            env,
+            optional_addr_validate(deps.api, owner)?,
+            start_after,
+            limit,
         )?),
-        
+        QueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(deps)?),
+        QueryMsg::ShutdownState {} => to_json_binary(&query_shutdown_state(deps)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
 
@@ -536,6 +917,11 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
             .to_string(),
         stable_denom: config.stable_denom,
         max_borrow_factor: config.max_borrow_factor,
+        interest_model_hook: config.interest_model_hook,
+        atoken_balance_hook: config.atoken_balance_hook,
+        depositor_reward_share: config.depositor_reward_share,
+        max_total_liabilities: config.max_total_liabilities,
+        max_total_deposits: config.max_total_deposits,
     })
 }
 
@@ -578,6 +964,87 @@ pub fn query_state(deps: Deps, env: Env, block_height: Option<u64>) -> StdResult
     })
 }
 
+pub fn query_rates(deps: Deps, env: Env) -> StdResult<RatesResponse> {
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    // Bring the state to the current block, so the rates are the ones applied from now on
+    compute_interest(deps, &config, &mut state, env.block.height, None)?;
+
+    let balance: Uint256 = query_balance(
+        deps,
+        deps.api.addr_humanize(&config.contract_addr)?,
+        config.stable_denom.to_string(),
+    )?;
+    let borrow_rate = query_borrow_rate(
+        deps,
+        deps.api.addr_humanize(&config.interest_model)?,
+        balance,
+        state.total_liabilities,
+        state.total_reserves,
+    )?
+    .rate;
+    let target_deposit_rate: Decimal256 =
+        query_target_deposit_rate(deps, deps.api.addr_humanize(&config.overseer_contract)?)?;
+
+    let total_deposits =
+        Decimal256::from_uint256(balance) + state.total_liabilities - state.total_reserves;
+    let utilization_ratio = if total_deposits.is_zero() {
+        Decimal256::zero()
+    } else {
+        state.total_liabilities / total_deposits
+    };
+
+    // same cap as compute_interest_raw, which moves the excess to the reserves
+    let deposit_rate = (borrow_rate * utilization_ratio).min(target_deposit_rate);
+
+    let blocks_per_year = Decimal256::from_uint256(Uint256::from(BLOCKS_PER_YEAR));
+    let deposit_emission = state.kpt_emission_rate * config.depositor_reward_share;
+    let borrow_emission = state.kpt_emission_rate - deposit_emission;
+    let kpt_emission_apr = if state.total_liabilities.is_zero() {
+        Decimal256::zero()
+    } else {
+        borrow_emission * blocks_per_year / state.total_liabilities
+    };
+    let kpt_deposit_emission_apr = if total_deposits.is_zero() {
+        Decimal256::zero()
+    } else {
+        deposit_emission * blocks_per_year / total_deposits
+    };
+
+    Ok(RatesResponse {
+        borrow_rate,
+        borrow_apy: compound_rate(borrow_rate, BLOCKS_PER_YEAR),
+        utilization_ratio,
+        deposit_rate,
+        deposit_apy: compound_rate(deposit_rate, BLOCKS_PER_YEAR),
+        target_deposit_rate,
+        kpt_emission_apr,
+        kpt_deposit_emission_apr,
+    })
+}
+
+/// (1 + rate)^periods - 1, by squaring. Saturates once the growth factor
+/// reaches `MAX_COMPOUND_FACTOR`, which only unrealistic rates can hit.
+fn compound_rate(rate: Decimal256, periods: u128) -> Decimal256 {
+    let max_factor = Decimal256::from_uint256(MAX_COMPOUND_FACTOR);
+    let mut base = Decimal256::one() + rate;
+    let mut result = Decimal256::one();
+    let mut periods = periods;
+    while periods > 0 {
+        if base > max_factor || result > max_factor {
+            return max_factor - Decimal256::one();
+        }
+        if periods & 1 == 1 {
+            result = result * base;
+        }
+        base = base * base;
+        periods >>= 1;
+    }
+
+    result.min(max_factor) - Decimal256::one()
+}
+
 pub fn query_epoch_state(
     deps: Deps,
     block_height: Option<u64>,
@@ -636,6 +1103,20 @@ pub fn query_epoch_state(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+
+    // tickets queued before escrowed aTokens were excluded from depositor rewards
+    let mut state: State = read_state(deps.storage)?;
+    state.escrowed_atokens = read_withdrawal_queue(deps.storage)?.total_atokens;
+    store_state(deps.storage, &state)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
 }
@@ -4,12 +4,17 @@ use cosmwasm_std::{
     StdResult, Uint128, WasmMsg,
 };
 
-use crate::borrow::{compute_interest, compute_reward};
+use crate::borrow::{compute_interest, compute_reward, compute_reward_raw};
 use crate::error::ContractError;
-use crate::state::{read_config, read_state, store_state, Config, State};
+use crate::withdrawal::{queued_redeem_amount, withdrawal_queue_messages};
+use crate::state::{
+    read_config, read_depositor_info, read_state, store_depositor_info, store_state, Config,
+    DepositorInfo, State,
+};
 
-use cw20::Cw20ExecuteMsg;
-use moneymarket::querier::{deduct_tax, query_balance, query_supply};
+use cw20::{Cw20Coin, Cw20ExecuteMsg};
+use moneymarket::market::DepositorInfoResponse;
+use moneymarket::querier::{deduct_tax, query_balance, query_supply, query_token_balance};
 
 pub fn deposit_stable(
     deps: DepsMut,
@@ -34,7 +39,7 @@ pub fn deposit_stable(
     // Update interest related state
     let mut state: State = read_state(deps.storage)?;
 
-    compute_interest(
+    let hook_messages = compute_interest(
         deps.as_ref(),
         &config,
         &mut state,
@@ -42,13 +47,16 @@ pub fn deposit_stable(
         Some(deposit_amount),
     )?;
 
-    compute_reward(&mut state, env.block.height);
+    compute_reward(&mut state, &config, env.block.height);
 
     // Load kryptonite token exchange rate with updated state
     let exchange_rate =
         compute_exchange_rate(deps.as_ref(), &config, &state, Some(deposit_amount))?;
     let mint_amount = deposit_amount / exchange_rate;
 
+    // Assert supply cap
+    assert_max_total_deposits(deps.as_ref(), &config, exchange_rate, deposit_amount)?;
+
     state.prev_atoken_supply += mint_amount;
     store_state(deps.storage, &state)?;
     Ok(Response::new()
@@ -60,6 +68,8 @@ pub fn deposit_stable(
                 amount: mint_amount.into(),
             })?,
         }))
+        .add_messages(hook_messages)
+        .add_messages(withdrawal_queue_messages(deps.as_ref(), &env)?)
         .add_attributes(vec![
             attr("action", "deposit_stable"),
             attr("depositor", info.sender),
@@ -93,21 +103,42 @@ pub fn redeem_stable(
     deps: DepsMut,
     env: Env,
     sender: Addr,
-    burn_amount: Uint128,
+    atoken_amount: Uint128,
+    to: Option<Addr>,
+    exact_underlying: Option<Uint256>,
 ) -> Result<Response, ContractError> {
     let config: Config = read_config(deps.storage)?;
 
+    // Cannot redeem an exact zero amount, it would burn nothing
+    if exact_underlying.is_some_and(|amount| amount.is_zero()) {
+        return Err(ContractError::ZeroRedeem(config.stable_denom));
+    }
+
     //let protocol_fee_rate: Decimal256 = Decimal256::from_ratio(5, 1000);
     // let protocol_fee: Decimal256 =
     //     (Decimal256::from_uint256(Uint256::from(burn_amount))) * protocol_fee_rate;
     // Update interest related state
     let mut state: State = read_state(deps.storage)?;
-    compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
-    compute_reward(&mut state, env.block.height);
+    let hook_messages =
+        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+    compute_reward(&mut state, &config, env.block.height);
 
     // Load kryptonite token exchange rate with updated state
     let exchange_rate = compute_exchange_rate(deps.as_ref(), &config, &state, None)?;
-    let redeem_amount = Uint256::from(burn_amount) * exchange_rate;
+    let (burn_amount, redeem_amount) = if let Some(redeem_amount) = exact_underlying {
+        // round up so the burnt aTokens always cover the redeemed amount
+        let mut burn_amount = redeem_amount / exchange_rate;
+        if burn_amount * exchange_rate < redeem_amount {
+            burn_amount += Uint256::one();
+        }
+        if burn_amount > Uint256::from(atoken_amount) {
+            return Err(ContractError::InsufficientRedeemAtokens(burn_amount.to_string()));
+        }
+
+        (Uint128::from(burn_amount), redeem_amount)
+    } else {
+        (atoken_amount, Uint256::from(atoken_amount) * exchange_rate)
+    };
 
     let current_balance = query_balance(
         deps.as_ref(),
@@ -120,14 +151,30 @@ pub fn redeem_stable(
     //     deps.as_ref().api.addr_humanize(&config.contract_addr)?,
     // )?;
     // Assert redeem amount
-    assert_redeem_amount(&config, &state, current_balance, redeem_amount)?;
+    let queued_amount = queued_redeem_amount(deps.as_ref(), &config, &state)?;
+    assert_redeem_amount(&config, &state, current_balance, redeem_amount, queued_amount)?;
 
     state.prev_atoken_supply = state.prev_atoken_supply - Uint256::from(burn_amount);
     store_state(deps.storage, &state)?;
+
+    // Payback left aTokens to sender
+    let atoken_addr = deps.api.addr_humanize(&config.atoken_contract)?;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if atoken_amount > burn_amount {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: atoken_addr.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: sender.to_string(),
+                amount: atoken_amount - burn_amount,
+            })?,
+        }));
+    }
+
     Ok(Response::new()
         .add_messages(vec![
             CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: deps.api.addr_humanize(&config.atoken_contract)?.to_string(),
+                contract_addr: atoken_addr.to_string(),
                 funds: vec![],
                 msg: to_json_binary(&Cw20ExecuteMsg::Burn {
                     amount: burn_amount,
@@ -141,7 +188,7 @@ pub fn redeem_stable(
             //         amount: redeem_amount.into(),
             //     })?,
             CosmosMsg::Bank(BankMsg::Send {
-                to_address: sender.to_string(),
+                to_address: to.unwrap_or(sender).to_string(),
                 amount: vec![deduct_tax(
                     deps.as_ref(),
                     Coin {
@@ -151,6 +198,8 @@ pub fn redeem_stable(
                 )?],
             }),
         ])
+        .add_messages(messages)
+        .add_messages(hook_messages)
         .add_attributes(vec![
             attr("action", "redeem_stable"),
             attr("burn_amount", burn_amount),
@@ -158,15 +207,104 @@ pub fn redeem_stable(
         ]))
 }
 
+/// Settle the rewards of aToken holders whose balance is about to change.
+/// Only the aToken contract can call this.
+pub fn balance_change_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    balances_before: Vec<Cw20Coin>,
+    total_supply_before: Uint128,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.atoken_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut state: State = read_state(deps.storage)?;
+    compute_reward_raw(
+        &mut state,
+        &config,
+        Uint256::from(total_supply_before),
+        env.block.height,
+    );
+
+    for balance in balances_before {
+        let depositor_raw = deps.api.addr_canonicalize(&balance.address)?;
+        // the market's own aTokens are escrowed or in transit to be burned
+        if depositor_raw == config.contract_addr {
+            continue;
+        }
+
+        let mut depositor_info: DepositorInfo = read_depositor_info(deps.storage, &depositor_raw);
+        compute_depositor_reward(&state, &mut depositor_info, Uint256::from(balance.amount));
+        store_depositor_info(deps.storage, &depositor_raw, &depositor_info)?;
+    }
+
+    store_state(deps.storage, &state)?;
+    Ok(Response::new().add_attributes(vec![attr("action", "balance_change_hook")]))
+}
+
+/// Compute reward amount an aToken holder received
+pub(crate) fn compute_depositor_reward(
+    state: &State,
+    depositor_info: &mut DepositorInfo,
+    atoken_balance: Uint256,
+) {
+    depositor_info.pending_rewards += Decimal256::from_uint256(atoken_balance)
+        * (state.global_deposit_reward_index - depositor_info.reward_index);
+    depositor_info.reward_index = state.global_deposit_reward_index;
+}
+
+pub fn query_depositor_info(
+    deps: Deps,
+    env: Env,
+    depositor: Addr,
+    block_height: Option<u64>,
+) -> StdResult<DepositorInfoResponse> {
+    let mut depositor_info: DepositorInfo = read_depositor_info(
+        deps.storage,
+        &deps.api.addr_canonicalize(depositor.as_str())?,
+    );
+
+    let block_height = if let Some(block_height) = block_height {
+        block_height
+    } else {
+        env.block.height
+    };
+
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+
+    compute_interest(deps, &config, &mut state, block_height, None)?;
+    compute_reward(&mut state, &config, block_height);
+
+    let atoken_balance = query_token_balance(
+        deps,
+        deps.api.addr_humanize(&config.atoken_contract)?,
+        depositor.clone(),
+    )?;
+    compute_depositor_reward(&state, &mut depositor_info, atoken_balance);
+
+    Ok(DepositorInfoResponse {
+        depositor: depositor.to_string(),
+        reward_index: depositor_info.reward_index,
+        pending_rewards: depositor_info.pending_rewards,
+    })
+}
+
 fn assert_redeem_amount(
     config: &Config,
     state: &State,
     current_balance: Uint256,
     redeem_amount: Uint256,
+    queued_amount: Uint256,
 ) -> Result<(), ContractError> {
     let current_balance = Decimal256::from_uint256(current_balance);
     let redeem_amount = Decimal256::from_uint256(redeem_amount);
-    if redeem_amount + state.total_reserves > current_balance {
+    let queued_amount = Decimal256::from_uint256(queued_amount);
+    // queued withdrawals are served first
+    if redeem_amount + queued_amount + state.total_reserves > current_balance {
         return Err(ContractError::NoStableAvailable(
             config.stable_denom.clone(),
         ));
@@ -174,6 +312,27 @@ fn assert_redeem_amount(
     Ok(())
 }
 
+fn assert_max_total_deposits(
+    deps: Deps,
+    config: &Config,
+    exchange_rate: Decimal256,
+    deposit_amount: Uint256,
+) -> Result<(), ContractError> {
+    if let Some(max_total_deposits) = config.max_total_deposits {
+        let atoken_supply =
+            query_supply(deps, deps.api.addr_humanize(&config.atoken_contract)?)?;
+        let total_deposits = Decimal256::from_uint256(atoken_supply) * exchange_rate
+            + Decimal256::from_uint256(deposit_amount);
+        if total_deposits > Decimal256::from_uint256(max_total_deposits) {
+            return Err(ContractError::MaxTotalDepositsReached(
+                max_total_deposits.to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 pub(crate) fn compute_exchange_rate(
     deps: Deps,
     config: &Config,
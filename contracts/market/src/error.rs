@@ -1,4 +1,5 @@
 use cosmwasm_std::{OverflowError, StdError};
+use moneymarket::pause::PauseError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -30,12 +31,56 @@ pub enum ContractError {
     #[error("Not enough {0} available; borrow demand too high")]
     NoStableAvailable(String),
 
+    #[error("Exceeds {0} max total liabilities")]
+    MaxTotalLiabilitiesReached(String),
+
+    #[error("Exceeds {0} max total deposits")]
+    MaxTotalDepositsReached(String),
+
+    #[error("Not enough aTokens to redeem the exact amount; {0} required")]
+    InsufficientRedeemAtokens(String),
+
     #[error("Deposit amount must be greater than 0 {0}")]
     ZeroDeposit(String),
 
     #[error("Repay amount must be greater than 0 {0}")]
     ZeroRepay(String),
 
+    #[error("Redeem amount must be greater than 0 {0}")]
+    ZeroRedeem(String),
+
     #[error("Functionality deprecated")]
     Deprecated {},
+
+    #[error("Market {0} are paused")]
+    Paused(String),
+
+    #[error("Market {0} cannot be paused")]
+    NotPausable(String),
+
+    #[error("Market is shut down")]
+    ShutDown {},
+
+    #[error("Market is not shut down")]
+    NotShutDown {},
+
+    #[error("Redemptions open at height {0}, once the borrowers are settled")]
+    SettlementPeriod(u64),
+
+    #[error("Depositor reward share must not exceed 1")]
+    InvalidDepositorRewardShare {},
+
+    #[error("Depositor rewards require the aToken balance hook")]
+    AtokenBalanceHookDisabled {},
+}
+
+impl From<PauseError> for ContractError {
+    fn from(err: PauseError) -> Self {
+        match err {
+            PauseError::Unauthorized => ContractError::Unauthorized {},
+            PauseError::NotPausable(operation) => ContractError::NotPausable(operation),
+            PauseError::Paused(operation) => ContractError::Paused(operation),
+            PauseError::Std(err) => ContractError::Std(err),
+        }
+    }
 }
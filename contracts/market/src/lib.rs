@@ -2,9 +2,12 @@ pub mod borrow;
 pub mod contract;
 pub mod deposit;
 pub mod error;
+pub mod pause;
 pub mod querier;
 pub mod response;
+pub mod shutdown;
 pub mod state;
+pub mod withdrawal;
 
 #[cfg(test)]
 mod testing;
@@ -50,6 +50,7 @@ pub fn query_kpt_emission_rate(
     target_deposit_rate: Decimal256,
     threshold_deposit_rate: Decimal256,
     current_emission_rate: Decimal256,
+    total_emitted: Decimal256,
 ) -> StdResult<KptEmissionRateResponse> {
     let kpt_emission_rate: KptEmissionRateResponse =
         deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
@@ -59,6 +60,7 @@ pub fn query_kpt_emission_rate(
                 target_deposit_rate,
                 threshold_deposit_rate,
                 current_emission_rate,
+                total_emitted: Some(total_emitted),
             })?,
         }))?;
 
@@ -74,3 +76,23 @@ pub fn query_target_deposit_rate(deps: Deps, overseer_contract: Addr) -> StdResu
 
     Ok(overseer_config.target_deposit_rate)
 }
+
+pub fn query_oracle_contract(deps: Deps, overseer_contract: Addr) -> StdResult<Addr> {
+    let overseer_config: ConfigResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: overseer_contract.to_string(),
+            msg: to_json_binary(&OverseerQueryMsg::Config {})?,
+        }))?;
+
+    deps.api.addr_validate(&overseer_config.oracle_contract)
+}
+
+pub fn query_liquidation_contract(deps: Deps, overseer_contract: Addr) -> StdResult<Addr> {
+    let overseer_config: ConfigResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: overseer_contract.to_string(),
+            msg: to_json_binary(&OverseerQueryMsg::Config {})?,
+        }))?;
+
+    deps.api.addr_validate(&overseer_config.liquidation_contract)
+}
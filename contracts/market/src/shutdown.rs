@@ -0,0 +1,228 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, BankMsg, CanonicalAddr, Coin, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Response, StdResult, Storage, Uint128, WasmMsg,
+};
+
+use cw20::Cw20ExecuteMsg;
+use moneymarket::market::ShutdownStateResponse;
+use moneymarket::querier::{deduct_tax, query_balance, query_supply, query_token_balance};
+
+use crate::borrow::{
+    compute_borrower_interest, compute_borrower_reward, compute_interest, compute_reward,
+};
+use crate::error::ContractError;
+use crate::state::{
+    read_borrower_info, read_config, read_shutdown_state, read_state, store_borrower_info,
+    store_shutdown_state, store_state, BorrowerInfo, Config, ShutdownState, State,
+};
+
+/// Accrue interest one last time and freeze it. Only the overseer can
+/// shut the market down, as part of the protocol shutdown
+pub fn shutdown(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collateral_tokens: Vec<String>,
+    settlement_period: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    assert_not_shutdown(deps.storage)?;
+
+    let mut state: State = read_state(deps.storage)?;
+    let hook_messages =
+        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+    compute_reward(&mut state, &config, env.block.height);
+    store_state(deps.storage, &state)?;
+
+    let collateral_tokens = collateral_tokens
+        .iter()
+        .map(|token| deps.api.addr_canonicalize(token))
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    store_shutdown_state(
+        deps.storage,
+        &ShutdownState {
+            shutdown_height: env.block.height,
+            redemption_height: env.block.height + settlement_period,
+            collateral_tokens,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_messages(hook_messages)
+        .add_attributes(vec![
+            attr("action", "shutdown"),
+            attr("shutdown_height", env.block.height.to_string()),
+            attr(
+                "redemption_height",
+                (env.block.height + settlement_period).to_string(),
+            ),
+        ]))
+}
+
+/// Write off the loan of a borrower whose collaterals were moved to the
+/// market by the overseer
+pub fn settle_loan(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    borrower: Addr,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.overseer_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if read_shutdown_state(deps.storage)?.is_none() {
+        return Err(ContractError::NotShutDown {});
+    }
+
+    let mut state: State = read_state(deps.storage)?;
+    compute_reward(&mut state, &config, env.block.height);
+
+    let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
+    let mut liability: BorrowerInfo = read_borrower_info(deps.storage, &borrower_raw);
+    compute_borrower_interest(&state, &mut liability);
+    compute_borrower_reward(&state, &mut liability);
+
+    let settled_amount = liability.loan_amount;
+    state.total_liabilities = state.total_liabilities
+        - std::cmp::min(
+            state.total_liabilities,
+            Decimal256::from_uint256(settled_amount),
+        );
+    liability.loan_amount = Uint256::zero();
+
+    store_borrower_info(deps.storage, &borrower_raw, &liability)?;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "settle_loan"),
+        attr("borrower", borrower),
+        attr("settled_amount", settled_amount),
+    ]))
+}
+
+/// Burn the aTokens for a pro-rata share of the stable balance above the
+/// reserves and of every collateral settled to the market. Redemptions only
+/// open after the settlement period, so the share of a depositor does not
+/// depend on how many loans were settled before they redeem
+pub fn redeem_stable_pro_rata(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    atoken_amount: Uint128,
+    to: Option<Addr>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let shutdown_state = match read_shutdown_state(deps.storage)? {
+        Some(shutdown_state) => shutdown_state,
+        None => return Err(ContractError::NotShutDown {}),
+    };
+    if env.block.height < shutdown_state.redemption_height {
+        return Err(ContractError::SettlementPeriod(
+            shutdown_state.redemption_height,
+        ));
+    }
+
+    let mut state: State = read_state(deps.storage)?;
+    compute_reward(&mut state, &config, env.block.height);
+
+    // the supply still includes the aTokens being redeemed
+    let atoken_addr = deps.api.addr_humanize(&config.atoken_contract)?;
+    let atoken_supply = query_supply(deps.as_ref(), atoken_addr.clone())?;
+    let share = Decimal256::from_ratio(Uint256::from(atoken_amount), atoken_supply);
+
+    let recipient = to.unwrap_or(sender).to_string();
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: atoken_addr.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+            amount: atoken_amount,
+        })?,
+    })];
+
+    let current_balance = Decimal256::from_uint256(query_balance(
+        deps.as_ref(),
+        env.contract.address.clone(),
+        config.stable_denom.to_string(),
+    )?);
+    let redeem_amount = if current_balance > state.total_reserves {
+        Uint256::one() * ((current_balance - state.total_reserves) * share)
+    } else {
+        Uint256::zero()
+    };
+    if !redeem_amount.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.clone(),
+            amount: vec![deduct_tax(
+                deps.as_ref(),
+                Coin {
+                    denom: config.stable_denom.clone(),
+                    amount: redeem_amount.into(),
+                },
+            )?],
+        }));
+    }
+
+    for collateral_token in shutdown_state.collateral_tokens.iter() {
+        let collateral_token = deps.api.addr_humanize(collateral_token)?;
+        let collateral_amount = query_token_balance(
+            deps.as_ref(),
+            collateral_token.clone(),
+            env.contract.address.clone(),
+        )? * share;
+        if collateral_amount.is_zero() {
+            continue;
+        }
+
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: collateral_token.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.clone(),
+                amount: collateral_amount.into(),
+            })?,
+        }));
+    }
+
+    state.prev_atoken_supply = atoken_supply - Uint256::from(atoken_amount);
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "redeem_stable_pro_rata"),
+        attr("burn_amount", atoken_amount),
+        attr("redeem_amount", redeem_amount),
+    ]))
+}
+
+pub fn assert_not_shutdown(storage: &dyn Storage) -> Result<(), ContractError> {
+    if read_shutdown_state(storage)?.is_some() {
+        return Err(ContractError::ShutDown {});
+    }
+
+    Ok(())
+}
+
+pub fn query_shutdown_state(deps: Deps) -> StdResult<ShutdownStateResponse> {
+    Ok(match read_shutdown_state(deps.storage)? {
+        Some(shutdown_state) => ShutdownStateResponse {
+            shutdown_height: Some(shutdown_state.shutdown_height),
+            redemption_height: Some(shutdown_state.redemption_height),
+            collateral_tokens: shutdown_state
+                .collateral_tokens
+                .iter()
+                .map(|token| Ok(deps.api.addr_humanize(token)?.to_string()))
+                .collect::<StdResult<Vec<String>>>()?,
+        },
+        None => ShutdownStateResponse {
+            shutdown_height: None,
+            redemption_height: None,
+            collateral_tokens: vec![],
+        },
+    })
+}
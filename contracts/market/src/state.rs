@@ -3,15 +3,78 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{CanonicalAddr, Deps, Order, StdResult, Storage};
-use cosmwasm_storage::{bucket, bucket_read, ReadonlyBucket, ReadonlySingleton, Singleton};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 use moneymarket::market::BorrowerInfoResponse;
+use moneymarket::migration::{legacy_singleton_exists, migrate_singleton};
 
-pub const KEY_CONFIG: &[u8] = b"config";
-pub const KEY_STATE: &[u8] = b"state";
-const KEY_NEWOWNER: &[u8] = b"newowner";
+const CONFIG: Item<Config> = Item::new("config");
+const STATE: Item<State> = Item::new("state");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const WITHDRAWAL_QUEUE: Item<WithdrawalQueue> = Item::new("withdrawal_queue");
+const SHUTDOWN: Item<ShutdownState> = Item::new("shutdown");
+/// Last borrower re-saved by `MigrateLiabilities`, stored until every
+/// liability of the legacy layout has its debt index
+const LIABILITY_MIGRATION: Item<Option<Vec<u8>>> = Item::new("liability_migration");
 
-const PREFIX_LIABILITY: &[u8] = b"liability";
+const DEPOSITORS: Map<&[u8], DepositorInfo> = Map::new("depositor");
+const CANCELLED_TICKETS: Map<u64, CancelledTickets> = Map::new("cancelled_tickets");
+
+/// Ticket ids covered by the cancelled tickets tree
+const CANCELLED_TICKETS_SIZE: u64 = 1 << 32;
+
+/// Withdrawal tickets keyed by ticket id, indexed by owner
+fn withdrawal_tickets<'a>() -> IndexedMap<'a, u64, WithdrawalTicket, WithdrawalTicketIndexes<'a>> {
+    let indexes = WithdrawalTicketIndexes {
+        owner: MultiIndex::new(
+            |_, ticket| ticket.owner.to_vec(),
+            "withdrawal_ticket",
+            "withdrawal_ticket__owner",
+        ),
+    };
+    IndexedMap::new("withdrawal_ticket", indexes)
+}
+
+pub struct WithdrawalTicketIndexes<'a> {
+    pub owner: MultiIndex<'a, Vec<u8>, WithdrawalTicket, u64>,
+}
+
+impl<'a> IndexList<WithdrawalTicket> for WithdrawalTicketIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<WithdrawalTicket>> + '_> {
+        let v: Vec<&dyn Index<WithdrawalTicket>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Liabilities keyed by borrower, indexed by recorded loan amount
+fn liabilities<'a>() -> IndexedMap<'a, &'a [u8], BorrowerInfo, LiabilityIndexes<'a>> {
+    let indexes = LiabilityIndexes {
+        debt: MultiIndex::new(
+            |_, info| debt_key(&info.loan_amount),
+            "liability",
+            "liability__debt",
+        ),
+    };
+    IndexedMap::new("liability", indexes)
+}
+
+pub struct LiabilityIndexes<'a> {
+    pub debt: MultiIndex<'a, Vec<u8>, BorrowerInfo, Vec<u8>>,
+}
+
+impl<'a> IndexList<BorrowerInfo> for LiabilityIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<BorrowerInfo>> + '_> {
+        let v: Vec<&dyn Index<BorrowerInfo>> = vec![&self.debt];
+        Box::new(v.into_iter())
+    }
+}
+
+// big-endian so the index orders borrowers by loan amount
+fn debt_key(loan_amount: &Uint256) -> Vec<u8> {
+    let mut key = [0u8; 32];
+    loan_amount.0.to_big_endian(&mut key);
+    key.to_vec()
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -25,6 +88,17 @@ pub struct Config {
     pub distributor_contract: CanonicalAddr,    //veSeilors token contract address
     pub stable_denom: String,
     pub max_borrow_factor: Decimal256,
+    #[serde(default)]
+    pub interest_model_hook: bool,
+    #[serde(default)]
+    pub atoken_balance_hook: bool,
+    /// Share of the KPT emission distributed to aToken holders
+    #[serde(default)]
+    pub depositor_reward_share: Decimal256,
+    #[serde(default)]
+    pub max_total_liabilities: Option<Uint256>,
+    #[serde(default)]
+    pub max_total_deposits: Option<Uint256>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -38,7 +112,17 @@ pub struct State {
     pub kpt_emission_rate: Decimal256,
     pub prev_atoken_supply: Uint256,
     pub prev_exchange_rate: Decimal256,
-
+    /// KPT distributed since genesis
+    #[serde(default)]
+    pub total_kpt_emitted: Decimal256,
+    /// Total KPT budget reported by the distribution model
+    #[serde(default)]
+    pub kpt_emission_budget: Option<Decimal256>,
+    #[serde(default)]
+    pub global_deposit_reward_index: Decimal256,
+    /// aTokens held by the market in withdrawal tickets, excluded from depositor rewards
+    #[serde(default)]
+    pub escrowed_atokens: Uint256,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -49,33 +133,93 @@ pub struct BorrowerInfo {
     pub pending_rewards: Decimal256,
 }
 
+/// KPT rewards accrued by an aToken holder
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositorInfo {
+    pub reward_index: Decimal256,
+    pub pending_rewards: Decimal256,
+}
+
+/// FIFO queue of redemptions waiting for market liquidity
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct WithdrawalQueue {
+    pub next_ticket_id: u64,
+    /// aTokens escrowed by all open tickets
+    pub total_atokens: Uint256,
+    /// aTokens ever queued
+    #[serde(default)]
+    pub total_queued: Uint256,
+    /// aTokens ever filled
+    #[serde(default)]
+    pub total_filled: Uint256,
+    /// Tickets completely filled
+    #[serde(default)]
+    pub filled_tickets: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalTicket {
+    pub owner: CanonicalAddr,
+    pub atoken_amount: Uint256,
+    pub queued_at: u64,
+    /// `total_queued` of the queue when the ticket was created
+    #[serde(default)]
+    pub queued_before: Uint256,
+}
+
+/// Node of a Fenwick tree over ticket ids, summing the cancelled tickets
+/// so a ticket finds its position without walking the queue ahead of it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct CancelledTickets {
+    pub tickets: u64,
+    pub atoken_amount: Uint256,
+}
+
+/// Recorded once the overseer shuts the protocol down
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShutdownState {
+    pub shutdown_height: u64,
+    /// First height redemptions are open at, once the borrowers are settled
+    pub redemption_height: u64,
+    /// Collaterals settled to the market, paid out with redemptions
+    pub collateral_tokens: Vec<CanonicalAddr>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct NewOwnerAddr {
     pub new_owner_addr: CanonicalAddr, 
 }
 
 pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
-    Singleton::new(storage, KEY_NEWOWNER).save(data)
+    NEWOWNER.save(storage, data)
 }
 
 pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
-    ReadonlySingleton::new(storage, KEY_NEWOWNER).load()
+    NEWOWNER.load(storage)
+}
+
+pub fn store_shutdown_state(storage: &mut dyn Storage, data: &ShutdownState) -> StdResult<()> {
+    SHUTDOWN.save(storage, data)
+}
+
+pub fn read_shutdown_state(storage: &dyn Storage) -> StdResult<Option<ShutdownState>> {
+    SHUTDOWN.may_load(storage)
 }
 
 pub fn store_config(storage: &mut dyn Storage, data: &Config) -> StdResult<()> {
-    Singleton::new(storage, KEY_CONFIG).save(data)
+    CONFIG.save(storage, data)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    ReadonlySingleton::new(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
 }
 
 pub fn store_state(storage: &mut dyn Storage, data: &State) -> StdResult<()> {
-    Singleton::new(storage, KEY_STATE).save(data)
+    STATE.save(storage, data)
 }
 
 pub fn read_state(storage: &dyn Storage) -> StdResult<State> {
-    ReadonlySingleton::new(storage, KEY_STATE).load()
+    STATE.load(storage)
 }
 
 pub fn store_borrower_info(
@@ -83,11 +227,11 @@ pub fn store_borrower_info(
     borrower: &CanonicalAddr,
     liability: &BorrowerInfo,
 ) -> StdResult<()> {
-    bucket(storage, PREFIX_LIABILITY).save(borrower.as_slice(), liability)
+    liabilities().save(storage, borrower.as_slice(), liability)
 }
 
 pub fn read_borrower_info(storage: &dyn Storage, borrower: &CanonicalAddr) -> BorrowerInfo {
-    match bucket_read(storage, PREFIX_LIABILITY).load(borrower.as_slice()) {
+    match liabilities().load(storage, borrower.as_slice()) {
         Ok(v) => v,
         _ => BorrowerInfo {
             interest_index: Decimal256::one(),
@@ -98,6 +242,107 @@ pub fn read_borrower_info(storage: &dyn Storage, borrower: &CanonicalAddr) -> Bo
     }
 }
 
+pub fn store_depositor_info(
+    storage: &mut dyn Storage,
+    depositor: &CanonicalAddr,
+    depositor_info: &DepositorInfo,
+) -> StdResult<()> {
+    DEPOSITORS.save(storage, depositor.as_slice(), depositor_info)
+}
+
+pub fn read_depositor_info(storage: &dyn Storage, depositor: &CanonicalAddr) -> DepositorInfo {
+    match DEPOSITORS.load(storage, depositor.as_slice()) {
+        Ok(v) => v,
+        _ => DepositorInfo {
+            reward_index: Decimal256::zero(),
+            pending_rewards: Decimal256::zero(),
+        },
+    }
+}
+
+pub fn store_withdrawal_queue(storage: &mut dyn Storage, data: &WithdrawalQueue) -> StdResult<()> {
+    WITHDRAWAL_QUEUE.save(storage, data)
+}
+
+pub fn read_withdrawal_queue(storage: &dyn Storage) -> StdResult<WithdrawalQueue> {
+    Ok(WITHDRAWAL_QUEUE.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn store_withdrawal_ticket(
+    storage: &mut dyn Storage,
+    ticket_id: u64,
+    ticket: &WithdrawalTicket,
+) -> StdResult<()> {
+    withdrawal_tickets().save(storage, ticket_id, ticket)
+}
+
+pub fn remove_withdrawal_ticket(storage: &mut dyn Storage, ticket_id: u64) -> StdResult<()> {
+    withdrawal_tickets().remove(storage, ticket_id)
+}
+
+pub fn read_withdrawal_ticket(storage: &dyn Storage, ticket_id: u64) -> StdResult<WithdrawalTicket> {
+    withdrawal_tickets().load(storage, ticket_id)
+}
+
+/// Open tickets in queue order, oldest first, optionally of a single owner
+pub fn read_withdrawal_tickets(
+    storage: &dyn Storage,
+    owner: Option<&CanonicalAddr>,
+    start_after: Option<u64>,
+    limit: usize,
+) -> StdResult<Vec<(u64, WithdrawalTicket)>> {
+    let start = start_after.map(Bound::exclusive);
+    match owner {
+        Some(owner) => withdrawal_tickets()
+            .idx
+            .owner
+            .prefix(owner.to_vec())
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect(),
+        None => withdrawal_tickets()
+            .range(storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect(),
+    }
+}
+
+/// Record a cancelled ticket in the cancelled tickets tree
+pub fn store_cancelled_ticket(
+    storage: &mut dyn Storage,
+    ticket_id: u64,
+    atoken_amount: Uint256,
+) -> StdResult<()> {
+    let mut index = ticket_id + 1;
+    while index <= CANCELLED_TICKETS_SIZE {
+        let mut node = CANCELLED_TICKETS.may_load(storage, index)?.unwrap_or_default();
+        node.tickets += 1;
+        node.atoken_amount += atoken_amount;
+        CANCELLED_TICKETS.save(storage, index, &node)?;
+        index += index & index.wrapping_neg();
+    }
+
+    Ok(())
+}
+
+/// Tickets cancelled ahead of `ticket_id`
+pub fn read_cancelled_tickets_before(
+    storage: &dyn Storage,
+    ticket_id: u64,
+) -> StdResult<CancelledTickets> {
+    let mut cancelled = CancelledTickets::default();
+    let mut index = ticket_id.min(CANCELLED_TICKETS_SIZE);
+    while index > 0 {
+        if let Some(node) = CANCELLED_TICKETS.may_load(storage, index)? {
+            cancelled.tickets += node.tickets;
+            cancelled.atoken_amount += node.atoken_amount;
+        }
+        index -= index & index.wrapping_neg();
+    }
+
+    Ok(cancelled)
+}
+
 // settings for pagination
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
@@ -106,32 +351,94 @@ pub fn read_borrower_infos(
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
 ) -> StdResult<Vec<BorrowerInfoResponse>> {
-    let liability_bucket: ReadonlyBucket<BorrowerInfo> =
-        bucket_read(deps.storage, PREFIX_LIABILITY);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|addr| Bound::ExclusiveRaw(addr.to_vec()));
 
+    liabilities()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (k, v) = elem?;
+            to_borrower_info_response(deps, k, v)
+        })
+        .collect()
+}
+
+/// Liabilities ordered by recorded loan amount, largest first
+pub fn read_borrowers_by_debt(
+    deps: Deps,
+    start_after: Option<CanonicalAddr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<BorrowerInfoResponse>> {
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let end = start_after.map(|addr| {
+        let loan_amount = read_borrower_info(deps.storage, &addr).loan_amount;
+        Bound::exclusive((debt_key(&loan_amount), addr.to_vec()))
+    });
 
-    liability_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+    liabilities()
+        .idx
+        .debt
+        .range(deps.storage, None, end, Order::Descending)
         .take(limit)
         .map(|elem| {
             let (k, v) = elem?;
-            let borrower = deps.api.addr_humanize(&CanonicalAddr::from(k))?.to_string();
-            Ok(BorrowerInfoResponse {
-                borrower,
-                interest_index: v.interest_index,
-                loan_amount: v.loan_amount,
-            })
+            to_borrower_info_response(deps, k, v)
         })
         .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
+fn to_borrower_info_response(
+    deps: Deps,
+    borrower: Vec<u8>,
+    info: BorrowerInfo,
+) -> StdResult<BorrowerInfoResponse> {
+    Ok(BorrowerInfoResponse {
+        borrower: deps.api.addr_humanize(&CanonicalAddr::from(borrower))?.to_string(),
+        interest_index: info.interest_index,
+        reward_index: info.reward_index,
+        loan_amount: info.loan_amount,
+        pending_rewards: info.pending_rewards,
     })
 }
+
+/// Move the singletons of the baseline release written by `cosmwasm_storage`,
+/// whether or not cw2 versions were recorded yet. Every step is a no-op once
+/// the state is moved. The liability
+/// bucket is read as is, and re-saved in pages by `migrate_liabilities` to
+/// build the debt index.
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    if legacy_singleton_exists(storage, &CONFIG) {
+        LIABILITY_MIGRATION.save(storage, &None)?;
+    }
+
+    migrate_singleton(storage, &CONFIG)?;
+    migrate_singleton(storage, &STATE)?;
+    migrate_singleton(storage, &NEWOWNER)
+}
+
+/// Re-save up to `limit` liabilities after the last migrated one, and return
+/// how many were re-saved. The migration is done once fewer than `limit` are
+pub fn migrate_liabilities(storage: &mut dyn Storage, limit: usize) -> StdResult<usize> {
+    let last = match LIABILITY_MIGRATION.may_load(storage)? {
+        Some(last) => last,
+        None => return Ok(0),
+    };
+
+    let start = last.as_deref().map(Bound::exclusive);
+    let elems = liabilities()
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (borrower, liability) in elems.iter() {
+        liabilities().save(storage, borrower, liability)?;
+    }
+
+    if elems.len() < limit {
+        LIABILITY_MIGRATION.remove(storage);
+    } else if let Some((borrower, _)) = elems.last() {
+        LIABILITY_MIGRATION.save(storage, &Some(borrower.clone()))?;
+    }
+
+    Ok(elems.len())
+}
@@ -1,9 +1,13 @@
-use crate::borrow::{compute_borrower_interest, compute_interest};
+use crate::borrow::{
+    compute_borrower_interest, compute_interest, compute_reward, compute_reward_raw,
+};
+use crate::contract::INITIAL_DEPOSIT_AMOUNT;
 use crate::state::{store_state, BorrowerInfo, Config, State};
 use crate::testing::mock_querier::mock_dependencies;
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, MOCK_CONTRACT_ADDR};
-use cosmwasm_std::{Api, Coin, Uint128};
+use cosmwasm_std::{to_json_binary, Api, Coin, CosmosMsg, Uint128, WasmMsg};
+use moneymarket::interest_model::ExecuteMsg as InterestModelExecuteMsg;
 
 #[test]
 fn proper_compute_borrower_interest() {
@@ -18,6 +22,10 @@ fn proper_compute_borrower_interest() {
         kpt_emission_rate: Decimal256::one(),
         prev_atoken_supply: Uint256::zero(),
         prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
     };
     let mut liability1 = BorrowerInfo {
         interest_index: Decimal256::one(),
@@ -44,6 +52,10 @@ fn proper_compute_borrower_interest() {
         kpt_emission_rate: Decimal256::zero(),
         prev_atoken_supply: Uint256::zero(),
         prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
     };
     let mut liability3 = BorrowerInfo {
         interest_index: Decimal256::from_uint256(4u128),
@@ -86,6 +98,11 @@ fn proper_compute_interest() {
         overseer_contract: deps.api.addr_canonicalize("overseer").unwrap(),
         stable_denom: "uusd".to_string(),
         max_borrow_factor: Decimal256::one(),
+        interest_model_hook: false,
+        atoken_balance_hook: false,
+        depositor_reward_share: Decimal256::zero(),
+        max_total_liabilities: None,
+        max_total_deposits: None,
     };
 
     deps.querier
@@ -101,6 +118,10 @@ fn proper_compute_interest() {
         kpt_emission_rate: Decimal256::one(),
         prev_atoken_supply: Uint256::zero(),
         prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
     };
     store_state(&mut deps.storage, &mock_state).unwrap();
 
@@ -126,6 +147,10 @@ fn proper_compute_interest() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 
@@ -151,6 +176,10 @@ fn proper_compute_interest() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::from(2000000u64),
             prev_exchange_rate: Decimal256::from_ratio(19995, 10000),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 
@@ -165,6 +194,10 @@ fn proper_compute_interest() {
         kpt_emission_rate: Decimal256::one(),
         prev_atoken_supply: Uint256::from(2000000u128),
         prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
     };
     store_state(&mut deps.storage, &mock_state).unwrap();
 
@@ -200,6 +233,193 @@ fn proper_compute_interest() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::from(2000000u64),
             prev_exchange_rate: Decimal256::from_uint256(2u64),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 }
+
+#[test]
+fn compute_interest_with_interest_model_hook() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(2000000u128),
+    }]);
+
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&"addr0000".to_string(), &Uint128::from(2000000u128))],
+    )]);
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+
+    let mut env = mock_env();
+
+    let mock_config = Config {
+        contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
+        owner_addr: deps.api.addr_canonicalize("owner").unwrap(),
+        atoken_contract: deps.api.addr_canonicalize("at-uusd").unwrap(),
+        interest_model: deps.api.addr_canonicalize("interest").unwrap(),
+        distribution_model: deps.api.addr_canonicalize("distribution").unwrap(),
+        distributor_contract: deps.api.addr_canonicalize("distributor").unwrap(),
+        collector_contract: deps.api.addr_canonicalize("collector").unwrap(),
+        overseer_contract: deps.api.addr_canonicalize("overseer").unwrap(),
+        stable_denom: "uusd".to_string(),
+        max_borrow_factor: Decimal256::one(),
+        interest_model_hook: true,
+        atoken_balance_hook: false,
+        depositor_reward_share: Decimal256::zero(),
+        max_total_liabilities: None,
+        max_total_deposits: None,
+    };
+
+    let mut mock_state = State {
+        total_liabilities: Decimal256::from_uint256(1000000u128),
+        total_reserves: Decimal256::zero(),
+        last_interest_updated: env.block.height,
+        last_reward_updated: env.block.height,
+        global_interest_index: Decimal256::one(),
+        global_reward_index: Decimal256::zero(),
+        kpt_emission_rate: Decimal256::one(),
+        prev_atoken_supply: Uint256::zero(),
+        prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
+    };
+
+    // interest already computed in this block, nothing to report
+    let hook_messages = compute_interest(
+        deps.as_ref(),
+        &mock_config,
+        &mut mock_state,
+        env.block.height,
+        None,
+    )
+    .unwrap();
+    assert!(hook_messages.is_empty());
+
+    env.block.height += 100;
+
+    // reports the values the borrow rate was computed with
+    let hook_messages = compute_interest(
+        deps.as_ref(),
+        &mock_config,
+        &mut mock_state,
+        env.block.height,
+        Some(Uint256::from(1000u128)),
+    )
+    .unwrap();
+    assert_eq!(
+        hook_messages,
+        vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "interest".to_string(),
+            funds: vec![],
+            msg: to_json_binary(&InterestModelExecuteMsg::UpdateRate {
+                market_balance: Uint256::from(1999000u128),
+                total_liabilities: Decimal256::from_uint256(1000000u128),
+                total_reserves: Decimal256::zero(),
+            })
+            .unwrap(),
+        })]
+    );
+}
+
+fn mock_reward_config(depositor_reward_share: Decimal256) -> Config {
+    let deps = mock_dependencies(&[]);
+    Config {
+        contract_addr: deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
+        owner_addr: deps.api.addr_canonicalize("owner").unwrap(),
+        atoken_contract: deps.api.addr_canonicalize("at-uusd").unwrap(),
+        interest_model: deps.api.addr_canonicalize("interest").unwrap(),
+        distribution_model: deps.api.addr_canonicalize("distribution").unwrap(),
+        distributor_contract: deps.api.addr_canonicalize("distributor").unwrap(),
+        collector_contract: deps.api.addr_canonicalize("collector").unwrap(),
+        overseer_contract: deps.api.addr_canonicalize("overseer").unwrap(),
+        stable_denom: "uusd".to_string(),
+        max_borrow_factor: Decimal256::one(),
+        interest_model_hook: false,
+        atoken_balance_hook: false,
+        depositor_reward_share,
+        max_total_liabilities: None,
+        max_total_deposits: None,
+    }
+}
+
+#[test]
+fn compute_reward_within_emission_budget() {
+    let config = mock_reward_config(Decimal256::zero());
+    let mut state = State {
+        total_liabilities: Decimal256::from_uint256(1000u128),
+        total_reserves: Decimal256::zero(),
+        last_interest_updated: 100,
+        last_reward_updated: 100,
+        global_interest_index: Decimal256::one(),
+        global_reward_index: Decimal256::zero(),
+        kpt_emission_rate: Decimal256::from_uint256(10u128),
+        prev_atoken_supply: Uint256::zero(),
+        prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::from_uint256(900u128),
+        kpt_emission_budget: Some(Decimal256::from_uint256(1000u128)),
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
+    };
+
+    // 5 blocks * 10 = 50 emitted
+    compute_reward(&mut state, &config, 105);
+    assert_eq!(state.global_reward_index, Decimal256::from_ratio(50u64, 1000u64));
+    assert_eq!(state.total_kpt_emitted, Decimal256::from_uint256(950u128));
+
+    // only 50 left in the budget
+    compute_reward(&mut state, &config, 115);
+    assert_eq!(state.global_reward_index, Decimal256::from_ratio(100u64, 1000u64));
+    assert_eq!(state.total_kpt_emitted, Decimal256::from_uint256(1000u128));
+    assert_eq!(state.last_reward_updated, 115);
+
+    // budget exhausted
+    compute_reward(&mut state, &config, 120);
+    assert_eq!(state.global_reward_index, Decimal256::from_ratio(100u64, 1000u64));
+    assert_eq!(state.total_kpt_emitted, Decimal256::from_uint256(1000u128));
+}
+
+#[test]
+fn compute_reward_with_depositor_share() {
+    let config = mock_reward_config(Decimal256::percent(40));
+    let mut state = State {
+        total_liabilities: Decimal256::from_uint256(1000u128),
+        total_reserves: Decimal256::zero(),
+        last_interest_updated: 100,
+        last_reward_updated: 100,
+        global_interest_index: Decimal256::one(),
+        global_reward_index: Decimal256::zero(),
+        kpt_emission_rate: Decimal256::from_uint256(10u128),
+        prev_atoken_supply: Uint256::from(INITIAL_DEPOSIT_AMOUNT + 2000u128),
+        prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
+    };
+
+    // 10 blocks * 10 = 100; 60 to borrowers, 40 to aToken holders, leaving out
+    // the initial deposit held by the market
+    compute_reward(&mut state, &config, 110);
+    assert_eq!(state.global_reward_index, Decimal256::from_ratio(60u64, 1000u64));
+    assert_eq!(state.global_deposit_reward_index, Decimal256::from_ratio(40u64, 2000u64));
+    assert_eq!(state.total_kpt_emitted, Decimal256::from_uint256(100u128));
+
+    // explicit supply, as reported by the aToken balance hook
+    compute_reward_raw(&mut state, &config, Uint256::from(INITIAL_DEPOSIT_AMOUNT + 4000u128), 120);
+    assert_eq!(state.global_reward_index, Decimal256::from_ratio(120u64, 1000u64));
+    assert_eq!(state.global_deposit_reward_index, Decimal256::from_ratio(30u64, 1000u64));
+    assert_eq!(state.total_kpt_emitted, Decimal256::from_uint256(200u128));
+
+    // escrowed aTokens earn nothing either
+    state.escrowed_atokens = Uint256::from(2000u128);
+    compute_reward_raw(&mut state, &config, Uint256::from(INITIAL_DEPOSIT_AMOUNT + 4000u128), 130);
+    assert_eq!(state.global_deposit_reward_index, Decimal256::from_ratio(50u64, 1000u64));
+    assert_eq!(state.total_kpt_emitted, Decimal256::from_uint256(300u128));
+}
@@ -24,6 +24,11 @@ fn proper_compute_exchange_rate() {
         overseer_contract: deps.api.addr_canonicalize("overseer").unwrap(),
         stable_denom: "uusd".to_string(),
         max_borrow_factor: Decimal256::one(),
+        interest_model_hook: false,
+        atoken_balance_hook: false,
+        depositor_reward_share: Decimal256::zero(),
+        max_total_liabilities: None,
+        max_total_deposits: None,
     };
     deps.querier.with_token_balances(&[(
         &"at-uusd".to_string(),
@@ -40,6 +45,10 @@ fn proper_compute_exchange_rate() {
         kpt_emission_rate: Decimal256::one(),
         prev_atoken_supply: Uint256::zero(),
         prev_exchange_rate: Decimal256::one(),
+        total_kpt_emitted: Decimal256::zero(),
+        kpt_emission_budget: None,
+        global_deposit_reward_index: Decimal256::zero(),
+        escrowed_atokens: Uint256::zero(),
     };
     let mock_deposit_amount = Some(Uint256::from(1000000u128));
 
@@ -11,10 +11,10 @@ use cosmwasm_std::{
 use cosmwasm_storage::to_length_prefixed;
 use std::collections::HashMap;
 
-use cw20::TokenInfoResponse;
+use cw20::{BalanceResponse as Cw20BalanceResponse, TokenInfoResponse};
 use moneymarket::distribution_model::KptEmissionRateResponse;
 use moneymarket::interest_model::BorrowRateResponse;
-use moneymarket::overseer::{BorrowLimitResponse, ConfigResponse};
+use moneymarket::overseer::{BorrowLimitResponse, ConfigResponse, FrozenLiquidationPolicy};
 
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -42,6 +42,8 @@ pub enum QueryMsg {
     Config {},
     /// Query cw20 Token Info
     TokenInfo {},
+    /// Query cw20 Token Balance
+    Balance { address: String },
 }
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
@@ -250,6 +252,7 @@ impl WasmMockQuerier {
                     } => SystemResult::Ok(ContractResult::from(to_json_binary(
                         &KptEmissionRateResponse {
                             emission_rate: Decimal256::from_uint256(5u64),
+                            total_budget: None,
                         },
                     ))),
                     QueryMsg::Config {} => {
@@ -257,7 +260,7 @@ impl WasmMockQuerier {
                             owner_addr: "".to_string(),
                             oracle_contract: "".to_string(),
                             market_contract: "".to_string(),
-                            liquidation_contract: "".to_string(),
+                            liquidation_contract: "liquidation".to_string(),
                             collector_contract: "".to_string(),
                             threshold_deposit_rate: Decimal256::one(),
                             target_deposit_rate: Decimal256::from_ratio(1, 100),
@@ -277,6 +280,8 @@ impl WasmMockQuerier {
                                 1200000000000u64,
                                 1000000000000000000u64,
                             ),
+                            frozen_liquidation_policy: FrozenLiquidationPolicy::Block,
+                            liquidation_cooldown: 0,
                         })))
                     }
                     QueryMsg::TokenInfo {} => {
@@ -299,6 +304,16 @@ impl WasmMockQuerier {
                             total_supply,
                         })))
                     }
+                    QueryMsg::Balance { address } => {
+                        let balance = match self.token_querier.balances.get(contract_addr) {
+                            Some(balances) => balances.get(&address).copied().unwrap_or_default(),
+                            None => Uint128::zero(),
+                        };
+
+                        SystemResult::Ok(ContractResult::from(to_json_binary(
+                            &Cw20BalanceResponse { balance },
+                        )))
+                    }
                 }
             }
             QueryRequest::Wasm(WasmQuery::Raw { contract_addr, key }) => {
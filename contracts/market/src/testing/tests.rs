@@ -1,23 +1,32 @@
 use crate::borrow::borrow_stable as _borrow_stable;
-use crate::contract::{execute, instantiate, query, reply, INITIAL_DEPOSIT_AMOUNT};
+use crate::contract::{execute, instantiate, migrate, query, reply, INITIAL_DEPOSIT_AMOUNT};
 use crate::error::ContractError;
 use crate::response::MsgInstantiateContractResponse;
-use crate::state::{read_borrower_infos, read_state, store_state, State};
+use crate::state::{
+    read_borrower_infos, read_config, read_depositor_info, read_state, store_borrower_info,
+    store_state, BorrowerInfo, State,
+};
 use crate::testing::mock_querier::mock_dependencies;
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, from_json, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Reply, SubMsg,
-    SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
+    attr, from_json, to_json_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, Deps, Order,
+    Reply, Storage, SubMsg, SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
 };
+use cosmwasm_storage::to_length_prefixed;
 use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
+use cw2::set_contract_version;
+use moneymarket::config_history::{ConfigChange, ConfigHistoryEntryResponse, ConfigHistoryResponse};
 use moneymarket::market::{
-    BorrowerInfoResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
-    StateResponse,
+    BorrowerInfoResponse, BorrowerInfosResponse, ConfigResponse, Cw20HookMsg,
+    DepositorInfoResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, RatesResponse,
+    ShutdownStateResponse, StateResponse, WithdrawalTicketResponse, WithdrawalTicketsResponse,
 };
+use moneymarket::pause::{PausableOperation, PauseStatusResponse};
 use moneymarket::querier::deduct_tax;
-use moneymarket::terraswap::InstantiateMsg as TokenInstantiateMsg;
+use moneymarket::thirdpart::ExecuteMsg as ThirdpartExecuteMsg;
+use moneymarket::terraswap::{InstantiateMsg as TokenInstantiateMsg, TokenExecuteMsg};
 use protobuf::Message;
 use std::str::FromStr;
 
@@ -34,6 +43,7 @@ fn proper_initialization() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -66,6 +76,7 @@ fn proper_initialization() {
                         minter: MOCK_CONTRACT_ADDR.to_string(),
                         cap: None,
                     }),
+                    balance_hook: None,
                 })
                 .unwrap(),
             }),
@@ -153,6 +164,7 @@ fn update_config() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -195,6 +207,10 @@ fn update_config() {
         interest_model: None,
         distribution_model: None,
         max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: None,
+        max_total_liabilities: None,
+        max_total_deposits: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -222,6 +238,10 @@ fn update_config() {
         interest_model: Some("interest2".to_string()),
         distribution_model: Some("distribution2".to_string()),
         max_borrow_factor: Some(Decimal256::percent(100)),
+        interest_model_hook: Some(true),
+        depositor_reward_share: None,
+        max_total_liabilities: None,
+        max_total_deposits: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -234,6 +254,26 @@ fn update_config() {
     assert_eq!("interest2".to_string(), config_res.interest_model);
     assert_eq!("distribution2".to_string(), config_res.distribution_model);
     assert_eq!(Decimal256::percent(100), config_res.max_borrow_factor);
+    assert!(config_res.interest_model_hook);
+    assert!(!config_res.atoken_balance_hook);
+
+    // depositor rewards need the aToken balance hook
+    let info = mock_info("owner1", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: Some(Decimal256::percent(50)),
+        max_total_liabilities: None,
+        max_total_deposits: None,
+    };
+
+    let res = execute(deps.as_mut(), mock_env(), info, msg);
+    match res {
+        Err(ContractError::AtokenBalanceHookDisabled {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 
     // Unauthorized err
     let info = mock_info("owner", &[]);
@@ -241,6 +281,10 @@ fn update_config() {
         interest_model: None,
         distribution_model: None,
         max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: None,
+        max_total_liabilities: None,
+        max_total_deposits: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -263,6 +307,7 @@ fn deposit_stable_huge_amount() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -419,6 +464,7 @@ fn deposit_stable() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -529,6 +575,10 @@ fn deposit_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::from(1000000u64),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 
@@ -568,6 +618,10 @@ fn deposit_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::from_ratio(1u64, 2u64),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -618,6 +672,10 @@ fn deposit_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::from_ratio(1u64, 2u64),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -645,6 +703,10 @@ fn deposit_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::from(INITIAL_DEPOSIT_AMOUNT + 1818181),
             prev_exchange_rate: Decimal256::from_ratio(55u64, 100u64),
+            total_kpt_emitted: Decimal256::from_uint256(100u64),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 }
@@ -662,6 +724,7 @@ fn redeem_stable() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -725,6 +788,10 @@ fn redeem_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::from(Uint128::from(1000000u128)),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -738,7 +805,11 @@ fn redeem_stable() {
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: "addr0000".to_string(),
         amount: Uint128::from(1000000u128),
-        msg: to_json_binary(&Cw20HookMsg::RedeemStable {}).unwrap(),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: None,
+            exact_underlying: None,
+        })
+        .unwrap(),
     });
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
@@ -787,6 +858,10 @@ fn redeem_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::from(2000000u64),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -840,6 +915,82 @@ fn redeem_stable() {
             }))
         ]
     );
+
+    // redeem an exact amount to another recipient
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: Some("addr0001".to_string()),
+            exact_underlying: Some(Uint256::zero()),
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg);
+    match res {
+        Err(ContractError::ZeroRedeem(denom)) => assert_eq!(denom, "uusd"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: Some("addr0001".to_string()),
+            exact_underlying: Some(Uint256::from(300001u64)),
+        })
+        .unwrap(),
+    });
+    let info = mock_info("at-uusd", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+    match res {
+        Err(ContractError::InsufficientRedeemAtokens(amount)) => assert_eq!(amount, "600002"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(1000000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: Some("addr0001".to_string()),
+            exact_underlying: Some(Uint256::from(300001u64)),
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "at-uusd".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(600002u128),
+                })
+                .unwrap()
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0001".to_string(),
+                amount: vec![deduct_tax(
+                    deps.as_ref(),
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::from(300001u128),
+                    }
+                )
+                .unwrap(),]
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "at-uusd".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0000".to_string(),
+                    amount: Uint128::from(399998u128),
+                })
+                .unwrap()
+            })),
+        ]
+    );
 }
 
 #[test]
@@ -859,6 +1010,7 @@ fn borrow_stable() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -913,6 +1065,10 @@ fn borrow_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -920,6 +1076,7 @@ fn borrow_stable() {
     let msg = ExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(500000u64),
         to: None,
+        price_update: None,
     };
 
     env.block.height += 100;
@@ -1039,9 +1196,9 @@ fn borrow_stable() {
         BorrowerInfoResponse {
             borrower: "addr0000".to_string(),
             interest_index: Decimal256::from_uint256(2u128),
-            //reward_index: Decimal256::from_str("0.0001").unwrap(),
+            reward_index: Decimal256::from_str("0.0001").unwrap(),
             loan_amount: Uint256::from(500000u64),
-            //pending_rewards: Decimal256::zero(),
+            pending_rewards: Decimal256::zero(),
         }
     );
 
@@ -1061,9 +1218,9 @@ fn borrow_stable() {
         BorrowerInfoResponse {
             borrower: "addr0000".to_string(),
             interest_index: Decimal256::from_uint256(2u128),
-            //reward_index: Decimal256::from_str("0.0001").unwrap(),
+            reward_index: Decimal256::from_str("0.0001").unwrap(),
             loan_amount: Uint256::from(500000u64),
-            // pending_rewards: Decimal256::zero(),
+            pending_rewards: Decimal256::zero(),
         }
     );
 
@@ -1085,16 +1242,32 @@ fn borrow_stable() {
         BorrowerInfoResponse {
             borrower: "addr0000".to_string(),
             interest_index: Decimal256::from_uint256(4u128),
-            // reward_index: Decimal256::from_str("0.00018").unwrap(),
+            reward_index: Decimal256::from_str("0.00018").unwrap(),
             loan_amount: Uint256::from(1000000u64),
-            // pending_rewards: Decimal256::from_uint256(20u64),
+            pending_rewards: Decimal256::from_uint256(20u64),
         }
     );
 
+    // paginated query is projected the same way
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::BorrowerInfos {
+            start_after: None,
+            limit: None,
+            block_height: Some(env.block.height + 100),
+        },
+    )
+    .unwrap();
+
+    let borrower_infos: BorrowerInfosResponse = from_json(&res).unwrap();
+    assert_eq!(borrower_infos.borrower_infos, vec![borrower_info]);
+
     // Cannot borrow more than borrow limit
     let msg = ExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(500001u64),
         to: None,
+        price_update: None,
     };
     let res = execute(deps.as_mut(), env, info, msg);
     match res {
@@ -1120,6 +1293,7 @@ fn assert_max_borrow_factor() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::percent(1),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -1173,6 +1347,10 @@ fn assert_max_borrow_factor() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -1180,6 +1358,7 @@ fn assert_max_borrow_factor() {
     let msg = ExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(10000u64),
         to: None,
+        price_update: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
@@ -1204,6 +1383,7 @@ fn assert_max_borrow_factor() {
     let msg = ExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(1u64),
         to: None,
+        price_update: None,
     };
     let res = execute(deps.as_mut(), mock_env(), info, msg);
     let _uusd_string = "uusd";
@@ -1230,6 +1410,7 @@ fn repay_stable() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -1284,6 +1465,10 @@ fn repay_stable() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -1291,12 +1476,13 @@ fn repay_stable() {
     let msg = ExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(500000u64),
         to: None,
+        price_update: None,
     };
 
     env.block.height += 100;
     let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-    let msg = ExecuteMsg::RepayStable {};
+    let msg = ExecuteMsg::RepayStable { borrower: None };
     info.funds = vec![Coin {
         denom: "ukrw".to_string(),
         amount: Uint128::from(100000u128),
@@ -1358,7 +1544,7 @@ fn repay_stable() {
         denom: "uusd".to_string(),
         amount: Uint128::from(500000u128),
     }];
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
@@ -1394,6 +1580,58 @@ fn repay_stable() {
             .unwrap()]
         }))]
     );
+
+    // repay on behalf of another borrower
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(100000u64),
+        to: None,
+        price_update: None,
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::RepayStable {
+        borrower: Some("addr0000".to_string()),
+    };
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(150000u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "repay_stable"),
+            attr("borrower", "addr0000"),
+            attr("repay_amount", "100000"),
+        ]
+    );
+
+    let res_loan = read_borrower_infos(deps.as_ref(), None, None)
+        .unwrap()
+        .first()
+        .unwrap()
+        .loan_amount;
+    assert_eq!(res_loan, Uint256::zero());
+
+    // overpayment is refunded to the sender
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![deduct_tax(
+                deps.as_ref(),
+                Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::from(50000u128),
+                }
+            )
+            .unwrap()]
+        }))]
+    );
 }
 
 #[test]
@@ -1413,6 +1651,7 @@ fn repay_stable_from_liquidation() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -1467,6 +1706,10 @@ fn repay_stable_from_liquidation() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -1498,7 +1741,6 @@ fn repay_stable_from_liquidation() {
 
     let msg = ExecuteMsg::RepayStableFromLiquidation {
         borrower: "addr0000".to_string(),
-        prev_balance: Uint256::from(INITIAL_DEPOSIT_AMOUNT),
     };
 
     let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
@@ -1507,25 +1749,32 @@ fn repay_stable_from_liquidation() {
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    let info = mock_info("overseer", &[]);
+    let info = mock_info("liquidation", &[]);
 
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     let _uusd_string = "uusd";
     match res {
         Err(ContractError::ZeroRepay(__uusd_string)) => (),
         _ => panic!("DO NOT ENTER HERE"),
     }
 
-    // update balance to make repay
+    // a deposit in the same transaction is not credited to the borrower
     deps.querier.update_balance(
         MOCK_CONTRACT_ADDR.to_string(),
         vec![Coin {
             denom: "uusd".to_string(),
-            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 100000u128),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 400000u128),
         }],
     );
 
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    let info = mock_info(
+        "liquidation",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
     assert_eq!(
         res.attributes,
         vec![
@@ -1539,10 +1788,18 @@ fn repay_stable_from_liquidation() {
         MOCK_CONTRACT_ADDR.to_string(),
         vec![Coin {
             denom: "uusd".to_string(),
-            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 500000u128),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 900000u128),
         }],
     );
 
+    // the overseer repays from the yield reserve the same way
+    let info = mock_info(
+        "overseer",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(500000u128),
+        }],
+    );
     let res = execute(deps.as_mut(), env, info, msg).unwrap();
     assert_eq!(
         res.attributes,
@@ -1570,15 +1827,11 @@ fn repay_stable_from_liquidation() {
 }
 
 #[test]
-fn claim_rewards() {
+fn repay_stable_with_atoken() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        amount: Uint128::from(1000000u128),
     }]);
-    deps.querier.with_tax(
-        Decimal::percent(1),
-        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
-    );
 
     let msg = InstantiateMsg {
         owner_addr: "owner".to_string(),
@@ -1586,8 +1839,8 @@ fn claim_rewards() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
-
     let info = mock_info(
         "addr0000",
         &[Coin {
@@ -1595,8 +1848,6 @@ fn claim_rewards() {
             amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
         }],
     );
-
-    // we can just call .unwrap() to assert this was a success
     let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     // Register kryptonite token contract
@@ -1611,7 +1862,6 @@ fn claim_rewards() {
     };
     let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
 
-    // Register overseer contract
     let msg = ExecuteMsg::RegisterContracts {
         overseer_contract: "overseer".to_string(),
         interest_model: "interest".to_string(),
@@ -1619,116 +1869,306 @@ fn claim_rewards() {
         collector_contract: "collector".to_string(),
         distributor_contract: "distributor".to_string(),
     };
-    let info = mock_info("addr0000", &[]);
     let env = mock_env();
-    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
     deps.querier
         .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
     deps.querier
         .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&"addr0000".to_string(), &Uint128::from(1000000u128))],
+    )]);
 
     store_state(
         deps.as_mut().storage,
         &State {
-            total_liabilities: Decimal256::from_uint256(1000000u128),
+            total_liabilities: Decimal256::zero(),
             total_reserves: Decimal256::zero(),
             last_interest_updated: env.block.height,
             last_reward_updated: env.block.height,
             global_interest_index: Decimal256::one(),
             global_reward_index: Decimal256::zero(),
             kpt_emission_rate: Decimal256::one(),
-            prev_atoken_supply: Uint256::zero(),
+            prev_atoken_supply: Uint256::from(1000000u128),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
 
-    // zero loan claim, will return empty messages
-    let msg = ExecuteMsg::ClaimRewards { to: None };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-    assert_eq!(res.messages.len(), 0);
-
     let msg = ExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(500000u64),
         to: None,
+        price_update: None,
     };
-    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
-
-    // zero block passed
-    let msg = ExecuteMsg::ClaimRewards {
-        to: Some("addr0001".to_string()),
-    };
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
-    assert_eq!(res.messages.len(), 0);
-
-    // 100 blocks passed
-    // env.block.height += 100;
-    // let res = execute(deps.as_mut(), env, info, msg).unwrap();
-    // assert_eq!(
-    //     res.messages,
-    //     vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-    //         contract_addr: "distributor".to_string(),
-    //         funds: vec![],
-    //         msg: to_json_binary(&FaucetExecuteMsg::Spend {
-    //             recipient: "addr0001".to_string(),
-    //             amount: Uint128::from(33u128),
-    //         })
-    //         .unwrap(),
-    //     }))]
-    // );
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    let _res: BorrowerInfoResponse = from_json(
-        &query(
-            deps.as_ref(),
-            mock_env(),
-            QueryMsg::BorrowerInfo {
-                borrower: "addr0000".to_string(),
-                block_height: None,
-            },
-        )
-        .unwrap(),
-    )
-    .unwrap();
-    // assert_eq!(
-    //     res.pending_rewards,
-    //     Decimal256::from_str("0.333333333333").unwrap()
-    // );
-    // assert_eq!(
-    //     res.reward_index,
-    //     Decimal256::from_str("0.000066666666666666").unwrap()
-    // );
-}
+    // exchange rate is (1000000 + 500000) / 1000000 = 1.5
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(200000u128),
+        msg: to_json_binary(&Cw20HookMsg::RepayStable { borrower: None }).unwrap(),
+    });
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 
-#[test]
-fn execute_epoch_operations() {
-    let mut deps = mock_dependencies(&[Coin {
-        denom: "uusd".to_string(),
-        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
-    }]);
-    deps.querier.with_tax(
-        Decimal::percent(1),
-        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    let res = execute(deps.as_mut(), env.clone(), mock_info("at-uusd", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "repay_stable_with_atoken"),
+            attr("borrower", "addr0000"),
+            attr("repay_amount", "300000"),
+            attr("burn_amount", "200000"),
+        ]
     );
-
-    let msg = InstantiateMsg {
-        owner_addr: "owner".to_string(),
-        stable_denom: "uusd".to_string(),
-        atoken_code_id: 123u64,
-        kpt_emission_rate: Decimal256::one(),
-        max_borrow_factor: Decimal256::one(),
-    };
-
-    let info = mock_info(
-        "addr0000",
-        &[Coin {
-            denom: "uusd".to_string(),
-            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
-        }],
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "at-uusd".to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::from(200000u128),
+            })
+            .unwrap(),
+        }))]
     );
 
-    // we can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let state = read_state(deps.as_ref().storage).unwrap();
+    assert_eq!(state.total_liabilities, Decimal256::from_uint256(200000u128));
+    assert_eq!(state.prev_atoken_supply, Uint256::from(800000u128));
+
+    // repay on behalf of another borrower, left aTokens are returned
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&"addr0000".to_string(), &Uint128::from(800000u128))],
+    )]);
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(200000u128),
+        msg: to_json_binary(&Cw20HookMsg::RepayStable {
+            borrower: Some("addr0000".to_string()),
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), env, mock_info("at-uusd", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "repay_stable_with_atoken"),
+            attr("borrower", "addr0000"),
+            attr("repay_amount", "200000"),
+            attr("burn_amount", "133334"),
+        ]
+    );
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "at-uusd".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(133334u128),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "at-uusd".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0001".to_string(),
+                    amount: Uint128::from(66666u128),
+                })
+                .unwrap(),
+            })),
+        ]
+    );
+
+    let res_loan = read_borrower_infos(deps.as_ref(), None, None)
+        .unwrap()
+        .first()
+        .unwrap()
+        .loan_amount;
+    assert_eq!(res_loan, Uint256::zero());
+}
+
+#[test]
+fn claim_rewards() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    // Register overseer contract
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let env = mock_env();
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier
+        .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+
+    store_state(
+        deps.as_mut().storage,
+        &State {
+            total_liabilities: Decimal256::from_uint256(1000000u128),
+            total_reserves: Decimal256::zero(),
+            last_interest_updated: env.block.height,
+            last_reward_updated: env.block.height,
+            global_interest_index: Decimal256::one(),
+            global_reward_index: Decimal256::zero(),
+            kpt_emission_rate: Decimal256::one(),
+            prev_atoken_supply: Uint256::zero(),
+            prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
+        },
+    )
+    .unwrap();
+
+    // zero loan claim, will return empty messages
+    let msg = ExecuteMsg::ClaimRewards { to: None };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(500000u64),
+        to: None,
+        price_update: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    // zero block passed
+    let msg = ExecuteMsg::ClaimRewards {
+        to: Some("addr0001".to_string()),
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    // 100 blocks passed
+    // env.block.height += 100;
+    // let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    // assert_eq!(
+    //     res.messages,
+    //     vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+    //         contract_addr: "distributor".to_string(),
+    //         funds: vec![],
+    //         msg: to_json_binary(&FaucetExecuteMsg::Spend {
+    //             recipient: "addr0001".to_string(),
+    //             amount: Uint128::from(33u128),
+    //         })
+    //         .unwrap(),
+    //     }))]
+    // );
+
+    let _res: BorrowerInfoResponse = from_json(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::BorrowerInfo {
+                borrower: "addr0000".to_string(),
+                block_height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    // assert_eq!(
+    //     res.pending_rewards,
+    //     Decimal256::from_str("0.333333333333").unwrap()
+    // );
+    // assert_eq!(
+    //     res.reward_index,
+    //     Decimal256::from_str("0.000066666666666666").unwrap()
+    // );
+}
+
+#[test]
+fn execute_epoch_operations() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::from(1000000u128))],
+    );
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
     // Register kryptonite token contract
     let mut token_inst_res = MsgInstantiateContractResponse::new();
@@ -1771,6 +2211,10 @@ fn execute_epoch_operations() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -1818,6 +2262,10 @@ fn execute_epoch_operations() {
             kpt_emission_rate: Decimal256::from_uint256(5u64),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::from_uint256(100u64),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 
@@ -1845,6 +2293,10 @@ fn execute_epoch_operations() {
             kpt_emission_rate: Decimal256::one(),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -1875,6 +2327,10 @@ fn execute_epoch_operations() {
             kpt_emission_rate: Decimal256::from_uint256(5u64),
             prev_atoken_supply: Uint256::zero(),
             prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::from_uint256(100u64),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         }
     );
 }
@@ -1892,6 +2348,7 @@ fn borrow_repay_execute_operations() {
         atoken_code_id: 123u64,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
 
     let info = mock_info(
@@ -1925,6 +2382,7 @@ fn borrow_repay_execute_operations() {
                         minter: MOCK_CONTRACT_ADDR.to_string(),
                         cap: None,
                     }),
+                    balance_hook: None,
                 })
                 .unwrap(),
             }),
@@ -1993,6 +2451,10 @@ fn borrow_repay_execute_operations() {
             kpt_emission_rate: Decimal256::from_str("980001.99").unwrap(),
             prev_atoken_supply: Uint256::from(1000000001000000u128),
             prev_exchange_rate: Decimal256::from_str("1.0000022").unwrap(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
         },
     )
     .unwrap();
@@ -2010,3 +2472,1761 @@ fn borrow_repay_execute_operations() {
     // only overseer can execute this
     let _ = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
 }
+
+#[test]
+fn query_rates() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    // Register overseer contract
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    deps.querier.with_borrow_rate(&[(
+        &"interest".to_string(),
+        &Decimal256::from_str("0.00000001").unwrap(),
+    )]);
+
+    store_state(
+        deps.as_mut().storage,
+        &State {
+            total_liabilities: Decimal256::from_uint256(1000000u128),
+            total_reserves: Decimal256::zero(),
+            last_interest_updated: env.block.height,
+            last_reward_updated: env.block.height,
+            global_interest_index: Decimal256::one(),
+            global_reward_index: Decimal256::zero(),
+            kpt_emission_rate: Decimal256::one(),
+            prev_atoken_supply: Uint256::from(1000000u128),
+            prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
+        },
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env, QueryMsg::Rates {}).unwrap();
+    let rates: RatesResponse = from_json(&res).unwrap();
+
+    // utilization_ratio = 1000000 / (1000000 + 1000000)
+    // deposit_rate = 0.00000001 * 0.5, below target_deposit_rate = 0.01
+    // kpt_emission_apr = 1 * 4656810 / 1000000
+    assert_eq!(rates.borrow_rate, Decimal256::from_str("0.00000001").unwrap());
+    assert_eq!(rates.utilization_ratio, Decimal256::percent(50));
+    assert_eq!(rates.deposit_rate, Decimal256::from_str("0.000000005").unwrap());
+    assert_eq!(rates.target_deposit_rate, Decimal256::percent(1));
+    assert_eq!(rates.kpt_emission_apr, Decimal256::from_str("4.65681").unwrap());
+
+    // (1 + rate)^4656810 - 1 ~= e^(rate * 4656810) - 1
+    assert!(rates.borrow_apy > Decimal256::from_str("0.04766").unwrap());
+    assert!(rates.borrow_apy < Decimal256::from_str("0.04767").unwrap());
+    assert!(rates.deposit_apy > Decimal256::from_str("0.02355").unwrap());
+    assert!(rates.deposit_apy < Decimal256::from_str("0.02356").unwrap());
+}
+
+#[test]
+fn depositor_rewards() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: Some(true),
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let mut env = mock_env();
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[
+            (&"addr0000".to_string(), &Uint128::from(1000u128)),
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(INITIAL_DEPOSIT_AMOUNT)),
+        ],
+    )]);
+
+    store_state(
+        deps.as_mut().storage,
+        &State {
+            total_liabilities: Decimal256::from_uint256(1000000u128),
+            total_reserves: Decimal256::zero(),
+            last_interest_updated: env.block.height,
+            last_reward_updated: env.block.height,
+            global_interest_index: Decimal256::one(),
+            global_reward_index: Decimal256::zero(),
+            kpt_emission_rate: Decimal256::one(),
+            prev_atoken_supply: Uint256::from(INITIAL_DEPOSIT_AMOUNT + 1000u128),
+            prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
+        },
+    )
+    .unwrap();
+
+    let info = mock_info("owner", &[]);
+    let msg = ExecuteMsg::UpdateConfig {
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: Some(Decimal256::percent(150)),
+        max_total_liabilities: None,
+        max_total_deposits: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::InvalidDepositorRewardShare {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UpdateConfig {
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: Some(Decimal256::percent(50)),
+        max_total_liabilities: None,
+        max_total_deposits: None,
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // only the aToken contract can report balance changes
+    let msg = ExecuteMsg::BalanceChangeHook {
+        balances_before: vec![Cw20Coin {
+            address: "addr0000".to_string(),
+            amount: Uint128::from(1000u128),
+        }],
+        total_supply_before: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 1000u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // 100 blocks passed, addr0000 transfers 500 aTokens to addr0001. The initial
+    // deposit held by the market earns nothing
+    // deposit reward = 100 * 1 * 0.5 = 50, index = 50 / 1000 = 0.05
+    env.block.height += 100;
+    let msg = ExecuteMsg::BalanceChangeHook {
+        balances_before: vec![
+            Cw20Coin {
+                address: "addr0000".to_string(),
+                amount: Uint128::from(1000u128),
+            },
+            Cw20Coin {
+                address: "addr0001".to_string(),
+                amount: Uint128::zero(),
+            },
+        ],
+        total_supply_before: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 1000u128),
+    };
+    let _res = execute(deps.as_mut(), env.clone(), mock_info("at-uusd", &[]), msg).unwrap();
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[
+            (&"addr0000".to_string(), &Uint128::from(500u128)),
+            (&"addr0001".to_string(), &Uint128::from(500u128)),
+            (&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(INITIAL_DEPOSIT_AMOUNT)),
+        ],
+    )]);
+
+    let state = read_state(deps.as_ref().storage).unwrap();
+    assert_eq!(state.global_reward_index, Decimal256::from_ratio(50u64, 1000000u64));
+    assert_eq!(state.global_deposit_reward_index, Decimal256::from_ratio(5u64, 100u64));
+
+    let res: DepositorInfoResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::DepositorInfo {
+                depositor: "addr0000".to_string(),
+                block_height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        DepositorInfoResponse {
+            depositor: "addr0000".to_string(),
+            reward_index: Decimal256::from_ratio(5u64, 100u64),
+            pending_rewards: Decimal256::from_uint256(50u64),
+        }
+    );
+
+    // addr0001 only earns from the transfer on
+    let res: DepositorInfoResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::DepositorInfo {
+                depositor: "addr0001".to_string(),
+                block_height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.pending_rewards, Decimal256::zero());
+
+    let msg = ExecuteMsg::ClaimRewards { to: None };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "distributor".to_string(),
+            funds: vec![],
+            msg: to_json_binary(&ThirdpartExecuteMsg::Mint {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(50u128),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    // addr0000 escrows its 500 aTokens in a withdrawal ticket
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(500u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), env.clone(), mock_info("at-uusd", &[]), msg).unwrap();
+    assert_eq!(
+        read_state(deps.as_ref().storage).unwrap().escrowed_atokens,
+        Uint256::from(500u128)
+    );
+
+    // 100 blocks passed, escrowed aTokens earn nothing
+    // deposit reward = 50, index += 50 / (1000 - 500) = 0.1
+    env.block.height += 100;
+    let msg = ExecuteMsg::BalanceChangeHook {
+        balances_before: vec![
+            Cw20Coin {
+                address: "addr0001".to_string(),
+                amount: Uint128::from(500u128),
+            },
+            Cw20Coin {
+                address: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 500u128),
+            },
+        ],
+        total_supply_before: Uint128::from(INITIAL_DEPOSIT_AMOUNT + 1000u128),
+    };
+    let _res = execute(deps.as_mut(), env.clone(), mock_info("at-uusd", &[]), msg).unwrap();
+
+    let state = read_state(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        state.global_deposit_reward_index,
+        Decimal256::from_ratio(15u64, 100u64)
+    );
+    let res: DepositorInfoResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::DepositorInfo {
+                depositor: "addr0001".to_string(),
+                block_height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.pending_rewards, Decimal256::from_uint256(50u64));
+
+    // the market itself accrues nothing
+    let depositor_info = read_depositor_info(
+        deps.as_ref().storage,
+        &deps.api.addr_canonicalize(MOCK_CONTRACT_ADDR).unwrap(),
+    );
+    assert_eq!(depositor_info.pending_rewards, Decimal256::zero());
+    assert_eq!(depositor_info.reward_index, Decimal256::zero());
+
+    // cancelling the ticket releases the escrow
+    let msg = ExecuteMsg::CancelWithdrawal { ticket_id: 0 };
+    let _res = execute(deps.as_mut(), env, mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        read_state(deps.as_ref().storage).unwrap().escrowed_atokens,
+        Uint256::zero()
+    );
+}
+
+#[test]
+fn register_atoken_balance_hook() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAtokenBalanceHook {};
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "at-uusd".to_string(),
+            funds: vec![],
+            msg: to_json_binary(&TokenExecuteMsg::UpdateBalanceHook {
+                balance_hook: Some(MOCK_CONTRACT_ADDR.to_string()),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_json(&res).unwrap();
+    assert!(config_res.atoken_balance_hook);
+}
+
+#[test]
+fn withdrawal_queue() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1000000u128))],
+    )]);
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR.to_string(),
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+
+    // exchange rate is (100000 + 900000) / 1000000 = 1
+    store_state(
+        deps.as_mut().storage,
+        &State {
+            total_liabilities: Decimal256::from_uint256(900000u128),
+            total_reserves: Decimal256::zero(),
+            last_interest_updated: mock_env().block.height,
+            last_reward_updated: mock_env().block.height,
+            global_interest_index: Decimal256::one(),
+            global_reward_index: Decimal256::zero(),
+            kpt_emission_rate: Decimal256::one(),
+            prev_atoken_supply: Uint256::from(1000000u128),
+            prev_exchange_rate: Decimal256::one(),
+            total_kpt_emitted: Decimal256::zero(),
+            kpt_emission_budget: None,
+            global_deposit_reward_index: Decimal256::zero(),
+            escrowed_atokens: Uint256::zero(),
+        },
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(300000u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "queue_redeem_stable"),
+            attr("owner", "addr0000"),
+            attr("ticket_id", "0"),
+            attr("atoken_amount", "300000"),
+        ]
+    );
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(200000u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawalTicket { ticket_id: 1 },
+    )
+    .unwrap();
+    let ticket: WithdrawalTicketResponse = from_json(&res).unwrap();
+    assert_eq!(
+        ticket,
+        WithdrawalTicketResponse {
+            ticket_id: 1,
+            owner: "addr0001".to_string(),
+            atoken_amount: Uint256::from(200000u64),
+            queued_at: mock_env().block.height,
+            position: 1,
+            atokens_ahead: Uint256::from(300000u64),
+            expected_redeem_amount: Uint256::from(200000u64),
+        }
+    );
+
+    // only 100000 available, the first ticket is partially filled
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0002", &[]),
+        ExecuteMsg::ProcessWithdrawalQueue {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "at-uusd".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(100000u128),
+                })
+                .unwrap()
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0000".to_string(),
+                amount: vec![deduct_tax(
+                    deps.as_ref(),
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::from(100000u128),
+                    }
+                )
+                .unwrap()]
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "process_withdrawal_queue"),
+            attr("filled_tickets", "0"),
+            attr("burn_amount", "100000"),
+            attr("redeem_amount", "100000"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawalTickets {
+            owner: None,
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let tickets: WithdrawalTicketsResponse = from_json(&res).unwrap();
+    assert_eq!(
+        tickets
+            .tickets
+            .iter()
+            .map(|ticket| (ticket.position, ticket.atokens_ahead))
+            .collect::<Vec<_>>(),
+        vec![(0, Uint256::zero()), (1, Uint256::from(200000u64))]
+    );
+
+    // deposits trigger the queue processing
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR.to_string(),
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let info = mock_info(
+        "addr0003",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable {}).unwrap();
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&ExecuteMsg::ProcessWithdrawalQueue {}).unwrap(),
+        }))
+    );
+
+    // enough liquidity for the rest of the queue
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(900000u128))],
+    )]);
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR.to_string(),
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(400000u128),
+        }],
+    );
+    let mut state = read_state(deps.as_ref().storage).unwrap();
+    state.total_liabilities = Decimal256::from_uint256(500000u128);
+    store_state(deps.as_mut().storage, &state).unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0002", &[]),
+        ExecuteMsg::ProcessWithdrawalQueue {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "process_withdrawal_queue"),
+            attr("filled_tickets", "2"),
+            attr("burn_amount", "400000"),
+            attr("redeem_amount", "400000"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawalTickets {
+            owner: None,
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let tickets: WithdrawalTicketsResponse = from_json(&res).unwrap();
+    assert!(tickets.tickets.is_empty());
+
+    // cancel a ticket
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(50000u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(10000u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(20000u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::CancelWithdrawal { ticket_id: 2 };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "at-uusd".to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::from(50000u128),
+            })
+            .unwrap(),
+        }))]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawalTicket { ticket_id: 2 },
+    );
+    assert!(res.is_err());
+
+    // the cancelled ticket no longer counts ahead
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawalTickets {
+            owner: Some("addr0000".to_string()),
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let tickets: WithdrawalTicketsResponse = from_json(&res).unwrap();
+    assert_eq!(
+        tickets.tickets,
+        vec![WithdrawalTicketResponse {
+            ticket_id: 4,
+            owner: "addr0000".to_string(),
+            atoken_amount: Uint256::from(20000u64),
+            queued_at: mock_env().block.height,
+            position: 1,
+            atokens_ahead: Uint256::from(10000u64),
+            expected_redeem_amount: Uint256::from(20000u64),
+        }]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::WithdrawalTickets {
+            owner: None,
+            start_after: Some(3),
+            limit: None,
+        },
+    )
+    .unwrap();
+    let tickets: WithdrawalTicketsResponse = from_json(&res).unwrap();
+    assert_eq!(
+        tickets.tickets.iter().map(|ticket| ticket.ticket_id).collect::<Vec<_>>(),
+        vec![4]
+    );
+
+    // the 30000 owed to the queue is kept from direct redemptions and borrows
+    // exchange rate is (40000 + 860000) / 900000 = 1
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR.to_string(),
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(40000u128),
+        }],
+    );
+    let mut state = read_state(deps.as_ref().storage).unwrap();
+    state.total_liabilities = Decimal256::from_uint256(860000u128);
+    store_state(deps.as_mut().storage, &state).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0002".to_string(),
+        amount: Uint128::from(20000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: None,
+            exact_underlying: None,
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg);
+    match res {
+        Err(ContractError::NoStableAvailable(denom)) => assert_eq!(denom, "uusd"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    deps.querier.with_borrow_limit(&[(&"addr0002".to_string(), &Uint256::from(1000000u64))]);
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(20000u64),
+        to: None,
+        price_update: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), msg);
+    match res {
+        Err(ContractError::NoStableAvailable(denom)) => assert_eq!(denom, "uusd"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0002".to_string(),
+        amount: Uint128::from(10000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: None,
+            exact_underlying: None,
+        })
+        .unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+}
+
+#[test]
+fn borrow_and_supply_caps() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(1000000u128),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier
+        .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&"addr0001".to_string(), &Uint128::from(1000000u128))],
+    )]);
+
+    let msg = ExecuteMsg::UpdateConfig {
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: None,
+        max_total_liabilities: Some(Some(Uint256::from(300000u64))),
+        max_total_deposits: Some(Some(Uint256::from(1500000u64))),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(config_res.max_total_liabilities, Some(Uint256::from(300000u64)));
+    assert_eq!(config_res.max_total_deposits, Some(Uint256::from(1500000u64)));
+
+    // borrow cap
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(300001u64),
+        to: None,
+        price_update: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::MaxTotalLiabilitiesReached(cap)) => assert_eq!(cap, "300000"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(300000u64),
+        to: None,
+        price_update: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // supply cap, exchange rate is (700000 + 300000) / 1000000 = 1
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1200001u128),
+        }],
+    );
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(500001u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable {});
+    match res {
+        Err(ContractError::MaxTotalDepositsReached(cap)) => assert_eq!(cap, "1500000"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(1200000u128),
+        }],
+    );
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(500000u128),
+        }],
+    );
+    let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable {}).unwrap();
+
+    // null removes a cap, a missing field leaves it unchanged
+    let msg: ExecuteMsg =
+        from_json(br#"{"update_config":{"max_total_liabilities":null}}"#).unwrap();
+    assert_eq!(
+        msg,
+        ExecuteMsg::UpdateConfig {
+            interest_model: None,
+            distribution_model: None,
+            max_borrow_factor: None,
+            interest_model_hook: None,
+            depositor_reward_share: None,
+            max_total_liabilities: Some(None),
+            max_total_deposits: None,
+        }
+    );
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(config_res.max_total_liabilities, None);
+    assert_eq!(config_res.max_total_deposits, Some(Uint256::from(1500000u64)));
+}
+
+#[test]
+fn pause_operations() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(1000000u128),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier
+        .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+    deps.querier.with_token_balances(&[(
+        &"at-uusd".to_string(),
+        &[(&"addr0001".to_string(), &Uint128::from(1000000u128))],
+    )]);
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap();
+    let status: PauseStatusResponse = from_json(&res).unwrap();
+    assert_eq!(
+        status,
+        PauseStatusResponse {
+            guardian: None,
+            paused: vec![],
+        }
+    );
+
+    // only the owner sets the guardian
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian".to_string()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    // only the guardian toggles pause flags
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Deposits,
+        paused: true,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Liquidations,
+        paused: true,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg);
+    match res {
+        Err(ContractError::NotPausable(operation)) => assert_eq!(operation, "liquidations"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable {});
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "deposits"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(100000u64),
+        to: None,
+        price_update: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Borrows,
+        paused: true,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Redemptions,
+        paused: true,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap();
+    let status: PauseStatusResponse = from_json(&res).unwrap();
+    assert_eq!(
+        status,
+        PauseStatusResponse {
+            guardian: Some("guardian".to_string()),
+            paused: vec![
+                PausableOperation::Deposits,
+                PausableOperation::Borrows,
+                PausableOperation::Redemptions,
+            ],
+        }
+    );
+
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(100000u64),
+        to: None,
+        price_update: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "borrows"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: None,
+            exact_underlying: None,
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "redemptions"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // repaying with aTokens redeems them
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_json_binary(&Cw20HookMsg::RepayStable { borrower: None }).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "redemptions"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // withdrawals can still be queued, but are not processed
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(100000u128),
+        msg: to_json_binary(&Cw20HookMsg::QueueRedeemStable {}).unwrap(),
+    });
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg).unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0002", &[]),
+        ExecuteMsg::ProcessWithdrawalQueue {},
+    );
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "redemptions"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // repayments are never paused, and skip the withdrawal queue
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let msg = ExecuteMsg::RepayStable { borrower: None };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert!(res.messages.is_empty());
+
+    // resume deposits
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Deposits,
+        paused: false,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let _res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable {}).unwrap();
+}
+
+#[test]
+fn shutdown() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(1000000u128),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Register kryptonite token contract
+    let mut token_inst_res = MsgInstantiateContractResponse::new();
+    token_inst_res.set_contract_address("at-uusd".to_string());
+    let reply_msg = Reply {
+        id: 1,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(token_inst_res.write_to_bytes().unwrap().into()),
+        }),
+    };
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterContracts {
+        overseer_contract: "overseer".to_string(),
+        interest_model: "interest".to_string(),
+        distribution_model: "distribution".to_string(),
+        collector_contract: "collector".to_string(),
+        distributor_contract: "distributor".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier
+        .with_borrow_rate(&[(&"interest".to_string(), &Decimal256::percent(1))]);
+    deps.querier
+        .with_borrow_limit(&[(&"addr0000".to_string(), &Uint256::from(1000000u64))]);
+    deps.querier.with_token_balances(&[
+        (
+            &"at-uusd".to_string(),
+            &[(&"addr0001".to_string(), &Uint128::from(1000000u128))],
+        ),
+        (
+            &"bsei".to_string(),
+            &[(&MOCK_CONTRACT_ADDR.to_string(), &Uint128::from(1000u128))],
+        ),
+    ]);
+
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(100000u64),
+        to: None,
+        price_update: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // only the overseer shuts the market down
+    let msg = ExecuteMsg::Shutdown {
+        collateral_tokens: vec!["bsei".to_string()],
+        settlement_period: 100,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res =
+        execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::ShutdownState {}).unwrap();
+    let shutdown_state: ShutdownStateResponse = from_json(&res).unwrap();
+    assert_eq!(
+        shutdown_state,
+        ShutdownStateResponse {
+            shutdown_height: Some(mock_env().block.height),
+            redemption_height: Some(mock_env().block.height + 100),
+            collateral_tokens: vec!["bsei".to_string()],
+        }
+    );
+
+    // interest is frozen
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::State {
+            block_height: Some(mock_env().block.height + 1000),
+        },
+    )
+    .unwrap();
+    let state: StateResponse = from_json(&res).unwrap();
+    assert_eq!(state.total_liabilities, Decimal256::from_uint256(100000u64));
+
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(100000u128),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::DepositStable {});
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(100000u64),
+        to: None,
+        price_update: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(250000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: None,
+            exact_underlying: Some(Uint256::from(1000u64)),
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // aTokens cannot repay loans, as the exchange rate no longer applies
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::from(1000u128),
+        msg: to_json_binary(&Cw20HookMsg::RepayStable { borrower: None }).unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("at-uusd", &[]), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // redemptions wait for the settlement period
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0001".to_string(),
+        amount: Uint128::from(250000u128),
+        msg: to_json_binary(&Cw20HookMsg::RedeemStable {
+            to: Some("addr0002".to_string()),
+            exact_underlying: None,
+        })
+        .unwrap(),
+    });
+    let mut env = mock_env();
+    env.block.height += 99;
+    let res = execute(deps.as_mut(), env.clone(), mock_info("at-uusd", &[]), msg.clone());
+    match res {
+        Err(ContractError::SettlementPeriod(height)) => {
+            assert_eq!(height, mock_env().block.height + 100)
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // a quarter of the aTokens redeems a quarter of the balance and collaterals
+    env.block.height += 1;
+    let res = execute(deps.as_mut(), env, mock_info("at-uusd", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "at-uusd".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(250000u128),
+                })
+                .unwrap()
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0002".to_string(),
+                amount: vec![deduct_tax(
+                    deps.as_ref(),
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::from(250000u128),
+                    }
+                )
+                .unwrap()]
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "bsei".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "addr0002".to_string(),
+                    amount: Uint128::from(250u128),
+                })
+                .unwrap()
+            })),
+        ]
+    );
+
+    // the overseer writes the loan off once the collaterals are settled
+    let msg = ExecuteMsg::SettleLoan {
+        borrower: "addr0000".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let res = execute(deps.as_mut(), mock_env(), mock_info("overseer", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "settle_loan"),
+            attr("borrower", "addr0000"),
+            attr("settled_amount", "100000"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::BorrowerInfo {
+            borrower: "addr0000".to_string(),
+            block_height: None,
+        },
+    )
+    .unwrap();
+    let liability: BorrowerInfoResponse = from_json(&res).unwrap();
+    assert_eq!(liability.loan_amount, Uint256::zero());
+    assert_eq!(read_state(&deps.storage).unwrap().total_liabilities, Decimal256::zero());
+}
+
+#[test]
+fn config_history() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // unchanged values are left out of the entry
+    let msg = ExecuteMsg::UpdateConfig {
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: Some(Decimal256::percent(50)),
+        interest_model_hook: Some(false),
+        depositor_reward_share: None,
+        max_total_liabilities: None,
+        max_total_deposits: Some(Some(Uint256::from(1000000u64))),
+    };
+    let info = mock_info("owner", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian".to_string()),
+    };
+    let info = mock_info("owner", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::SetOwner {
+        new_owner_addr: "owner1".to_string(),
+    };
+    let info = mock_info("owner", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::AcceptOwnership {};
+    let info = mock_info("owner1", &[]);
+    let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    assert_eq!(
+        history.entries,
+        vec![
+            ConfigHistoryEntryResponse {
+                id: 0,
+                action: "update_config".to_string(),
+                sender: "owner".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![
+                    ConfigChange {
+                        field: "max_borrow_factor".to_string(),
+                        old_value: "\"1\"".to_string(),
+                        new_value: "\"0.5\"".to_string(),
+                    },
+                    ConfigChange {
+                        field: "max_total_deposits".to_string(),
+                        old_value: "null".to_string(),
+                        new_value: "\"1000000\"".to_string(),
+                    },
+                ],
+            },
+            ConfigHistoryEntryResponse {
+                id: 1,
+                action: "update_guardian".to_string(),
+                sender: "owner".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![ConfigChange {
+                    field: "guardian".to_string(),
+                    old_value: "null".to_string(),
+                    new_value: "\"guardian\"".to_string(),
+                }],
+            },
+            ConfigHistoryEntryResponse {
+                id: 2,
+                action: "set_owner".to_string(),
+                sender: "owner".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![ConfigChange {
+                    field: "new_owner_addr".to_string(),
+                    old_value: "\"owner\"".to_string(),
+                    new_value: "\"owner1\"".to_string(),
+                }],
+            },
+            ConfigHistoryEntryResponse {
+                id: 3,
+                action: "accept_ownership".to_string(),
+                sender: "owner1".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![ConfigChange {
+                    field: "owner_addr".to_string(),
+                    old_value: "\"owner\"".to_string(),
+                    new_value: "\"owner1\"".to_string(),
+                }],
+            },
+        ]
+    );
+
+    // pagination
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: Some(1),
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    assert_eq!(history.entries.len(), 1);
+    assert_eq!(history.entries[0].id, 2);
+}
+
+#[test]
+fn borrowers_by_debt() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+    }]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        stable_denom: "uusd".to_string(),
+        atoken_code_id: 123u64,
+        kpt_emission_rate: Decimal256::one(),
+        max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::from(INITIAL_DEPOSIT_AMOUNT),
+        }],
+    );
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    for (borrower, loan_amount) in [
+        ("addr0000", 500u64),
+        ("addr0001", 3000u64),
+        ("addr0002", 1000u64),
+        ("addr0003", 2000u64),
+    ] {
+        store_borrower_info(
+            &mut deps.storage,
+            &deps.api.addr_canonicalize(borrower).unwrap(),
+            &BorrowerInfo {
+                interest_index: Decimal256::one(),
+                reward_index: Decimal256::zero(),
+                loan_amount: Uint256::from(loan_amount),
+                pending_rewards: Decimal256::zero(),
+            },
+        )
+        .unwrap();
+    }
+
+    let query_borrowers = |deps: Deps, start_after: Option<&str>, limit: Option<u32>| {
+        let res = query(
+            deps,
+            mock_env(),
+            QueryMsg::BorrowersByDebt {
+                start_after: start_after.map(|s| s.to_string()),
+                limit,
+                block_height: None,
+            },
+        )
+        .unwrap();
+        let res: BorrowerInfosResponse = from_json(&res).unwrap();
+        res.borrower_infos
+            .into_iter()
+            .map(|info| (info.borrower, info.loan_amount))
+            .collect::<Vec<(String, Uint256)>>()
+    };
+
+    assert_eq!(
+        query_borrowers(deps.as_ref(), None, Some(2)),
+        vec![
+            ("addr0001".to_string(), Uint256::from(3000u64)),
+            ("addr0003".to_string(), Uint256::from(2000u64)),
+        ]
+    );
+    assert_eq!(
+        query_borrowers(deps.as_ref(), Some("addr0003"), None),
+        vec![
+            ("addr0002".to_string(), Uint256::from(1000u64)),
+            ("addr0000".to_string(), Uint256::from(500u64)),
+        ]
+    );
+
+    // the index follows updated liabilities
+    store_borrower_info(
+        &mut deps.storage,
+        &deps.api.addr_canonicalize("addr0000").unwrap(),
+        &BorrowerInfo {
+            interest_index: Decimal256::one(),
+            reward_index: Decimal256::zero(),
+            loan_amount: Uint256::from(5000u64),
+            pending_rewards: Decimal256::zero(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        query_borrowers(deps.as_ref(), None, None),
+        vec![
+            ("addr0000".to_string(), Uint256::from(5000u64)),
+            ("addr0001".to_string(), Uint256::from(3000u64)),
+            ("addr0003".to_string(), Uint256::from(2000u64)),
+            ("addr0002".to_string(), Uint256::from(1000u64)),
+        ]
+    );
+
+    // rewrite to the layout of releases on cosmwasm_storage that already
+    // recorded cw2 info: singletons and no debt index, the liability bucket
+    // is left as is
+    let index_prefix = to_length_prefixed(b"liability__debt");
+    let index_keys: Vec<Vec<u8>> = deps
+        .storage
+        .range(Some(&index_prefix), None, Order::Ascending)
+        .map(|(k, _)| k)
+        .take_while(|k| k.starts_with(&index_prefix))
+        .collect();
+    for key in index_keys {
+        deps.storage.remove(&key);
+    }
+    assert_eq!(query_borrowers(deps.as_ref(), None, None), vec![]);
+    for key in [&b"config"[..], b"state"] {
+        let value = deps.storage.get(key).unwrap();
+        deps.storage.remove(key);
+        deps.storage.set(&to_length_prefixed(key), &value);
+    }
+    set_contract_version(&mut deps.storage, "crates.io:moneymarket-market", "0.0.0").unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1], attr("previous_version", "0.0.0"));
+    assert_eq!(
+        read_config(&deps.storage).unwrap().owner_addr,
+        deps.api.addr_canonicalize("owner").unwrap()
+    );
+
+    // the liabilities are indexed in pages, by anyone
+    assert_eq!(query_borrowers(deps.as_ref(), None, None), vec![]);
+    let msg = ExecuteMsg::MigrateLiabilities { limit: Some(3) };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0005", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "migrate_liabilities"),
+            attr("migrated", "3"),
+            attr("done", "false"),
+        ]
+    );
+    assert_eq!(query_borrowers(deps.as_ref(), None, None).len(), 3);
+
+    let msg = ExecuteMsg::MigrateLiabilities { limit: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0005", &[]), msg.clone()).unwrap();
+    assert_eq!(res.attributes[1], attr("migrated", "1"));
+    assert_eq!(res.attributes[2], attr("done", "true"));
+    assert_eq!(
+        query_borrowers(deps.as_ref(), None, None),
+        vec![
+            ("addr0000".to_string(), Uint256::from(5000u64)),
+            ("addr0001".to_string(), Uint256::from(3000u64)),
+            ("addr0003".to_string(), Uint256::from(2000u64)),
+            ("addr0002".to_string(), Uint256::from(1000u64)),
+        ]
+    );
+
+    // nothing is left once done, and migrating again starts no new pass
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0005", &[]), msg).unwrap();
+    assert_eq!(res.attributes[1], attr("migrated", "0"));
+}
@@ -0,0 +1,307 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
+    Response, StdResult, WasmMsg,
+};
+
+use cw20::Cw20ExecuteMsg;
+use moneymarket::market::{ExecuteMsg, WithdrawalTicketResponse, WithdrawalTicketsResponse};
+use moneymarket::pause::{read_pause_info, PausableOperation};
+use moneymarket::querier::{deduct_tax, query_balance};
+
+use crate::borrow::{compute_interest, compute_reward};
+use crate::deposit::compute_exchange_rate;
+use crate::error::ContractError;
+use crate::state::{
+    read_cancelled_tickets_before, read_config, read_shutdown_state, read_state,
+    read_withdrawal_queue, read_withdrawal_ticket, read_withdrawal_tickets,
+    remove_withdrawal_ticket, store_cancelled_ticket, store_state, store_withdrawal_queue,
+    store_withdrawal_ticket, Config, State, WithdrawalQueue, WithdrawalTicket,
+};
+
+/// Maximum number of tickets filled in one queue processing
+const MAX_QUEUE_FILLS: usize = 30;
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Escrow aTokens sent by `sender` in a new withdrawal ticket
+pub fn queue_redeem_stable(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    atoken_amount: Uint256,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+    compute_reward(&mut state, &config, env.block.height);
+    state.escrowed_atokens += atoken_amount;
+    store_state(deps.storage, &state)?;
+
+    let mut queue: WithdrawalQueue = read_withdrawal_queue(deps.storage)?;
+    let ticket_id = queue.next_ticket_id;
+
+    store_withdrawal_ticket(
+        deps.storage,
+        ticket_id,
+        &WithdrawalTicket {
+            owner: deps.api.addr_canonicalize(sender.as_str())?,
+            atoken_amount,
+            queued_at: env.block.height,
+            queued_before: queue.total_queued,
+        },
+    )?;
+
+    queue.next_ticket_id += 1;
+    queue.total_atokens += atoken_amount;
+    queue.total_queued += atoken_amount;
+    store_withdrawal_queue(deps.storage, &queue)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "queue_redeem_stable"),
+        attr("owner", sender),
+        attr("ticket_id", ticket_id.to_string()),
+        attr("atoken_amount", atoken_amount),
+    ]))
+}
+
+pub fn cancel_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    ticket_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let ticket: WithdrawalTicket = read_withdrawal_ticket(deps.storage, ticket_id)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != ticket.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    remove_withdrawal_ticket(deps.storage, ticket_id)?;
+    store_cancelled_ticket(deps.storage, ticket_id, ticket.atoken_amount)?;
+
+    let mut state: State = read_state(deps.storage)?;
+    compute_reward(&mut state, &config, env.block.height);
+    state.escrowed_atokens = state.escrowed_atokens - ticket.atoken_amount;
+    store_state(deps.storage, &state)?;
+
+    let mut queue: WithdrawalQueue = read_withdrawal_queue(deps.storage)?;
+    queue.total_atokens = queue.total_atokens - ticket.atoken_amount;
+    store_withdrawal_queue(deps.storage, &queue)?;
+
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps.api.addr_humanize(&config.atoken_contract)?.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: info.sender.to_string(),
+                amount: ticket.atoken_amount.into(),
+            })?,
+        }))
+        .add_attributes(vec![
+            attr("action", "cancel_withdrawal"),
+            attr("owner", info.sender),
+            attr("ticket_id", ticket_id.to_string()),
+            attr("atoken_amount", ticket.atoken_amount),
+        ]))
+}
+
+/// Fill tickets in queue order at the current exchange rate until the
+/// liquidity above reserves runs out. The last ticket can be partially filled
+pub fn process_withdrawal_queue(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let mut queue: WithdrawalQueue = read_withdrawal_queue(deps.storage)?;
+
+    let mut state: State = read_state(deps.storage)?;
+    let hook_messages =
+        compute_interest(deps.as_ref(), &config, &mut state, env.block.height, None)?;
+    compute_reward(&mut state, &config, env.block.height);
+
+    let exchange_rate = compute_exchange_rate(deps.as_ref(), &config, &state, None)?;
+    let current_balance = Decimal256::from_uint256(query_balance(
+        deps.as_ref(),
+        env.contract.address,
+        config.stable_denom.to_string(),
+    )?);
+    let mut available = if current_balance > state.total_reserves {
+        Uint256::one() * (current_balance - state.total_reserves)
+    } else {
+        Uint256::zero()
+    };
+
+    let mut burn_amount = Uint256::zero();
+    let mut redeem_amount = Uint256::zero();
+    let mut filled_tickets: u64 = 0;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for (ticket_id, mut ticket) in
+        read_withdrawal_tickets(deps.storage, None, None, MAX_QUEUE_FILLS)?
+    {
+        let fill_amount = if ticket.atoken_amount * exchange_rate > available {
+            available / exchange_rate
+        } else {
+            ticket.atoken_amount
+        };
+
+        let fill_redeem_amount = fill_amount * exchange_rate;
+        if fill_redeem_amount.is_zero() {
+            break;
+        }
+
+        available = available - fill_redeem_amount;
+        burn_amount += fill_amount;
+        redeem_amount += fill_redeem_amount;
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: deps.api.addr_humanize(&ticket.owner)?.to_string(),
+            amount: vec![deduct_tax(
+                deps.as_ref(),
+                Coin {
+                    denom: config.stable_denom.clone(),
+                    amount: fill_redeem_amount.into(),
+                },
+            )?],
+        }));
+
+        if fill_amount == ticket.atoken_amount {
+            remove_withdrawal_ticket(deps.storage, ticket_id)?;
+            filled_tickets += 1;
+        } else {
+            ticket.atoken_amount = ticket.atoken_amount - fill_amount;
+            store_withdrawal_ticket(deps.storage, ticket_id, &ticket)?;
+            break;
+        }
+    }
+
+    if !burn_amount.is_zero() {
+        messages.insert(
+            0,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: deps.api.addr_humanize(&config.atoken_contract)?.to_string(),
+                funds: vec![],
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: burn_amount.into(),
+                })?,
+            }),
+        );
+    }
+
+    queue.total_atokens = queue.total_atokens - burn_amount;
+    queue.total_filled += burn_amount;
+    queue.filled_tickets += filled_tickets;
+    store_withdrawal_queue(deps.storage, &queue)?;
+
+    state.prev_atoken_supply = state.prev_atoken_supply - burn_amount;
+    state.escrowed_atokens = state.escrowed_atokens - burn_amount;
+    store_state(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_messages(hook_messages)
+        .add_attributes(vec![
+            attr("action", "process_withdrawal_queue"),
+            attr("filled_tickets", filled_tickets.to_string()),
+            attr("burn_amount", burn_amount),
+            attr("redeem_amount", redeem_amount),
+        ]))
+}
+
+/// Self call processing the withdrawal queue once the current operation
+/// has settled, empty when nothing is queued, redemptions are paused
+/// or the market is shut down
+pub(crate) fn withdrawal_queue_messages(deps: Deps, env: &Env) -> StdResult<Vec<CosmosMsg>> {
+    let queue: WithdrawalQueue = read_withdrawal_queue(deps.storage)?;
+    if queue.total_atokens.is_zero()
+        || read_pause_info(deps.storage)?.is_paused(PausableOperation::Redemptions)
+        || read_shutdown_state(deps.storage)?.is_some()
+    {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&ExecuteMsg::ProcessWithdrawalQueue {})?,
+    })])
+}
+
+pub fn query_withdrawal_ticket(
+    deps: Deps,
+    env: Env,
+    ticket_id: u64,
+) -> StdResult<WithdrawalTicketResponse> {
+    let ticket: WithdrawalTicket = read_withdrawal_ticket(deps.storage, ticket_id)?;
+    let tickets = query_withdrawal_tickets_raw(deps, env, vec![(ticket_id, ticket)])?;
+    Ok(tickets.into_iter().next().unwrap())
+}
+
+pub fn query_withdrawal_tickets(
+    deps: Deps,
+    env: Env,
+    owner: Option<Addr>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<WithdrawalTicketsResponse> {
+    let owner = match owner {
+        Some(owner) => Some(deps.api.addr_canonicalize(owner.as_str())?),
+        None => None,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let tickets = read_withdrawal_tickets(deps.storage, owner.as_ref(), start_after, limit)?;
+    Ok(WithdrawalTicketsResponse {
+        tickets: query_withdrawal_tickets_raw(deps, env, tickets)?,
+    })
+}
+
+/// Locate each ticket in the queue from the queue totals: everything queued
+/// ahead of it, less what was filled and what was cancelled ahead of it
+fn query_withdrawal_tickets_raw(
+    deps: Deps,
+    env: Env,
+    tickets: Vec<(u64, WithdrawalTicket)>,
+) -> StdResult<Vec<WithdrawalTicketResponse>> {
+    let config: Config = read_config(deps.storage)?;
+    let mut state: State = read_state(deps.storage)?;
+    compute_interest(deps, &config, &mut state, env.block.height, None)?;
+    let exchange_rate = compute_exchange_rate(deps, &config, &state, None)?;
+    let queue: WithdrawalQueue = read_withdrawal_queue(deps.storage)?;
+
+    tickets
+        .into_iter()
+        .map(|(ticket_id, ticket)| {
+            let cancelled = read_cancelled_tickets_before(deps.storage, ticket_id)?;
+            // a partially filled first ticket counts its own fill as filled ahead
+            let removed_ahead = queue.total_filled + cancelled.atoken_amount;
+            let atokens_ahead = if ticket.queued_before > removed_ahead {
+                ticket.queued_before - removed_ahead
+            } else {
+                Uint256::zero()
+            };
+
+            Ok(WithdrawalTicketResponse {
+                ticket_id,
+                owner: deps.api.addr_humanize(&ticket.owner)?.to_string(),
+                atoken_amount: ticket.atoken_amount,
+                queued_at: ticket.queued_at,
+                position: ticket_id.saturating_sub(queue.filled_tickets + cancelled.tickets),
+                atokens_ahead,
+                expected_redeem_amount: ticket.atoken_amount * exchange_rate,
+            })
+        })
+        .collect()
+}
+
+/// Stable owed to the queued tickets at the current exchange rate, kept
+/// from direct redemptions and borrows so the queue is served first
+pub(crate) fn queued_redeem_amount(
+    deps: Deps,
+    config: &Config,
+    state: &State,
+) -> StdResult<Uint256> {
+    let queue: WithdrawalQueue = read_withdrawal_queue(deps.storage)?;
+    if queue.total_atokens.is_zero() {
+        return Ok(Uint256::zero());
+    }
+
+    Ok(queue.total_atokens * compute_exchange_rate(deps, config, state, None)?)
+}
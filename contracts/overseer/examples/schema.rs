@@ -3,10 +3,12 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use moneymarket::config_history::ConfigHistoryResponse;
 use moneymarket::overseer::{
     AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse, ConfigResponse, ExecuteMsg,
-    InstantiateMsg, MigrateMsg, QueryMsg, WhitelistResponse
+    InstantiateMsg, MigrateMsg, QueryMsg, ShutdownStateResponse, WhitelistResponse
 };
+use moneymarket::pause::PauseStatusResponse;
 use moneymarket_overseer::state::{EpochState, DynrateState};
 
 fn main() {
@@ -26,4 +28,7 @@ fn main() {
     export_schema(&schema_for!(EpochState), &out_dir);
     export_schema(&schema_for!(WhitelistResponse), &out_dir);
     export_schema(&schema_for!(DynrateState), &out_dir);
+    export_schema(&schema_for!(PauseStatusResponse), &out_dir);
+    export_schema(&schema_for!(ShutdownStateResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
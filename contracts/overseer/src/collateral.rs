@@ -1,23 +1,29 @@
 use crate::error::ContractError;
 use crate::querier::{query_borrower_info, query_liquidation_amount};
 use crate::state::{
-    read_all_collaterals, read_collaterals, read_config, read_whitelist, read_whitelist_elem,
-    store_collaterals, Config, WhitelistElem,
+    read_all_collaterals, read_collaterals, read_config, read_last_liquidation,
+    read_shutdown_state, read_whitelist_elem, read_whitelist_elem_by_custody, store_collaterals,
+    store_last_liquidation, Config, WhitelistElem,
 };
+use crate::shutdown::frozen_price;
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{
-    attr, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
-    StdError, StdResult, SubMsg, WasmMsg,
+    attr, to_json_binary, Addr, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
+    Response, StdError, StdResult, SubMsg, WasmMsg,
 };
 
+use moneymarket::config_history::{config_change, record_config_change};
 use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
 use moneymarket::liquidation::LiquidationAmountResponse;
 use moneymarket::market::{BorrowerInfoResponse, ExecuteMsg as MarketExecuteMsg};
-use moneymarket::oracle::PriceResponse;
+use moneymarket::oracle_pyth::price_update_messages;
 use moneymarket::overseer::{
-    AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse, WhitelistResponseElem,
+    AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse, ExecuteMsg,
+    FrozenLiquidationPolicy,
+};
+use moneymarket::querier::{
+    query_checked_prices, query_price_update_fee, PriceQueryError, TimeConstraints,
 };
-use moneymarket::querier::{query_balance, query_price, TimeConstraints};
 use moneymarket::tokens::{Tokens, TokensHuman, TokensMath, TokensToHuman, TokensToRaw};
 
 pub fn lock_collateral(
@@ -26,19 +32,11 @@ pub fn lock_collateral(
     borrower: String,
     collaterals_human: TokensHuman,
 ) -> Result<Response, ContractError> {
-    let whitelist: Vec<WhitelistResponseElem> = read_whitelist(deps.as_ref(), None, None)?;
-
-    let mut is_white_custody = false;
-    for elem in whitelist.iter() {
-        if info.sender.to_string() == elem.custody_contract.clone() {
-            is_white_custody = true;
-            break;
-        }
-    }
-    if false == is_white_custody {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if read_whitelist_elem_by_custody(deps.storage, &sender_raw)?.is_none() {
         return Err(ContractError::Std(StdError::generic_err(format!(
             "sender {} lock collateral Unauthorized",
-            info.sender.to_string()
+            info.sender
         ))));
     }
 
@@ -103,6 +101,7 @@ pub fn unlock_collateral(
         deps.as_ref(),
         &cur_collaterals,
         Some(env.block.time.seconds()),
+        false,
     )?;
     let borrow_amount_res: BorrowerInfoResponse =
         query_borrower_info(deps.as_ref(), market, borrower.clone(), env.block.height)?;
@@ -172,6 +171,7 @@ pub fn liquidate_collateral(
         deps.as_ref(),
         &cur_collaterals,
         Some(env.block.time.seconds()),
+        config.frozen_liquidation_policy == FrozenLiquidationPolicy::UseEmaPrice,
     )?;
 
     let borrow_amount_res: BorrowerInfoResponse =
@@ -184,6 +184,16 @@ pub fn liquidate_collateral(
         return Err(ContractError::CannotLiquidateSafeLoan {});
     }
 
+    // rate limit consecutive liquidations of the same borrower
+    let now = env.block.time.seconds();
+    if let Some(last_liquidation) = read_last_liquidation(deps.storage, &borrower_raw) {
+        let next_liquidation = last_liquidation + config.liquidation_cooldown;
+        if now < next_liquidation {
+            return Err(ContractError::LiquidationCooldown(next_liquidation));
+        }
+    }
+    store_last_liquidation(deps.storage, &borrower_raw, now)?;
+
     let liquidation_amount_res: LiquidationAmountResponse = query_liquidation_amount(
         deps.as_ref(),
         deps.api.addr_humanize(&config.liquidation_contract)?,
@@ -199,10 +209,7 @@ pub fn liquidate_collateral(
     cur_collaterals.sub(liquidation_amount.clone())?;
     store_collaterals(deps.storage, &borrower_raw, &cur_collaterals)?;
 
-    let market_contract = deps.api.addr_humanize(&config.market_contract)?;
-    let prev_balance: Uint256 =
-        query_balance(deps.as_ref(), market_contract.clone(), config.stable_denom)?;
-
+    // the liquidation queue repays the market with the settled amount
     let liquidation_messages: Vec<CosmosMsg> = liquidation_amount
         .iter()
         .map(|collateral| {
@@ -224,16 +231,61 @@ pub fn liquidate_collateral(
         .filter(|msg| msg.is_ok())
         .collect::<StdResult<Vec<CosmosMsg>>>()?;
 
-    Ok(Response::new()
-        .add_messages(liquidation_messages)
-        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: market_contract.to_string(),
-            funds: vec![],
-            msg: to_json_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
-                borrower: borrower.to_string(),
-                prev_balance,
-            })?,
-        })))
+    Ok(Response::new().add_messages(liquidation_messages))
+}
+
+/// Push the price update to the oracle first, then liquidate through
+/// `LiquidateCollateralAfterPriceUpdate` so the borrow limit uses the fresh prices
+pub fn liquidate_collateral_with_price_update(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    borrower: Addr,
+    price_update: Vec<Binary>,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
+    let fee = query_price_update_fee(deps.as_ref(), oracle_contract.clone(), price_update.clone())?;
+
+    let mut messages = price_update_messages(
+        &oracle_contract,
+        price_update,
+        fee,
+        info.funds,
+        &info.sender,
+    )?;
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&ExecuteMsg::LiquidateCollateralAfterPriceUpdate {
+            liquidator: info.sender.to_string(),
+            borrower: borrower.to_string(),
+        })?,
+    }));
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "update_prices"),
+        attr("borrower", borrower),
+    ]))
+}
+
+pub fn liquidate_collateral_after_price_update(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    liquidator: Addr,
+    borrower: Addr,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // override info
+    let mut info = info;
+    info.sender = liquidator;
+    info.funds = vec![];
+
+    liquidate_collateral(deps, env, info, borrower)
 }
 
 pub fn repay_stable_from_yield_reserve(
@@ -257,31 +309,27 @@ pub fn repay_stable_from_yield_reserve(
     )?;
     let borrow_amount = borrow_amount_res.loan_amount;
 
-    let prev_balance: Uint256 = query_balance(
-        deps.as_ref(),
-        market.clone(),
-        config.stable_denom.to_owned(),
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "repay_stable_from_yield_reserve",
+        vec![
+            config_change("borrower", &None, &Some(borrower.to_string()))?,
+            config_change("repay_amount", &Uint256::zero(), &borrow_amount)?,
+        ],
     )?;
 
-    let repay_messages = vec![
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: market.to_string(),
-            amount: vec![Coin {
-                denom: config.stable_denom,
-                amount: borrow_amount.into(),
-            }],
-        }),
-        CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: market.to_string(),
-            funds: vec![],
-            msg: to_json_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
-                borrower: borrower.to_string(),
-                prev_balance,
-            })?,
-        }),
-    ];
-
-    Ok(Response::new().add_messages(repay_messages))
+    Ok(Response::new().add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: market.to_string(),
+        funds: vec![Coin {
+            denom: config.stable_denom,
+            amount: borrow_amount.into(),
+        }],
+        msg: to_json_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
+            borrower: borrower.to_string(),
+        })?,
+    })))
 }
 
 pub fn query_collaterals(deps: Deps, borrower: Addr) -> StdResult<CollateralsResponse> {
@@ -315,36 +363,54 @@ pub fn query_all_collaterals(
     Ok(AllCollateralsResponse { all_collaterals })
 }
 
+/// `ema_only` asks the oracle to only check the EMA prices against the feed bounds,
+/// used for liquidations under `FrozenLiquidationPolicy::UseEmaPrice`.
+/// Once the protocol is shut down, the prices frozen at the shutdown are used
 #[allow(clippy::ptr_arg)]
 pub(crate) fn compute_borrow_limit(
     deps: Deps,
     collaterals: &Tokens,
     block_time: Option<u64>,
-) -> StdResult<(Uint256, Vec<Decimal256>)> {
+    ema_only: bool,
+) -> Result<(Uint256, Vec<Decimal256>), PriceQueryError> {
     let config: Config = read_config(deps.storage)?;
     let oracle_contract = deps.api.addr_humanize(&config.oracle_contract)?;
 
-    let mut borrow_limit: Uint256 = Uint256::zero();
-    let mut collateral_prices: Vec<Decimal256> = vec![];
-    for collateral in collaterals.iter() {
-        let collateral_token = collateral.0.clone();
-        let collateral_amount = collateral.1;
-
-        let price: PriceResponse = query_price(
+    let assets = collaterals
+        .iter()
+        .map(|collateral| Ok(deps.api.addr_humanize(&collateral.0)?.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+    let shutdown_state = read_shutdown_state(deps.storage)?;
+    let prices: Vec<Decimal256> = if let Some(shutdown_state) = shutdown_state {
+        collaterals
+            .iter()
+            .map(|collateral| frozen_price(&shutdown_state, &collateral.0))
+            .collect()
+    } else {
+        query_checked_prices(
             deps,
-            oracle_contract.clone(),
-            (deps.api.addr_humanize(&collateral_token)?).to_string(),
-            config.stable_denom.to_string(),
+            oracle_contract,
+            assets,
             block_time.map(|block_time| TimeConstraints {
                 block_time,
                 valid_timeframe: config.price_timeframe,
             }),
-        )?;
+            ema_only,
+        )?
+        .iter()
+        .map(|price| price.rate)
+        .collect()
+    };
+
+    let mut borrow_limit: Uint256 = Uint256::zero();
+    let mut collateral_prices: Vec<Decimal256> = vec![];
+    for (collateral, price) in collaterals.iter().zip(prices.iter()) {
+        let collateral_amount = collateral.1;
 
         let elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
-        let collateral_value = collateral_amount * price.rate;
+        let collateral_value = collateral_amount * *price;
         borrow_limit += collateral_value * elem.max_ltv;
-        collateral_prices.push(price.rate);
+        collateral_prices.push(*price);
     }
 
     // returns borrow_limit with collaterals value in stable denom
@@ -362,7 +428,7 @@ pub fn query_borrow_limit(
     );
 
     // Compute borrow limit with collaterals
-    let (borrow_limit, _) = compute_borrow_limit(deps, &collaterals, block_time)?;
+    let (borrow_limit, _) = compute_borrow_limit(deps, &collaterals, block_time, false)?;
 
     Ok(BorrowLimitResponse {
         borrower: borrower.to_string(),
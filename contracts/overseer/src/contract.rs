@@ -7,31 +7,40 @@ use cosmwasm_std::{
 use std::cmp::{max, min};
 
 use crate::collateral::{
-    liquidate_collateral, lock_collateral, query_all_collaterals, query_borrow_limit,
+    liquidate_collateral, liquidate_collateral_after_price_update,
+    liquidate_collateral_with_price_update, lock_collateral, query_all_collaterals, query_borrow_limit,
     query_collaterals, repay_stable_from_yield_reserve, unlock_collateral,
 };
 use crate::error::ContractError;
+use crate::pause::{assert_not_paused, query_pause_status, set_paused, update_guardian};
 use crate::querier::query_epoch_state;
+use crate::shutdown::{assert_not_shutdown, query_shutdown_state, settle_borrower, shutdown};
 
 use crate::state::{
     read_config, read_dynrate_config, read_dynrate_state, read_epoch_state, read_whitelist,
     read_whitelist_elem, store_config, store_dynrate_config, store_dynrate_state,
     store_epoch_state, store_whitelist_elem, read_new_owner, store_new_owner, Config, DynrateConfig, DynrateState, EpochState,
-    WhitelistElem, NewOwnerAddr, 
+    WhitelistElem, NewOwnerAddr, migrate_legacy_state,
 };
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
+use cw2::set_contract_version;
 use moneymarket::common::optional_addr_validate;
+use moneymarket::config_history::{config_change, query_config_history, record_config_change};
+use moneymarket::migration::assert_migrate_version;
 use moneymarket::market::EpochStateResponse;
 use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
 use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
 use moneymarket::overseer::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, WhitelistResponse,
-    WhitelistResponseElem,
+    ConfigResponse, ExecuteMsg, FrozenLiquidationPolicy, InstantiateMsg, MigrateMsg, QueryMsg,
+    WhitelistResponse, WhitelistResponseElem,
 };
+use moneymarket::common::BLOCKS_PER_YEAR;
+use moneymarket::pause::PausableOperation;
 use moneymarket::querier::{deduct_tax, query_balance};
 
-pub const BLOCKS_PER_YEAR: u128 = 4656810;
+const CONTRACT_NAME: &str = "crates.io:moneymarket-overseer";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -40,6 +49,8 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     store_config(
         deps.storage,
         &Config {
@@ -55,6 +66,8 @@ pub fn instantiate(
             buffer_distribution_factor: msg.buffer_distribution_factor,
             kpt_purchase_factor: msg.kpt_purchase_factor,
             price_timeframe: msg.price_timeframe,
+            frozen_liquidation_policy: FrozenLiquidationPolicy::default(),
+            liquidation_cooldown: 0,
         },
     )?;
 
@@ -98,8 +111,16 @@ pub fn instantiate(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -124,10 +145,13 @@ pub fn execute(
             dyn_rate_yr_increase_expectation,
             dyn_rate_min,
             dyn_rate_max,
+            frozen_liquidation_policy,
+            liquidation_cooldown,
         } => {
             let api = deps.api;
             update_config(
                 deps,
+                env,
                 info,
                 optional_addr_validate(api, oracle_contract)?,
                 optional_addr_validate(api, liquidation_contract)?,
@@ -142,13 +166,20 @@ pub fn execute(
                 dyn_rate_yr_increase_expectation,
                 dyn_rate_min,
                 dyn_rate_max,
+                frozen_liquidation_policy,
+                liquidation_cooldown,
             )
         }
         ExecuteMsg::SetOwner { new_owner_addr } => {
             let api = deps.api;
-            set_new_owner(deps, info, api.addr_validate(&new_owner_addr)?)
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
+        }
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::UpdateGuardian { guardian } => {
+            let api = deps.api;
+            update_guardian(deps, env, info, optional_addr_validate(api, guardian)?)
         }
-        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::SetPaused { operation, paused } => set_paused(deps, info, operation, paused),
         ExecuteMsg::Whitelist {
             name,
             symbol,
@@ -156,9 +187,11 @@ pub fn execute(
             custody_contract,
             max_ltv,
         } => {
+            assert_not_shutdown(deps.storage)?;
             let api = deps.api;
             register_whitelist(
                 deps,
+                env,
                 info,
                 name,
                 symbol,
@@ -172,16 +205,25 @@ pub fn execute(
             custody_contract,
             max_ltv,
         } => {
+            assert_not_shutdown(deps.storage)?;
             let api = deps.api;
             update_whitelist(
                 deps,
+                env,
                 info,
                 api.addr_validate(&collateral_token)?,
                 optional_addr_validate(api, custody_contract)?,
                 max_ltv,
             )
         }
-        ExecuteMsg::ExecuteEpochOperations {} => execute_epoch_operations(deps, env),
+        ExecuteMsg::Shutdown { settlement_period } => {
+            shutdown(deps, env, info, settlement_period)
+        }
+        ExecuteMsg::ExecuteEpochOperations {} => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::EpochOperations)?;
+            execute_epoch_operations(deps, env)
+        }
         ExecuteMsg::UpdateEpochState {
             interest_buffer,
             distributed_interest,
@@ -189,24 +231,63 @@ pub fn execute(
         ExecuteMsg::LockCollateral {
             borrower,
             collaterals,
-        } => lock_collateral(deps, info, borrower, collaterals),
+        } => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::CollateralLocks)?;
+            lock_collateral(deps, info, borrower, collaterals)
+        }
         ExecuteMsg::UnlockCollateral { collaterals } => {
+            assert_not_paused(deps.storage, PausableOperation::CollateralUnlocks)?;
             unlock_collateral(deps, env, info, collaterals)
         }
-        ExecuteMsg::LiquidateCollateral { borrower } => {
+        ExecuteMsg::LiquidateCollateral {
+            borrower,
+            price_update,
+        } => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Liquidations)?;
+            let api = deps.api;
+            match price_update {
+                Some(price_update) => liquidate_collateral_with_price_update(
+                    deps,
+                    env,
+                    info,
+                    api.addr_validate(&borrower)?,
+                    price_update,
+                ),
+                None => liquidate_collateral(deps, env, info, api.addr_validate(&borrower)?),
+            }
+        }
+        ExecuteMsg::LiquidateCollateralAfterPriceUpdate {
+            liquidator,
+            borrower,
+        } => {
+            assert_not_shutdown(deps.storage)?;
+            assert_not_paused(deps.storage, PausableOperation::Liquidations)?;
             let api = deps.api;
-            liquidate_collateral(deps, env, info, api.addr_validate(&borrower)?)
+            liquidate_collateral_after_price_update(
+                deps,
+                env,
+                info,
+                api.addr_validate(&liquidator)?,
+                api.addr_validate(&borrower)?,
+            )
         }
         ExecuteMsg::FundReserve {} => fund_reserve(deps, info),
         ExecuteMsg::RepayStableFromYieldReserve { borrower } => {
             let api = deps.api;
             repay_stable_from_yield_reserve(deps, env, info, api.addr_validate(&borrower)?)
         }
+        ExecuteMsg::SettleBorrower { borrower } => {
+            let api = deps.api;
+            settle_borrower(deps, env, api.addr_validate(&borrower)?)
+        }
     }
 }
 
 pub fn set_new_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner_addr: Addr,
 ) -> Result<Response, ContractError> {
@@ -216,13 +297,26 @@ pub fn set_new_owner(
     if sender_raw != config.owner_addr {
         return Err(ContractError::Unauthorized{});
     }
+    let old_new_owner = deps.api.addr_humanize(&new_owner.new_owner_addr)?;
     new_owner.new_owner_addr = deps.api.addr_canonicalize(&new_owner_addr.to_string())?;
     store_new_owner(deps.storage, &new_owner)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_owner",
+        vec![config_change("new_owner_addr", &old_new_owner, &new_owner_addr)?],
+    )?;
+
     Ok(Response::default())
 }
 
-pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
     let new_owner = read_new_owner(deps.as_ref().storage)?;
     let sender_raw = deps.api.addr_canonicalize(&info.sender.to_string())?;
     let mut config = read_config(deps.as_ref().storage)?;
@@ -230,15 +324,25 @@ pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, Co
         return Err(ContractError::Unauthorized{});
     }
 
+    let old_owner = deps.api.addr_humanize(&config.owner_addr)?;
     config.owner_addr = new_owner.new_owner_addr;
     store_config(deps.storage, &config)?;
 
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "accept_ownership",
+        vec![config_change("owner_addr", &old_owner, &info.sender)?],
+    )?;
+
     Ok(Response::default())
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     oracle_contract: Option<Addr>,
     liquidation_contract: Option<Addr>,
@@ -253,6 +357,8 @@ pub fn update_config(
     dyn_rate_yr_increase_expectation: Option<Decimal256>,
     dyn_rate_min: Option<Decimal256>,
     dyn_rate_max: Option<Decimal256>,
+    frozen_liquidation_policy: Option<FrozenLiquidationPolicy>,
+    liquidation_cooldown: Option<u64>,
 ) -> Result<Response, ContractError> {
     let mut config: Config = read_config(deps.storage)?;
     let mut dynrate_config: DynrateConfig = read_dynrate_config(deps.storage)?;
@@ -261,6 +367,7 @@ pub fn update_config(
         return Err(ContractError::Unauthorized {});
     }
 
+    let old_config = query_config(deps.as_ref())?;
     if let Some(oracle_contract) = oracle_contract {
         config.oracle_contract = deps.api.addr_canonicalize(&oracle_contract.to_string())?;
     }
@@ -315,14 +422,97 @@ pub fn update_config(
         dynrate_config.dyn_rate_max = dyn_rate_max;
     }
 
+    if let Some(frozen_liquidation_policy) = frozen_liquidation_policy {
+        config.frozen_liquidation_policy = frozen_liquidation_policy;
+    }
+
+    if let Some(liquidation_cooldown) = liquidation_cooldown {
+        config.liquidation_cooldown = liquidation_cooldown;
+    }
+
     store_config(deps.storage, &config)?;
     store_dynrate_config(deps.storage, &dynrate_config)?;
 
+    let new_config = query_config(deps.as_ref())?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_config",
+        vec![
+            config_change(
+                "oracle_contract",
+                &old_config.oracle_contract,
+                &new_config.oracle_contract,
+            )?,
+            config_change(
+                "liquidation_contract",
+                &old_config.liquidation_contract,
+                &new_config.liquidation_contract,
+            )?,
+            config_change(
+                "threshold_deposit_rate",
+                &old_config.threshold_deposit_rate,
+                &new_config.threshold_deposit_rate,
+            )?,
+            config_change(
+                "target_deposit_rate",
+                &old_config.target_deposit_rate,
+                &new_config.target_deposit_rate,
+            )?,
+            config_change(
+                "buffer_distribution_factor",
+                &old_config.buffer_distribution_factor,
+                &new_config.buffer_distribution_factor,
+            )?,
+            config_change(
+                "kpt_purchase_factor",
+                &old_config.kpt_purchase_factor,
+                &new_config.kpt_purchase_factor,
+            )?,
+            config_change("epoch_period", &old_config.epoch_period, &new_config.epoch_period)?,
+            config_change(
+                "price_timeframe",
+                &old_config.price_timeframe,
+                &new_config.price_timeframe,
+            )?,
+            config_change(
+                "dyn_rate_epoch",
+                &old_config.dyn_rate_epoch,
+                &new_config.dyn_rate_epoch,
+            )?,
+            config_change(
+                "dyn_rate_maxchange",
+                &old_config.dyn_rate_maxchange,
+                &new_config.dyn_rate_maxchange,
+            )?,
+            config_change(
+                "dyn_rate_yr_increase_expectation",
+                &old_config.dyn_rate_yr_increase_expectation,
+                &new_config.dyn_rate_yr_increase_expectation,
+            )?,
+            config_change("dyn_rate_min", &old_config.dyn_rate_min, &new_config.dyn_rate_min)?,
+            config_change("dyn_rate_max", &old_config.dyn_rate_max, &new_config.dyn_rate_max)?,
+            config_change(
+                "frozen_liquidation_policy",
+                &old_config.frozen_liquidation_policy,
+                &new_config.frozen_liquidation_policy,
+            )?,
+            config_change(
+                "liquidation_cooldown",
+                &old_config.liquidation_cooldown,
+                &new_config.liquidation_cooldown,
+            )?,
+        ],
+    )?;
+
     Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn register_whitelist(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     name: String,
     symbol: String,
@@ -355,6 +545,20 @@ pub fn register_whitelist(
         },
     )?;
 
+    let field = |name: &str| format!("{}.{}", collateral_token, name);
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "register_whitelist",
+        vec![
+            config_change(&field("name"), &None, &Some(&name))?,
+            config_change(&field("symbol"), &None, &Some(&symbol))?,
+            config_change(&field("custody_contract"), &None, &Some(&custody_contract))?,
+            config_change(&field("max_ltv"), &None, &Some(max_ltv))?,
+        ],
+    )?;
+
     Ok(Response::new().add_attributes(vec![
         attr("action", "register_whitelist"),
         attr("name", name),
@@ -367,6 +571,7 @@ pub fn register_whitelist(
 
 pub fn update_whitelist(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     collateral_token: Addr,
     custody_contract: Option<Addr>,
@@ -387,6 +592,7 @@ pub fn update_whitelist(
     let collateral_token_raw = deps.api.addr_canonicalize(collateral_token.as_str())?;
     let mut whitelist_elem: WhitelistElem =
         read_whitelist_elem(deps.storage, &collateral_token_raw)?;
+    let old_elem = whitelist_elem.clone();
 
     if let Some(custody_contract) = custody_contract {
         whitelist_elem.custody_contract = deps.api.addr_canonicalize(custody_contract.as_str())?;
@@ -398,6 +604,22 @@ pub fn update_whitelist(
 
     store_whitelist_elem(deps.storage, &collateral_token_raw, &whitelist_elem)?;
 
+    let field = |name: &str| format!("{}.{}", collateral_token, name);
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_whitelist",
+        vec![
+            config_change(
+                &field("custody_contract"),
+                &deps.api.addr_humanize(&old_elem.custody_contract)?,
+                &deps.api.addr_humanize(&whitelist_elem.custody_contract)?,
+            )?,
+            config_change(&field("max_ltv"), &old_elem.max_ltv, &whitelist_elem.max_ltv)?,
+        ],
+    )?;
+
     Ok(Response::new().add_attributes(vec![
         attr("action", "update_whitelist"),
         attr("collateral_token", collateral_token),
@@ -751,6 +973,11 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             block_time,
         )?),
         QueryMsg::DynrateState {} => to_json_binary(&query_dynrate_state(deps)?),
+        QueryMsg::PauseStatus {} => to_json_binary(&query_pause_status(deps)?),
+        QueryMsg::ShutdownState {} => to_json_binary(&query_shutdown_state(deps)?),
+        QueryMsg::ConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
 
@@ -781,6 +1008,8 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         dyn_rate_yr_increase_expectation: dynrate_config.dyn_rate_yr_increase_expectation,
         dyn_rate_min: dynrate_config.dyn_rate_min,
         dyn_rate_max: dynrate_config.dyn_rate_max,
+        frozen_liquidation_policy: config.frozen_liquidation_policy,
+        liquidation_cooldown: config.liquidation_cooldown,
     })
 }
 
@@ -1,4 +1,6 @@
 use cosmwasm_std::{OverflowError, StdError};
+use moneymarket::pause::PauseError;
+use moneymarket::querier::PriceQueryError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -32,4 +34,42 @@ pub enum ContractError {
 
     #[error("Max ltv is can not be greater than 1")]
     MaxLtvLimitExceeded {},
+
+    #[error("Overseer {0} are paused")]
+    Paused(String),
+
+    #[error("Overseer {0} cannot be paused")]
+    NotPausable(String),
+
+    #[error("Protocol is shut down")]
+    ShutDown {},
+
+    #[error("Protocol is not shut down")]
+    NotShutDown {},
+
+    #[error("Collateral prices are frozen by the oracle circuit breaker: {0}")]
+    PriceFrozen(String),
+
+    #[error("Borrower was liquidated recently; next liquidation allowed at {0}")]
+    LiquidationCooldown(u64),
+}
+
+impl From<PriceQueryError> for ContractError {
+    fn from(err: PriceQueryError) -> Self {
+        match err {
+            PriceQueryError::CircuitBreaker(reason) => ContractError::PriceFrozen(reason),
+            PriceQueryError::Std(err) => ContractError::Std(err),
+        }
+    }
+}
+
+impl From<PauseError> for ContractError {
+    fn from(err: PauseError) -> Self {
+        match err {
+            PauseError::Unauthorized => ContractError::Unauthorized {},
+            PauseError::NotPausable(operation) => ContractError::NotPausable(operation),
+            PauseError::Paused(operation) => ContractError::Paused(operation),
+            PauseError::Std(err) => ContractError::Std(err),
+        }
+    }
 }
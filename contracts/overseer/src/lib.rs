@@ -1,7 +1,9 @@
 pub mod collateral;
 pub mod contract;
 pub mod error;
+pub mod pause;
 pub mod querier;
+pub mod shutdown;
 pub mod state;
 
 #[cfg(test)]
@@ -0,0 +1,200 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, CanonicalAddr, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
+    Response, StdResult, Storage, WasmMsg,
+};
+
+use moneymarket::config_history::{config_change, record_config_change};
+use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
+use moneymarket::market::{BorrowerInfoResponse, ExecuteMsg as MarketExecuteMsg};
+use moneymarket::oracle::PriceResponse;
+use moneymarket::overseer::{ShutdownStateResponse, WhitelistResponseElem};
+use moneymarket::querier::query_ema_prices;
+use moneymarket::tokens::Tokens;
+
+use crate::error::ContractError;
+use crate::querier::query_borrower_info;
+use crate::state::{
+    read_collaterals, read_config, read_shutdown_state, read_whitelist, read_whitelist_elem,
+    store_collaterals, store_shutdown_state, Config, ShutdownState, WhitelistElem,
+};
+
+/// Freeze the price of every whitelisted collateral and shut the market
+/// and custody contracts down. There is no way back
+pub fn shutdown(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    settlement_period: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    assert_not_shutdown(deps.storage)?;
+
+    let whitelist: Vec<WhitelistResponseElem> = read_whitelist(deps.as_ref(), None, None)?;
+    let collateral_tokens: Vec<String> = whitelist
+        .iter()
+        .map(|elem| elem.collateral_token.clone())
+        .collect();
+    // only the EMA prices are checked, so a tripped circuit breaker
+    // cannot hold an emergency shutdown back
+    let prices: Vec<PriceResponse> = query_ema_prices(
+        deps.as_ref(),
+        deps.api.addr_humanize(&config.oracle_contract)?,
+        collateral_tokens.clone(),
+    )?;
+
+    let prices = collateral_tokens
+        .iter()
+        .zip(prices.iter())
+        .map(|(token, price)| Ok((deps.api.addr_canonicalize(token)?, price.rate)))
+        .collect::<StdResult<Vec<(CanonicalAddr, Decimal256)>>>()?;
+    store_shutdown_state(
+        deps.storage,
+        &ShutdownState {
+            shutdown_height: env.block.height,
+            prices,
+        },
+    )?;
+
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "shutdown",
+        vec![config_change("shutdown", &false, &true)?],
+    )?;
+
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&config.market_contract)?.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&MarketExecuteMsg::Shutdown {
+            collateral_tokens,
+            settlement_period,
+        })?,
+    })];
+    for elem in whitelist {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: elem.custody_contract,
+            funds: vec![],
+            msg: to_json_binary(&CustodyExecuteMsg::Shutdown {})?,
+        }));
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "shutdown"),
+        attr("shutdown_height", env.block.height.to_string()),
+    ]))
+}
+
+/// Move the collaterals of `borrower` worth its loan at the frozen prices
+/// to the market and write the loan off. Any collateral left over can be
+/// unlocked by the borrower, while a shortfall is borne by the depositors
+pub fn settle_borrower(
+    deps: DepsMut,
+    env: Env,
+    borrower: Addr,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    let shutdown_state = match read_shutdown_state(deps.storage)? {
+        Some(shutdown_state) => shutdown_state,
+        None => return Err(ContractError::NotShutDown {}),
+    };
+
+    let market = deps.api.addr_humanize(&config.market_contract)?;
+    let borrower_info: BorrowerInfoResponse =
+        query_borrower_info(deps.as_ref(), market.clone(), borrower.clone(), env.block.height)?;
+
+    let borrower_raw = deps.api.addr_canonicalize(borrower.as_str())?;
+    let mut collaterals: Tokens = read_collaterals(deps.storage, &borrower_raw);
+    let mut remaining = borrower_info.loan_amount;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for collateral in collaterals.iter_mut() {
+        let price = frozen_price(&shutdown_state, &collateral.0);
+        if remaining.is_zero() || price.is_zero() {
+            continue;
+        }
+
+        // round up, so the settled collateral covers the loan
+        let mut amount = remaining / price;
+        if amount * price < remaining {
+            amount += Uint256::one();
+        }
+        let amount = std::cmp::min(amount, collateral.1);
+        remaining = remaining - std::cmp::min(remaining, amount * price);
+        collateral.1 = collateral.1 - amount;
+
+        let whitelist_elem: WhitelistElem = read_whitelist_elem(deps.storage, &collateral.0)?;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: deps
+                .api
+                .addr_humanize(&whitelist_elem.custody_contract)?
+                .to_string(),
+            funds: vec![],
+            msg: to_json_binary(&CustodyExecuteMsg::SettleCollateral {
+                borrower: borrower.to_string(),
+                amount,
+            })?,
+        }));
+    }
+
+    collaterals.retain(|collateral| !collateral.1.is_zero());
+    store_collaterals(deps.storage, &borrower_raw, &collaterals)?;
+
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: market.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&MarketExecuteMsg::SettleLoan {
+            borrower: borrower.to_string(),
+        })?,
+    }));
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        attr("action", "settle_borrower"),
+        attr("borrower", borrower),
+        attr("loan_amount", borrower_info.loan_amount),
+        attr("shortfall", remaining),
+    ]))
+}
+
+/// Frozen price of `collateral_token`, zero if it was not whitelisted
+/// at the shutdown
+pub(crate) fn frozen_price(
+    shutdown_state: &ShutdownState,
+    collateral_token: &CanonicalAddr,
+) -> Decimal256 {
+    shutdown_state
+        .prices
+        .iter()
+        .find(|(token, _)| token == collateral_token)
+        .map(|(_, price)| *price)
+        .unwrap_or_else(Decimal256::zero)
+}
+
+pub fn assert_not_shutdown(storage: &dyn Storage) -> Result<(), ContractError> {
+    if read_shutdown_state(storage)?.is_some() {
+        return Err(ContractError::ShutDown {});
+    }
+
+    Ok(())
+}
+
+pub fn query_shutdown_state(deps: Deps) -> StdResult<ShutdownStateResponse> {
+    Ok(match read_shutdown_state(deps.storage)? {
+        Some(shutdown_state) => ShutdownStateResponse {
+            shutdown_height: Some(shutdown_state.shutdown_height),
+            prices: shutdown_state
+                .prices
+                .iter()
+                .map(|(token, price)| Ok((deps.api.addr_humanize(token)?.to_string(), *price)))
+                .collect::<StdResult<Vec<(String, Decimal256)>>>()?,
+        },
+        None => ShutdownStateResponse {
+            shutdown_height: None,
+            prices: vec![],
+        },
+    })
+}
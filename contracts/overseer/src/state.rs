@@ -3,20 +3,45 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::{CanonicalAddr, Deps, Order, StdError, StdResult, Storage};
-use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, UniqueIndex};
 
-use moneymarket::overseer::{CollateralsResponse, WhitelistResponseElem};
+use moneymarket::overseer::{
+    CollateralsResponse, FrozenLiquidationPolicy, WhitelistResponseElem,
+};
+use moneymarket::migration::{legacy_singleton_exists, migrate_singleton};
 use moneymarket::tokens::Tokens;
 
-const KEY_CONFIG: &[u8] = b"config";
-const KEY_DYNRATE_CONFIG: &[u8] = b"dynrate_config";
-const KEY_EPOCH_STATE: &[u8] = b"epoch_state";
-const KEY_DYNRATE_STATE: &[u8] = b"dynrate_state";
-const KEY_NEWOWNER: &[u8] = b"newowner";
+const CONFIG: Item<Config> = Item::new("config");
+const DYNRATE_CONFIG: Item<DynrateConfig> = Item::new("dynrate_config");
+const EPOCH_STATE: Item<EpochState> = Item::new("epoch_state");
+const DYNRATE_STATE: Item<DynrateState> = Item::new("dynrate_state");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const SHUTDOWN: Item<ShutdownState> = Item::new("shutdown");
+
+const COLLATERALS: Map<&[u8], Tokens> = Map::new("collateral");
+const LAST_LIQUIDATION: Map<&[u8], u64> = Map::new("last_liquidation");
+
+/// Whitelist keyed by collateral token, indexed by custody contract
+fn whitelist<'a>() -> IndexedMap<'a, &'a [u8], WhitelistElem, WhitelistIndexes<'a>> {
+    let indexes = WhitelistIndexes {
+        custody: UniqueIndex::new(
+            |elem| elem.custody_contract.as_slice().to_vec(),
+            "whitelist__custody",
+        ),
+    };
+    IndexedMap::new("whitelist", indexes)
+}
 
-const PREFIX_WHITELIST: &[u8] = b"whitelist";
-const PREFIX_COLLATERALS: &[u8] = b"collateral";
+pub struct WhitelistIndexes<'a> {
+    pub custody: UniqueIndex<'a, Vec<u8>, WhitelistElem, Vec<u8>>,
+}
 
+impl<'a> IndexList<WhitelistElem> for WhitelistIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<WhitelistElem>> + '_> {
+        let v: Vec<&dyn Index<WhitelistElem>> = vec![&self.custody];
+        Box::new(v.into_iter())
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -32,6 +57,11 @@ pub struct Config {
     pub buffer_distribution_factor: Decimal256,
     pub kpt_purchase_factor: Decimal256,
     pub price_timeframe: u64,
+    #[serde(default)]
+    pub frozen_liquidation_policy: FrozenLiquidationPolicy,
+    /// Minimum seconds between two liquidations of the same borrower (0 disables)
+    #[serde(default)]
+    pub liquidation_cooldown: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -67,6 +97,14 @@ pub struct WhitelistElem {
     pub custody_contract: CanonicalAddr,
 }
 
+/// Recorded once the protocol is shut down
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShutdownState {
+    pub shutdown_height: u64,
+    /// Price of each whitelisted collateral at the shutdown
+    pub prices: Vec<(CanonicalAddr, Decimal256)>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct NewOwnerAddr {
     pub new_owner_addr: CanonicalAddr, 
@@ -74,43 +112,51 @@ pub struct NewOwnerAddr {
 
 
 pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
-    Singleton::new(storage, KEY_NEWOWNER).save(data)
+    NEWOWNER.save(storage, data)
 }
 
 pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
-    ReadonlySingleton::new(storage, KEY_NEWOWNER).load()
+    NEWOWNER.load(storage)
+}
+
+pub fn store_shutdown_state(storage: &mut dyn Storage, data: &ShutdownState) -> StdResult<()> {
+    SHUTDOWN.save(storage, data)
+}
+
+pub fn read_shutdown_state(storage: &dyn Storage) -> StdResult<Option<ShutdownState>> {
+    SHUTDOWN.may_load(storage)
 }
 
 pub fn store_config(storage: &mut dyn Storage, data: &Config) -> StdResult<()> {
-    Singleton::new(storage, KEY_CONFIG).save(data)
+    CONFIG.save(storage, data)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    ReadonlySingleton::new(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
 }
 
 pub fn store_dynrate_config(storage: &mut dyn Storage, data: &DynrateConfig) -> StdResult<()> {
-    Singleton::new(storage, KEY_DYNRATE_CONFIG).save(data)
+    DYNRATE_CONFIG.save(storage, data)
 }
 
 pub fn read_dynrate_config(storage: &dyn Storage) -> StdResult<DynrateConfig> {
-    ReadonlySingleton::new(storage, KEY_DYNRATE_CONFIG).load()
+    DYNRATE_CONFIG.load(storage)
 }
 
 pub fn store_epoch_state(storage: &mut dyn Storage, data: &EpochState) -> StdResult<()> {
-    Singleton::new(storage, KEY_EPOCH_STATE).save(data)
+    EPOCH_STATE.save(storage, data)
 }
 
 pub fn read_epoch_state(storage: &dyn Storage) -> StdResult<EpochState> {
-    ReadonlySingleton::new(storage, KEY_EPOCH_STATE).load()
+    EPOCH_STATE.load(storage)
 }
 
 pub fn store_dynrate_state(storage: &mut dyn Storage, data: &DynrateState) -> StdResult<()> {
-    Singleton::new(storage, KEY_DYNRATE_STATE).save(data)
+    DYNRATE_STATE.save(storage, data)
 }
 
 pub fn read_dynrate_state(storage: &dyn Storage) -> StdResult<DynrateState> {
-    ReadonlySingleton::new(storage, KEY_DYNRATE_STATE).load()
+    DYNRATE_STATE.load(storage)
 }
 
 pub fn store_whitelist_elem(
@@ -118,19 +164,14 @@ pub fn store_whitelist_elem(
     collateral_token: &CanonicalAddr,
     whitelist_elem: &WhitelistElem,
 ) -> StdResult<()> {
-    let mut whitelist_bucket: Bucket<WhitelistElem> = Bucket::new(storage, PREFIX_WHITELIST);
-    whitelist_bucket.save(collateral_token.as_slice(), whitelist_elem)?;
-
-    Ok(())
+    whitelist().save(storage, collateral_token.as_slice(), whitelist_elem)
 }
 
 pub fn read_whitelist_elem(
     storage: &dyn Storage,
     collateral_token: &CanonicalAddr,
 ) -> StdResult<WhitelistElem> {
-    let whitelist_bucket: ReadonlyBucket<WhitelistElem> =
-        ReadonlyBucket::new(storage, PREFIX_WHITELIST);
-    match whitelist_bucket.load(collateral_token.as_slice()) {
+    match whitelist().load(storage, collateral_token.as_slice()) {
         Ok(v) => Ok(v),
         _ => Err(StdError::generic_err(
             "Token is not registered as collateral",
@@ -138,19 +179,28 @@ pub fn read_whitelist_elem(
     }
 }
 
+/// Returns the collateral token and whitelist entry held by `custody_contract`
+pub fn read_whitelist_elem_by_custody(
+    storage: &dyn Storage,
+    custody_contract: &CanonicalAddr,
+) -> StdResult<Option<(CanonicalAddr, WhitelistElem)>> {
+    Ok(whitelist()
+        .idx
+        .custody
+        .item(storage, custody_contract.as_slice().to_vec())?
+        .map(|(k, v)| (CanonicalAddr::from(k), v)))
+}
+
 pub fn read_whitelist(
     deps: Deps,
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
 ) -> StdResult<Vec<WhitelistResponseElem>> {
-    let whitelist_bucket: ReadonlyBucket<WhitelistElem> =
-        ReadonlyBucket::new(deps.storage, PREFIX_WHITELIST);
-
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let start = start_after.map(|addr| Bound::ExclusiveRaw(addr.to_vec()));
 
-    whitelist_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+    whitelist()
+        .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|elem| {
             let (k, v) = elem?;
@@ -173,23 +223,29 @@ pub fn store_collaterals(
     borrower: &CanonicalAddr,
     collaterals: &Tokens,
 ) -> StdResult<()> {
-    let mut collaterals_bucket: Bucket<Tokens> = Bucket::new(storage, PREFIX_COLLATERALS);
     if collaterals.is_empty() {
-        collaterals_bucket.remove(borrower.as_slice());
+        COLLATERALS.remove(storage, borrower.as_slice());
     } else {
-        collaterals_bucket.save(borrower.as_slice(), collaterals)?;
+        COLLATERALS.save(storage, borrower.as_slice(), collaterals)?;
     }
 
     Ok(())
 }
 
 pub fn read_collaterals(storage: &dyn Storage, borrower: &CanonicalAddr) -> Tokens {
-    let collaterals_bucket: ReadonlyBucket<Tokens> =
-        ReadonlyBucket::new(storage, PREFIX_COLLATERALS);
-    match collaterals_bucket.load(borrower.as_slice()) {
-        Ok(v) => v,
-        _ => vec![],
-    }
+    COLLATERALS.load(storage, borrower.as_slice()).unwrap_or_default()
+}
+
+pub fn store_last_liquidation(
+    storage: &mut dyn Storage,
+    borrower: &CanonicalAddr,
+    time: u64,
+) -> StdResult<()> {
+    LAST_LIQUIDATION.save(storage, borrower.as_slice(), &time)
+}
+
+pub fn read_last_liquidation(storage: &dyn Storage, borrower: &CanonicalAddr) -> Option<u64> {
+    LAST_LIQUIDATION.may_load(storage, borrower.as_slice()).unwrap_or_default()
 }
 
 // settings for pagination
@@ -200,14 +256,11 @@ pub fn read_all_collaterals(
     start_after: Option<CanonicalAddr>,
     limit: Option<u32>,
 ) -> StdResult<Vec<CollateralsResponse>> {
-    let whitelist_bucket: ReadonlyBucket<Tokens> =
-        ReadonlyBucket::new(deps.storage, PREFIX_COLLATERALS);
-
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = calc_range_start(start_after);
+    let start = start_after.map(|addr| Bound::ExclusiveRaw(addr.to_vec()));
 
-    whitelist_bucket
-        .range(start.as_deref(), None, Order::Ascending)
+    COLLATERALS
+        .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|elem| {
             let (k, v) = elem?;
@@ -225,11 +278,27 @@ pub fn read_all_collaterals(
         .collect()
 }
 
-// this will set the first key after the provided key, by appending a 1 byte
-fn calc_range_start(start_after: Option<CanonicalAddr>) -> Option<Vec<u8>> {
-    start_after.map(|addr| {
-        let mut v = addr.as_slice().to_vec();
-        v.push(1);
-        v
-    })
+/// Move state written by `cosmwasm_storage`, whether or not cw2 versions were
+/// recorded yet. Singletons move to their `Item`s, buckets share the byte
+/// layout of the maps that replace them and only the new indexes are built,
+/// once. The whitelist is bounded by the owner, so it is re-saved at once.
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    let legacy = legacy_singleton_exists(storage, &CONFIG);
+    migrate_singleton(storage, &CONFIG)?;
+    migrate_singleton(storage, &DYNRATE_CONFIG)?;
+    migrate_singleton(storage, &EPOCH_STATE)?;
+    migrate_singleton(storage, &DYNRATE_STATE)?;
+    migrate_singleton(storage, &NEWOWNER)?;
+    if !legacy {
+        return Ok(());
+    }
+
+    let elems = whitelist()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (collateral_token, elem) in elems {
+        whitelist().save(storage, &collateral_token, &elem)?;
+    }
+
+    Ok(())
 }
@@ -98,7 +98,7 @@ fn proper_compute_borrow_limit() {
     );
     collaterals.push(token2);
 
-    let res = compute_borrow_limit(deps.as_ref(), &collaterals, None).unwrap();
+    let res = compute_borrow_limit(deps.as_ref(), &collaterals, None, false).unwrap();
     let vec: Vec<Decimal256> = vec![
         Decimal256::from_uint256(1000u128),
         Decimal256::from_uint256(2000u128),
@@ -1,27 +1,41 @@
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env};
 use cosmwasm_std::{
-    coin, coins, to_json_binary, Addr, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response,
-    StdError, StdResult, Timestamp,
+    coin, coins, to_json_binary, Addr, Binary, Coin, Deps, DepsMut, Env, Event, MessageInfo,
+    Response, StdError, StdResult, Storage, Timestamp,
 };
+use cosmwasm_storage::to_length_prefixed;
+use cw2::query_contract_info;
 use cw20_legacy::msg::InstantiateMsg as TokenInstantiateMsg;
+use moneymarket::config_history::{ConfigChange, ConfigHistoryResponse};
 use moneymarket::custody::{
     BAssetInfo, ExecuteMsg as CustodyExecuteMsg, InstantiateMsg as CustodyInstantiateMsg,
     QueryMsg as CustodyQueryMsg,
 };
-use moneymarket::distribution_model::InstantiateMsg as DistributionModelInstantiateMsg;
-use moneymarket::interest_model::InstantiateMsg as InterestModelInstantiateMsg;
+use moneymarket::distribution_model::{
+    ConfigResponse as DistributionModelConfigResponse,
+    InstantiateMsg as DistributionModelInstantiateMsg, MigrateMsg as DistributionModelMigrateMsg,
+    QueryMsg as DistributionModelQueryMsg,
+};
+use moneymarket::interest_model::{
+    ConfigResponse as InterestModelConfigResponse, ExecuteMsg as InterestModelExecuteMsg,
+    InstantiateMsg as InterestModelInstantiateMsg, MigrateMsg as InterestModelMigrateMsg,
+    QueryMsg as InterestModelQueryMsg,
+};
 use moneymarket::market::{
     BorrowerInfoResponse, ExecuteMsg as MarketExecuteMsg, InstantiateMsg as MarketInstantiateMsg,
     MigrateMsg as MarketMigrateMsg, QueryMsg as MarketQueryMsg,
 };
 use moneymarket::oracle_pyth::{
-    ExecuteMsg as OraclePythExecuteMsg, InstantiateMsg as OraclePythInstantiateMsg, PriceResponse,
-    QueryMsg as OraclePythMsg,
+    ConfigResponse as OraclePythConfigResponse, ExecuteMsg as OraclePythExecuteMsg,
+    InstantiateMsg as OraclePythInstantiateMsg, PriceResponse, QueryMsg as OraclePythMsg,
 };
+use moneymarket_interest_model::error::ContractError as InterestModelContractError;
+use oracle_pyth::msg::MigrateMsg as OraclePythMigrateMsg;
 
 use moneymarket::mock_pyth_contract::{
     ExecuteMsg as MockPythContractExecuteMsg, InstantiateMsg as MockPythContractInstantiateMsg,
+    MockPriceUpdate,
 };
 
 use moneymarket::overseer::{
@@ -120,11 +134,14 @@ fn store_overseer_contract_code(app: &mut App) -> u64 {
 }
 
 fn store_oracle_contract_code(app: &mut App) -> u64 {
-    let oracle_contract = Box::new(ContractWrapper::new_with_empty(
-        oracle_pyth::contract::execute,
-        oracle_pyth::contract::instantiate,
-        oracle_pyth::contract::query,
-    ));
+    let oracle_contract = Box::new(
+        ContractWrapper::new_with_empty(
+            oracle_pyth::contract::execute,
+            oracle_pyth::contract::instantiate,
+            oracle_pyth::contract::query,
+        )
+        .with_migrate_empty(oracle_pyth::contract::migrate),
+    );
 
     app.store_code(oracle_contract)
 }
@@ -140,25 +157,76 @@ fn store_mock_pyth_contract_code(app: &mut App) -> u64 {
 }
 
 fn store_interest_model_code(app: &mut App) -> u64 {
-    let interest_model_contract = Box::new(ContractWrapper::new_with_empty(
-        moneymarket_interest_model::contract::execute,
-        moneymarket_interest_model::contract::instantiate,
-        moneymarket_interest_model::contract::query,
-    ));
+    let interest_model_contract = Box::new(
+        ContractWrapper::new_with_empty(
+            moneymarket_interest_model::contract::execute,
+            moneymarket_interest_model::contract::instantiate,
+            moneymarket_interest_model::contract::query,
+        )
+        .with_migrate_empty(moneymarket_interest_model::contract::migrate),
+    );
 
     app.store_code(interest_model_contract)
 }
 
 fn store_distribution_model_code(app: &mut App) -> u64 {
-    let distribution_model_contract = Box::new(ContractWrapper::new_with_empty(
-        moneymarket_distribution_model::contract::execute,
-        moneymarket_distribution_model::contract::instantiate,
-        moneymarket_distribution_model::contract::query,
-    ));
+    let distribution_model_contract = Box::new(
+        ContractWrapper::new_with_empty(
+            moneymarket_distribution_model::contract::execute,
+            moneymarket_distribution_model::contract::instantiate,
+            moneymarket_distribution_model::contract::query,
+        )
+        .with_migrate_empty(moneymarket_distribution_model::contract::migrate),
+    );
 
     app.store_code(distribution_model_contract)
 }
 
+/// Rewrite the `Item`s under `keys` to the `cosmwasm_storage` singleton layout
+/// and drop the cw2 info, as stored by releases before contract versions
+fn into_legacy_layout(storage: &mut dyn Storage, keys: &[&[u8]]) {
+    for key in keys {
+        let value = storage.get(key).unwrap();
+        storage.remove(key);
+        storage.set(&to_length_prefixed(key), &value);
+    }
+    storage.remove(b"contract_info");
+}
+
+fn legacy_interest_model_instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InterestModelInstantiateMsg,
+) -> Result<Response, InterestModelContractError> {
+    let res = moneymarket_interest_model::contract::instantiate(deps.branch(), env, info, msg)?;
+    into_legacy_layout(deps.storage, &[b"config", b"newowner"]);
+    Ok(res)
+}
+
+fn legacy_distribution_model_instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: DistributionModelInstantiateMsg,
+) -> StdResult<Response> {
+    let res =
+        moneymarket_distribution_model::contract::instantiate(deps.branch(), env, info, msg)?;
+    into_legacy_layout(deps.storage, &[b"config", b"newowner"]);
+    Ok(res)
+}
+
+fn legacy_oracle_instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: oracle_pyth::msg::InstantiateMsg,
+) -> StdResult<Response> {
+    let res = oracle_pyth::contract::instantiate(deps.branch(), env, info, msg)?;
+    into_legacy_layout(deps.storage, &[b"config"]);
+    Ok(res)
+}
+
 fn create_contracts(input_coins: Option<Vec<Coin>>) -> (App, Addr, Addr, Addr, Addr, Addr, Addr) {
     let owner = Addr::unchecked(OWNER);
     let admin = Addr::unchecked(ADMIN);
@@ -221,6 +289,8 @@ fn create_contracts(input_coins: Option<Vec<Coin>>) -> (App, Addr, Addr, Addr, A
         owner: owner.to_string(),
         base_rate: Decimal256::percent(10),
         interest_multiplier: Decimal256::percent(10),
+        jump_rate: None,
+        adaptive_rate: None,
     };
     let interest_model_addr = app
         .instantiate_contract(
@@ -259,6 +329,7 @@ fn create_contracts(input_coins: Option<Vec<Coin>>) -> (App, Addr, Addr, Addr, A
         atoken_code_id: token_code_id,
         kpt_emission_rate: Decimal256::one(),
         max_borrow_factor: Decimal256::one(),
+        atoken_balance_hook: None,
     };
     let market_addr = app
         .instantiate_contract(
@@ -321,6 +392,7 @@ fn create_contracts(input_coins: Option<Vec<Coin>>) -> (App, Addr, Addr, Addr, A
         decimals: 6,
         initial_balances: vec![],
         mint: None,
+        balance_hook: None,
     };
 
     let bsei_token_addr = app
@@ -389,6 +461,191 @@ fn migrate_contracts(app: &mut App, market_addr: &Addr, overseer_addr: &Addr) {
 fn test_migration() {
     let (mut app, market_addr, overseer_addr, _, _, _, _) = create_contracts(None);
     migrate_contracts(&mut app, &market_addr, &overseer_addr);
+
+    let version = query_contract_info(&app, &market_addr).unwrap();
+    assert_eq!(version.contract, "crates.io:moneymarket-market");
+    let version = query_contract_info(&app, &overseer_addr).unwrap();
+    assert_eq!(version.contract, "crates.io:moneymarket-overseer");
+
+    // the market cannot be migrated to the code of another contract
+    let overseer_code_id = store_overseer_contract_code(&mut app);
+    let err = app
+        .migrate_contract(
+            Addr::unchecked(ADMIN),
+            market_addr,
+            &OverseerMigrateMsg {},
+            overseer_code_id,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Generic error: Cannot migrate crates.io:moneymarket-market to \
+         crates.io:moneymarket-overseer"
+    );
+}
+
+#[test]
+fn test_migration_from_legacy_layout() {
+    let owner = Addr::unchecked(OWNER);
+    let admin = Addr::unchecked(ADMIN);
+    let mut app = mock_app(owner.clone(), vec![], None);
+
+    // store the code of releases without cw2 info
+    let legacy_interest_model_code_id = app.store_code(Box::new(ContractWrapper::new_with_empty(
+        moneymarket_interest_model::contract::execute,
+        legacy_interest_model_instantiate,
+        moneymarket_interest_model::contract::query,
+    )));
+    let legacy_distribution_model_code_id =
+        app.store_code(Box::new(ContractWrapper::new_with_empty(
+            moneymarket_distribution_model::contract::execute,
+            legacy_distribution_model_instantiate,
+            moneymarket_distribution_model::contract::query,
+        )));
+    let legacy_oracle_code_id = app.store_code(Box::new(ContractWrapper::new_with_empty(
+        oracle_pyth::contract::execute,
+        legacy_oracle_instantiate,
+        oracle_pyth::contract::query,
+    )));
+
+    let msg = InterestModelInstantiateMsg {
+        owner: owner.to_string(),
+        base_rate: Decimal256::percent(10),
+        interest_multiplier: Decimal256::percent(10),
+        jump_rate: None,
+        adaptive_rate: None,
+    };
+    let interest_model_addr = app
+        .instantiate_contract(
+            legacy_interest_model_code_id,
+            owner.clone(),
+            &msg,
+            &[],
+            String::from("INTEREST MODEL"),
+            Some(admin.to_string()),
+        )
+        .unwrap();
+
+    let msg = DistributionModelInstantiateMsg {
+        owner: owner.to_string(),
+        emission_cap: Decimal256::from_uint256(100u64),
+        emission_floor: Decimal256::from_uint256(10u64),
+        increment_multiplier: Decimal256::percent(110),
+        decrement_multiplier: Decimal256::percent(90),
+    };
+    let distribution_model_addr = app
+        .instantiate_contract(
+            legacy_distribution_model_code_id,
+            owner.clone(),
+            &msg,
+            &[],
+            String::from("DISTRIBUTION MODEL"),
+            Some(admin.to_string()),
+        )
+        .unwrap();
+
+    let msg = OraclePythInstantiateMsg {
+        owner: owner.clone(),
+        pyth_contract: "pyth_contract".to_string(),
+    };
+    let oracle_addr = app
+        .instantiate_contract(
+            legacy_oracle_code_id,
+            owner.clone(),
+            &msg,
+            &[],
+            String::from("PYTH_ORACLE"),
+            Some(admin.to_string()),
+        )
+        .unwrap();
+
+    // the current code cannot read the legacy layout before migrating
+    let res: StdResult<InterestModelConfigResponse> = app
+        .wrap()
+        .query_wasm_smart(&interest_model_addr, &InterestModelQueryMsg::Config {});
+    assert!(res.is_err());
+    assert!(query_contract_info(&app, &interest_model_addr).is_err());
+
+    let interest_model_code_id = store_interest_model_code(&mut app);
+    let res = app
+        .migrate_contract(
+            admin.clone(),
+            interest_model_addr.clone(),
+            &InterestModelMigrateMsg {},
+            interest_model_code_id,
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("action", "migrate")
+            .add_attribute("previous_version", "none")
+    ));
+
+    let distribution_model_code_id = store_distribution_model_code(&mut app);
+    app.migrate_contract(
+        admin.clone(),
+        distribution_model_addr.clone(),
+        &DistributionModelMigrateMsg {},
+        distribution_model_code_id,
+    )
+    .unwrap();
+
+    let oracle_code_id = store_oracle_contract_code(&mut app);
+    app.migrate_contract(
+        admin,
+        oracle_addr.clone(),
+        &OraclePythMigrateMsg {},
+        oracle_code_id,
+    )
+    .unwrap();
+
+    let res: InterestModelConfigResponse = app
+        .wrap()
+        .query_wasm_smart(&interest_model_addr, &InterestModelQueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.owner, OWNER);
+    assert_eq!(res.base_rate, Decimal256::percent(10));
+    let version = query_contract_info(&app, &interest_model_addr).unwrap();
+    assert_eq!(version.contract, "crates.io:moneymarket-interest-model");
+
+    let res: DistributionModelConfigResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &distribution_model_addr,
+            &DistributionModelQueryMsg::Config {},
+        )
+        .unwrap();
+    assert_eq!(res.owner, OWNER);
+    assert_eq!(res.emission_cap, Decimal256::from_uint256(100u64));
+    let version = query_contract_info(&app, &distribution_model_addr).unwrap();
+    assert_eq!(version.contract, "crates.io:moneymarket-distribution-model");
+
+    let res: OraclePythConfigResponse = app
+        .wrap()
+        .query_wasm_smart(&oracle_addr, &OraclePythMsg::QueryConfig {})
+        .unwrap();
+    assert_eq!(res.owner, OWNER);
+    assert_eq!(res.pyth_contract, "pyth_contract");
+    let version = query_contract_info(&app, &oracle_addr).unwrap();
+    assert_eq!(version.contract, "crates.io:oracle-pyth");
+
+    // the owner can still be transferred with the migrated state
+    app.execute_contract(
+        owner.clone(),
+        interest_model_addr.clone(),
+        &InterestModelExecuteMsg::SetOwner {
+            new_owner_addr: USER.to_string(),
+        },
+        &[],
+    )
+    .unwrap();
+    app.execute_contract(
+        Addr::unchecked(USER),
+        interest_model_addr,
+        &InterestModelExecuteMsg::AcceptOwnership {},
+        &[],
+    )
+    .unwrap();
 }
 
 #[test]
@@ -529,6 +786,7 @@ fn test_successfully_repay_stable_from_yield_reserve() {
     let msg = MarketExecuteMsg::BorrowStable {
         borrow_amount: Uint256::from(847_426_363u64),
         to: None,
+        price_update: None,
     };
     app.execute_contract(user.clone(), market_addr.clone(), &msg, &[])
         .unwrap();
@@ -556,4 +814,144 @@ fn test_successfully_repay_stable_from_yield_reserve() {
         .unwrap();
 
     assert_eq!(res.loan_amount, Uint256::zero());
+
+    // the repayment is logged with the owner actions
+    let res: ConfigHistoryResponse = app
+        .wrap()
+        .query_wasm_smart(
+            overseer_addr,
+            &OverseerQueryMsg::ConfigHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    let entry = res.entries.last().unwrap();
+    assert_eq!(entry.action, "repay_stable_from_yield_reserve");
+    assert_eq!(entry.sender, OWNER);
+    assert_eq!(
+        entry.changes,
+        vec![
+            ConfigChange {
+                field: "borrower".to_string(),
+                old_value: "null".to_string(),
+                new_value: format!("\"{}\"", USER),
+            },
+            ConfigChange {
+                field: "repay_amount".to_string(),
+                old_value: "\"0\"".to_string(),
+                new_value: "\"847426363\"".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_borrow_stable_with_price_update() {
+    let owner = Addr::unchecked(OWNER);
+    let user = Addr::unchecked(USER);
+
+    let (
+        mut app,
+        market_addr,
+        overseer_addr,
+        bsei_token_addr,
+        custody_contract_addr,
+        oracle_addr,
+        mock_pyth_contract_addr,
+    ) = create_contracts(Some(coins(1_000_000, "usei")));
+    app.send_tokens(
+        owner.clone(),
+        market_addr.clone(),
+        &[coin(10_000_000_000u128, "uusd")],
+    )
+    .unwrap();
+    app.send_tokens(owner.clone(), user.clone(), &[coin(10u128, "usei")])
+        .unwrap();
+
+    let msg = OverseerExecuteMsg::Whitelist {
+        name: "bsei".to_string(),
+        symbol: "bsei".to_string(),
+        collateral_token: bsei_token_addr.to_string(),
+        custody_contract: custody_contract_addr.to_string(),
+        max_ltv: Decimal256::percent(60),
+    };
+    app.execute_contract(owner.clone(), overseer_addr.clone(), &msg, &[])
+        .unwrap();
+
+    let msg = OverseerExecuteMsg::LockCollateral {
+        borrower: user.to_string(),
+        collaterals: vec![(bsei_token_addr.to_string(), Uint256::from(1_000_000_000u64))],
+    };
+    app.execute_contract(custody_contract_addr, overseer_addr.clone(), &msg, &[])
+        .unwrap();
+
+    let feed_id = String::from("53614f1cb0c031d4af66c04cb9c756234adad0e1cee85303795091499a4084eb");
+    let msg = OraclePythExecuteMsg::ConfigFeedInfo {
+        asset: bsei_token_addr.to_string(),
+        price_feed_id: feed_id.clone(),
+        price_feed_symbol: "bSEI".to_string(),
+        price_feed_decimal: 8,
+        check_feed_age: true,
+        price_feed_age: 60,
+    };
+    app.execute_contract(owner.clone(), oracle_addr.clone(), &msg, &[])
+        .unwrap();
+
+    // bSEI at 1 uusd, borrow limit 600_000_000
+    let msg = MockPythContractExecuteMsg::UpdatePriceFeed {
+        id: PriceIdentifier::from_hex(&feed_id).unwrap(),
+        price: 100_000_000i64,
+    };
+    app.execute_contract(owner, mock_pyth_contract_addr, &msg, &[])
+        .unwrap();
+
+    let msg = MarketExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(1_000_000_000u64),
+        to: None,
+        price_update: None,
+    };
+    app.execute_contract(user.clone(), market_addr.clone(), &msg, &[])
+        .unwrap_err();
+
+    // pushing bSEI at 2 uusd in the same transaction raises the limit to 1_200_000_000
+    let price_update = vec![to_json_binary(&MockPriceUpdate {
+        id: PriceIdentifier::from_hex(&feed_id).unwrap(),
+        price: 200_000_000i64,
+    })
+    .unwrap()];
+    let msg = MarketExecuteMsg::BorrowStable {
+        borrow_amount: Uint256::from(1_000_000_000u64),
+        to: None,
+        price_update: Some(price_update),
+    };
+    app.execute_contract(user.clone(), market_addr.clone(), &msg, &[coin(5u128, "usei")])
+        .unwrap();
+
+    // the update fee is 1 usei per price update, the rest is refunded
+    assert_eq!(
+        app.wrap().query_balance(user.clone(), "usei").unwrap(),
+        coin(9u128, "usei")
+    );
+
+    let res: BorrowerInfoResponse = app
+        .wrap()
+        .query_wasm_smart(
+            market_addr.clone(),
+            &MarketQueryMsg::BorrowerInfo {
+                borrower: user.to_string(),
+                block_height: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(res.loan_amount, Uint256::from(1_000_000_000u64));
+
+    // the internal continuation can not be called directly
+    let msg = MarketExecuteMsg::BorrowStableAfterPriceUpdate {
+        borrower: user.to_string(),
+        borrow_amount: Uint256::from(1u64),
+        to: None,
+    };
+    app.execute_contract(user, market_addr, &msg, &[])
+        .unwrap_err();
 }
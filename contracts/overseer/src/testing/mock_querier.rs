@@ -14,7 +14,7 @@ use moneymarket::liquidation::LiquidationAmountResponse;
 use moneymarket::market::{BorrowerInfoResponse, EpochStateResponse, StateResponse};
 
 use moneymarket::tokens::TokensHuman;
-use moneymarket::oracle_pyth::PriceResponse;
+use moneymarket::oracle_pyth::{CheckedPriceResponse, PriceResponse};
 
 use std::str::FromStr;
 
@@ -35,6 +35,12 @@ pub enum QueryMsg {
     },
     /// Query oracle price to oracle contract
     QueryPrice { asset: String },
+    /// Query oracle prices to oracle contract
+    QueryPrices { assets: Vec<String> },
+    /// Query oracle prices, only checking the ema prices
+    QueryEmaPrices { assets: Vec<String> },
+    /// Query oracle prices, reporting circuit breaker trips in the response
+    QueryCheckedPrices { assets: Vec<String>, ema_only: bool },
     /// Query liquidation amount to liquidation model contract
     LiquidationAmount {
         borrow_amount: Uint256,
@@ -97,6 +103,8 @@ pub(crate) fn caps_to_map(caps: &[(&String, &Uint128)]) -> HashMap<String, Uint1
 pub struct OraclePriceQuerier {
     // this lets us iterate over all pairs that match the first string
     oracle_price: HashMap<String, (Decimal256, i64, Decimal256, i64, u64, u64)>,
+    // assets whose spot price trips the oracle circuit breaker
+    frozen_assets: Vec<String>,
 }
 
 #[allow(clippy::type_complexity)]
@@ -104,8 +112,21 @@ impl OraclePriceQuerier {
     pub fn new(oracle_price: &[(&String, &(Decimal256, i64, Decimal256, i64, u64, u64))]) -> Self {
         OraclePriceQuerier {
             oracle_price: oracle_price_to_map(oracle_price),
+            frozen_assets: vec![],
         }
     }
+
+    fn price(&self, asset: &str) -> Option<PriceResponse> {
+        self.oracle_price.get(asset).map(|v| PriceResponse {
+            asset: asset.to_string(),
+            emv_price: v.0,
+            emv_price_raw: v.1,
+            price: v.2,
+            price_raw: v.3,
+            last_updated_base: v.4,
+            last_updated_quote: v.5,
+        })
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -280,9 +301,9 @@ impl WasmMockQuerier {
                             &BorrowerInfoResponse {
                                 borrower,
                                 interest_index: Decimal256::one(),
-                                //reward_index: Decimal256::zero(),
+                                reward_index: Decimal256::zero(),
                                 loan_amount: *v,
-                                //pending_rewards: Decimal256::zero(),
+                                pending_rewards: Decimal256::zero(),
                             },
                         ))),
                         None => SystemResult::Err(SystemError::InvalidRequest {
@@ -309,6 +330,65 @@ impl WasmMockQuerier {
                             }),
                         }
                     }
+                    QueryMsg::QueryPrices { assets } => {
+                        let mut prices = vec![];
+                        for asset in assets {
+                            if self.oracle_price_querier.frozen_assets.contains(&asset) {
+                                return SystemResult::Err(SystemError::InvalidRequest {
+                                    error: format!("{} price deviates from ema price", asset),
+                                    request: msg.as_slice().into(),
+                                });
+                            }
+                            match self.oracle_price_querier.price(&asset) {
+                                Some(price) => prices.push(price),
+                                None => {
+                                    return SystemResult::Err(SystemError::InvalidRequest {
+                                        error: "No oracle price exists".to_string(),
+                                        request: msg.as_slice().into(),
+                                    })
+                                }
+                            }
+                        }
+                        SystemResult::Ok(ContractResult::from(to_json_binary(&prices)))
+                    }
+                    QueryMsg::QueryEmaPrices { assets } => {
+                        let mut prices = vec![];
+                        for asset in assets {
+                            match self.oracle_price_querier.price(&asset) {
+                                Some(price) => prices.push(price),
+                                None => {
+                                    return SystemResult::Err(SystemError::InvalidRequest {
+                                        error: "No oracle price exists".to_string(),
+                                        request: msg.as_slice().into(),
+                                    })
+                                }
+                            }
+                        }
+                        SystemResult::Ok(ContractResult::from(to_json_binary(&prices)))
+                    }
+                    QueryMsg::QueryCheckedPrices { assets, ema_only } => {
+                        let mut prices = vec![];
+                        for asset in assets {
+                            let price = match self.oracle_price_querier.price(&asset) {
+                                Some(price) => price,
+                                None => {
+                                    return SystemResult::Err(SystemError::InvalidRequest {
+                                        error: "No oracle price exists".to_string(),
+                                        request: msg.as_slice().into(),
+                                    })
+                                }
+                            };
+                            let frozen = !ema_only
+                                && self.oracle_price_querier.frozen_assets.contains(&asset);
+                            prices.push(CheckedPriceResponse {
+                                circuit_breaker: frozen
+                                    .then(|| format!("{} price deviates from ema price", asset)),
+                                price: (!frozen).then_some(price),
+                                asset,
+                            });
+                        }
+                        SystemResult::Ok(ContractResult::from(to_json_binary(&prices)))
+                    }
                     QueryMsg::LiquidationAmount {
                         borrow_amount,
                         borrow_limit,
@@ -381,6 +461,11 @@ impl WasmMockQuerier {
         self.oracle_price_querier = OraclePriceQuerier::new(oracle_price);
     }
 
+    pub fn with_frozen_oracle_prices(&mut self, frozen_assets: &[&String]) {
+        self.oracle_price_querier.frozen_assets =
+            frozen_assets.iter().map(|asset| (*asset).clone()).collect();
+    }
+
     // #[allow(clippy::type_complexity)]
     // pub fn with_oracle_price(
     //     &mut self,
@@ -1,5 +1,5 @@
 use crate::collateral::lock_collateral as _lock_collateral;
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::error::ContractError;
 use crate::querier::query_epoch_state;
 use crate::state::{
@@ -11,14 +11,18 @@ use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
     attr, from_json, to_json_binary, Addr, Api, BankMsg, CanonicalAddr, Coin, CosmosMsg, Decimal,
-    DepsMut, SubMsg, Uint128, WasmMsg,
+    DepsMut, StdError, Storage, SubMsg, Uint128, WasmMsg,
 };
+use cosmwasm_storage::to_length_prefixed;
+use moneymarket::config_history::{ConfigChange, ConfigHistoryEntryResponse, ConfigHistoryResponse};
 use moneymarket::custody::ExecuteMsg as CustodyExecuteMsg;
 use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
 use moneymarket::overseer::{
     AllCollateralsResponse, BorrowLimitResponse, CollateralsResponse, ConfigResponse, ExecuteMsg,
-    InstantiateMsg, QueryMsg, WhitelistResponse, WhitelistResponseElem,
+    FrozenLiquidationPolicy, InstantiateMsg, MigrateMsg, QueryMsg, ShutdownStateResponse,
+    WhitelistResponse, WhitelistResponseElem,
 };
+use moneymarket::pause::{PausableOperation, PauseStatusResponse};
 use moneymarket::querier::deduct_tax;
 
 use std::str::FromStr;
@@ -74,6 +78,8 @@ fn proper_initialization() {
             dyn_rate_yr_increase_expectation: Decimal256::permille(1),
             dyn_rate_min: Decimal256::from_ratio(1000000000000u64, 1000000000000000000u64),
             dyn_rate_max: Decimal256::from_ratio(1200000000000u64, 1000000000000000000u64),
+            frozen_liquidation_policy: FrozenLiquidationPolicy::Block,
+            liquidation_cooldown: 0,
         }
     );
 
@@ -135,6 +141,8 @@ fn update_config() {
         dyn_rate_yr_increase_expectation: None,
         dyn_rate_min: None,
         dyn_rate_max: None,
+        frozen_liquidation_policy: None,
+        liquidation_cooldown: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -178,6 +186,8 @@ fn update_config() {
             1200000000000u64,
             1000000000000000000u64,
         )),
+        frozen_liquidation_policy: Some(FrozenLiquidationPolicy::UseEmaPrice),
+        liquidation_cooldown: Some(600u64),
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -198,6 +208,11 @@ fn update_config() {
     assert_eq!(Decimal256::percent(10), config_res.kpt_purchase_factor);
     assert_eq!(100000u64, config_res.epoch_period);
     assert_eq!(120u64, config_res.price_timeframe);
+    assert_eq!(
+        FrozenLiquidationPolicy::UseEmaPrice,
+        config_res.frozen_liquidation_policy
+    );
+    assert_eq!(600u64, config_res.liquidation_cooldown);
 
     // Unauthorized err
     let info = mock_info("owner", &[]);
@@ -215,6 +230,8 @@ fn update_config() {
         dyn_rate_yr_increase_expectation: None,
         dyn_rate_min: None,
         dyn_rate_max: None,
+        frozen_liquidation_policy: None,
+        liquidation_cooldown: None,
     };
 
     let res = execute(deps.as_mut(), mock_env(), info, msg);
@@ -369,6 +386,22 @@ fn whitelist() {
             }]
         }
     );
+
+    // a custody contract holds a single collateral
+    let msg = ExecuteMsg::Whitelist {
+        name: "batom".to_string(),
+        symbol: "batom".to_string(),
+        collateral_token: "batom".to_string(),
+        custody_contract: "custody2".to_string(),
+        max_ltv: Decimal256::percent(60),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Violates unique constraint on index")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 }
 
 #[test]
@@ -951,6 +984,32 @@ fn lock_collateral() {
             }]
         }
     );
+
+    // custody contracts past the first whitelist page can lock as well
+    for i in 0..10 {
+        let msg = ExecuteMsg::Whitelist {
+            name: format!("token{}", i),
+            symbol: format!("token{}", i),
+            collateral_token: format!("token{}", i),
+            custody_contract: format!("custody{}", i),
+            max_ltv: Decimal256::percent(60),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::LockCollateral {
+        borrower: "addr0000".to_string(),
+        collaterals: vec![("token9".to_string(), Uint256::from(1000u64))],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("custody9", &[]), msg.clone()).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("custody", &[]), msg).unwrap_err();
+    match res {
+        ContractError::Std(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "sender custody lock collateral Unauthorized")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
 }
 
 #[test]
@@ -1322,6 +1381,7 @@ fn liquidate_collateral() {
 
     let msg = ExecuteMsg::LiquidateCollateral {
         borrower: "addr0000".to_string(),
+        price_update: None,
     };
     let info = mock_info("addr0001", &[]);
     let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
@@ -1332,7 +1392,42 @@ fn liquidate_collateral() {
 
     deps.querier
         .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(12600000001u64))]);
-    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // spot price trips the oracle circuit breaker, liquidations are blocked by default
+    deps.querier.with_frozen_oracle_prices(&[&bsei_collat_token]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::PriceFrozen(_)) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // liquidate with the ema prices while frozen
+    let update_config_msg = ExecuteMsg::UpdateConfig {
+        oracle_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        kpt_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        dyn_rate_epoch: None,
+        dyn_rate_maxchange: None,
+        dyn_rate_yr_increase_expectation: None,
+        dyn_rate_min: None,
+        dyn_rate_max: None,
+        frozen_liquidation_policy: Some(FrozenLiquidationPolicy::UseEmaPrice),
+        liquidation_cooldown: None,
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner", &[]),
+        update_config_msg,
+    )
+    .unwrap();
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
     assert_eq!(
         res.messages,
         vec![
@@ -1356,21 +1451,12 @@ fn liquidate_collateral() {
                 })
                 .unwrap(),
             })),
-            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "market".to_string(),
-                funds: vec![],
-                msg: to_json_binary(&MarketExecuteMsg::RepayStableFromLiquidation {
-                    borrower: "addr0000".to_string(),
-                    prev_balance: Uint256::zero(),
-                })
-                .unwrap(),
-            }))
         ]
     );
 
     let res = query(
         deps.as_ref(),
-        env,
+        env.clone(),
         QueryMsg::Collaterals {
             borrower: "addr0000".to_string(),
         },
@@ -1387,6 +1473,44 @@ fn liquidate_collateral() {
             ]
         }
     );
+
+    // consecutive liquidations of the same borrower are rate limited
+    let update_config_msg = ExecuteMsg::UpdateConfig {
+        oracle_contract: None,
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        kpt_purchase_factor: None,
+        epoch_period: None,
+        price_timeframe: None,
+        dyn_rate_epoch: None,
+        dyn_rate_maxchange: None,
+        dyn_rate_yr_increase_expectation: None,
+        dyn_rate_min: None,
+        dyn_rate_max: None,
+        frozen_liquidation_policy: None,
+        liquidation_cooldown: Some(600u64),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner", &[]),
+        update_config_msg,
+    )
+    .unwrap();
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+    match res {
+        Err(ContractError::LiquidationCooldown(next)) => {
+            assert_eq!(next, env.block.time.seconds() + 600)
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let mut env = env;
+    env.block.time = env.block.time.plus_seconds(600);
+    execute(deps.as_mut(), env, info, msg).unwrap();
 }
 
 #[test]
@@ -1652,6 +1776,624 @@ fn validate_deposit_rates(deps: DepsMut, rate: Decimal256) {
             dyn_rate_yr_increase_expectation: Decimal256::permille(1),
             dyn_rate_min: Decimal256::from_ratio(1000000000000u64, 1000000000000000000u64),
             dyn_rate_max: Decimal256::from_ratio(1200000000000u64, 1000000000000000000u64),
+            frozen_liquidation_policy: FrozenLiquidationPolicy::Block,
+            liquidation_cooldown: 0,
+        }
+    );
+}
+
+#[test]
+fn pause_operations() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        kpt_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+        dyn_rate_epoch: 8600u64,
+        dyn_rate_maxchange: Decimal256::permille(5),
+        dyn_rate_yr_increase_expectation: Decimal256::permille(1),
+        dyn_rate_min: Decimal256::from_ratio(1000000000000u64, 1000000000000000000u64),
+        dyn_rate_max: Decimal256::from_ratio(1200000000000u64, 1000000000000000000u64),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateGuardian {
+        guardian: Some("guardian".to_string()),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Borrows,
+        paused: true,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg);
+    match res {
+        Err(ContractError::NotPausable(operation)) => assert_eq!(operation, "borrows"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    for operation in [
+        PausableOperation::CollateralLocks,
+        PausableOperation::CollateralUnlocks,
+        PausableOperation::Liquidations,
+        PausableOperation::EpochOperations,
+    ] {
+        let msg = ExecuteMsg::SetPaused {
+            operation,
+            paused: true,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => (),
+            _ => panic!("DO NOT ENTER HERE"),
+        }
+        let _res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg).unwrap();
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap();
+    let status: PauseStatusResponse = from_json(&res).unwrap();
+    assert_eq!(
+        status,
+        PauseStatusResponse {
+            guardian: Some("guardian".to_string()),
+            paused: vec![
+                PausableOperation::CollateralLocks,
+                PausableOperation::CollateralUnlocks,
+                PausableOperation::Liquidations,
+                PausableOperation::EpochOperations,
+            ],
         }
     );
+
+    let msg = ExecuteMsg::LockCollateral {
+        borrower: "addr0000".to_string(),
+        collaterals: vec![("bsei".to_string(), Uint256::from(1000000u64))],
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("custody_bsei", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "collateral_locks"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::UnlockCollateral {
+        collaterals: vec![("bsei".to_string(), Uint256::from(1000000u64))],
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "collateral_unlocks"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::LiquidateCollateral {
+        borrower: "addr0000".to_string(),
+        price_update: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "liquidations"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let msg = ExecuteMsg::ExecuteEpochOperations {};
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res {
+        Err(ContractError::Paused(operation)) => assert_eq!(operation, "epoch_operations"),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // removing the guardian keeps the pause flags
+    let msg = ExecuteMsg::UpdateGuardian { guardian: None };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetPaused {
+        operation: PausableOperation::Liquidations,
+        paused: false,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("guardian", &[]), msg);
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap();
+    let status: PauseStatusResponse = from_json(&res).unwrap();
+    assert_eq!(status.guardian, None);
+    assert_eq!(status.paused.len(), 4);
+}
+
+#[test]
+fn shutdown() {
+    let mut deps = mock_dependencies(&[]);
+
+    let info = mock_info("owner", &[]);
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        kpt_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+        dyn_rate_epoch: 86400u64,
+        dyn_rate_maxchange: Decimal256::from_str("0.03").unwrap(),
+        dyn_rate_yr_increase_expectation: Decimal256::from_str("0.01").unwrap(),
+        dyn_rate_min: Decimal256::zero(),
+        dyn_rate_max: Decimal256::one(),
+    };
+
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let batom_collat_token = deps
+        .api
+        .addr_humanize(&CanonicalAddr::from(vec![
+            1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ]))
+        .unwrap()
+        .to_string();
+
+    let bsei_collat_token = deps
+        .api
+        .addr_humanize(&CanonicalAddr::from(vec![
+            1, 1, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ]))
+        .unwrap()
+        .to_string();
+
+    // store whitelist elems
+    let msg = ExecuteMsg::Whitelist {
+        name: "bsei".to_string(),
+        symbol: "bsei".to_string(),
+        collateral_token: bsei_collat_token.clone(),
+        custody_contract: "custody_bsei".to_string(),
+        max_ltv: Decimal256::percent(60),
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "batom".to_string(),
+        symbol: "batom".to_string(),
+        collateral_token: batom_collat_token.clone(),
+        custody_contract: "custody_batom".to_string(),
+        max_ltv: Decimal256::percent(60),
+    };
+    let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let collaterals = vec![
+        (bsei_collat_token.clone(), Uint256::from(1000000u64)),
+        (batom_collat_token.clone(), Uint256::from(10000000u64)),
+    ];
+    _lock_collateral(
+        deps.as_mut(),
+        mock_info("custody_batom", &[]),
+        "addr0000".to_string(),
+        collaterals,
+    )
+    .unwrap();
+
+    deps.querier.with_oracle_price(&[
+        (
+            &(bsei_collat_token.clone()),
+            &(
+                Decimal256::from_ratio(1000u64, 1u64),
+                1000,
+                Decimal256::from_ratio(1000u64, 1u64),
+                1000,
+                env.block.time.seconds(),
+                env.block.time.seconds(),
+            ),
+        ),
+        (
+            &(batom_collat_token.clone()),
+            &(
+                Decimal256::from_ratio(2000u64, 1u64),
+                2000,
+                Decimal256::from_ratio(2000u64, 1u64),
+                2000,
+                env.block.time.seconds(),
+                env.block.time.seconds(),
+            ),
+        ),
+    ]);
+    deps.querier
+        .with_loan_amount(&[(&"addr0000".to_string(), &Uint256::from(20500000000u64))]);
+
+    let msg = ExecuteMsg::SettleBorrower {
+        borrower: "addr0000".to_string(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res {
+        Err(ContractError::NotShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // a tripped circuit breaker does not hold the shutdown back
+    deps.querier.with_frozen_oracle_prices(&[&bsei_collat_token]);
+
+    // only the owner shuts the protocol down
+    let msg = ExecuteMsg::Shutdown {
+        settlement_period: 100,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "market".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&MarketExecuteMsg::Shutdown {
+                    collateral_tokens: vec![batom_collat_token.clone(), bsei_collat_token.clone()],
+                    settlement_period: 100,
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&CustodyExecuteMsg::Shutdown {}).unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_bsei".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&CustodyExecuteMsg::Shutdown {}).unwrap(),
+            })),
+        ]
+    );
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::ShutdownState {}).unwrap();
+    let shutdown_state: ShutdownStateResponse = from_json(&res).unwrap();
+    assert_eq!(
+        shutdown_state,
+        ShutdownStateResponse {
+            shutdown_height: Some(env.block.height),
+            prices: vec![
+                (batom_collat_token.clone(), Decimal256::from_ratio(2000u64, 1u64)),
+                (bsei_collat_token.clone(), Decimal256::from_ratio(1000u64, 1u64)),
+            ],
+        }
+    );
+
+    let msg = ExecuteMsg::LockCollateral {
+        borrower: "addr0000".to_string(),
+        collaterals: vec![(bsei_collat_token.clone(), Uint256::from(1000000u64))],
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("custody_bsei", &[]), msg);
+    match res {
+        Err(ContractError::ShutDown {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // the borrow limit keeps using the frozen prices
+    deps.querier.with_oracle_price(&[
+        (
+            &(bsei_collat_token.clone()),
+            &(
+                Decimal256::one(),
+                1,
+                Decimal256::one(),
+                1,
+                env.block.time.seconds(),
+                env.block.time.seconds(),
+            ),
+        ),
+        (
+            &(batom_collat_token.clone()),
+            &(
+                Decimal256::one(),
+                1,
+                Decimal256::one(),
+                1,
+                env.block.time.seconds(),
+                env.block.time.seconds(),
+            ),
+        ),
+    ]);
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::BorrowLimit {
+            borrower: "addr0000".to_string(),
+            block_time: None,
+        },
+    )
+    .unwrap();
+    let borrow_limit_res: BorrowLimitResponse = from_json(&res).unwrap();
+    assert_eq!(borrow_limit_res.borrow_limit, Uint256::from(12600000000u64));
+
+    // 20,000,000,000 of batom and 500,000,000 of bsei settle the loan
+    let msg = ExecuteMsg::SettleBorrower {
+        borrower: "addr0000".to_string(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_batom".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&CustodyExecuteMsg::SettleCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(10000000u64),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "custody_bsei".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&CustodyExecuteMsg::SettleCollateral {
+                    borrower: "addr0000".to_string(),
+                    amount: Uint256::from(500000u64),
+                })
+                .unwrap(),
+            })),
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "market".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&MarketExecuteMsg::SettleLoan {
+                    borrower: "addr0000".to_string(),
+                })
+                .unwrap(),
+            })),
+        ]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "settle_borrower"),
+            attr("borrower", "addr0000"),
+            attr("loan_amount", "20500000000"),
+            attr("shortfall", "0"),
+        ]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Collaterals {
+            borrower: "addr0000".to_string(),
+        },
+    )
+    .unwrap();
+    let collaterals_res: CollateralsResponse = from_json(&res).unwrap();
+    assert_eq!(
+        collaterals_res.collaterals,
+        vec![(bsei_collat_token, Uint256::from(500000u64))]
+    );
+}
+
+#[test]
+fn config_history() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        kpt_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+        dyn_rate_epoch: 8600u64,
+        dyn_rate_maxchange: Decimal256::permille(5),
+        dyn_rate_yr_increase_expectation: Decimal256::permille(1),
+        dyn_rate_min: Decimal256::from_ratio(1000000000000u64, 1000000000000000000u64),
+        dyn_rate_max: Decimal256::from_ratio(1200000000000u64, 1000000000000000000u64),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateConfig {
+        oracle_contract: Some("oracle1".to_string()),
+        liquidation_contract: None,
+        threshold_deposit_rate: None,
+        target_deposit_rate: None,
+        buffer_distribution_factor: None,
+        kpt_purchase_factor: None,
+        epoch_period: Some(86400u64),
+        price_timeframe: Some(120u64),
+        dyn_rate_epoch: None,
+        dyn_rate_maxchange: None,
+        dyn_rate_yr_increase_expectation: None,
+        dyn_rate_min: None,
+        dyn_rate_max: None,
+        frozen_liquidation_policy: None,
+        liquidation_cooldown: None,
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "bsei".to_string(),
+        symbol: "bsei".to_string(),
+        collateral_token: "bsei".to_string(),
+        custody_contract: "custody".to_string(),
+        max_ltv: Decimal256::percent(60),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::UpdateWhitelist {
+        collateral_token: "bsei".to_string(),
+        custody_contract: None,
+        max_ltv: Some(Decimal256::percent(50)),
+    };
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ConfigHistory {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let history: ConfigHistoryResponse = from_json(&res).unwrap();
+    let change = |field: &str, old_value: &str, new_value: &str| ConfigChange {
+        field: field.to_string(),
+        old_value: old_value.to_string(),
+        new_value: new_value.to_string(),
+    };
+    assert_eq!(
+        history.entries,
+        vec![
+            ConfigHistoryEntryResponse {
+                id: 0,
+                action: "update_config".to_string(),
+                sender: "owner".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![
+                    change("oracle_contract", "\"oracle\"", "\"oracle1\""),
+                    change("price_timeframe", "60", "120"),
+                ],
+            },
+            ConfigHistoryEntryResponse {
+                id: 1,
+                action: "register_whitelist".to_string(),
+                sender: "owner".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![
+                    change("bsei.name", "null", "\"bsei\""),
+                    change("bsei.symbol", "null", "\"bsei\""),
+                    change("bsei.custody_contract", "null", "\"custody\""),
+                    change("bsei.max_ltv", "null", "\"0.6\""),
+                ],
+            },
+            ConfigHistoryEntryResponse {
+                id: 2,
+                action: "update_whitelist".to_string(),
+                sender: "owner".to_string(),
+                block_height: mock_env().block.height,
+                changes: vec![change("bsei.max_ltv", "\"0.6\"", "\"0.5\"")],
+            },
+        ]
+    );
+}
+
+#[test]
+fn migrate_legacy_state() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        owner_addr: "owner".to_string(),
+        oracle_contract: "oracle".to_string(),
+        market_contract: "market".to_string(),
+        liquidation_contract: "liquidation".to_string(),
+        collector_contract: "collector".to_string(),
+        stable_denom: "uusd".to_string(),
+        epoch_period: 86400u64,
+        threshold_deposit_rate: Decimal256::permille(3),
+        target_deposit_rate: Decimal256::permille(5),
+        buffer_distribution_factor: Decimal256::percent(20),
+        kpt_purchase_factor: Decimal256::percent(20),
+        price_timeframe: 60u64,
+        dyn_rate_epoch: 86400u64,
+        dyn_rate_maxchange: Decimal256::from_str("0.03").unwrap(),
+        dyn_rate_yr_increase_expectation: Decimal256::from_str("0.01").unwrap(),
+        dyn_rate_min: Decimal256::zero(),
+        dyn_rate_max: Decimal256::one(),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Whitelist {
+        name: "bsei".to_string(),
+        symbol: "bsei".to_string(),
+        collateral_token: "bsei".to_string(),
+        custody_contract: "custody_bsei".to_string(),
+        max_ltv: Decimal256::percent(60),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+
+    let lock_msg = ExecuteMsg::LockCollateral {
+        borrower: "addr0000".to_string(),
+        collaterals: vec![("bsei".to_string(), Uint256::from(1000u64))],
+    };
+    let info = mock_info("custody_bsei", &[]);
+    execute(deps.as_mut(), mock_env(), info.clone(), lock_msg.clone()).unwrap();
+
+    // rewrite to the layout of releases without cw2 info: singletons and no
+    // custody index, the whitelist and collateral buckets are left as is
+    for key in [
+        &b"config"[..],
+        b"dynrate_config",
+        b"epoch_state",
+        b"dynrate_state",
+        b"newowner",
+    ] {
+        let value = deps.storage.get(key).unwrap();
+        deps.storage.remove(key);
+        deps.storage.set(&to_length_prefixed(key), &value);
+    }
+    let custody_raw = deps.api.addr_canonicalize("custody_bsei").unwrap();
+    deps.storage
+        .remove(&[to_length_prefixed(b"whitelist__custody"), custody_raw.to_vec()].concat());
+    deps.storage.remove(b"contract_info");
+
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), lock_msg.clone());
+    assert!(res.is_err());
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1], attr("previous_version", "none"));
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let config_res: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(config_res.owner_addr, "owner");
+
+    // the custody index is rebuilt and the collaterals are read in place
+    execute(deps.as_mut(), mock_env(), info, lock_msg).unwrap();
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Collaterals {
+            borrower: "addr0000".to_string(),
+        },
+    )
+    .unwrap();
+    let collaterals_res: CollateralsResponse = from_json(&res).unwrap();
+    assert_eq!(
+        collaterals_res.collaterals,
+        vec![("bsei".to_string(), Uint256::from(2000u64))]
+    );
 }
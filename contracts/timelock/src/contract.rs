@@ -0,0 +1,292 @@
+use crate::error::ContractError;
+use crate::state::{
+    read_action, read_actions, read_config, read_new_owner, read_next_action_id, remove_action,
+    store_action, store_config, store_new_owner, store_next_action_id, Action, Config,
+    NewOwnerAddr,
+};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+
+use cosmwasm_std::{
+    attr, to_json_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
+    StdResult, WasmMsg,
+};
+use cw2::set_contract_version;
+use moneymarket::timelock::{
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, QueuedActionResponse,
+    QueuedActionsResponse,
+};
+use moneymarket::migration::assert_migrate_version;
+
+const CONTRACT_NAME: &str = "crates.io:moneymarket-timelock";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    store_config(
+        deps.storage,
+        &Config {
+            owner: deps.api.addr_canonicalize(&msg.owner)?,
+            delay: msg.delay,
+            grace_period: msg.grace_period,
+        },
+    )?;
+
+    store_new_owner(deps.storage, &{
+        NewOwnerAddr {
+            new_owner_addr: deps.api.addr_canonicalize(&msg.owner)?,
+        }
+    })?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::QueueAction { target, msg, eta } => {
+            let api = deps.api;
+            queue_action(deps, env, info, api.addr_validate(&target)?, msg, eta)
+        }
+        ExecuteMsg::CancelAction { action_id } => cancel_action(deps, info, action_id),
+        ExecuteMsg::ExecuteAction { action_id } => execute_action(deps, env, info, action_id),
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, info),
+        ExecuteMsg::UpdateConfig {
+            delay,
+            grace_period,
+        } => update_config(deps, env, info, delay, grace_period),
+        ExecuteMsg::SetOwner { new_owner_addr } => {
+            let api = deps.api;
+            set_new_owner(deps, env, info, api.addr_validate(&new_owner_addr)?)
+        }
+    }
+}
+
+pub fn queue_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    target: Addr,
+    msg: Binary,
+    eta: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let min_eta = env.block.time.seconds() + config.delay;
+    if eta < min_eta {
+        return Err(ContractError::EtaTooEarly(min_eta));
+    }
+
+    let action_id = read_next_action_id(deps.storage)?;
+    store_action(
+        deps.storage,
+        action_id,
+        &Action {
+            target: deps.api.addr_canonicalize(target.as_str())?,
+            msg,
+            eta,
+        },
+    )?;
+    store_next_action_id(deps.storage, action_id + 1)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "queue_action"),
+        attr("action_id", action_id.to_string()),
+        attr("target", target),
+        attr("eta", eta.to_string()),
+    ]))
+}
+
+pub fn cancel_action(
+    deps: DepsMut,
+    info: MessageInfo,
+    action_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // make sure the action exists
+    read_action(deps.storage, action_id)?;
+    remove_action(deps.storage, action_id);
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "cancel_action"),
+        attr("action_id", action_id.to_string()),
+    ]))
+}
+
+pub fn execute_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    action_id: u64,
+) -> Result<Response, ContractError> {
+    let config: Config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let action: Action = read_action(deps.storage, action_id)?;
+    let now = env.block.time.seconds();
+    if now < action.eta {
+        return Err(ContractError::ActionNotReady(action.eta));
+    }
+
+    let expiry = action.eta + config.grace_period;
+    if now > expiry {
+        return Err(ContractError::ActionExpired(expiry));
+    }
+
+    remove_action(deps.storage, action_id);
+
+    let target = deps.api.addr_humanize(&action.target)?;
+    Ok(Response::new()
+        .add_message(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: target.to_string(),
+            funds: vec![],
+            msg: action.msg,
+        }))
+        .add_attributes(vec![
+            attr("action", "execute_action"),
+            attr("action_id", action_id.to_string()),
+            attr("target", target),
+        ]))
+}
+
+/// Only the timelock itself, through a queued action
+pub fn update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    delay: Option<u64>,
+    grace_period: Option<u64>,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut config: Config = read_config(deps.storage)?;
+    if let Some(delay) = delay {
+        config.delay = delay;
+    }
+
+    if let Some(grace_period) = grace_period {
+        config.grace_period = grace_period;
+    }
+
+    store_config(deps.storage, &config)?;
+    Ok(Response::new().add_attributes(vec![attr("action", "update_config")]))
+}
+
+/// Only the timelock itself, through a queued action
+pub fn set_new_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_owner_addr: Addr,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut new_owner = read_new_owner(deps.as_ref().storage)?;
+    new_owner.new_owner_addr = deps.api.addr_canonicalize(new_owner_addr.as_ref())?;
+    store_new_owner(deps.storage, &new_owner)?;
+
+    Ok(Response::default())
+}
+
+pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let new_owner = read_new_owner(deps.as_ref().storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_ref())?;
+    let mut config = read_config(deps.as_ref().storage)?;
+    if sender_raw != new_owner.new_owner_addr {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.owner = new_owner.new_owner_addr;
+    store_config(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::QueuedAction { action_id } => {
+            to_json_binary(&query_queued_action(deps, action_id)?)
+        }
+        QueryMsg::QueuedActions { start_after, limit } => {
+            to_json_binary(&query_queued_actions(deps, start_after, limit)?)
+        }
+    }
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config: Config = read_config(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: deps.api.addr_humanize(&config.owner)?.to_string(),
+        delay: config.delay,
+        grace_period: config.grace_period,
+    })
+}
+
+pub fn query_queued_action(deps: Deps, action_id: u64) -> StdResult<QueuedActionResponse> {
+    let action: Action = read_action(deps.storage, action_id)?;
+    to_action_response(deps, action_id, action)
+}
+
+pub fn query_queued_actions(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<QueuedActionsResponse> {
+    let actions = read_actions(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(action_id, action)| to_action_response(deps, action_id, action))
+        .collect::<StdResult<Vec<QueuedActionResponse>>>()?;
+    Ok(QueuedActionsResponse { actions })
+}
+
+fn to_action_response(
+    deps: Deps,
+    action_id: u64,
+    action: Action,
+) -> StdResult<QueuedActionResponse> {
+    Ok(QueuedActionResponse {
+        action_id,
+        target: deps.api.addr_humanize(&action.target)?.to_string(),
+        msg: action.msg,
+        eta: action.eta,
+    })
+}
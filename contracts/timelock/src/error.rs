@@ -0,0 +1,23 @@
+use cosmwasm_std::{OverflowError, StdError};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    OverflowError(#[from] OverflowError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Eta must be at least {0}")]
+    EtaTooEarly(u64),
+
+    #[error("Action cannot be executed before {0}")]
+    ActionNotReady(u64),
+
+    #[error("Action expired at {0}")]
+    ActionExpired(u64),
+}
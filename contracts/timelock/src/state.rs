@@ -0,0 +1,85 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{Binary, CanonicalAddr, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, Item, Map};
+
+const CONFIG: Item<Config> = Item::new("config");
+const NEWOWNER: Item<NewOwnerAddr> = Item::new("newowner");
+const NEXT_ACTION_ID: Item<u64> = Item::new("next_action_id");
+
+const ACTIONS: Map<u64, Action> = Map::new("action");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub owner: CanonicalAddr,
+    pub delay: u64,
+    pub grace_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Action {
+    pub target: CanonicalAddr,
+    pub msg: Binary,
+    pub eta: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NewOwnerAddr {
+    pub new_owner_addr: CanonicalAddr,
+}
+
+pub fn store_new_owner(storage: &mut dyn Storage, data: &NewOwnerAddr) -> StdResult<()> {
+    NEWOWNER.save(storage, data)
+}
+
+pub fn read_new_owner(storage: &dyn Storage) -> StdResult<NewOwnerAddr> {
+    NEWOWNER.load(storage)
+}
+
+pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
+    CONFIG.save(storage, config)
+}
+
+pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
+    CONFIG.load(storage)
+}
+
+pub fn store_next_action_id(storage: &mut dyn Storage, action_id: u64) -> StdResult<()> {
+    NEXT_ACTION_ID.save(storage, &action_id)
+}
+
+pub fn read_next_action_id(storage: &dyn Storage) -> StdResult<u64> {
+    Ok(NEXT_ACTION_ID.may_load(storage)?.unwrap_or(0))
+}
+
+pub fn store_action(storage: &mut dyn Storage, action_id: u64, action: &Action) -> StdResult<()> {
+    ACTIONS.save(storage, action_id, action)
+}
+
+pub fn remove_action(storage: &mut dyn Storage, action_id: u64) {
+    ACTIONS.remove(storage, action_id)
+}
+
+pub fn read_action(storage: &dyn Storage, action_id: u64) -> StdResult<Action> {
+    ACTIONS.load(storage, action_id)
+}
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Queued actions in queue order, oldest first
+pub fn read_actions(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, Action)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    ACTIONS
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
@@ -0,0 +1,249 @@
+use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
+use cosmwasm_std::testing::{
+    mock_dependencies_with_balance, mock_env, mock_info, MOCK_CONTRACT_ADDR,
+};
+use cosmwasm_std::{attr, from_json, to_json_binary, CosmosMsg, StdError, SubMsg, WasmMsg};
+use moneymarket::market::ExecuteMsg as MarketExecuteMsg;
+use moneymarket::timelock::{
+    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, QueuedActionResponse,
+    QueuedActionsResponse,
+};
+
+#[test]
+fn proper_initialization() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        delay: 172800u64,
+        grace_period: 86400u64,
+    };
+
+    let info = mock_info("addr0000", &[]);
+
+    // we can just call .unwrap() to assert this was a success
+    let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(0, res.messages.len());
+
+    // it worked, let's query the state
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(
+        value,
+        ConfigResponse {
+            owner: "owner0000".to_string(),
+            delay: 172800u64,
+            grace_period: 86400u64,
+        }
+    );
+}
+
+#[test]
+fn queue_and_execute_action() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        delay: 172800u64,
+        grace_period: 86400u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let now = mock_env().block.time.seconds();
+    let market_msg = to_json_binary(&MarketExecuteMsg::UpdateConfig {
+        interest_model: None,
+        distribution_model: None,
+        max_borrow_factor: None,
+        interest_model_hook: None,
+        depositor_reward_share: None,
+        max_total_liabilities: None,
+        max_total_deposits: None,
+    })
+    .unwrap();
+
+    // only the owner queues actions
+    let msg = ExecuteMsg::QueueAction {
+        target: "market".to_string(),
+        msg: market_msg.clone(),
+        eta: now + 172800u64,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let early_msg = ExecuteMsg::QueueAction {
+        target: "market".to_string(),
+        msg: market_msg.clone(),
+        eta: now + 172799u64,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), early_msg);
+    match res {
+        Err(ContractError::EtaTooEarly(min_eta)) => assert_eq!(min_eta, now + 172800u64),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg.clone()).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "queue_action"),
+            attr("action_id", "0"),
+            attr("target", "market"),
+            attr("eta", (now + 172800u64).to_string()),
+        ]
+    );
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::QueuedActions {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: QueuedActionsResponse = from_json(&res).unwrap();
+    assert_eq!(value.actions.len(), 2);
+    assert_eq!(
+        value.actions[0],
+        QueuedActionResponse {
+            action_id: 0,
+            target: "market".to_string(),
+            msg: market_msg.clone(),
+            eta: now + 172800u64,
+        }
+    );
+
+    // cancel the second action
+    let msg = ExecuteMsg::CancelAction { action_id: 1 };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::QueuedActions {
+            start_after: Some(0),
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: QueuedActionsResponse = from_json(&res).unwrap();
+    assert_eq!(value.actions, vec![]);
+
+    // the last possible id does not overflow the page bound
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::QueuedActions {
+            start_after: Some(u64::MAX),
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: QueuedActionsResponse = from_json(&res).unwrap();
+    assert_eq!(value.actions, vec![]);
+
+    // cannot execute before the eta
+    let msg = ExecuteMsg::ExecuteAction { action_id: 0 };
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(172799u64);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::ActionNotReady(eta)) => assert_eq!(eta, now + 172800u64),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    // nor after the grace period
+    env.block.time = env.block.time.plus_seconds(86402u64);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::ActionExpired(expiry)) => {
+            assert_eq!(expiry, now + 172800u64 + 86400u64)
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    env.block.time = env.block.time.minus_seconds(2u64);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("owner0000", &[]), msg.clone());
+    let res = res.unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "market".to_string(),
+            funds: vec![],
+            msg: market_msg,
+        }))]
+    );
+
+    // the action is gone once executed
+    let res = execute(deps.as_mut(), env, mock_info("owner0000", &[]), msg);
+    match res {
+        Err(ContractError::Std(StdError::NotFound { .. })) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn timelocked_operations() {
+    let mut deps = mock_dependencies_with_balance(&[]);
+
+    let msg = InstantiateMsg {
+        owner: "owner0000".to_string(),
+        delay: 172800u64,
+        grace_period: 86400u64,
+    };
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // the owner cannot bypass the delay
+    let msg = ExecuteMsg::UpdateConfig {
+        delay: Some(0u64),
+        grace_period: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(MOCK_CONTRACT_ADDR, &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::SetOwner {
+        new_owner_addr: "owner0001".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info(MOCK_CONTRACT_ADDR, &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::AcceptOwnership {};
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg.clone());
+    match res {
+        Err(ContractError::Unauthorized {}) => (),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+    let _res = execute(deps.as_mut(), mock_env(), mock_info("owner0001", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap();
+    let value: ConfigResponse = from_json(&res).unwrap();
+    assert_eq!(
+        value,
+        ConfigResponse {
+            owner: "owner0001".to_string(),
+            delay: 0u64,
+            grace_period: 86400u64,
+        }
+    );
+}
@@ -4,6 +4,7 @@ use cosmwasm_std::{
 };
 use cw20::{AllowanceResponse, Cw20ReceiveMsg, Expiration};
 
+use crate::contract::balance_hook_msg;
 use crate::error::ContractError;
 use crate::state::{ALLOWANCES, BALANCES, TOKEN_INFO};
 
@@ -145,6 +146,8 @@ pub fn execute_transfer_from(
         amount,
     )?;
 
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&owner_addr, &rcpt_addr])?;
+
     BALANCES.update(
         deps.storage,
         deps.api
@@ -162,7 +165,7 @@ pub fn execute_transfer_from(
         |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
     )?;
 
-    Ok(Response::new().add_attributes(vec![
+    Ok(Response::new().add_messages(hook_msg).add_attributes(vec![
         attr("action", "transfer_from"),
         attr("from", owner),
         attr("to", recipient),
@@ -191,6 +194,8 @@ pub fn execute_burn_from(
         amount,
     )?;
 
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&owner_addr])?;
+
     // lower balance
     BALANCES.update(
         deps.storage,
@@ -207,7 +212,7 @@ pub fn execute_burn_from(
         Ok(meta)
     })?;
 
-    Ok(Response::new().add_attributes(vec![
+    Ok(Response::new().add_messages(hook_msg).add_attributes(vec![
         attr("action", "burn_from"),
         attr("from", owner),
         attr("by", info.sender),
@@ -237,6 +242,8 @@ pub fn execute_send_from(
         amount,
     )?;
 
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&owner_addr, &rcpt_addr])?;
+
     // move the tokens to the contract
     BALANCES.update(
         deps.storage,
@@ -263,7 +270,7 @@ pub fn execute_send_from(
         attr("amount", amount),
     ];
 
-    Ok(Response::new().add_attributes(attrs).add_message(
+    Ok(Response::new().add_attributes(attrs).add_messages(hook_msg).add_message(
         // create a send message
         Cw20ReceiveMsg {
             sender: info.sender.into(),
@@ -321,6 +328,7 @@ mod tests {
                 amount,
             }],
             mint: None,
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
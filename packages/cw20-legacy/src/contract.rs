@@ -1,8 +1,8 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
-    Uint128,
+    attr, to_json_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
+    StdError, StdResult, Uint128, WasmMsg,
 };
 
 use cw2::set_contract_version;
@@ -14,8 +14,8 @@ use crate::allowances::{
 };
 use crate::enumerable::{query_all_accounts, query_all_allowances};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{MinterData, TokenInfo, BALANCES, TOKEN_INFO};
+use crate::msg::{BalanceHookMsg, ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::state::{MinterData, TokenInfo, BALANCES, BALANCE_HOOK, TOKEN_INFO};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw20-base";
@@ -57,6 +57,12 @@ pub fn instantiate(
         mint,
     };
     TOKEN_INFO.save(deps.storage, &data)?;
+
+    if let Some(balance_hook) = msg.balance_hook {
+        let balance_hook = deps.api.addr_validate(&balance_hook)?;
+        BALANCE_HOOK.save(deps.storage, &deps.api.addr_canonicalize(balance_hook.as_str())?)?;
+    }
+
     Ok(Response::default())
 }
 
@@ -110,7 +116,70 @@ pub fn execute(
             amount,
             msg,
         } => execute_send_from(deps, env, info, owner, contract, amount, msg),
+        ExecuteMsg::UpdateBalanceHook { balance_hook } => {
+            execute_update_balance_hook(deps, env, info, balance_hook)
+        }
+    }
+}
+
+/// Builds the balance hook notification, if a hook is registered.
+/// Must be called before `addresses` balances or the total supply are updated.
+pub fn balance_hook_msg(deps: Deps, addresses: &[&Addr]) -> StdResult<Option<CosmosMsg>> {
+    let balance_hook = match BALANCE_HOOK.may_load(deps.storage)? {
+        Some(balance_hook) => balance_hook,
+        None => return Ok(None),
+    };
+
+    let mut balances_before: Vec<Cw20Coin> = vec![];
+    for address in addresses {
+        let amount = BALANCES
+            .may_load(
+                deps.storage,
+                deps.api.addr_canonicalize(address.as_str())?.as_slice(),
+            )?
+            .unwrap_or_default();
+        balances_before.push(Cw20Coin {
+            address: address.to_string(),
+            amount,
+        });
     }
+
+    Ok(Some(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: deps.api.addr_humanize(&balance_hook)?.to_string(),
+        funds: vec![],
+        msg: to_json_binary(&BalanceHookMsg::BalanceChangeHook {
+            balances_before,
+            total_supply_before: TOKEN_INFO.load(deps.storage)?.total_supply,
+        })?,
+    })))
+}
+
+pub fn execute_update_balance_hook(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    balance_hook: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = TOKEN_INFO.load(deps.storage)?;
+    if config.mint.is_none()
+        || config.mint.as_ref().unwrap().minter
+            != deps.api.addr_canonicalize(info.sender.as_str())?
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match &balance_hook {
+        Some(balance_hook) => {
+            let balance_hook = deps.api.addr_validate(balance_hook)?;
+            BALANCE_HOOK.save(deps.storage, &deps.api.addr_canonicalize(balance_hook.as_str())?)?;
+        }
+        None => BALANCE_HOOK.remove(deps.storage),
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_balance_hook"),
+        attr("balance_hook", balance_hook.unwrap_or_default()),
+    ]))
 }
 
 pub fn execute_transfer(
@@ -125,6 +194,7 @@ pub fn execute_transfer(
     }
 
     let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&info.sender, &rcpt_addr])?;
 
     BALANCES.update(
         deps.storage,
@@ -143,7 +213,7 @@ pub fn execute_transfer(
         |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
     )?;
 
-    Ok(Response::new().add_attributes(vec![
+    Ok(Response::new().add_messages(hook_msg).add_attributes(vec![
         attr("action", "transfer"),
         attr("from", info.sender),
         attr("to", recipient),
@@ -161,6 +231,8 @@ pub fn execute_burn(
         return Err(ContractError::InvalidZeroAmount {});
     }
 
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&info.sender])?;
+
     // lower balance
     BALANCES.update(
         deps.storage,
@@ -177,7 +249,7 @@ pub fn execute_burn(
         Ok(info)
     })?;
 
-    Ok(Response::new().add_attributes(vec![
+    Ok(Response::new().add_messages(hook_msg).add_attributes(vec![
         attr("action", "burn"),
         attr("from", info.sender),
         attr("amount", amount),
@@ -203,6 +275,9 @@ pub fn execute_mint(
         return Err(ContractError::Unauthorized {});
     }
 
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&rcpt_addr])?;
+
     // update supply and enforce cap
     config.total_supply += amount;
     if let Some(limit) = config.get_cap() {
@@ -213,7 +288,6 @@ pub fn execute_mint(
     TOKEN_INFO.save(deps.storage, &config)?;
 
     // add amount to recipient balance
-    let rcpt_addr = deps.api.addr_validate(&recipient)?;
     BALANCES.update(
         deps.storage,
         deps.api
@@ -222,7 +296,7 @@ pub fn execute_mint(
         |balance: Option<Uint128>| -> StdResult<_> { Ok(balance.unwrap_or_default() + amount) },
     )?;
 
-    Ok(Response::new().add_attributes(vec![
+    Ok(Response::new().add_messages(hook_msg).add_attributes(vec![
         attr("action", "mint"),
         attr("to", recipient),
         attr("amount", amount),
@@ -242,6 +316,7 @@ pub fn execute_send(
     }
 
     let rcpt_addr = deps.api.addr_validate(&contract)?;
+    let hook_msg = balance_hook_msg(deps.as_ref(), &[&info.sender, &rcpt_addr])?;
 
     // move the tokens to the contract
     BALANCES.update(
@@ -268,7 +343,7 @@ pub fn execute_send(
         attr("amount", amount),
     ];
 
-    Ok(Response::new().add_attributes(attrs).add_message(
+    Ok(Response::new().add_attributes(attrs).add_messages(hook_msg).add_message(
         Cw20ReceiveMsg {
             sender: info.sender.into(),
             amount,
@@ -380,6 +455,7 @@ mod tests {
                 amount,
             }],
             mint: mint.clone(),
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -414,6 +490,7 @@ mod tests {
                 amount,
             }],
             mint: None,
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -453,6 +530,7 @@ mod tests {
                 minter: minter.clone(),
                 cap: Some(limit),
             }),
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -499,6 +577,7 @@ mod tests {
                 minter,
                 cap: Some(limit),
             }),
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -614,6 +693,7 @@ mod tests {
                 },
             ],
             mint: None,
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -852,4 +932,134 @@ mod tests {
             amount1
         );
     }
+
+    #[test]
+    fn balance_hook() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let addr1 = String::from("addr0001");
+        let addr2 = String::from("addr0002");
+        let amount1 = Uint128::from(12340000u128);
+        let transfer = Uint128::from(76543u128);
+
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr1.clone(),
+                amount: amount1,
+            }],
+            mint: Some(MinterResponse {
+                minter: "minter".to_string(),
+                cap: None,
+            }),
+            balance_hook: Some("hook".to_string()),
+        };
+        let info = mock_info("creator", &[]);
+        instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
+
+        // balances before the transfer are reported
+        let info = mock_info(addr1.as_ref(), &[]);
+        let msg = ExecuteMsg::Transfer {
+            recipient: addr2.clone(),
+            amount: transfer,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "hook".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&BalanceHookMsg::BalanceChangeHook {
+                    balances_before: vec![
+                        Cw20Coin {
+                            address: addr1.clone(),
+                            amount: amount1,
+                        },
+                        Cw20Coin {
+                            address: addr2.clone(),
+                            amount: Uint128::zero(),
+                        },
+                    ],
+                    total_supply_before: amount1,
+                })
+                .unwrap(),
+            }))]
+        );
+
+        // supply before the mint is reported
+        let info = mock_info("minter", &[]);
+        let msg = ExecuteMsg::Mint {
+            recipient: addr2.clone(),
+            amount: transfer,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "hook".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&BalanceHookMsg::BalanceChangeHook {
+                    balances_before: vec![Cw20Coin {
+                        address: addr2,
+                        amount: transfer,
+                    }],
+                    total_supply_before: amount1,
+                })
+                .unwrap(),
+            }))]
+        );
+        assert_eq!(
+            query_token_info(deps.as_ref()).unwrap().total_supply,
+            amount1 + transfer
+        );
+    }
+
+    #[test]
+    fn update_balance_hook() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+        let addr1 = String::from("addr0001");
+        let amount1 = Uint128::from(12340000u128);
+        do_instantiate_with_minter(deps.as_mut(), &addr1, amount1, "minter", None);
+
+        // only the minter can register the hook
+        let msg = ExecuteMsg::UpdateBalanceHook {
+            balance_hook: Some("hook".to_string()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(&addr1, &[]), msg.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(deps.as_mut(), mock_env(), mock_info("minter", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::Burn {
+            amount: Uint128::from(1u128),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(&addr1, &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "hook".to_string(),
+                funds: vec![],
+                msg: to_json_binary(&BalanceHookMsg::BalanceChangeHook {
+                    balances_before: vec![Cw20Coin {
+                        address: addr1.clone(),
+                        amount: amount1,
+                    }],
+                    total_supply_before: amount1,
+                })
+                .unwrap(),
+            }))]
+        );
+
+        // clearing the hook stops the notifications
+        let msg = ExecuteMsg::UpdateBalanceHook { balance_hook: None };
+        execute(deps.as_mut(), mock_env(), mock_info("minter", &[]), msg).unwrap();
+
+        let msg = ExecuteMsg::Burn {
+            amount: Uint128::from(1u128),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(&addr1, &[]), msg).unwrap();
+        assert!(res.messages.is_empty());
+    }
 }
@@ -98,6 +98,7 @@ mod tests {
                 amount,
             }],
             mint: None,
+            balance_hook: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
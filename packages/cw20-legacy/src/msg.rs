@@ -10,6 +10,9 @@ pub struct InstantiateMsg {
     pub decimals: u8,
     pub initial_balances: Vec<Cw20Coin>,
     pub mint: Option<MinterResponse>,
+    /// Contract notified with `BalanceHookMsg` whenever balances change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance_hook: Option<String>,
 }
 
 impl InstantiateMsg {
@@ -107,6 +110,20 @@ pub enum ExecuteMsg {
     },
     /// Only with "approval" extension. Destroys tokens forever
     BurnFrom { owner: String, amount: Uint128 },
+    /// Only with "mintable" extension. If authorized, sets or clears the contract
+    /// notified with `BalanceHookMsg` whenever balances change.
+    UpdateBalanceHook { balance_hook: Option<String> },
+}
+
+/// Sent to the balance hook contract right after balances change, carrying the
+/// balances and the total supply from before the change
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceHookMsg {
+    BalanceChangeHook {
+        balances_before: Vec<Cw20Coin>,
+        total_supply_before: Uint128,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -32,6 +32,7 @@ impl TokenInfo {
 pub const TOKEN_INFO: Item<TokenInfo> = Item::new("\u{0}\ntoken_info");
 pub const BALANCES: Map<&[u8], Uint128> = Map::new("balance");
 pub const ALLOWANCES: Map<(&[u8], &[u8]), AllowanceResponse> = Map::new("allowance");
+pub const BALANCE_HOOK: Item<CanonicalAddr> = Item::new("balance_hook");
 
 #[cfg(test)]
 mod test {
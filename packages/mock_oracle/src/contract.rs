@@ -1,11 +1,17 @@
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use crate::msg::{ExecuteMsg, InstantiateMsg, MockPriceUpdate, QueryMsg};
 use crate::state::{read_oracle_price, store_oracle_price};
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    from_json, to_json_binary, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Uint128,
 };
 use pyth_sdk::{Price, PriceFeed};
 use pyth_sdk_cw::{PriceFeedResponse, PriceIdentifier};
 
+pub const UPDATE_FEE_DENOM: &str = "usei";
+pub const UPDATE_FEE_PER_VAA: u128 = 1;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     _deps: DepsMut,
@@ -20,11 +26,12 @@ pub fn instantiate(
 pub fn execute(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> StdResult<Response> {
     match msg {
         ExecuteMsg::UpdatePriceFeed { id, price } => update_price_feed(deps, id, price),
+        ExecuteMsg::UpdatePriceFeeds { data } => update_price_feeds(deps, info, data),
     }
 }
 
@@ -32,6 +39,7 @@ pub fn execute(
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::PriceFeed { id } => to_json_binary(&query_price_feed(&deps, env, id)?),
+        QueryMsg::GetUpdateFee { vaas } => to_json_binary(&query_update_fee(&vaas)),
     }
 }
 
@@ -40,6 +48,37 @@ pub fn update_price_feed(deps: DepsMut, id: PriceIdentifier, price: i64) -> StdR
     Ok(Response::new().add_attribute("price", price.to_string()))
 }
 
+/// Apply the json encoded `MockPriceUpdate`s, charging `UPDATE_FEE_PER_VAA` for each entry
+pub fn update_price_feeds(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: Vec<Binary>,
+) -> StdResult<Response> {
+    let fee = query_update_fee(&data);
+    let sent = info
+        .funds
+        .iter()
+        .find(|c| c.denom == fee.denom)
+        .map(|c| c.amount)
+        .unwrap_or_else(Uint128::zero);
+    if sent < fee.amount {
+        return Err(StdError::generic_err("Insufficient fee"));
+    }
+
+    for update in data.iter() {
+        let update: MockPriceUpdate = from_json(update)?;
+        store_oracle_price(deps.storage, update.id.as_ref(), update.price)?;
+    }
+    Ok(Response::new().add_attribute("updated_feeds", data.len().to_string()))
+}
+
+pub fn query_update_fee(vaas: &[Binary]) -> Coin {
+    Coin {
+        denom: UPDATE_FEE_DENOM.to_string(),
+        amount: Uint128::from(UPDATE_FEE_PER_VAA * vaas.len() as u128),
+    }
+}
+
 /// Get the most recent value of the price feed indicated by `feed_id`.
 pub fn query_price_feed(
     deps: &Deps,
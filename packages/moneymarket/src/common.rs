@@ -3,7 +3,10 @@ use cosmwasm_std::{
     Uint128,
 };
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Expected number of blocks per year, used to annualize per block rates
+pub const BLOCKS_PER_YEAR: u128 = 4656810;
 
 pub fn optional_addr_validate(api: &dyn Api, addr: Option<String>) -> StdResult<Option<Addr>> {
     let addr = if let Some(addr) = addr {
@@ -15,6 +18,17 @@ pub fn optional_addr_validate(api: &dyn Api, addr: Option<String>) -> StdResult<
     Ok(addr)
 }
 
+/// Deserialize an optional config update whose value can be unset:
+/// a missing field leaves it unchanged, `null` clears it. Use along
+/// `#[serde(default, deserialize_with = "double_option")]`
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 /// QueryTaxWrapper is an override of QueryRequest::Custom for testing
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
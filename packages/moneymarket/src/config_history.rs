@@ -0,0 +1,113 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{to_json_string, Addr, Env, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, Item, Map};
+
+const CONFIG_HISTORY_COUNT: Item<u64> = Item::new("config_history_count");
+const CONFIG_HISTORY: Map<u64, ConfigHistoryEntry> = Map::new("config_history");
+
+// settings for pagination
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// A changed value, both sides serialized as JSON
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigChange {
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+/// One owner action, appended to the config history of the contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigHistoryEntry {
+    pub action: String,
+    pub sender: String,
+    pub block_height: u64,
+    pub changes: Vec<ConfigChange>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigHistoryEntryResponse {
+    pub id: u64,
+    pub action: String,
+    pub sender: String,
+    pub block_height: u64,
+    pub changes: Vec<ConfigChange>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigHistoryResponse {
+    pub entries: Vec<ConfigHistoryEntryResponse>,
+}
+
+/// `Some` change of `field` when `old` and `new` differ
+pub fn config_change<T: Serialize + PartialEq>(
+    field: &str,
+    old: &T,
+    new: &T,
+) -> StdResult<Option<ConfigChange>> {
+    if old == new {
+        return Ok(None);
+    }
+
+    Ok(Some(ConfigChange {
+        field: field.to_string(),
+        old_value: to_json_string(old)?,
+        new_value: to_json_string(new)?,
+    }))
+}
+
+/// Append an owner action to the config history. The changes are the
+/// `config_change` results, unchanged values are left out
+pub fn record_config_change(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    action: &str,
+    changes: Vec<Option<ConfigChange>>,
+) -> StdResult<()> {
+    let id: u64 = CONFIG_HISTORY_COUNT.may_load(storage)?.unwrap_or(0);
+
+    CONFIG_HISTORY.save(
+        storage,
+        id,
+        &ConfigHistoryEntry {
+            action: action.to_string(),
+            sender: sender.to_string(),
+            block_height: env.block.height,
+            changes: changes.into_iter().flatten().collect(),
+        },
+    )?;
+    CONFIG_HISTORY_COUNT.save(storage, &(id + 1))
+}
+
+/// Config history entries, oldest first
+pub fn query_config_history(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ConfigHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let entries = CONFIG_HISTORY
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|elem| {
+            let (id, v) = elem?;
+            Ok(ConfigHistoryEntryResponse {
+                id,
+                action: v.action,
+                sender: v.sender,
+                block_height: v.block_height,
+                changes: v.changes,
+            })
+        })
+        .collect::<StdResult<Vec<ConfigHistoryEntryResponse>>>()?;
+
+    Ok(ConfigHistoryResponse { entries })
+}
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use cosmwasm_bignumber::Uint256;
 use cw20::Cw20ReceiveMsg;
 
+use crate::pause::PausableOperation;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
@@ -47,6 +49,11 @@ pub enum ExecuteMsg {
     AcceptOwnership {
     },
 
+    /// Set or clear the guardian allowed to pause custody operations
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
+
     /// Make specified amount of tokens unspendable
     LockCollateral { borrower: String, amount: Uint256 },
     /// Make specified amount of collateral tokens spendable
@@ -62,6 +69,15 @@ pub enum ExecuteMsg {
         amount: Uint256,
     },
 
+    /// Stop taking collateral deposits and locks for good
+    Shutdown {},
+    /// After the shutdown, send locked collateral of `borrower`
+    /// to the market to settle its loan
+    SettleCollateral {
+        borrower: String,
+        amount: Uint256,
+    },
+
     ////////////////////
     /// User operations
     ////////////////////
@@ -81,6 +97,16 @@ pub enum ExecuteMsg {
         swap_denom: String,
         is_add: bool,
     },
+
+    ////////////////////
+    /// Guardian operations
+    ////////////////////
+    /// Pause or resume collateral deposits, locks and unlocks
+    /// or liquidations. Pausing unlocks also pauses withdrawals
+    SetPaused {
+        operation: PausableOperation,
+        paused: bool,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -105,6 +131,12 @@ pub enum QueryMsg {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    PauseStatus {},
+    /// Owner actions on the contract, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 // We define a custom struct for each query response
@@ -28,8 +28,31 @@ pub enum ExecuteMsg {
 
     AcceptOwnership {
     },
+
+    /// Replace the time based emission schedule. An empty phase list
+    /// falls back to the configured emission cap and floor
+    UpdateEmissionSchedule {
+        phases: Vec<EmissionPhase>,
+        total_budget: Option<Decimal256>,
+    },
+}
+
+/// Emission bounds applied from `start_time` until the next phase starts.
+/// The deposit rate feedback multipliers keep acting within these bounds.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionPhase {
+    /// Block time (in seconds) at which the phase starts
+    pub start_time: u64,
+    pub emission_cap: Decimal256,
+    pub emission_floor: Decimal256,
+    /// Halve the cap and floor every `halving_period` seconds since `start_time`
+    pub halving_period: Option<u64>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
@@ -39,6 +62,25 @@ pub enum QueryMsg {
         target_deposit_rate: Decimal256,
         threshold_deposit_rate: Decimal256,
         current_emission_rate: Decimal256,
+        /// KPT emitted so far, checked against the schedule budget
+        total_emitted: Option<Decimal256>,
+    },
+    EmissionSchedule {},
+    /// Project emissions over the next `epochs` epochs, assuming the
+    /// deposit rate stays within the target band
+    EmissionProjection {
+        current_emission_rate: Decimal256,
+        total_emitted: Option<Decimal256>,
+        /// Epoch length in blocks
+        epoch_period: u64,
+        /// Epoch length in seconds
+        epoch_duration: u64,
+        epochs: u32,
+    },
+    /// Owner actions on the contract, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
     },
 }
 
@@ -56,4 +98,30 @@ pub struct ConfigResponse {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct KptEmissionRateResponse {
     pub emission_rate: Decimal256,
+    /// Total KPT that can ever be emitted, if the schedule sets a budget
+    #[serde(default)]
+    pub total_budget: Option<Decimal256>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionScheduleResponse {
+    pub phases: Vec<EmissionPhase>,
+    pub total_budget: Option<Decimal256>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EpochEmission {
+    pub start_time: u64,
+    pub emission_rate: Decimal256,
+    /// KPT emitted during the epoch
+    pub emission: Decimal256,
+    /// KPT emitted at the end of the epoch
+    pub total_emitted: Decimal256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionProjectionResponse {
+    pub epochs: Vec<EpochEmission>,
 }
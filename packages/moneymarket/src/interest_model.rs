@@ -8,14 +8,54 @@ pub struct InstantiateMsg {
     pub owner: String,
     pub base_rate: Decimal256,
     pub interest_multiplier: Decimal256,
+    /// Steeper slope above an optimal utilization, linear model when omitted
+    pub jump_rate: Option<JumpRate>,
+    /// Curve moving toward a target utilization, overrides the other parameters
+    pub adaptive_rate: Option<AdaptiveRate>,
+}
+
+/// Kinked (jump rate) part of the model. Below `kink` the rate grows with
+/// `interest_multiplier`, above it with `jump_multiplier`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct JumpRate {
+    /// Optimal utilization ratio where the slope changes
+    pub kink: Decimal256,
+    pub jump_multiplier: Decimal256,
+}
+
+/// Adaptive part of the model. The rate at `target_utilization` rises while
+/// utilization stays above target and falls while it stays below, at most by
+/// `adjustment_speed` (relative, per block) at 100% and 0% utilization.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdaptiveRate {
+    /// Market contract allowed to call `UpdateRate`
+    pub market_contract: String,
+    pub target_utilization: Decimal256,
+    pub adjustment_speed: Decimal256,
+    /// Rate multiplier at 100% utilization, its inverse applies at 0%
+    pub curve_steepness: Decimal256,
+    pub min_rate_at_target: Decimal256,
+    pub max_rate_at_target: Decimal256,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     UpdateConfig {
         base_rate: Option<Decimal256>,
         interest_multiplier: Option<Decimal256>,
+        /// Set `kink` to 1 to fall back to the linear model
+        jump_rate: Option<JumpRate>,
+        adaptive_rate: Option<AdaptiveRate>,
+    },
+
+    /// Hook called by the market whenever it computes interest,
+    /// moves the adaptive rate. No-op for the other models.
+    UpdateRate {
+        market_balance: Uint256,
+        total_liabilities: Decimal256,
+        total_reserves: Decimal256,
     },
     
     SetOwner {
@@ -26,6 +66,10 @@ pub enum ExecuteMsg {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
@@ -35,6 +79,12 @@ pub enum QueryMsg {
         total_liabilities: Decimal256,
         total_reserves: Decimal256,
     },
+    AdaptiveState {},
+    /// Owner actions on the contract, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 // We define a custom struct for each query response
@@ -43,6 +93,8 @@ pub struct ConfigResponse {
     pub owner: String,
     pub base_rate: Decimal256,
     pub interest_multiplier: Decimal256,
+    pub jump_rate: Option<JumpRate>,
+    pub adaptive_rate: Option<AdaptiveRate>,
 }
 
 // We define a custom struct for each query response
@@ -50,3 +102,11 @@ pub struct ConfigResponse {
 pub struct BorrowRateResponse {
     pub rate: Decimal256,
 }
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdaptiveStateResponse {
+    pub rate_at_target: Decimal256,
+    pub last_utilization: Decimal256,
+    pub last_updated_height: u64,
+}
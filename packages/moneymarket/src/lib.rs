@@ -1,4 +1,5 @@
 pub mod common;
+pub mod config_history;
 pub mod custody;
 pub mod custody_base;
 pub mod distribution_model;
@@ -6,10 +7,13 @@ pub mod interest_model;
 pub mod liquidation;
 pub mod liquidation_queue;
 pub mod market;
+pub mod migration;
 pub mod oracle;
 pub mod overseer;
+pub mod pause;
 pub mod querier;
 pub mod terraswap;
+pub mod timelock;
 pub mod tokens;
 pub mod oracle_pyth;
 pub mod mock_pyth_contract;
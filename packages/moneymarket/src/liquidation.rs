@@ -61,6 +61,7 @@ pub enum Cw20HookMsg {
         liquidator: String,
         fee_address: Option<String>,
         repay_address: Option<String>,
+        borrower: Option<String>,
     },
 
     SubmitBid {
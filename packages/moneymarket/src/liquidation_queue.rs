@@ -5,6 +5,8 @@ use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::Uint128;
 use cw20::Cw20ReceiveMsg;
 
+use crate::common::double_option;
+use crate::pause::PausableOperation;
 use crate::tokens::TokensHuman;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -45,6 +47,22 @@ pub enum ExecuteMsg {
         price_timeframe: Option<u64>,
         waiting_period: Option<u64>,
         overseer: Option<String>,
+        /// Maximum share of the loan repaid by one partial liquidation,
+        /// `null` removes it
+        #[serde(
+            default,
+            deserialize_with = "double_option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        close_factor: Option<Option<Decimal256>>,
+        /// Contract swapping claimed collateral to the stable denom,
+        /// `null` removes it
+        #[serde(
+            default,
+            deserialize_with = "double_option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        swap_contract: Option<Option<String>>,
     },
     
     SetOwner {
@@ -54,6 +72,17 @@ pub enum ExecuteMsg {
     AcceptOwnership {
     },
 
+    /// Set or clear the guardian allowed to pause bids and liquidations
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
+
+    /// Guardian operation to pause or resume bid submissions or liquidations
+    SetPaused {
+        operation: PausableOperation,
+        paused: bool,
+    },
+
     /// Owner operation to whitelist a new collateral
     WhitelistCollateral {
         collateral_token: String,
@@ -66,10 +95,12 @@ pub enum ExecuteMsg {
         bid_threshold: Option<Uint256>,
         max_slot: Option<u8>,
     },
-    /// Submit a new bid to a bid pool
+    /// Submit a new bid to a bid pool. A bid with `expires_at` (seconds)
+    /// stops absorbing liquidations once that time is reached
     SubmitBid {
         collateral_token: String,
         premium_slot: u8,
+        expires_at: Option<u64>,
     },
     /// Withdraw a bid
     RetractBid {
@@ -81,10 +112,25 @@ pub enum ExecuteMsg {
         collateral_token: String,
         bids_idx: Option<Vec<Uint128>>,
     },
-    /// Claim the corresponding amount of liquidated collateral
+    /// Claim the corresponding amount of liquidated collateral. With
+    /// `swap_to_stable`, the collateral is swapped to `stable_denom` first
     ClaimLiquidations {
         collateral_token: String,
         bids_idx: Option<Vec<Uint128>>,
+        swap_to_stable: Option<SwapToStable>,
+    },
+    /// Remove expired bids of a collateral from its bid pools, refunding
+    /// the remaining bids and the claimable collateral to the bidders
+    CleanupExpiredBids {
+        collateral_token: String,
+        limit: Option<u32>,
+    },
+    /// Re-save up to `limit` bids left by a migration from the
+    /// `cosmwasm_storage` layout, moving them off the legacy per-bidder
+    /// index. Callable by anyone until every bid is migrated, `BidsByUser`
+    /// misses the remaining ones until then
+    MigrateBids {
+        limit: Option<u32>,
     },
     /// after receive collateral asset denom forms execute bid
     ExecuteBid {
@@ -93,9 +139,22 @@ pub enum ExecuteMsg {
         fee_address: String,
         collateral_denom: String,
         amount: Uint256,
+        /// Repay the loan of `borrower` on the market at `repay_address`
+        /// with the settled amount instead of a plain transfer
+        borrower: Option<String>,
     },
 }
 
+/// Swap of claimed collateral through the swap contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapToStable {
+    /// Minimum amount of `stable_denom` the swap has to return, greater than zero
+    pub min_return: Uint256,
+    /// Submit the proceeds as a new bid with the premium slot and expiry of the
+    /// claimed bids
+    pub resubmit_bid: bool,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
@@ -104,9 +163,16 @@ pub enum Cw20HookMsg {
         liquidator: String, // Legacy parameter, ignored
         fee_address: Option<String>,
         repay_address: Option<String>,
+        /// Repay the loan of `borrower` on the market at `repay_address`
+        /// with the settled amount instead of a plain transfer
+        borrower: Option<String>,
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
@@ -138,6 +204,12 @@ pub enum QueryMsg {
         start_after: Option<u8>,
         limit: Option<u8>,
     },
+    PauseStatus {},
+    /// Owner actions on the contract, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -152,6 +224,8 @@ pub struct ConfigResponse {
     pub price_timeframe: u64,
     pub waiting_period: u64,
     pub overseer: String,
+    pub close_factor: Option<Decimal256>,
+    pub swap_contract: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -172,6 +246,7 @@ pub struct BidResponse {
     pub wait_end: Option<u64>,
     pub epoch_snapshot: Uint128,
     pub scale_snapshot: Uint128,
+    pub expires_at: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -2,7 +2,11 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_std::{Binary, Uint128};
+use cw20::{Cw20Coin, Cw20ReceiveMsg};
+
+use crate::common::double_option;
+use crate::pause::PausableOperation;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -17,6 +21,9 @@ pub struct InstantiateMsg {
     pub kpt_emission_rate: Decimal256,
     /// Maximum allowed borrow rate over deposited stable balance
     pub max_borrow_factor: Decimal256,
+    /// Register the market as the aToken balance hook, required for depositor rewards.
+    /// The aToken code must support it, as cw20-legacy does.
+    pub atoken_balance_hook: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -47,21 +54,69 @@ pub enum ExecuteMsg {
         max_borrow_factor: Option<Decimal256>,
         interest_model: Option<String>,
         distribution_model: Option<String>,
+        /// Call the interest model `UpdateRate` hook on every interest computation,
+        /// required by adaptive interest models
+        interest_model_hook: Option<bool>,
+        /// Share of the KPT emission distributed to aToken holders
+        depositor_reward_share: Option<Decimal256>,
+        /// Cap on the total liabilities, `null` removes it
+        #[serde(
+            default,
+            deserialize_with = "double_option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        max_total_liabilities: Option<Option<Uint256>>,
+        /// Cap on the total deposits, aToken supply times the exchange rate,
+        /// `null` removes it
+        #[serde(
+            default,
+            deserialize_with = "double_option",
+            skip_serializing_if = "Option::is_none"
+        )]
+        max_total_deposits: Option<Option<Uint256>>,
     },
     
+    /// Register the market as the balance hook of an existing aToken,
+    /// required for depositor rewards. Uses the cw20-legacy `UpdateBalanceHook`
+    RegisterAtokenBalanceHook {},
+
+    /// Re-save up to `limit` liabilities left by a migration from the
+    /// `cosmwasm_storage` layout, to build their debt index. Callable by
+    /// anyone until every liability is migrated, `BorrowersByDebt` misses
+    /// the remaining ones until then
+    MigrateLiabilities {
+        limit: Option<u32>,
+    },
+
     SetOwner {
         new_owner_addr: String,
     },
 
     AcceptOwnership {
     },
+
+    /// Set or clear the guardian allowed to pause market operations
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
+
+    ////////////////////
+    /// Guardian operations
+    ////////////////////
+    /// Pause or resume deposits, borrows or redemptions.
+    /// Native repayments are never paused, aToken repayments pause with redemptions
+    SetPaused {
+        operation: PausableOperation,
+        paused: bool,
+    },
+
     ////////////////////
     /// Overseer operations
     ////////////////////
-    /// Repay stable with liquidated collaterals
+    /// Repay stable with liquidated collaterals. The liquidation queue sends
+    /// the settled repay amount along, unused funds go back to the borrower
     RepayStableFromLiquidation {
         borrower: String,
-        prev_balance: Uint256,
     },
 
     /// Execute epoch operations
@@ -74,6 +129,21 @@ pub enum ExecuteMsg {
         distributed_interest: Uint256,
     },
 
+    /// Freeze interest and disable deposits and borrows for good.
+    /// After `settlement_period` blocks, left to settle the borrowers,
+    /// redemptions pay out a pro-rata share of the stable balance
+    /// and of the `collateral_tokens` settled to the market
+    Shutdown {
+        collateral_tokens: Vec<String>,
+        settlement_period: u64,
+    },
+
+    /// Write off the liability of `borrower`, whose collaterals
+    /// were settled to the market after the shutdown
+    SettleLoan {
+        borrower: String,
+    },
+
     ////////////////////
     /// User operations
     ////////////////////
@@ -84,25 +154,72 @@ pub enum ExecuteMsg {
     BorrowStable {
         borrow_amount: Uint256,
         to: Option<String>,
+        /// Pyth price update data (VAAs) pushed to the oracle
+        /// before the borrow limit is checked
+        price_update: Option<Vec<Binary>>,
     },
 
-    /// Repay stable asset to decrease liability
-    RepayStable {},
+    /// Repay stable asset to decrease liability of `borrower`,
+    /// defaults to the sender. Overpayment is refunded to the sender
+    RepayStable {
+        borrower: Option<String>,
+    },
 
-    /// Claim distributed KPT rewards
+    /// Claim distributed KPT rewards, for both borrowing and holding aTokens
     ClaimRewards {
         to: Option<String>,
     },
+
+    /// Fill queued withdrawal tickets, oldest first, with the available liquidity.
+    /// Triggered after deposits and repayments, and callable by anyone
+    ProcessWithdrawalQueue {},
+
+    /// Leave the withdrawal queue and get the escrowed aTokens back
+    CancelWithdrawal {
+        ticket_id: u64,
+    },
+
+    ////////////////////
+    /// aToken operations
+    ////////////////////
+    /// Settle aToken holder rewards, sent by the aToken contract
+    /// with the balances from before they changed
+    BalanceChangeHook {
+        balances_before: Vec<Cw20Coin>,
+        total_supply_before: Uint128,
+    },
+
+    ////////////////////
+    /// Internal operations
+    ////////////////////
+    /// Borrow for `borrower` once the oracle prices are updated
+    BorrowStableAfterPriceUpdate {
+        borrower: String,
+        borrow_amount: Uint256,
+        to: Option<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Cw20HookMsg {
-    /// Return stable coins to a user
-    /// according to exchange rate
-    RedeemStable {},
+    /// Return stable coins to a user, or to `to` if given,
+    /// according to exchange rate. With `exact_underlying` only the aTokens
+    /// needed for that stable amount are burnt and the rest is returned
+    RedeemStable {
+        to: Option<String>,
+        exact_underlying: Option<Uint256>,
+    },
     DepositStable {},
-    RepayStable {},
+    /// Burn the sent aTokens at the current exchange rate to repay
+    /// the liability of `borrower`, defaults to the sender.
+    /// Paused along with redemptions, and disabled by the shutdown
+    RepayStable {
+        borrower: Option<String>,
+    },
+    /// Escrow the sent aTokens in the withdrawal queue, to be redeemed
+    /// once the market has enough liquidity
+    QueueRedeemStable {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -123,6 +240,33 @@ pub enum QueryMsg {
     BorrowerInfos {
         start_after: Option<String>,
         limit: Option<u32>,
+        block_height: Option<u64>,
+    },
+    /// Borrowers ordered by recorded loan amount, largest first
+    BorrowersByDebt {
+        start_after: Option<String>,
+        limit: Option<u32>,
+        block_height: Option<u64>,
+    },
+    DepositorInfo {
+        depositor: String,
+        block_height: Option<u64>,
+    },
+    Rates {},
+    WithdrawalTicket {
+        ticket_id: u64,
+    },
+    WithdrawalTickets {
+        owner: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    PauseStatus {},
+    ShutdownState {},
+    /// Owner actions on the contract, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
     },
 }
 
@@ -139,6 +283,11 @@ pub struct ConfigResponse {
     pub distributor_contract: String,
     pub stable_denom: String,
     pub max_borrow_factor: Decimal256,
+    pub interest_model_hook: bool,
+    pub atoken_balance_hook: bool,
+    pub depositor_reward_share: Decimal256,
+    pub max_total_liabilities: Option<Uint256>,
+    pub max_total_deposits: Option<Uint256>,
 }
 
 // We define a custom struct for each query response
@@ -152,6 +301,35 @@ pub struct StateResponse {
     pub prev_exchange_rate: Decimal256,
 }
 
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RatesResponse {
+    /// Borrow rate per block
+    pub borrow_rate: Decimal256,
+    /// Borrow rate compounded per block over a year
+    pub borrow_apy: Decimal256,
+    pub utilization_ratio: Decimal256,
+    /// Deposit rate per block, the interest paid by borrowers spread over deposits
+    /// and capped at `target_deposit_rate` (the excess goes to reserves)
+    pub deposit_rate: Decimal256,
+    /// Deposit rate compounded per block over a year
+    pub deposit_apy: Decimal256,
+    /// Overseer target deposit rate per block
+    pub target_deposit_rate: Decimal256,
+    /// KPT distributed over a year per borrowed stable unit
+    pub kpt_emission_apr: Decimal256,
+    /// KPT distributed over a year per deposited stable unit
+    pub kpt_deposit_emission_apr: Decimal256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositorInfoResponse {
+    pub depositor: String,
+    pub reward_index: Decimal256,
+    pub pending_rewards: Decimal256,
+}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct EpochStateResponse {
@@ -164,8 +342,9 @@ pub struct EpochStateResponse {
 pub struct BorrowerInfoResponse {
     pub borrower: String,
     pub interest_index: Decimal256,
+    pub reward_index: Decimal256,
     pub loan_amount: Uint256,
-   
+    pub pending_rewards: Decimal256,
 }
 
 // We define a custom struct for each query response
@@ -174,6 +353,35 @@ pub struct BorrowerInfosResponse {
     pub borrower_infos: Vec<BorrowerInfoResponse>,
 }
 
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalTicketResponse {
+    pub ticket_id: u64,
+    pub owner: String,
+    pub atoken_amount: Uint256,
+    pub queued_at: u64,
+    /// Number of tickets ahead in the queue
+    pub position: u64,
+    /// aTokens escrowed by the tickets ahead
+    pub atokens_ahead: Uint256,
+    /// Stable paid for the ticket at the current exchange rate
+    pub expected_redeem_amount: Uint256,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawalTicketsResponse {
+    pub tickets: Vec<WithdrawalTicketResponse>,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShutdownStateResponse {
+    pub shutdown_height: Option<u64>,
+    pub redemption_height: Option<u64>,
+    pub collateral_tokens: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct MigrateMsg {}
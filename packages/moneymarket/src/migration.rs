@@ -0,0 +1,68 @@
+use cosmwasm_std::{StdError, StdResult, Storage};
+use cosmwasm_storage::{singleton, singleton_read, to_length_prefixed};
+use cw2::CONTRACT;
+use cw_storage_plus::Item;
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Check the stored cw2 info allows a migration to `version` of `contract`,
+/// and return the version being migrated from. `None` means the contract was
+/// instantiated before cw2 versions were recorded. State may be in the legacy
+/// layout either way, see `legacy_singleton_exists`.
+pub fn assert_migrate_version(
+    storage: &dyn Storage,
+    contract: &str,
+    version: &str,
+) -> StdResult<Option<String>> {
+    let stored = match CONTRACT.may_load(storage)? {
+        Some(stored) => stored,
+        None => return Ok(None),
+    };
+
+    if stored.contract != contract {
+        return Err(StdError::generic_err(format!(
+            "Cannot migrate {} to {}",
+            stored.contract, contract
+        )));
+    }
+
+    if parse_version(&stored.version)? > parse_version(version)? {
+        return Err(StdError::generic_err(format!(
+            "Cannot migrate from version {} to older version {}",
+            stored.version, version
+        )));
+    }
+
+    Ok(Some(stored.version))
+}
+
+/// Move a value from the legacy `cosmwasm_storage` singleton with the same key
+/// to `item`. Singletons length-prefix their key, so the value is rewritten
+/// under the raw key `item` reads from.
+pub fn migrate_singleton<T>(storage: &mut dyn Storage, item: &Item<T>) -> StdResult<()>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let key = item.as_slice().to_vec();
+    if let Some(value) = singleton_read::<T>(storage, &key).may_load()? {
+        item.save(storage, &value)?;
+        singleton::<T>(storage, &key).remove();
+    }
+
+    Ok(())
+}
+
+/// Whether the legacy `cosmwasm_storage` singleton of `item` is still stored,
+/// telling state that has yet to be moved apart from state in the new layout
+pub fn legacy_singleton_exists<T>(storage: &dyn Storage, item: &Item<T>) -> bool
+where
+    T: Serialize + DeserializeOwned,
+{
+    storage.get(&to_length_prefixed(item.as_slice())).is_some()
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    Version::parse(version)
+        .map_err(|e| StdError::generic_err(format!("Invalid version {}: {}", version, e)))
+}
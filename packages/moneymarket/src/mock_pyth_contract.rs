@@ -1,4 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Binary, Coin};
 use pyth_sdk_cw::PriceIdentifier;
 
 #[cw_serde]
@@ -6,6 +7,13 @@ pub struct InstantiateMsg {}
 #[cw_serde]
 pub enum ExecuteMsg {
     UpdatePriceFeed { id: PriceIdentifier, price: i64 },
+    UpdatePriceFeeds { data: Vec<Binary> },
+}
+
+#[cw_serde]
+pub struct MockPriceUpdate {
+    pub id: PriceIdentifier,
+    pub price: i64,
 }
 
 #[cw_serde]
@@ -13,6 +21,8 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     #[returns(PriceFeedResponse)]
     PriceFeed { id: PriceIdentifier },
+    #[returns(Coin)]
+    GetUpdateFee { vaas: Vec<Binary> },
 }
 
 #[cw_serde]
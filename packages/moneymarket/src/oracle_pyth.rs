@@ -1,4 +1,6 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, StdError, StdResult, WasmMsg,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use cosmwasm_bignumber::Decimal256;
@@ -23,12 +25,64 @@ pub struct PriceResponse {
     pub last_updated_quote: u64,
 }
 
+/// `price` is set unless the price tripped the circuit breaker, then `circuit_breaker`
+/// holds the reason
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CheckedPriceResponse {
+    pub asset: String,
+    pub price: Option<PriceResponse>,
+    pub circuit_breaker: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     QueryPrice {
         asset: String
-    }
+    },
+    QueryPrices {
+        assets: Vec<String>,
+    },
+    QueryEmaPrices {
+        assets: Vec<String>,
+    },
+    QueryCheckedPrices {
+        assets: Vec<String>,
+        ema_only: bool,
+    },
+    QueryConfig {},
+    QueryUpdateFee {
+        data: Vec<Binary>,
+    },
+    QueryFeeds {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConfigResponse {
+    pub owner: String,
+    pub pyth_contract: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FeedResponse {
+    pub asset: String,
+    pub price_feed_id: String,
+    pub price_feed_symbol: String,
+    pub price_feed_decimal: u32,
+    pub price_feed_age: u64,
+    pub check_feed_age: bool,
+    pub is_valid: bool,
+    pub min_price: Option<Decimal256>,
+    pub max_price: Option<Decimal256>,
+    pub max_deviation: Option<Decimal256>,
+    #[serde(default)]
+    pub underlying_asset: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -42,6 +96,24 @@ pub enum ExecuteMsg {
         check_feed_age: bool,
         price_feed_age: u64,
     },
+    ConfigDerivedFeedInfo {
+        asset: String,
+        underlying_asset: String,
+        hub_contract: String,
+        max_rate_change: Decimal256,
+    },
+    UpdateDerivedExchangeRate {
+        asset: String,
+    },
+    UpdatePriceFeeds {
+        data: Vec<Binary>,
+    },
+    SetPriceBounds {
+        asset: String,
+        min_price: Option<Decimal256>,
+        max_price: Option<Decimal256>,
+        max_deviation: Option<Decimal256>,
+    },
 
     SetConfigFeedValid {
         asset: String,
@@ -54,3 +126,44 @@ pub enum ExecuteMsg {
         pyth_contract: String,
     },
 }
+
+/// Builds the messages pushing `data` to the oracle before the caller's own logic runs.
+/// Exactly `fee` is forwarded to the oracle, the rest of `funds` is refunded to `refund_to`.
+pub fn price_update_messages(
+    oracle_contract: &Addr,
+    data: Vec<Binary>,
+    fee: Coin,
+    funds: Vec<Coin>,
+    refund_to: &Addr,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut refunds: Vec<Coin> = vec![];
+    let mut fee_paid = false;
+    for mut coin in funds.into_iter() {
+        if coin.denom == fee.denom {
+            coin.amount = coin.amount.checked_sub(fee.amount)?;
+            fee_paid = true;
+        }
+        if !coin.amount.is_zero() {
+            refunds.push(coin);
+        }
+    }
+    if !fee_paid && !fee.amount.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "Insufficient price update fee: {}",
+            fee
+        )));
+    }
+
+    let mut messages = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: oracle_contract.to_string(),
+        msg: to_json_binary(&ExecuteMsg::UpdatePriceFeeds { data })?,
+        funds: if fee.amount.is_zero() { vec![] } else { vec![fee] },
+    })];
+    if !refunds.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: refund_to.to_string(),
+            amount: refunds,
+        }));
+    }
+    Ok(messages)
+}
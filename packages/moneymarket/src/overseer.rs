@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::tokens::TokensHuman;
 use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::Binary;
+
+use crate::pause::PausableOperation;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -73,6 +76,8 @@ pub enum ExecuteMsg {
         dyn_rate_yr_increase_expectation: Option<Decimal256>,
         dyn_rate_min: Option<Decimal256>,
         dyn_rate_max: Option<Decimal256>,
+        frozen_liquidation_policy: Option<FrozenLiquidationPolicy>,
+        liquidation_cooldown: Option<u64>,
     },
 
     SetOwner {
@@ -81,6 +86,11 @@ pub enum ExecuteMsg {
 
     AcceptOwnership {
     },
+
+    /// Set or clear the guardian allowed to pause overseer operations
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
     
     /// Create new custody contract for the given collateral token
     Whitelist {
@@ -97,6 +107,12 @@ pub enum ExecuteMsg {
         max_ltv: Option<Decimal256>,      // Loan To Value ratio
     },
 
+    /// Shut the protocol down for good. The price of every whitelisted
+    /// collateral is frozen at its current oracle price, and the market
+    /// and custody contracts stop taking deposits and new borrows.
+    /// Market redemptions open `settlement_period` blocks later
+    Shutdown { settlement_period: u64 },
+
     /// Claims all staking rewards from the bAsset contracts
     /// and also do a epoch basis updates
     /// 1. Distribute interest buffers to depositors
@@ -108,6 +124,16 @@ pub enum ExecuteMsg {
         distributed_interest: Uint256,
     },
 
+    ////////////////////
+    /// Guardian operations
+    ////////////////////
+    /// Pause or resume collateral locks and unlocks,
+    /// liquidations or epoch operations
+    SetPaused {
+        operation: PausableOperation,
+        paused: bool,
+    },
+
     ////////////////////
     /// User operations
     ////////////////////
@@ -124,6 +150,9 @@ pub enum ExecuteMsg {
     /////////////////////////////
     LiquidateCollateral {
         borrower: String,
+        /// Pyth price update data (VAAs) pushed to the oracle
+        /// before the borrow limit is checked
+        price_update: Option<Vec<Binary>>,
     },
 
     FundReserve {},
@@ -131,6 +160,21 @@ pub enum ExecuteMsg {
     RepayStableFromYieldReserve {
         borrower: String,
     },
+
+    /// After the shutdown, move the collaterals of `borrower` worth its loan
+    /// at the frozen prices to the market, and write the loan off
+    SettleBorrower {
+        borrower: String,
+    },
+
+    ////////////////////
+    /// Internal operations
+    ////////////////////
+    /// Liquidate on behalf of `liquidator` once the oracle prices are updated
+    LiquidateCollateralAfterPriceUpdate {
+        liquidator: String,
+        borrower: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -155,6 +199,13 @@ pub enum QueryMsg {
         borrower: String,
         block_time: Option<u64>,
     },
+    PauseStatus {},
+    ShutdownState {},
+    /// Owner actions on the contract, oldest first
+    ConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 // We define a custom struct for each query response
@@ -177,6 +228,20 @@ pub struct ConfigResponse {
     pub dyn_rate_yr_increase_expectation: Decimal256,
     pub dyn_rate_min: Decimal256,
     pub dyn_rate_max: Decimal256,
+    pub frozen_liquidation_policy: FrozenLiquidationPolicy,
+    pub liquidation_cooldown: u64,
+}
+
+/// How liquidations behave while an oracle price trips its circuit breaker.
+/// Borrows and unlocks are always blocked.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FrozenLiquidationPolicy {
+    /// Liquidations are blocked too
+    #[default]
+    Block,
+    /// Liquidations are priced with the EMA price, as long as it is within bounds
+    UseEmaPrice,
 }
 
 // We define a custom struct for each query response
@@ -213,3 +278,10 @@ pub struct BorrowLimitResponse {
     pub borrower: String,
     pub borrow_limit: Uint256,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ShutdownStateResponse {
+    pub shutdown_height: Option<u64>,
+    /// Frozen price of each collateral token
+    pub prices: Vec<(String, Decimal256)>,
+}
@@ -0,0 +1,183 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use cosmwasm_std::{
+    attr, Addr, CanonicalAddr, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Storage,
+};
+use cw_storage_plus::Item;
+
+use crate::config_history::{config_change, record_config_change};
+
+pub const PAUSE: Item<PauseInfo> = Item::new("pause");
+
+/// Operations the guardian can pause. Each contract only accepts the ones it
+/// performs, and repayments can never be paused
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PausableOperation {
+    Deposits,
+    Borrows,
+    Redemptions,
+    CollateralLocks,
+    CollateralUnlocks,
+    Liquidations,
+    BidSubmissions,
+    EpochOperations,
+}
+
+impl fmt::Display for PausableOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PausableOperation::Deposits => write!(f, "deposits"),
+            PausableOperation::Borrows => write!(f, "borrows"),
+            PausableOperation::Redemptions => write!(f, "redemptions"),
+            PausableOperation::CollateralLocks => write!(f, "collateral_locks"),
+            PausableOperation::CollateralUnlocks => write!(f, "collateral_unlocks"),
+            PausableOperation::Liquidations => write!(f, "liquidations"),
+            PausableOperation::BidSubmissions => write!(f, "bid_submissions"),
+            PausableOperation::EpochOperations => write!(f, "epoch_operations"),
+        }
+    }
+}
+
+/// Guardian and paused operations, stored by each pausable contract
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PauseInfo {
+    pub guardian: Option<CanonicalAddr>,
+    pub paused: Vec<PausableOperation>,
+}
+
+impl PauseInfo {
+    pub fn is_paused(&self, operation: PausableOperation) -> bool {
+        self.paused.contains(&operation)
+    }
+
+    pub fn set_paused(&mut self, operation: PausableOperation, paused: bool) {
+        self.paused.retain(|op| *op != operation);
+        if paused {
+            self.paused.push(operation);
+        }
+    }
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseStatusResponse {
+    pub guardian: Option<String>,
+    pub paused: Vec<PausableOperation>,
+}
+
+/// Error of the shared pause handlers, mapped into each contract's own error type
+#[derive(Debug, PartialEq)]
+pub enum PauseError {
+    Unauthorized,
+    /// The operation is not performed by the contract, with the operation name
+    NotPausable(String),
+    /// The operation is paused by the guardian, with the operation name
+    Paused(String),
+    Std(StdError),
+}
+
+impl From<StdError> for PauseError {
+    fn from(err: StdError) -> Self {
+        PauseError::Std(err)
+    }
+}
+
+pub fn store_pause_info(storage: &mut dyn Storage, data: &PauseInfo) -> StdResult<()> {
+    PAUSE.save(storage, data)
+}
+
+pub fn read_pause_info(storage: &dyn Storage) -> StdResult<PauseInfo> {
+    Ok(PAUSE.may_load(storage)?.unwrap_or_default())
+}
+
+/// Sets or clears the guardian; only the address returned by `owner` may call it
+pub fn update_guardian(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    guardian: Option<Addr>,
+    owner: fn(&dyn Storage) -> StdResult<CanonicalAddr>,
+) -> Result<Response, PauseError> {
+    if deps.api.addr_canonicalize(info.sender.as_str())? != owner(deps.storage)? {
+        return Err(PauseError::Unauthorized);
+    }
+
+    let old_guardian = query_pause_status(deps.as_ref())?.guardian;
+    let mut pause_info: PauseInfo = read_pause_info(deps.storage)?;
+    pause_info.guardian = match &guardian {
+        Some(guardian) => Some(deps.api.addr_canonicalize(guardian.as_str())?),
+        None => None,
+    };
+    store_pause_info(deps.storage, &pause_info)?;
+
+    let new_guardian = guardian.as_ref().map(|g| g.to_string());
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "update_guardian",
+        vec![config_change("guardian", &old_guardian, &new_guardian)?],
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_guardian"),
+        attr(
+            "guardian",
+            guardian.map(|g| g.to_string()).unwrap_or_default(),
+        ),
+    ]))
+}
+
+/// Pauses or resumes one of `operations`, the ones the calling contract performs
+pub fn set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    operations: &[PausableOperation],
+    operation: PausableOperation,
+    paused: bool,
+) -> Result<Response, PauseError> {
+    let mut pause_info: PauseInfo = read_pause_info(deps.storage)?;
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if pause_info.guardian != Some(sender_raw) {
+        return Err(PauseError::Unauthorized);
+    }
+
+    if !operations.contains(&operation) {
+        return Err(PauseError::NotPausable(operation.to_string()));
+    }
+
+    pause_info.set_paused(operation, paused);
+    store_pause_info(deps.storage, &pause_info)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_paused"),
+        attr("operation", operation.to_string()),
+        attr("paused", paused.to_string()),
+    ]))
+}
+
+pub fn assert_not_paused(
+    storage: &dyn Storage,
+    operation: PausableOperation,
+) -> Result<(), PauseError> {
+    if read_pause_info(storage)?.is_paused(operation) {
+        return Err(PauseError::Paused(operation.to_string()));
+    }
+
+    Ok(())
+}
+
+pub fn query_pause_status(deps: Deps) -> StdResult<PauseStatusResponse> {
+    let pause_info: PauseInfo = read_pause_info(deps.storage)?;
+    Ok(PauseStatusResponse {
+        guardian: match pause_info.guardian {
+            Some(guardian) => Some(deps.api.addr_humanize(&guardian)?.to_string()),
+            None => None,
+        },
+        paused: pause_info.paused,
+    })
+}
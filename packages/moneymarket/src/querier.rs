@@ -10,9 +10,11 @@ use cosmwasm_std::{
     AllBalanceResponse,
     BalanceResponse,
     BankQuery,
+    Binary,
     Coin,
     Deps,
     QueryRequest,
+    StdError,
     StdResult,
     Uint128,
     WasmQuery, 
@@ -23,7 +25,9 @@ use crate::common::CustomQuerier;
 // use crate::common::QueryTaxWrapper;
 
 use crate::oracle::PriceResponse;
-use crate::oracle_pyth::{PriceResponse as PythPriceResponse,QueryMsg as PythOracleQueryMsg};
+use crate::oracle_pyth::{
+    CheckedPriceResponse, PriceResponse as PythPriceResponse, QueryMsg as PythOracleQueryMsg,
+};
 
 pub fn query_all_balances(deps: Deps, account_addr: Addr) -> StdResult<Vec<Coin>> {
     // load price form the oracle
@@ -185,3 +189,131 @@ pub fn query_price(
 
     Ok(oracle_price)
 }
+
+/// Fee the oracle charges for applying the pyth price update `data`
+pub fn query_price_update_fee(deps: Deps, oracle_addr: Addr, data: Vec<Binary>) -> StdResult<Coin> {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_addr.to_string(),
+        msg: to_json_binary(&PythOracleQueryMsg::QueryUpdateFee { data })?,
+    }))
+}
+
+/// Batched version of `query_price`, returns the prices in the order of `bases`
+pub fn query_prices(
+    deps: Deps,
+    oracle_addr: Addr,
+    bases: Vec<String>,
+    _quote: String,
+    _time_constraints: Option<TimeConstraints>,
+) -> StdResult<Vec<PriceResponse>> {
+    if bases.is_empty() {
+        return Ok(vec![]);
+    }
+
+    query_pyth_prices(
+        deps,
+        oracle_addr,
+        PythOracleQueryMsg::QueryPrices { assets: bases },
+    )
+}
+
+/// Same as `query_prices`, but the oracle only checks the EMA prices against the
+/// feed sanity bounds, so it keeps answering while a spot price trips the breaker
+pub fn query_ema_prices(
+    deps: Deps,
+    oracle_addr: Addr,
+    bases: Vec<String>,
+) -> StdResult<Vec<PriceResponse>> {
+    if bases.is_empty() {
+        return Ok(vec![]);
+    }
+
+    query_pyth_prices(
+        deps,
+        oracle_addr,
+        PythOracleQueryMsg::QueryEmaPrices { assets: bases },
+    )
+}
+
+/// Error of `query_checked_prices`, telling circuit breaker trips apart from other failures
+#[derive(Debug, PartialEq)]
+pub enum PriceQueryError {
+    /// The oracle rejected a price against its sanity bounds, with the oracle's reason
+    CircuitBreaker(String),
+    Std(StdError),
+}
+
+impl From<StdError> for PriceQueryError {
+    fn from(err: StdError) -> Self {
+        PriceQueryError::Std(err)
+    }
+}
+
+impl From<PriceQueryError> for StdError {
+    fn from(err: PriceQueryError) -> Self {
+        match err {
+            PriceQueryError::CircuitBreaker(reason) => {
+                StdError::generic_err(format!("Price circuit breaker: {}", reason))
+            }
+            PriceQueryError::Std(err) => err,
+        }
+    }
+}
+
+/// Same as `query_prices` (`query_ema_prices` with `ema_only`), but a price tripping the
+/// oracle circuit breaker fails with `PriceQueryError::CircuitBreaker`
+pub fn query_checked_prices(
+    deps: Deps,
+    oracle_addr: Addr,
+    bases: Vec<String>,
+    _time_constraints: Option<TimeConstraints>,
+    ema_only: bool,
+) -> Result<Vec<PriceResponse>, PriceQueryError> {
+    if bases.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let checked_prices: Vec<CheckedPriceResponse> =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: oracle_addr.to_string(),
+            msg: to_json_binary(&PythOracleQueryMsg::QueryCheckedPrices {
+                assets: bases,
+                ema_only,
+            })?,
+        }))?;
+
+    checked_prices
+        .into_iter()
+        .map(|checked_price| match checked_price.price {
+            Some(pyth_oracle_price) => Ok(PriceResponse {
+                rate: pyth_oracle_price.emv_price,
+                last_updated_base: pyth_oracle_price.last_updated_base,
+                last_updated_quote: pyth_oracle_price.last_updated_quote,
+            }),
+            None => Err(PriceQueryError::CircuitBreaker(
+                checked_price.circuit_breaker.unwrap_or(checked_price.asset),
+            )),
+        })
+        .collect()
+}
+
+fn query_pyth_prices(
+    deps: Deps,
+    oracle_addr: Addr,
+    msg: PythOracleQueryMsg,
+) -> StdResult<Vec<PriceResponse>> {
+    let pyth_oracle_prices: Vec<PythPriceResponse> =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: oracle_addr.to_string(),
+            msg: to_json_binary(&msg)?,
+        }))?;
+
+    Ok(pyth_oracle_prices
+        .into_iter()
+        .map(|pyth_oracle_price| PriceResponse {
+            rate: pyth_oracle_price.emv_price,
+            last_updated_base: pyth_oracle_price.last_updated_base,
+            last_updated_quote: pyth_oracle_price.last_updated_quote,
+        })
+        .collect())
+}
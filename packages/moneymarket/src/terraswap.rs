@@ -12,6 +12,17 @@ pub struct InstantiateMsg {
     pub decimals: u8,
     pub initial_balances: Vec<Cw20Coin>,
     pub mint: Option<MinterResponse>,
+    /// Contract notified whenever balances change, only supported by cw20-legacy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance_hook: Option<String>,
+}
+
+/// TokenContract ExecuteMsg extension, only supported by cw20-legacy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenExecuteMsg {
+    /// Set or clear the contract notified whenever balances change, minter only
+    UpdateBalanceHook { balance_hook: Option<String> },
 }
 
 impl InstantiateMsg {
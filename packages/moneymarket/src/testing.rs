@@ -1,10 +1,16 @@
+use crate::config_history::{config_change, query_config_history, record_config_change};
+use crate::migration::{assert_migrate_version, migrate_singleton};
 use crate::mock_querier::mock_dependencies;
 use crate::oracle::PriceResponse;
 use crate::querier::{compute_tax, deduct_tax, query_price, query_tax_rate};
 use crate::tokens::{Tokens, TokensHuman, TokensMath, TokensToRaw};
 
 use cosmwasm_bignumber::{Decimal256, Uint256};
-use cosmwasm_std::{Addr, Api, CanonicalAddr, Coin, Decimal, Uint128};
+use cosmwasm_std::testing::mock_env;
+use cosmwasm_std::{Addr, Api, CanonicalAddr, Coin, Decimal, StdError, Uint128};
+use cosmwasm_storage::{singleton, singleton_read};
+use cw2::set_contract_version;
+use cw_storage_plus::Item;
 
 #[test]
 fn tax_rate_querier() {
@@ -318,3 +324,89 @@ fn token_math_invalid_token_2() {
 
     let _ = tokens_1_raw.sub(tokens_2_raw);
 }
+
+#[test]
+fn migrate_version_checks() {
+    let mut deps = mock_dependencies(&[]);
+
+    // legacy deployment without cw2 info
+    assert_eq!(
+        assert_migrate_version(&deps.storage, "crates.io:contract", "0.1.0").unwrap(),
+        None
+    );
+
+    set_contract_version(&mut deps.storage, "crates.io:contract", "0.1.0").unwrap();
+    assert_eq!(
+        assert_migrate_version(&deps.storage, "crates.io:contract", "0.1.0").unwrap(),
+        Some("0.1.0".to_string())
+    );
+    assert_eq!(
+        assert_migrate_version(&deps.storage, "crates.io:contract", "0.2.0").unwrap(),
+        Some("0.1.0".to_string())
+    );
+
+    match assert_migrate_version(&deps.storage, "crates.io:other", "0.2.0") {
+        Err(StdError::GenericErr { msg, .. }) => {
+            assert_eq!(msg, "Cannot migrate crates.io:contract to crates.io:other")
+        }
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+
+    match assert_migrate_version(&deps.storage, "crates.io:contract", "0.0.9") {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(
+            msg,
+            "Cannot migrate from version 0.1.0 to older version 0.0.9"
+        ),
+        _ => panic!("DO NOT ENTER HERE"),
+    }
+}
+
+#[test]
+fn migrate_legacy_singleton() {
+    let mut deps = mock_dependencies(&[]);
+    let item: Item<Uint256> = Item::new("value");
+
+    // nothing to move
+    migrate_singleton(&mut deps.storage, &item).unwrap();
+    assert_eq!(item.may_load(&deps.storage).unwrap(), None);
+
+    singleton(&mut deps.storage, b"value")
+        .save(&Uint256::from(100u64))
+        .unwrap();
+    migrate_singleton(&mut deps.storage, &item).unwrap();
+    assert_eq!(item.load(&deps.storage).unwrap(), Uint256::from(100u64));
+    assert_eq!(
+        singleton_read::<Uint256>(&deps.storage, b"value")
+            .may_load()
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn config_history_pages() {
+    let mut deps = mock_dependencies(&[]);
+
+    for value in 0u64..3 {
+        record_config_change(
+            &mut deps.storage,
+            &mock_env(),
+            &Addr::unchecked("owner"),
+            "update_config",
+            vec![config_change("value", &value, &(value + 1)).unwrap()],
+        )
+        .unwrap();
+    }
+
+    let res = query_config_history(&deps.storage, Some(0), Some(1)).unwrap();
+    assert_eq!(res.entries.len(), 1);
+    assert_eq!(res.entries[0].id, 1);
+    assert_eq!(res.entries[0].changes[0].new_value, "2");
+
+    let res = query_config_history(&deps.storage, Some(2), None).unwrap();
+    assert_eq!(res.entries, vec![]);
+
+    // the last possible id does not overflow the page bound
+    let res = query_config_history(&deps.storage, Some(u64::MAX), None).unwrap();
+    assert_eq!(res.entries, vec![]);
+}
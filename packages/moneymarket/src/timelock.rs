@@ -0,0 +1,85 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::Binary;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub owner: String,
+    /// Minimum time between queueing an action and executing it, in seconds
+    pub delay: u64,
+    /// Time after its eta during which an action can still be executed, in seconds
+    pub grace_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    ////////////////////
+    /// Owner operations
+    ////////////////////
+
+    /// Queue the execution of `msg` on `target`, no earlier than `eta`
+    /// (a unix time in seconds, at least `delay` from now)
+    QueueAction {
+        target: String,
+        msg: Binary,
+        eta: u64,
+    },
+    /// Drop a queued action
+    CancelAction { action_id: u64 },
+    /// Execute a queued action once its eta has passed
+    ExecuteAction { action_id: u64 },
+    /// Accept the pending ownership
+    AcceptOwnership {},
+
+    ////////////////////
+    /// Timelocked operations, only executed through a queued action
+    ////////////////////
+
+    UpdateConfig {
+        delay: Option<u64>,
+        grace_period: Option<u64>,
+    },
+    SetOwner { new_owner_addr: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Config {},
+    QueuedAction {
+        action_id: u64,
+    },
+    QueuedActions {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: String,
+    pub delay: u64,
+    pub grace_period: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedActionResponse {
+    pub action_id: u64,
+    pub target: String,
+    pub msg: Binary,
+    pub eta: u64,
+}
+
+// We define a custom struct for each query response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedActionsResponse {
+    pub actions: Vec<QueuedActionResponse>,
+}
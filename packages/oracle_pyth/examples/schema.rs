@@ -2,9 +2,10 @@ use std::env::current_dir;
 use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
+use moneymarket::config_history::ConfigHistoryResponse;
 use oracle_pyth::msg::{
-    ChangeOwnerMsg, ConfigResponse, ExecuteMsg, InstantiateMsg, PriceResponse,
-    PythFeederConfigResponse, QueryMsg, SetConfigFeedValidMsg,
+    ChangeOwnerMsg, ConfigResponse, DerivedFeederConfigResponse, ExecuteMsg, FeedResponse,
+    InstantiateMsg, PriceResponse, PythFeederConfigResponse, QueryMsg, SetConfigFeedValidMsg,
 };
 
 fn main() {
@@ -22,4 +23,7 @@ fn main() {
     export_schema(&schema_for!(PriceResponse), &out_dir);
     export_schema(&schema_for!(PythFeederConfigResponse), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(DerivedFeederConfigResponse), &out_dir);
+    export_schema(&schema_for!(FeedResponse), &out_dir);
+    export_schema(&schema_for!(ConfigHistoryResponse), &out_dir);
 }
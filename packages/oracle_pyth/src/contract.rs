@@ -1,14 +1,28 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
 use crate::error::ContractError;
-use crate::handler::{change_owner, change_pyth_contract, config_feed_info, set_config_feed_valid};
+use crate::handler::{
+    change_owner, change_pyth_contract, config_derived_feed_info, config_feed_info,
+    set_config_feed_valid, set_price_bounds, update_derived_exchange_rate, update_price_feeds,
+};
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use crate::querier::{
-    query_config, query_exchange_rate_by_asset_label, query_price, query_prices,
-    query_pyth_feeder_config,
+    query_checked_prices, query_config, query_derived_feeder_config, query_ema_prices,
+    query_exchange_rate_by_asset_label, query_feeds,
+    query_price, query_prices, query_pyth_feeder_config, query_update_fee,
+};
+use crate::state::{migrate_legacy_state, store_config, Config};
+use moneymarket::config_history::query_config_history;
+use moneymarket::migration::assert_migrate_version;
+use cosmwasm_std::{
+    attr, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
 };
-use crate::state::{store_config, Config};
-use cosmwasm_std::{ to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,};
+use cw2::set_contract_version;
 use pyth_sdk_cw::PriceIdentifier;
 
+const CONTRACT_NAME: &str = "crates.io:oracle-pyth";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -16,6 +30,8 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     store_config(
         deps.storage,
         &Config {
@@ -29,7 +45,7 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -45,6 +61,7 @@ pub fn execute(
             let price_feed_id_type = PriceIdentifier::from_hex(price_feed_id).unwrap();
             config_feed_info(
                 deps,
+                env,
                 info,
                 asset,
                 price_feed_id_type,
@@ -54,12 +71,36 @@ pub fn execute(
                 price_feed_age,
             )
         }
+        ExecuteMsg::ConfigDerivedFeedInfo {
+            asset,
+            underlying_asset,
+            hub_contract,
+            max_rate_change,
+        } => config_derived_feed_info(
+            deps,
+            env,
+            info,
+            asset,
+            underlying_asset,
+            hub_contract,
+            max_rate_change,
+        ),
+        ExecuteMsg::UpdateDerivedExchangeRate { asset } => {
+            update_derived_exchange_rate(deps, env, asset)
+        }
+        ExecuteMsg::UpdatePriceFeeds { data } => update_price_feeds(deps, info, data),
+        ExecuteMsg::SetPriceBounds {
+            asset,
+            min_price,
+            max_price,
+            max_deviation,
+        } => set_price_bounds(deps, env, info, asset, min_price, max_price, max_deviation),
         ExecuteMsg::SetConfigFeedValid { asset, valid } => {
-            set_config_feed_valid(deps, info, asset, valid)
+            set_config_feed_valid(deps, env, info, asset, valid)
         }
-        ExecuteMsg::ChangeOwner { new_owner } => change_owner(deps, info, new_owner),
+        ExecuteMsg::ChangeOwner { new_owner } => change_owner(deps, env, info, new_owner),
         ExecuteMsg::ChangePythContract { pyth_contract } => {
-            change_pyth_contract(deps, info, pyth_contract)
+            change_pyth_contract(deps, env, info, pyth_contract)
         }
     }
 }
@@ -69,10 +110,23 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::QueryPrice { asset } => to_json_binary(&query_price(deps, env, asset)?),
         QueryMsg::QueryPrices { assets } => to_json_binary(&query_prices(deps, env, assets)?),
+        QueryMsg::QueryEmaPrices { assets } => {
+            to_json_binary(&query_ema_prices(deps, env, assets)?)
+        }
+        QueryMsg::QueryCheckedPrices { assets, ema_only } => {
+            to_json_binary(&query_checked_prices(deps, env, assets, ema_only)?)
+        }
         QueryMsg::QueryConfig {} => to_json_binary(&query_config(deps)?),
         QueryMsg::QueryPythFeederConfig { asset } => {
             to_json_binary(&query_pyth_feeder_config(deps, asset)?)
         }
+        QueryMsg::QueryDerivedFeederConfig { asset } => {
+            to_json_binary(&query_derived_feeder_config(deps, asset)?)
+        }
+        QueryMsg::QueryUpdateFee { data } => to_json_binary(&query_update_fee(deps, data)?),
+        QueryMsg::QueryFeeds { start_after, limit } => {
+            to_json_binary(&query_feeds(deps, start_after, limit)?)
+        }
         QueryMsg::QueryExchangeRateByAssetLabel {
             base_label,
             quote_label,
@@ -82,10 +136,21 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             base_label,
             quote_label,
         )?),
+        QueryMsg::QueryConfigHistory { start_after, limit } => {
+            to_json_binary(&query_config_history(deps.storage, start_after, limit)?)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> StdResult<Response> {
+    let previous_version = assert_migrate_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    migrate_legacy_state(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "migrate"),
+        attr("previous_version", previous_version.as_deref().unwrap_or("none")),
+        attr("version", CONTRACT_VERSION),
+    ]))
 }
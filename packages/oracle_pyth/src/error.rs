@@ -14,4 +14,10 @@ pub enum ContractError {
 
     #[error("InvalidInput")]
     InvalidInput {},
+
+    #[error("Price circuit breaker: {0}")]
+    PriceCircuitBreaker(String),
+
+    #[error("Insufficient price update fee: {0}")]
+    InsufficientFee(String),
 }
@@ -1,9 +1,18 @@
 use crate::error::ContractError;
+use crate::querier::{cap_exchange_rate, query_hub_exchange_rate, query_update_fee};
 use crate::state::{
-    read_config, read_pyth_feeder_config, store_config, store_pyth_feeder_config, Config,
+    may_read_derived_feeder_config, may_read_pyth_feeder_config, read_config,
+    read_derived_feeder_config, read_pyth_feeder_config, store_config,
+    store_derived_feeder_config, store_pyth_feeder_config, Config, DerivedFeederConfig,
     PythFeederConfig,
 };
-use cosmwasm_std::{DepsMut, MessageInfo, Response};
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::{
+    to_json_binary, BankMsg, Binary, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response,
+    Uint128, WasmMsg,
+};
+use moneymarket::config_history::{config_change, record_config_change};
+use pyth_sdk_cw::ExecuteMsg as PythExecuteMsg;
 use pyth_sdk_cw::PriceIdentifier;
 
 /**
@@ -12,6 +21,7 @@ use pyth_sdk_cw::PriceIdentifier;
 #[allow(clippy::too_many_arguments)]
 pub fn config_feed_info(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     asset: String,
     price_feed_id: PriceIdentifier,
@@ -25,16 +35,62 @@ pub fn config_feed_info(
         return Err(ContractError::Unauthorized {});
     }
 
+    // keep the sanity bounds when a feed is reconfigured
+    let prev_config = may_read_pyth_feeder_config(deps.storage, asset.clone())?;
     let pyth_feeder_config = &PythFeederConfig {
-        price_feed_id: price_feed_id.clone(),
+        price_feed_id,
         price_feed_symbol: price_feed_symbol.clone(),
-        price_feed_decimal: price_feed_decimal.clone(),
+        price_feed_decimal,
         is_valid: true,
-        check_feed_age: check_feed_age.clone(),
-        price_feed_age: price_feed_age.clone(),
+        check_feed_age,
+        price_feed_age,
+        min_price: prev_config.as_ref().and_then(|c| c.min_price),
+        max_price: prev_config.as_ref().and_then(|c| c.max_price),
+        max_deviation: prev_config.as_ref().and_then(|c| c.max_deviation),
     };
 
-    store_pyth_feeder_config(deps.storage, asset.clone(), &pyth_feeder_config)?;
+    store_pyth_feeder_config(deps.storage, asset.clone(), pyth_feeder_config)?;
+
+    let field = |name: &str| format!("{}.{}", asset, name);
+    let prev_config = prev_config.as_ref();
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "config_feed_info",
+        vec![
+            config_change(
+                &field("price_feed_id"),
+                &prev_config.map(|c| c.price_feed_id),
+                &Some(price_feed_id),
+            )?,
+            config_change(
+                &field("price_feed_symbol"),
+                &prev_config.map(|c| c.price_feed_symbol.clone()),
+                &Some(price_feed_symbol.clone()),
+            )?,
+            config_change(
+                &field("price_feed_decimal"),
+                &prev_config.map(|c| c.price_feed_decimal),
+                &Some(price_feed_decimal),
+            )?,
+            config_change(
+                &field("is_valid"),
+                &prev_config.map(|c| c.is_valid),
+                &Some(true),
+            )?,
+            config_change(
+                &field("check_feed_age"),
+                &prev_config.map(|c| c.check_feed_age),
+                &Some(check_feed_age),
+            )?,
+            config_change(
+                &field("price_feed_age"),
+                &prev_config.map(|c| c.price_feed_age),
+                &Some(price_feed_age),
+            )?,
+        ],
+    )?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "config_feed_info"),
@@ -47,11 +103,243 @@ pub fn config_feed_info(
     ]))
 }
 
+/**
+ * Configure an asset priced from an underlying pyth feed and a hub exchange rate
+ */
+pub fn config_derived_feed_info(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: String,
+    underlying_asset: String,
+    hub_contract: String,
+    max_rate_change: Decimal256,
+) -> Result<Response, ContractError> {
+    let config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if asset == underlying_asset
+        || max_rate_change.is_zero()
+        || max_rate_change > Decimal256::one()
+    {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    // the underlying asset must be priced by its own pyth feed
+    read_pyth_feeder_config(deps.storage, underlying_asset.clone())?;
+
+    let hub_addr = deps.api.addr_validate(&hub_contract)?;
+    let exchange_rate = query_hub_exchange_rate(deps.as_ref(), hub_addr.to_string())?;
+    if exchange_rate.is_zero() {
+        return Err(ContractError::InvalidInput {});
+    }
+
+    // keep the sanity bounds when a feed is reconfigured
+    let prev_config = may_read_derived_feeder_config(deps.storage, asset.clone())?;
+    let derived_feeder_config = &DerivedFeederConfig {
+        underlying_asset: underlying_asset.clone(),
+        hub_contract: deps.api.addr_canonicalize(hub_addr.as_str())?,
+        max_rate_change,
+        last_exchange_rate: exchange_rate,
+        last_updated_time: env.block.time.seconds(),
+        min_price: prev_config.as_ref().and_then(|c| c.min_price),
+        max_price: prev_config.as_ref().and_then(|c| c.max_price),
+        max_deviation: prev_config.as_ref().and_then(|c| c.max_deviation),
+    };
+    store_derived_feeder_config(deps.storage, asset.clone(), derived_feeder_config)?;
+
+    let prev_hub_contract = match &prev_config {
+        Some(c) => Some(deps.api.addr_humanize(&c.hub_contract)?),
+        None => None,
+    };
+    let field = |name: &str| format!("{}.{}", asset, name);
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "config_derived_feed_info",
+        vec![
+            config_change(
+                &field("underlying_asset"),
+                &prev_config.as_ref().map(|c| c.underlying_asset.clone()),
+                &Some(underlying_asset.clone()),
+            )?,
+            config_change(&field("hub_contract"), &prev_hub_contract, &Some(hub_addr.clone()))?,
+            config_change(
+                &field("max_rate_change"),
+                &prev_config.as_ref().map(|c| c.max_rate_change),
+                &Some(max_rate_change),
+            )?,
+        ],
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "config_derived_feed_info"),
+        ("asset_address", asset.as_str()),
+        ("underlying_asset", underlying_asset.as_str()),
+        ("hub_contract", hub_addr.as_str()),
+        ("max_rate_change", &max_rate_change.to_string()),
+        ("exchange_rate", &exchange_rate.to_string()),
+    ]))
+}
+
+/**
+ * Sync the stored exchange rate of a derived asset with its hub, within the rate change cap
+ */
+pub fn update_derived_exchange_rate(
+    deps: DepsMut,
+    env: Env,
+    asset: String,
+) -> Result<Response, ContractError> {
+    let mut derived_feeder_config: DerivedFeederConfig =
+        read_derived_feeder_config(deps.storage, asset.clone())?;
+    let hub_exchange_rate = query_hub_exchange_rate(
+        deps.as_ref(),
+        deps.api
+            .addr_humanize(&derived_feeder_config.hub_contract)?
+            .to_string(),
+    )?;
+    let current_time = env.block.time.seconds();
+    let exchange_rate = cap_exchange_rate(&derived_feeder_config, hub_exchange_rate, current_time);
+
+    derived_feeder_config.last_exchange_rate = exchange_rate;
+    derived_feeder_config.last_updated_time = current_time;
+    store_derived_feeder_config(deps.storage, asset.clone(), &derived_feeder_config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_derived_exchange_rate"),
+        ("asset_address", asset.as_str()),
+        ("exchange_rate", &exchange_rate.to_string()),
+    ]))
+}
+
+/**
+ * Forward price update data to the pyth contract and refund the funds not spent on the fee
+ */
+pub fn update_price_feeds(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: Vec<Binary>,
+) -> Result<Response, ContractError> {
+    let config = read_config(deps.storage)?;
+    let pyth_contract = deps.api.addr_humanize(&config.pyth_contract)?;
+    let fee: Coin = query_update_fee(deps.as_ref(), data.clone())?;
+
+    let mut refunds: Vec<Coin> = vec![];
+    let mut fee_paid = Uint128::zero();
+    for coin in info.funds.into_iter() {
+        if coin.denom == fee.denom {
+            fee_paid = coin.amount;
+            if coin.amount > fee.amount {
+                refunds.push(Coin {
+                    denom: coin.denom,
+                    amount: coin.amount - fee.amount,
+                });
+            }
+        } else {
+            refunds.push(coin);
+        }
+    }
+    if fee_paid < fee.amount {
+        return Err(ContractError::InsufficientFee(fee.to_string()));
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: pyth_contract.to_string(),
+        msg: to_json_binary(&PythExecuteMsg::UpdatePriceFeeds { data })?,
+        funds: if fee.amount.is_zero() {
+            vec![]
+        } else {
+            vec![fee.clone()]
+        },
+    })];
+    if !refunds.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: refunds,
+        }));
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "update_price_feeds"),
+        ("fee", &fee.to_string()),
+    ]))
+}
+
+/**
+ * Set the sanity bounds of a feed, or of the derived price for a derived asset
+ */
+pub fn set_price_bounds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: String,
+    min_price: Option<Decimal256>,
+    max_price: Option<Decimal256>,
+    max_deviation: Option<Decimal256>,
+) -> Result<Response, ContractError> {
+    let config = read_config(deps.storage)?;
+    if deps.api.addr_canonicalize(info.sender.as_str())? != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let (Some(min_price), Some(max_price)) = (min_price, max_price) {
+        if min_price > max_price {
+            return Err(ContractError::InvalidInput {});
+        }
+    }
+
+    let prev_bounds = match may_read_derived_feeder_config(deps.storage, asset.clone())? {
+        Some(mut derived_feeder_config) => {
+            let prev_bounds = derived_feeder_config.bounds();
+            derived_feeder_config.min_price = min_price;
+            derived_feeder_config.max_price = max_price;
+            derived_feeder_config.max_deviation = max_deviation;
+            store_derived_feeder_config(deps.storage, asset.clone(), &derived_feeder_config)?;
+            prev_bounds
+        }
+        None => {
+            let mut pyth_feeder_config: PythFeederConfig =
+                read_pyth_feeder_config(deps.storage, asset.clone())?;
+            let prev_bounds = pyth_feeder_config.bounds();
+            pyth_feeder_config.min_price = min_price;
+            pyth_feeder_config.max_price = max_price;
+            pyth_feeder_config.max_deviation = max_deviation;
+            store_pyth_feeder_config(deps.storage, asset.clone(), &pyth_feeder_config)?;
+            prev_bounds
+        }
+    };
+
+    let field = |name: &str| format!("{}.{}", asset, name);
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_price_bounds",
+        vec![
+            config_change(&field("min_price"), &prev_bounds.min_price, &min_price)?,
+            config_change(&field("max_price"), &prev_bounds.max_price, &max_price)?,
+            config_change(&field("max_deviation"), &prev_bounds.max_deviation, &max_deviation)?,
+        ],
+    )?;
+
+    let fmt = |value: Option<Decimal256>| value.map_or("none".to_string(), |v| v.to_string());
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_price_bounds"),
+        ("asset_address", asset.as_str()),
+        ("min_price", &fmt(min_price)),
+        ("max_price", &fmt(max_price)),
+        ("max_deviation", &fmt(max_deviation)),
+    ]))
+}
+
 /**
  * Update the config of the contract
  */
 pub fn set_config_feed_valid(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     asset: String,
     is_valid: bool,
@@ -63,9 +351,17 @@ pub fn set_config_feed_valid(
 
     let mut pyth_feeder_config: PythFeederConfig =
         read_pyth_feeder_config(deps.storage, asset.clone())?;
+    let prev_is_valid = pyth_feeder_config.is_valid;
     pyth_feeder_config.is_valid = is_valid;
 
     store_pyth_feeder_config(deps.storage, asset.clone(), &pyth_feeder_config)?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "set_config_feed_valid",
+        vec![config_change(&format!("{}.is_valid", asset), &prev_is_valid, &is_valid)?],
+    )?;
     Ok(Response::new().add_attributes(vec![
         ("action", "set_config_feed_valid"),
         ("asset_address", asset.as_str()),
@@ -78,6 +374,7 @@ pub fn set_config_feed_valid(
  */
 pub fn change_owner(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_owner: String,
 ) -> Result<Response, ContractError> {
@@ -86,11 +383,19 @@ pub fn change_owner(
         return Err(ContractError::Unauthorized {});
     }
 
+    let prev_owner = deps.api.addr_humanize(&config.owner)?.to_string();
     config.owner = deps
         .api
         .addr_canonicalize(&new_owner)
         .map_err(|_| ContractError::InvalidInput {})?;
     store_config(deps.storage, &config)?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "change_owner",
+        vec![config_change("owner", &prev_owner, &new_owner)?],
+    )?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "change_owner"),
@@ -100,6 +405,7 @@ pub fn change_owner(
 
 pub fn change_pyth_contract(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     new_pyth_contract: String,
 ) -> Result<Response, ContractError> {
@@ -108,11 +414,19 @@ pub fn change_pyth_contract(
         return Err(ContractError::Unauthorized {});
     }
 
+    let prev_pyth_contract = deps.api.addr_humanize(&config.pyth_contract)?.to_string();
     config.pyth_contract = deps
         .api
         .addr_canonicalize(&new_pyth_contract)
         .map_err(|_| ContractError::InvalidInput {})?;
     store_config(deps.storage, &config)?;
+    record_config_change(
+        deps.storage,
+        &env,
+        &info.sender,
+        "change_pyth_contract",
+        vec![config_change("pyth_contract", &prev_pyth_contract, &new_pyth_contract)?],
+    )?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "change_pyth_contract"),
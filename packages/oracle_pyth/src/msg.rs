@@ -1,7 +1,10 @@
 use cosmwasm_bignumber::Decimal256;
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Binary, Coin};
+use moneymarket::config_history::ConfigHistoryResponse;
 use pyth_sdk_cw::PriceIdentifier;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -36,6 +39,15 @@ pub struct PriceResponse {
     pub last_updated_quote: u64,
 }
 
+/// `price` is set unless the price tripped the circuit breaker, then `circuit_breaker`
+/// holds the reason
+#[cw_serde]
+pub struct CheckedPriceResponse {
+    pub asset: String,
+    pub price: Option<PriceResponse>,
+    pub circuit_breaker: Option<String>,
+}
+
 #[cw_serde]
 pub struct PythFeederConfigResponse {
     pub price_feed_id: PriceIdentifier,
@@ -44,6 +56,37 @@ pub struct PythFeederConfigResponse {
     pub price_feed_age: u64,
     pub check_feed_age: bool,
     pub is_valid: bool,
+    pub min_price: Option<Decimal256>,
+    pub max_price: Option<Decimal256>,
+    pub max_deviation: Option<Decimal256>,
+}
+
+#[cw_serde]
+pub struct FeedResponse {
+    pub asset: String,
+    pub price_feed_id: PriceIdentifier,
+    pub price_feed_symbol: String,
+    pub price_feed_decimal: u32,
+    pub price_feed_age: u64,
+    pub check_feed_age: bool,
+    pub is_valid: bool,
+    pub min_price: Option<Decimal256>,
+    pub max_price: Option<Decimal256>,
+    pub max_deviation: Option<Decimal256>,
+    /// Set for derived assets, whose feed fields are those of the underlying asset
+    pub underlying_asset: Option<String>,
+}
+
+#[cw_serde]
+pub struct DerivedFeederConfigResponse {
+    pub underlying_asset: String,
+    pub hub_contract: String,
+    pub max_rate_change: Decimal256,
+    pub last_exchange_rate: Decimal256,
+    pub last_updated_time: u64,
+    pub min_price: Option<Decimal256>,
+    pub max_price: Option<Decimal256>,
+    pub max_deviation: Option<Decimal256>,
 }
 
 #[cw_serde]
@@ -71,6 +114,30 @@ pub enum ExecuteMsg {
         price_feed_age: u64,
     },
 
+    ConfigDerivedFeedInfo {
+        asset: String,
+        underlying_asset: String,
+        hub_contract: String,
+        max_rate_change: Decimal256,
+    },
+    UpdateDerivedExchangeRate {
+        asset: String,
+    },
+    /// Forward pyth price update data (VAAs) to the pyth contract, paying the update fee
+    /// from the sent funds and refunding the excess
+    UpdatePriceFeeds {
+        data: Vec<Binary>,
+    },
+
+    /// Sanity bounds on the feed, or on the derived price for a derived asset;
+    /// queries error once a price violates them
+    SetPriceBounds {
+        asset: String,
+        min_price: Option<Decimal256>,
+        max_price: Option<Decimal256>,
+        max_deviation: Option<Decimal256>,
+    },
+
     SetConfigFeedValid {
         asset: String,
         valid: bool,
@@ -90,16 +157,51 @@ pub enum QueryMsg {
     QueryPrice { asset: String },
     #[returns(Vec < PriceResponse >)]
     QueryPrices { assets: Vec<String> },
+    /// Like `QueryPrices`, but only the EMA price is checked against the sanity bounds
+    #[returns(Vec < PriceResponse >)]
+    QueryEmaPrices { assets: Vec<String> },
+    /// Like `QueryPrices` (`QueryEmaPrices` with `ema_only`), but a price tripping the circuit
+    /// breaker is reported in its response instead of failing the query
+    #[returns(Vec < CheckedPriceResponse >)]
+    QueryCheckedPrices { assets: Vec<String>, ema_only: bool },
     #[returns(ConfigResponse)]
     QueryConfig {},
     #[returns(PythFeederConfigResponse)]
     QueryPythFeederConfig { asset: String },
+    #[returns(DerivedFeederConfigResponse)]
+    QueryDerivedFeederConfig { asset: String },
+    #[returns(Coin)]
+    QueryUpdateFee { data: Vec<Binary> },
+    #[returns(Vec < FeedResponse >)]
+    QueryFeeds {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(Decimal256)]
     QueryExchangeRateByAssetLabel {
         base_label: String,
         quote_label: String,
     },
+    /// Owner actions on the contract, oldest first
+    #[returns(ConfigHistoryResponse)]
+    QueryConfigHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
 pub struct MigrateMsg {}
+
+/// Query interface of a liquid staking hub contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HubQueryMsg {
+    State {},
+}
+
+/// Only the exchange rate is read; other fields of the hub state are ignored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HubStateResponse {
+    pub exchange_rate: Decimal256,
+}
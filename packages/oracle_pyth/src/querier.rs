@@ -1,13 +1,23 @@
-use crate::msg::{ConfigResponse, PriceResponse, PythFeederConfigResponse};
-use cosmwasm_bignumber::Decimal256;
-use cosmwasm_std::{Deps, Env, StdError, StdResult};
-use pyth_sdk_cw::{query_price_feed, Price, PriceFeedResponse};
+use crate::msg::{
+    CheckedPriceResponse, ConfigResponse, DerivedFeederConfigResponse, FeedResponse, HubQueryMsg,
+    HubStateResponse, PriceResponse, PythFeederConfigResponse,
+};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{
+    to_json_binary, Binary, Coin, Deps, Env, QueryRequest, StdError, StdResult, WasmQuery,
+};
+use pyth_sdk_cw::{get_update_fee, query_price_feed, Price, PriceFeedResponse};
 use std::ops::Div;
 
 use crate::error::ContractError;
-use crate::state::{read_config, read_pyth_feeder_config, Config, PythFeederConfig};
+use crate::state::{
+    may_read_derived_feeder_config, read_config, read_derived_feeder_config, read_feed_assets,
+    read_pyth_feeder_config, Config, DerivedFeederConfig, PriceBounds, PythFeederConfig,
+};
 use bigint::uint::U256;
 
+const SECONDS_PER_DAY: u64 = 86400;
+
 /**
  * Query the config of the oracle
  */
@@ -32,6 +42,77 @@ pub fn query_pyth_feeder_config(deps: Deps, asset: String) -> StdResult<PythFeed
         price_feed_age: pyth_feeder_config.price_feed_age,
         check_feed_age: pyth_feeder_config.check_feed_age,
         is_valid: pyth_feeder_config.is_valid,
+        min_price: pyth_feeder_config.min_price,
+        max_price: pyth_feeder_config.max_price,
+        max_deviation: pyth_feeder_config.max_deviation,
+    })
+}
+
+/**
+ * Query the fee required by the pyth contract to apply the given price update data
+ */
+pub fn query_update_fee(deps: Deps, data: Vec<Binary>) -> StdResult<Coin> {
+    let config: Config = read_config(deps.storage)?;
+    let pyth_contract = deps.api.addr_humanize(&config.pyth_contract)?;
+    get_update_fee(&deps.querier, pyth_contract, &data)
+}
+
+/**
+ * Query the feeds of all registered assets. Derived assets report the pyth feed of their
+ * underlying asset
+ */
+pub fn query_feeds(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<FeedResponse>> {
+    read_feed_assets(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|asset| {
+            let underlying_asset = may_read_derived_feeder_config(deps.storage, asset.clone())?
+                .map(|derived_feeder_config| derived_feeder_config.underlying_asset);
+            let pyth_feeder_config = read_pyth_feeder_config(
+                deps.storage,
+                underlying_asset.clone().unwrap_or_else(|| asset.clone()),
+            )?;
+            Ok(FeedResponse {
+                asset,
+                price_feed_id: pyth_feeder_config.price_feed_id,
+                price_feed_symbol: pyth_feeder_config.price_feed_symbol,
+                price_feed_decimal: pyth_feeder_config.price_feed_decimal,
+                price_feed_age: pyth_feeder_config.price_feed_age,
+                check_feed_age: pyth_feeder_config.check_feed_age,
+                is_valid: pyth_feeder_config.is_valid,
+                min_price: pyth_feeder_config.min_price,
+                max_price: pyth_feeder_config.max_price,
+                max_deviation: pyth_feeder_config.max_deviation,
+                underlying_asset,
+            })
+        })
+        .collect()
+}
+
+/**
+ * Query the derived feeder config of the asset
+ */
+pub fn query_derived_feeder_config(
+    deps: Deps,
+    asset: String,
+) -> StdResult<DerivedFeederConfigResponse> {
+    let derived_feeder_config: DerivedFeederConfig =
+        read_derived_feeder_config(deps.storage, asset)?;
+    Ok(DerivedFeederConfigResponse {
+        underlying_asset: derived_feeder_config.underlying_asset,
+        hub_contract: deps
+            .api
+            .addr_humanize(&derived_feeder_config.hub_contract)?
+            .to_string(),
+        max_rate_change: derived_feeder_config.max_rate_change,
+        last_exchange_rate: derived_feeder_config.last_exchange_rate,
+        last_updated_time: derived_feeder_config.last_updated_time,
+        min_price: derived_feeder_config.min_price,
+        max_price: derived_feeder_config.max_price,
+        max_deviation: derived_feeder_config.max_deviation,
     })
 }
 
@@ -39,12 +120,47 @@ pub fn query_pyth_feeder_config(deps: Deps, asset: String) -> StdResult<PythFeed
  * Query the price of the asset
  */
 pub fn query_price(deps: Deps, env: Env, asset: String) -> StdResult<PriceResponse> {
+    Ok(query_checked_price(deps, env, asset, PriceCheck::Full)?)
+}
+
+/// Which prices of a feed are checked against its sanity bounds
+#[derive(Clone, Copy, PartialEq)]
+pub enum PriceCheck {
+    /// EMA and spot prices must be within bounds and within `max_deviation` of each other
+    Full,
+    /// Only the EMA price must be within bounds
+    EmaOnly,
+}
+
+fn query_checked_price(
+    deps: Deps,
+    env: Env,
+    asset: String,
+    check: PriceCheck,
+) -> Result<PriceResponse, ContractError> {
+    match may_read_derived_feeder_config(deps.storage, asset.clone())? {
+        Some(derived_feeder_config) => {
+            query_derived_price(deps, env, asset, derived_feeder_config, check)
+        }
+        None => query_pyth_price(deps, env, asset, check),
+    }
+}
+
+/**
+ * Query the price of an asset that has its own pyth feed
+ */
+fn query_pyth_price(
+    deps: Deps,
+    env: Env,
+    asset: String,
+    check: PriceCheck,
+) -> Result<PriceResponse, ContractError> {
     let config: Config = read_config(deps.storage)?;
 
     let pyth_feeder_config: PythFeederConfig =
         read_pyth_feeder_config(deps.storage, asset.clone())?;
     if !pyth_feeder_config.is_valid {
-        return Err(StdError::generic_err("Asset is not valid"));
+        return Err(ContractError::Std(StdError::generic_err("Asset is not valid")));
     }
 
     let pyth_contract = deps.api.addr_humanize(&config.pyth_contract)?;
@@ -88,10 +204,18 @@ pub fn query_price(deps: Deps, env: Env, asset: String) -> StdResult<PriceRespon
 
     let decimal: u32 = pyth_feeder_config.price_feed_decimal;
     let decimal_places =
-        Decimal256::from_ratio(U256::from(1u64), U256::from(10u64.pow(decimal as u32)));
+        Decimal256::from_ratio(U256::from(1u64), U256::from(10u64.pow(decimal)));
     let evm_price_decimal = Decimal256::from_ratio(ema_price.price, 1) * decimal_places;
     let current_price_decimal = Decimal256::from_ratio(current_price.price, 1) * decimal_places;
 
+    check_price_sanity(
+        &asset,
+        &pyth_feeder_config.bounds(),
+        evm_price_decimal,
+        current_price_decimal,
+        check,
+    )?;
+
     let feed_time_u64: u64 = ema_price
         .publish_time
         .try_into()
@@ -107,13 +231,199 @@ pub fn query_price(deps: Deps, env: Env, asset: String) -> StdResult<PriceRespon
     })
 }
 
+/**
+ * Query the price of a derived asset: underlying pyth price * hub exchange rate
+ */
+fn query_derived_price(
+    deps: Deps,
+    env: Env,
+    asset: String,
+    derived_feeder_config: DerivedFeederConfig,
+    check: PriceCheck,
+) -> Result<PriceResponse, ContractError> {
+    let underlying_price = query_pyth_price(
+        deps,
+        env.clone(),
+        derived_feeder_config.underlying_asset.clone(),
+        check,
+    )?;
+    let hub_exchange_rate = query_hub_exchange_rate(
+        deps,
+        deps.api
+            .addr_humanize(&derived_feeder_config.hub_contract)?
+            .to_string(),
+    )?;
+    let exchange_rate =
+        cap_exchange_rate(&derived_feeder_config, hub_exchange_rate, env.block.time.seconds());
+
+    let emv_price = underlying_price.emv_price * exchange_rate;
+    let price = underlying_price.price * exchange_rate;
+    check_price_sanity(&asset, &derived_feeder_config.bounds(), emv_price, price, check)?;
+
+    Ok(PriceResponse {
+        asset,
+        emv_price,
+        emv_price_raw: apply_rate_to_raw(underlying_price.emv_price_raw, exchange_rate)?,
+        price,
+        price_raw: apply_rate_to_raw(underlying_price.price_raw, exchange_rate)?,
+        last_updated_base: underlying_price.last_updated_base,
+        last_updated_quote: underlying_price.last_updated_quote,
+    })
+}
+
+/**
+ * Query the current exchange rate of a liquid staking hub
+ */
+pub fn query_hub_exchange_rate(deps: Deps, hub_contract: String) -> StdResult<Decimal256> {
+    let state: HubStateResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: hub_contract,
+        msg: to_json_binary(&HubQueryMsg::State {})?,
+    }))?;
+    Ok(state.exchange_rate)
+}
+
+/**
+ * Limit the hub exchange rate to the maximum increase allowed since the last stored rate.
+ * The allowance stops growing after a day, so a stale rate can never jump by more than
+ * `max_rate_change` at once. Decreases are passed through unchanged.
+ */
+pub fn cap_exchange_rate(
+    derived_feeder_config: &DerivedFeederConfig,
+    hub_exchange_rate: Decimal256,
+    current_time: u64,
+) -> Decimal256 {
+    let last_exchange_rate = derived_feeder_config.last_exchange_rate;
+    let elapsed = current_time
+        .saturating_sub(derived_feeder_config.last_updated_time)
+        .min(SECONDS_PER_DAY);
+    let max_exchange_rate = last_exchange_rate
+        + last_exchange_rate
+            * derived_feeder_config.max_rate_change
+            * Decimal256::from_ratio(elapsed, SECONDS_PER_DAY);
+
+    if hub_exchange_rate > max_exchange_rate {
+        max_exchange_rate
+    } else {
+        hub_exchange_rate
+    }
+}
+
+fn apply_rate_to_raw(raw: i64, exchange_rate: Decimal256) -> StdResult<i64> {
+    let raw_u64: u64 = raw
+        .try_into()
+        .map_err(|_| StdError::generic_err("Negative price can not be derived"))?;
+    let derived: u128 = (Uint256::from(raw_u64) * exchange_rate).into();
+    derived
+        .try_into()
+        .map_err(|_| StdError::generic_err("Failed to convert u128 to i64"))
+}
+
+/**
+ * Reject prices outside the feed's bounds, or with an EMA/spot gap above `max_deviation`
+ */
+pub fn check_price_sanity(
+    asset: &str,
+    bounds: &PriceBounds,
+    ema_price: Decimal256,
+    price: Decimal256,
+    check: PriceCheck,
+) -> Result<(), ContractError> {
+    let checked_prices = match check {
+        PriceCheck::Full => vec![ema_price, price],
+        PriceCheck::EmaOnly => vec![ema_price],
+    };
+    for checked_price in checked_prices {
+        if bounds
+            .min_price
+            .is_some_and(|min_price| checked_price < min_price)
+            || bounds
+                .max_price
+                .is_some_and(|max_price| checked_price > max_price)
+        {
+            return Err(ContractError::PriceCircuitBreaker(format!(
+                "{} price {} out of bounds",
+                asset, checked_price
+            )));
+        }
+    }
+
+    if check == PriceCheck::Full {
+        if let Some(max_deviation) = bounds.max_deviation {
+            let deviation = if ema_price > price {
+                ema_price - price
+            } else {
+                price - ema_price
+            };
+            if ema_price.is_zero() || deviation / ema_price > max_deviation {
+                return Err(ContractError::PriceCircuitBreaker(format!(
+                    "{} price {} deviates from ema price {}",
+                    asset, price, ema_price
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /**
  * Query the prices of the given assets
  */
 pub fn query_prices(deps: Deps, env: Env, assets: Vec<String>) -> StdResult<Vec<PriceResponse>> {
     let mut prices = Vec::new();
     for asset in assets {
-        let price = query_price(deps.clone(), env.clone(), asset)?;
+        let price = query_price(deps, env.clone(), asset)?;
+        prices.push(price);
+    }
+    Ok(prices)
+}
+
+/**
+ * Query the prices of the given assets, only checking the EMA prices against the sanity bounds
+ */
+pub fn query_ema_prices(
+    deps: Deps,
+    env: Env,
+    assets: Vec<String>,
+) -> StdResult<Vec<PriceResponse>> {
+    let mut prices = Vec::new();
+    for asset in assets {
+        let price = query_checked_price(deps, env.clone(), asset, PriceCheck::EmaOnly)?;
+        prices.push(price);
+    }
+    Ok(prices)
+}
+
+/**
+ * Query the prices of the given assets, reporting a price that trips the circuit breaker in its
+ * response instead of failing the query
+ */
+pub fn query_checked_prices(
+    deps: Deps,
+    env: Env,
+    assets: Vec<String>,
+    ema_only: bool,
+) -> StdResult<Vec<CheckedPriceResponse>> {
+    let check = if ema_only {
+        PriceCheck::EmaOnly
+    } else {
+        PriceCheck::Full
+    };
+    let mut prices = Vec::new();
+    for asset in assets {
+        let price = match query_checked_price(deps, env.clone(), asset.clone(), check) {
+            Ok(price) => CheckedPriceResponse {
+                asset,
+                price: Some(price),
+                circuit_breaker: None,
+            },
+            Err(ContractError::PriceCircuitBreaker(reason)) => CheckedPriceResponse {
+                asset,
+                price: None,
+                circuit_breaker: Some(reason),
+            },
+            Err(err) => return Err(err.into()),
+        };
         prices.push(price);
     }
     Ok(prices)
@@ -125,8 +435,8 @@ pub fn query_exchange_rate_by_asset_label(
     base_label: String,
     quote_label: String,
 ) -> StdResult<Decimal256> {
-    let base_price = query_price(deps.clone(), env.clone(), base_label)?;
-    let quote_price = query_price(deps.clone(), env.clone(), quote_label)?;
+    let base_price = query_price(deps, env.clone(), base_label)?;
+    let quote_price = query_price(deps, env.clone(), quote_label)?;
     Ok(base_price.emv_price.div(quote_price.emv_price))
 }
 
@@ -1,10 +1,11 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, StdError, StdResult, Storage};
-use cosmwasm_storage::{singleton, singleton_read};
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::{CanonicalAddr, Order, StdError, StdResult, Storage};
 
-use cw_storage_plus::Map;
+use cw_storage_plus::{Bound, Item, Map};
+use moneymarket::migration::migrate_singleton;
 use pyth_sdk_cw::PriceIdentifier;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -15,6 +16,61 @@ pub struct PythFeederConfig {
     pub is_valid: bool,
     pub check_feed_age: bool,
     pub price_feed_age: u64,
+    /// Prices below `min_price` or above `max_price` are rejected
+    #[serde(default)]
+    pub min_price: Option<Decimal256>,
+    #[serde(default)]
+    pub max_price: Option<Decimal256>,
+    /// Maximum relative gap between the EMA and the spot price
+    #[serde(default)]
+    pub max_deviation: Option<Decimal256>,
+}
+
+impl PythFeederConfig {
+    pub fn bounds(&self) -> PriceBounds {
+        PriceBounds {
+            min_price: self.min_price,
+            max_price: self.max_price,
+            max_deviation: self.max_deviation,
+        }
+    }
+}
+
+/// Prices an asset as `underlying_asset` price * hub exchange rate.
+/// Rate increases are capped at `max_rate_change` (relative, per day, at most one day's worth)
+/// measured from `last_exchange_rate`; rate decreases (e.g. slashing) are applied immediately.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DerivedFeederConfig {
+    pub underlying_asset: String,
+    pub hub_contract: CanonicalAddr,
+    pub max_rate_change: Decimal256,
+    pub last_exchange_rate: Decimal256,
+    pub last_updated_time: u64,
+    /// Sanity bounds on the derived price, checked on top of the underlying feed's bounds
+    #[serde(default)]
+    pub min_price: Option<Decimal256>,
+    #[serde(default)]
+    pub max_price: Option<Decimal256>,
+    #[serde(default)]
+    pub max_deviation: Option<Decimal256>,
+}
+
+impl DerivedFeederConfig {
+    pub fn bounds(&self) -> PriceBounds {
+        PriceBounds {
+            min_price: self.min_price,
+            max_price: self.max_price,
+            max_deviation: self.max_deviation,
+        }
+    }
+}
+
+/// Sanity bounds a price must satisfy, see `check_price_sanity`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PriceBounds {
+    pub min_price: Option<Decimal256>,
+    pub max_price: Option<Decimal256>,
+    pub max_deviation: Option<Decimal256>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -24,15 +80,24 @@ pub struct Config {
 }
 
 pub const PYTH_FEEDER_CONFIG: Map<String, PythFeederConfig> = Map::new("pyth_feeder_config");
+pub const DERIVED_FEEDER_CONFIG: Map<String, DerivedFeederConfig> =
+    Map::new("derived_feeder_config");
 
-static KEY_CONFIG: &[u8] = b"config";
+const CONFIG: Item<Config> = Item::new("config");
 
 pub fn store_config(storage: &mut dyn Storage, config: &Config) -> StdResult<()> {
-    singleton(storage, KEY_CONFIG).save(config)
+    CONFIG.save(storage, config)
 }
 
 pub fn read_config(storage: &dyn Storage) -> StdResult<Config> {
-    singleton_read(storage, KEY_CONFIG).load()
+    CONFIG.load(storage)
+}
+
+/// Move the config written by a `cosmwasm_storage` singleton to `CONFIG`,
+/// whether or not cw2 versions were recorded yet; a no-op once moved. The
+/// feed maps already use `cw-storage-plus`
+pub fn migrate_legacy_state(storage: &mut dyn Storage) -> StdResult<()> {
+    migrate_singleton(storage, &CONFIG)
 }
 
 pub fn store_pyth_feeder_config(
@@ -46,12 +111,69 @@ pub fn store_pyth_feeder_config(
     })
 }
 
+pub fn may_read_pyth_feeder_config(
+    storage: &dyn Storage,
+    asset: String,
+) -> StdResult<Option<PythFeederConfig>> {
+    PYTH_FEEDER_CONFIG.may_load(storage, asset)
+}
+
 pub fn read_pyth_feeder_config(
     storage: &dyn Storage,
     asset: String,
 ) -> Result<PythFeederConfig, StdError> {
-    let pyth_feeder_config = PYTH_FEEDER_CONFIG
+    PYTH_FEEDER_CONFIG
         .may_load(storage, asset)?
-        .ok_or_else(|| StdError::generic_err("Pyth feeder config not found"));
-    Ok(pyth_feeder_config.unwrap())
+        .ok_or_else(|| StdError::generic_err("Pyth feeder config not found"))
+}
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+
+/// Assets with a pyth or a derived feed, ordered by asset
+pub fn read_feed_assets(
+    storage: &dyn Storage,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<String>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let mut assets = PYTH_FEEDER_CONFIG
+        .keys(storage, start_after.clone().map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<String>>>()?;
+    for asset in DERIVED_FEEDER_CONFIG
+        .keys(storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+        .take(limit)
+    {
+        assets.push(asset?);
+    }
+    assets.sort();
+    assets.dedup();
+    assets.truncate(limit);
+    Ok(assets)
+}
+
+pub fn store_derived_feeder_config(
+    storage: &mut dyn Storage,
+    asset: String,
+    derived_feeder_config: &DerivedFeederConfig,
+) -> StdResult<()> {
+    DERIVED_FEEDER_CONFIG.save(storage, asset, derived_feeder_config)
+}
+
+pub fn read_derived_feeder_config(
+    storage: &dyn Storage,
+    asset: String,
+) -> StdResult<DerivedFeederConfig> {
+    DERIVED_FEEDER_CONFIG
+        .may_load(storage, asset)?
+        .ok_or_else(|| StdError::generic_err("Derived feeder config not found"))
+}
+
+pub fn may_read_derived_feeder_config(
+    storage: &dyn Storage,
+    asset: String,
+) -> StdResult<Option<DerivedFeederConfig>> {
+    DERIVED_FEEDER_CONFIG.may_load(storage, asset)
 }
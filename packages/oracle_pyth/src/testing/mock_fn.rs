@@ -7,10 +7,11 @@ use cosmwasm_std::{Addr, Env, MessageInfo, OwnedDeps, Response, StdResult};
 
 pub const CREATOR: &str = "creator";
 pub const PYTH_CONTRACT: &str = "pyth_contract";
+pub const HUB_CONTRACT: &str = "hub_contract";
 
-pub fn mock_instantiate_msg(pyth_contract: &String) -> InstantiateMsg {
+pub fn mock_instantiate_msg(pyth_contract: &str) -> InstantiateMsg {
     InstantiateMsg {
-        pyth_contract: pyth_contract.clone(),
+        pyth_contract: pyth_contract.to_string(),
         owner: Addr::unchecked(CREATOR),
     }
 }
@@ -1,26 +1,57 @@
-use crate::handler::{change_owner, change_pyth_contract, config_feed_info, set_config_feed_valid};
-use crate::querier::{query_config, query_pyth_feeder_config};
-use crate::testing::mock_fn::{mock_instantiate, mock_instantiate_msg, CREATOR, PYTH_CONTRACT};
-use cosmwasm_std::testing::mock_info;
-use pyth_sdk_cw::PriceIdentifier;
+use crate::error::ContractError;
+use crate::handler::{
+    change_owner, change_pyth_contract, config_derived_feed_info, config_feed_info,
+    set_config_feed_valid, set_price_bounds, update_derived_exchange_rate, update_price_feeds,
+};
+use crate::contract::migrate;
+use crate::msg::{HubStateResponse, MigrateMsg};
+use crate::querier::{
+    check_price_sanity, query_checked_prices, query_config, query_derived_feeder_config,
+    query_feeds, query_price, query_pyth_feeder_config, PriceCheck,
+};
+use crate::state::{read_config, read_pyth_feeder_config};
+use crate::testing::mock_fn::{
+    mock_instantiate, mock_instantiate_msg, CREATOR, HUB_CONTRACT, PYTH_CONTRACT,
+};
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{
+    attr, coin, to_json_binary, BankMsg, Binary, ContractResult, CosmosMsg, Storage, SubMsg,
+    SystemError, SystemResult, WasmMsg, WasmQuery,
+};
+use cosmwasm_storage::singleton;
+use cw2::get_contract_version;
+use moneymarket::config_history::{query_config_history, ConfigChange};
+use pyth_sdk_cw::{
+    ExecuteMsg as PythExecuteMsg, Price, PriceFeed, PriceFeedResponse, PriceIdentifier,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::str::FromStr;
 
 #[test]
 fn test_instantiate() {
-    let msg = mock_instantiate_msg(&PYTH_CONTRACT.clone().to_string());
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
     let (mut deps, _env, info, res) = mock_instantiate(msg);
     assert!(res.is_ok());
     let config = query_config(deps.as_ref()).unwrap();
-    assert_eq!(config.pyth_contract, PYTH_CONTRACT.clone().to_string());
-    assert_eq!(config.owner, CREATOR.clone().to_string());
+    assert_eq!(config.pyth_contract, PYTH_CONTRACT.to_string());
+    assert_eq!(config.owner, CREATOR.to_string());
 
     //change owner and pyth contract
-    let res = change_owner(deps.as_mut(), info.clone(), "new_owner".to_string());
+    let res = change_owner(deps.as_mut(), mock_env(), info.clone(), "new_owner".to_string());
     assert!(res.is_ok());
-    let res = change_pyth_contract(deps.as_mut(), info.clone(), "new_pyth_contract".to_string());
+    let res = change_pyth_contract(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        "new_pyth_contract".to_string(),
+    );
     assert!(res.is_err());
     let new_info = mock_info("new_owner", &[]);
     let res = change_pyth_contract(
         deps.as_mut(),
+        mock_env(),
         new_info.clone(),
         "new_pyth_contract".to_string(),
     );
@@ -32,7 +63,7 @@ fn test_instantiate() {
 
 #[test]
 fn test_update_config_feed_info() {
-    let msg = mock_instantiate_msg(&PYTH_CONTRACT.clone().to_string());
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
     let (mut deps, _env, info, res) = mock_instantiate(msg);
     assert!(res.is_ok());
 
@@ -47,13 +78,14 @@ fn test_update_config_feed_info() {
     let price_feed_id_type = PriceIdentifier::from_hex(price_feed_id).unwrap();
     let config_feed_info_res = config_feed_info(
         deps.as_mut(),
+        mock_env(),
         info.clone(),
         asset.clone(),
-        price_feed_id_type.clone(),
-        price_feed_symbol.clone().to_string(),
-        price_feed_decimal.clone(),
-        check_feed_age.clone(),
-        price_feed_age.clone(),
+        price_feed_id_type,
+        price_feed_symbol.to_string(),
+        price_feed_decimal,
+        check_feed_age,
+        price_feed_age,
     );
 
     assert!(config_feed_info_res.is_ok());
@@ -64,29 +96,518 @@ fn test_update_config_feed_info() {
     assert_eq!(feeder_config.price_feed_decimal, price_feed_decimal);
     assert_eq!(feeder_config.price_feed_id, price_feed_id_type);
     assert_eq!(feeder_config.price_feed_symbol, price_feed_symbol);
-    assert_eq!(feeder_config.is_valid, true);
+    assert!(feeder_config.is_valid);
 
     // set config feed valid
-    let res = set_config_feed_valid(deps.as_mut(), info.clone(), asset.clone(), false);
+    let res = set_config_feed_valid(deps.as_mut(), mock_env(), info.clone(), asset.clone(), false);
     assert!(res.is_ok());
     let feeder_config = query_pyth_feeder_config(deps.as_ref(), asset.clone()).unwrap();
-    assert_eq!(feeder_config.is_valid, false);
+    assert!(!feeder_config.is_valid);
 
     // change owner
-    let res = change_owner(deps.as_mut(), info.clone(), "new_owner".to_string());
+    let res = change_owner(deps.as_mut(), mock_env(), info.clone(), "new_owner".to_string());
     assert!(res.is_ok());
 
-    let res = set_config_feed_valid(deps.as_mut(), info.clone(), asset.clone(), false);
+    let res = set_config_feed_valid(deps.as_mut(), mock_env(), info.clone(), asset.clone(), false);
     assert!(res.is_err());
     let config_feed_info_res = config_feed_info(
         deps.as_mut(),
+        mock_env(),
         info.clone(),
         asset.clone(),
-        price_feed_id_type.clone(),
-        price_feed_symbol.clone().to_string(),
-        price_feed_decimal.clone(),
-        check_feed_age.clone(),
-        price_feed_age.clone(),
+        price_feed_id_type,
+        price_feed_symbol.to_string(),
+        price_feed_decimal,
+        check_feed_age,
+        price_feed_age,
     );
     assert!(config_feed_info_res.is_err());
 }
+
+#[test]
+fn test_derived_feed_price() {
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
+    let (mut deps, env, info, res) = mock_instantiate(msg);
+    assert!(res.is_ok());
+
+    let price_feed_id_type = PriceIdentifier::from_hex(
+        "53614f1cb0c031d4af66c04cb9c756234adad0e1cee85303795091499a4084eb",
+    )
+    .unwrap();
+    let publish_time = env.block.time.seconds() as i64;
+    let hub_exchange_rate = Rc::new(RefCell::new(Decimal256::from_str("1.05").unwrap()));
+    let querier_rate = hub_exchange_rate.clone();
+    deps.querier.update_wasm(move |request| match request {
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == PYTH_CONTRACT => {
+            let price = Price {
+                price: 50_000_000,
+                conf: 0,
+                expo: -8,
+                publish_time,
+            };
+            let response = PriceFeedResponse {
+                price_feed: PriceFeed::new(price_feed_id_type, price, price),
+            };
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&response).unwrap()))
+        }
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == HUB_CONTRACT => {
+            let response = HubStateResponse {
+                exchange_rate: *querier_rate.borrow(),
+            };
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&response).unwrap()))
+        }
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "unexpected wasm query".to_string(),
+        }),
+    });
+
+    // the underlying asset must have its own feed
+    let res = config_derived_feed_info(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        "bsei".to_string(),
+        "usei".to_string(),
+        HUB_CONTRACT.to_string(),
+        Decimal256::percent(1),
+    );
+    assert!(res.is_err());
+
+    config_feed_info(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        "usei".to_string(),
+        price_feed_id_type,
+        "Crypto.SEI/USD".to_string(),
+        8,
+        false,
+        0,
+    )
+    .unwrap();
+
+    let res = config_derived_feed_info(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        "bsei".to_string(),
+        "usei".to_string(),
+        HUB_CONTRACT.to_string(),
+        Decimal256::percent(1),
+    );
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+    // an asset can not be derived from itself, and the rate change must be within (0, 1]
+    for (asset, max_rate_change) in [
+        ("usei", Decimal256::percent(1)),
+        ("bsei", Decimal256::zero()),
+        ("bsei", Decimal256::percent(101)),
+    ] {
+        let res = config_derived_feed_info(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            asset.to_string(),
+            "usei".to_string(),
+            HUB_CONTRACT.to_string(),
+            max_rate_change,
+        );
+        assert_eq!(res, Err(ContractError::InvalidInput {}));
+    }
+
+    config_derived_feed_info(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        "bsei".to_string(),
+        "usei".to_string(),
+        HUB_CONTRACT.to_string(),
+        Decimal256::percent(1),
+    )
+    .unwrap();
+
+    let feeds = query_feeds(deps.as_ref(), None, None).unwrap();
+    assert_eq!(
+        feeds
+            .iter()
+            .map(|f| (f.asset.as_str(), f.underlying_asset.as_deref()))
+            .collect::<Vec<_>>(),
+        vec![("bsei", Some("usei")), ("usei", None)]
+    );
+    assert_eq!(feeds[0].price_feed_id, price_feed_id_type);
+
+    let derived_config = query_derived_feeder_config(deps.as_ref(), "bsei".to_string()).unwrap();
+    assert_eq!(
+        derived_config.last_exchange_rate,
+        Decimal256::from_str("1.05").unwrap()
+    );
+
+    let price = query_price(deps.as_ref(), env.clone(), "bsei".to_string()).unwrap();
+    assert_eq!(price.emv_price, Decimal256::from_str("0.525").unwrap());
+    assert_eq!(price.price, Decimal256::from_str("0.525").unwrap());
+    assert_eq!(price.price_raw, 52_500_000);
+
+    // the derived price has its own circuit breaker bounds
+    set_price_bounds(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        "bsei".to_string(),
+        None,
+        Some(Decimal256::percent(50)),
+        None,
+    )
+    .unwrap();
+    let derived_config = query_derived_feeder_config(deps.as_ref(), "bsei".to_string()).unwrap();
+    assert_eq!(derived_config.max_price, Some(Decimal256::percent(50)));
+    assert!(query_price(deps.as_ref(), env.clone(), "bsei".to_string()).is_err());
+    let prices = query_checked_prices(
+        deps.as_ref(),
+        env.clone(),
+        vec!["usei".to_string(), "bsei".to_string()],
+        false,
+    )
+    .unwrap();
+    assert_eq!(prices[0].price.as_ref().unwrap().price, Decimal256::percent(50));
+    assert_eq!(prices[1].price, None);
+    assert!(prices[1].circuit_breaker.is_some());
+
+    set_price_bounds(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        "bsei".to_string(),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    // a sudden jump of the hub rate is capped at 1% per day
+    *hub_exchange_rate.borrow_mut() = Decimal256::from_str("2").unwrap();
+    let mut env = env;
+    env.block.time = env.block.time.plus_seconds(86400);
+    let price = query_price(deps.as_ref(), env.clone(), "bsei".to_string()).unwrap();
+    assert_eq!(price.price, Decimal256::from_str("0.53025").unwrap());
+
+    update_derived_exchange_rate(deps.as_mut(), env.clone(), "bsei".to_string()).unwrap();
+    let derived_config = query_derived_feeder_config(deps.as_ref(), "bsei".to_string()).unwrap();
+    assert_eq!(
+        derived_config.last_exchange_rate,
+        Decimal256::from_str("1.0605").unwrap()
+    );
+
+    // decreases are applied immediately
+    *hub_exchange_rate.borrow_mut() = Decimal256::from_str("0.9").unwrap();
+    let price = query_price(deps.as_ref(), env.clone(), "bsei".to_string()).unwrap();
+    assert_eq!(price.price, Decimal256::from_str("0.45").unwrap());
+
+    // a stale rate still only allows one day's worth of increase
+    *hub_exchange_rate.borrow_mut() = Decimal256::from_str("2").unwrap();
+    env.block.time = env.block.time.plus_seconds(30 * 86400);
+    let price = query_price(deps.as_ref(), env, "bsei".to_string()).unwrap();
+    assert_eq!(price.price, Decimal256::from_str("0.5355525").unwrap());
+}
+
+#[test]
+fn test_query_feeds() {
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
+    let (mut deps, _env, info, res) = mock_instantiate(msg);
+    assert!(res.is_ok());
+
+    let price_feed_id_type = PriceIdentifier::from_hex(
+        "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace",
+    )
+    .unwrap();
+    for asset in ["asset0", "asset1", "asset2"] {
+        config_feed_info(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            asset.to_string(),
+            price_feed_id_type,
+            "Crypto.ETH/USD".to_string(),
+            8,
+            true,
+            360,
+        )
+        .unwrap();
+    }
+
+    let feeds = query_feeds(deps.as_ref(), None, None).unwrap();
+    assert_eq!(
+        feeds.iter().map(|f| f.asset.as_str()).collect::<Vec<_>>(),
+        vec!["asset0", "asset1", "asset2"]
+    );
+    assert_eq!(feeds[0].price_feed_id, price_feed_id_type);
+
+    let feeds = query_feeds(deps.as_ref(), Some("asset0".to_string()), Some(1)).unwrap();
+    assert_eq!(feeds.len(), 1);
+    assert_eq!(feeds[0].asset, "asset1");
+}
+
+#[test]
+fn test_update_price_feeds() {
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
+    let (mut deps, _env, _info, res) = mock_instantiate(msg);
+    assert!(res.is_ok());
+
+    deps.querier.update_wasm(|request| match request {
+        WasmQuery::Smart { contract_addr, .. } if contract_addr == PYTH_CONTRACT => {
+            SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&coin(2, "usei")).unwrap(),
+            ))
+        }
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "unexpected wasm query".to_string(),
+        }),
+    });
+    let data = vec![Binary::from(b"vaa".to_vec())];
+
+    let res = update_price_feeds(
+        deps.as_mut(),
+        mock_info("addr0000", &[coin(1, "usei")]),
+        data.clone(),
+    );
+    assert_eq!(res, Err(ContractError::InsufficientFee("2usei".to_string())));
+
+    let res = update_price_feeds(
+        deps.as_mut(),
+        mock_info("addr0000", &[coin(5, "usei"), coin(3, "uusd")]),
+        data.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: PYTH_CONTRACT.to_string(),
+                msg: to_json_binary(&PythExecuteMsg::UpdatePriceFeeds { data }).unwrap(),
+                funds: vec![coin(2, "usei")],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "addr0000".to_string(),
+                amount: vec![coin(3, "usei"), coin(3, "uusd")],
+            })),
+        ]
+    );
+}
+
+#[test]
+fn test_price_sanity_bounds() {
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
+    let (mut deps, _env, info, res) = mock_instantiate(msg);
+    assert!(res.is_ok());
+
+    let asset = "bsei".to_string();
+    let price_feed_id_type = PriceIdentifier::from_hex(
+        "53614f1cb0c031d4af66c04cb9c756234adad0e1cee85303795091499a4084eb",
+    )
+    .unwrap();
+    config_feed_info(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        asset.clone(),
+        price_feed_id_type,
+        "Crypto.SEI/USD".to_string(),
+        8,
+        false,
+        0,
+    )
+    .unwrap();
+
+    let res = set_price_bounds(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        asset.clone(),
+        None,
+        None,
+        None,
+    );
+    assert_eq!(res, Err(ContractError::Unauthorized {}));
+
+    let res = set_price_bounds(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        asset.clone(),
+        Some(Decimal256::percent(200)),
+        Some(Decimal256::percent(100)),
+        None,
+    );
+    assert_eq!(res, Err(ContractError::InvalidInput {}));
+
+    set_price_bounds(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        asset.clone(),
+        Some(Decimal256::percent(10)),
+        Some(Decimal256::percent(1000)),
+        Some(Decimal256::percent(5)),
+    )
+    .unwrap();
+
+    // bounds survive a reconfiguration of the feed
+    config_feed_info(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        asset.clone(),
+        price_feed_id_type,
+        "Crypto.SEI/USD".to_string(),
+        8,
+        true,
+        60,
+    )
+    .unwrap();
+    let feeder_config = query_pyth_feeder_config(deps.as_ref(), asset.clone()).unwrap();
+    assert_eq!(feeder_config.min_price, Some(Decimal256::percent(10)));
+    assert_eq!(feeder_config.max_price, Some(Decimal256::percent(1000)));
+    assert_eq!(feeder_config.max_deviation, Some(Decimal256::percent(5)));
+
+    let config = read_pyth_feeder_config(deps.as_ref().storage, asset.clone()).unwrap();
+    let check = |ema: u64, spot: u64, price_check: PriceCheck| {
+        check_price_sanity(
+            &asset,
+            &config.bounds(),
+            Decimal256::percent(ema),
+            Decimal256::percent(spot),
+            price_check,
+        )
+    };
+    assert!(check(100, 104, PriceCheck::Full).is_ok());
+    assert!(matches!(
+        check(100, 106, PriceCheck::Full),
+        Err(ContractError::PriceCircuitBreaker(_))
+    ));
+    assert!(matches!(
+        check(5, 5, PriceCheck::Full),
+        Err(ContractError::PriceCircuitBreaker(_))
+    ));
+    assert!(matches!(
+        check(100, 1001, PriceCheck::Full),
+        Err(ContractError::PriceCircuitBreaker(_))
+    ));
+    // only the ema price is checked for liquidations that ignore the spot price
+    assert!(check(100, 1001, PriceCheck::EmaOnly).is_ok());
+    assert!(matches!(
+        check(1001, 100, PriceCheck::EmaOnly),
+        Err(ContractError::PriceCircuitBreaker(_))
+    ));
+}
+
+#[test]
+fn test_config_history() {
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
+    let (mut deps, _env, info, res) = mock_instantiate(msg);
+    assert!(res.is_ok());
+
+    let asset = "bsei".to_string();
+    let price_feed_id_type = PriceIdentifier::from_hex(
+        "53614f1cb0c031d4af66c04cb9c756234adad0e1cee85303795091499a4084eb",
+    )
+    .unwrap();
+    config_feed_info(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        asset.clone(),
+        price_feed_id_type,
+        "Crypto.SEI/USD".to_string(),
+        8,
+        false,
+        0,
+    )
+    .unwrap();
+    set_price_bounds(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        asset.clone(),
+        Some(Decimal256::percent(10)),
+        None,
+        None,
+    )
+    .unwrap();
+    change_owner(deps.as_mut(), mock_env(), info.clone(), "new_owner".to_string()).unwrap();
+
+    let history = query_config_history(deps.as_ref().storage, None, None).unwrap();
+    let actions: Vec<&str> = history.entries.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, vec!["config_feed_info", "set_price_bounds", "change_owner"]);
+    assert!(history.entries.iter().all(|e| e.sender == CREATOR));
+
+    // a new feed has no previous values
+    let change = &history.entries[0].changes[0];
+    assert_eq!(change.field, "bsei.price_feed_id");
+    assert_eq!(change.old_value, "null");
+    assert_eq!(
+        change.new_value,
+        "\"53614f1cb0c031d4af66c04cb9c756234adad0e1cee85303795091499a4084eb\""
+    );
+
+    // unchanged bounds are left out
+    assert_eq!(
+        history.entries[1].changes,
+        vec![ConfigChange {
+            field: "bsei.min_price".to_string(),
+            old_value: "null".to_string(),
+            new_value: "\"0.1\"".to_string(),
+        }]
+    );
+    assert_eq!(
+        history.entries[2].changes,
+        vec![ConfigChange {
+            field: "owner".to_string(),
+            old_value: "\"creator\"".to_string(),
+            new_value: "\"new_owner\"".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_migrate_legacy_state() {
+    let msg = mock_instantiate_msg(PYTH_CONTRACT);
+    let (mut deps, _env, info, res) = mock_instantiate(msg);
+    assert!(res.is_ok());
+
+    let asset = "factory/sei1h3ukufh4lhacftdf6kyxzum4p86rcnel35v4jk/usdt".to_string();
+    let price_feed_id = PriceIdentifier::from_hex(
+        "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace",
+    )
+    .unwrap();
+    config_feed_info(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        asset.clone(),
+        price_feed_id,
+        "Crypto.ETH/USD".to_string(),
+        8,
+        true,
+        360,
+    )
+    .unwrap();
+
+    // rewrite the config as a legacy singleton without cw2 info
+    let config = read_config(&deps.storage).unwrap();
+    deps.storage.remove(b"config");
+    deps.storage.remove(b"contract_info");
+    singleton(&mut deps.storage, b"config").save(&config).unwrap();
+    assert!(query_config(deps.as_ref()).is_err());
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1], attr("previous_version", "none"));
+    assert_eq!(
+        get_contract_version(&deps.storage).unwrap().version,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let config = query_config(deps.as_ref()).unwrap();
+    assert_eq!(config.pyth_contract, PYTH_CONTRACT.to_string());
+    assert_eq!(config.owner, CREATOR.to_string());
+    let feeder_config = query_pyth_feeder_config(deps.as_ref(), asset).unwrap();
+    assert_eq!(feeder_config.price_feed_id, price_feed_id);
+}